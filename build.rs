@@ -4,4 +4,5 @@
 fn main() {
     let c = autocfg::new();
     c.emit_rustc_version(1, 57);
+    c.emit_rustc_version(1, 63);
 }
@@ -0,0 +1,8 @@
+// BSL 1.0 License
+
+#![no_main]
+
+use genimage_fuzz::BuilderInput;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: BuilderInput<'_>| genimage_fuzz::builder(input));
@@ -0,0 +1,8 @@
+// BSL 1.0 License
+
+#![no_main]
+
+use genimage_fuzz::PixelInput;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: PixelInput<'_>| genimage_fuzz::pixel_new(input));
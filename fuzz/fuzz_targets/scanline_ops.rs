@@ -0,0 +1,8 @@
+// BSL 1.0 License
+
+#![no_main]
+
+use genimage_fuzz::ScanlineInput;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: ScanlineInput<'_>| genimage_fuzz::scanline_ops(input));
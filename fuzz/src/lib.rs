@@ -0,0 +1,222 @@
+// BSL 1.0 License
+
+//! Fuzz targets for the byte-parsing paths of `genimage`.
+//!
+//! The targets live in `fuzz_targets/` and only decode their input here,
+//! so that the logic can be shared and debugged without libFuzzer. Run
+//! them with `cargo fuzz run <target>` from the root of the repository.
+
+use arbitrary::Arbitrary;
+use genimage::{
+    blit_bits, convert, find_pixel, from_planar_bytes, invert, to_planar_bytes, Builder,
+    Endianness, Format, Image, Layout, OobPolicy, Pixel, Rect,
+};
+
+const FORMATS: [Format; 34] = [
+    Format::ARGB32,
+    Format::XRGB32,
+    Format::ABGR32,
+    Format::XBGR32,
+    Format::RGBA32,
+    Format::RGBX32,
+    Format::BGRA32,
+    Format::BGRX32,
+    Format::RGB24,
+    Format::BGR24,
+    Format::RGB48,
+    Format::BGR48,
+    Format::RGBA64,
+    Format::ABGR64,
+    Format::RG8,
+    Format::RG16,
+    Format::LA8,
+    Format::GRAY8,
+    Format::GRAY16,
+    Format::RGB565,
+    Format::RGB555,
+    Format::ARGB1555,
+    Format::CMYK32,
+    Format::ARGB16,
+    Format::XRGB16,
+    Format::ABGR16,
+    Format::XBGR16,
+    Format::A8,
+    Format::A4,
+    Format::A1,
+    Format::ARGB_F32,
+    Format::RGB_F32,
+    Format::ARGB_F16,
+    Format::RGB_F16,
+];
+
+/// The largest number of pixels in an image that is converted.
+const MAX_PIXELS: usize = 1 << 16;
+
+fn format(index: u8) -> Format {
+    FORMATS[index as usize % FORMATS.len()]
+}
+
+fn endianness(big: bool) -> Endianness {
+    if big {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    }
+}
+
+fn oob_policy(index: u8) -> OobPolicy {
+    match index % 3 {
+        0 => OobPolicy::Empty,
+        1 => OobPolicy::Transparent,
+        _ => OobPolicy::Clamp,
+    }
+}
+
+/// Raw bytes to be read as a pixel.
+#[derive(Arbitrary, Debug)]
+pub struct PixelInput<'a> {
+    format: u8,
+    big_endian: bool,
+    index: u8,
+    bytes: &'a [u8],
+}
+
+/// Read a pixel out of arbitrary bytes, and then convert it to every
+/// format.
+pub fn pixel_new(input: PixelInput<'_>) {
+    let format = format(input.format);
+    let endianness = endianness(input.big_endian);
+    let pixel = match Pixel::try_with_index(input.bytes, input.index, endianness, format) {
+        Ok(pixel) => pixel,
+        Err(_) => return,
+    };
+
+    pixel.raw_u32();
+    pixel.to_id();
+    for &other in FORMATS.iter() {
+        pixel.into_new_format(Endianness::Little, other);
+        pixel.into_new_format(Endianness::Big, other);
+    }
+}
+
+/// Image metadata, as it would be reported by a foreign API.
+#[derive(Arbitrary, Debug)]
+pub struct Metadata {
+    width: usize,
+    height: usize,
+    format: u8,
+    big_endian: bool,
+    bytes_per_scanline: Option<usize>,
+    repeat: bool,
+    oob_policy: u8,
+    column_major: bool,
+}
+
+impl Metadata {
+    fn builder(&self, bytes: &[u8]) -> Option<Builder<Vec<u8>>> {
+        let format = format(self.format);
+        let layout = if self.column_major {
+            Layout::ColumnMajor
+        } else {
+            Layout::RowMajor
+        };
+        let mut builder = Builder::from_buffer(self.width, self.height, format, bytes.to_vec())
+            .with_layout(layout)
+            .with_endianness(endianness(self.big_endian))
+            .with_oob_policy(oob_policy(self.oob_policy));
+        if let Some(value) = self.bytes_per_scanline {
+            builder = builder.try_with_bytes_per_scanline(value).ok()?;
+        }
+        if self.repeat {
+            builder = builder.repeat();
+        }
+        Some(builder)
+    }
+}
+
+/// Metadata and a buffer for an image, and a place to read from it.
+#[derive(Arbitrary, Debug)]
+pub struct BuilderInput<'a> {
+    metadata: Metadata,
+    x: usize,
+    y: usize,
+    len: u16,
+    bytes: &'a [u8],
+}
+
+/// Build an image out of arbitrary metadata and bytes, and read from it.
+pub fn builder(input: BuilderInput<'_>) {
+    let BuilderInput {
+        metadata,
+        x,
+        y,
+        len,
+        bytes,
+    } = input;
+
+    // images with too little storage must not panic either
+    if let Some(builder) = metadata.builder(bytes) {
+        let image = builder.finish();
+        let mut scanline = vec![0u8; len as usize];
+        image.scanline(x, y, &mut scanline);
+        image.column(x, y, &mut scanline);
+    }
+
+    let image = match metadata.builder(bytes).map(Builder::try_finish) {
+        Some(Ok(image)) => image,
+        _ => return,
+    };
+
+    let (width, height) = image.dimensions();
+    if width > 0 && height > 0 {
+        image.pixel(x % width, y % height);
+    }
+}
+
+/// An image and a second format to run bulk operations with.
+#[derive(Arbitrary, Debug)]
+pub struct ScanlineInput<'a> {
+    width: u8,
+    height: u8,
+    format: u8,
+    big_endian: bool,
+    other_format: u8,
+    rect: (u8, u8, u8, u8),
+    bytes: &'a [u8],
+}
+
+/// Run the bulk operations on an image made of arbitrary bytes.
+pub fn scanline_ops(input: ScanlineInput<'_>) {
+    let (width, height) = (input.width as usize, input.height as usize);
+    if width * height > MAX_PIXELS {
+        return;
+    }
+
+    let src_format = format(input.format);
+    let src = match Builder::from_buffer(width, height, src_format, input.bytes.to_vec())
+        .with_endianness(endianness(input.big_endian))
+        .try_finish()
+    {
+        Ok(src) => src,
+        Err(_) => return,
+    };
+
+    let mut dst = Builder::owned(width, height, format(input.other_format)).finish();
+    convert(&src, &mut dst);
+    invert(&mut dst);
+
+    let (x, y, rect_width, rect_height) = input.rect;
+    let rect = Rect::new(x as usize, y as usize, rect_width as usize, rect_height as usize);
+    blit_bits(&src, rect, &mut dst, (y as usize, x as usize));
+
+    if width > 0 && height > 0 {
+        let pixel = src.pixel(x as usize % width, y as usize % height);
+        assert!(find_pixel(&src, pixel).is_some());
+    }
+
+    // the planar bytes must restore the image exactly
+    let planar = to_planar_bytes(&dst);
+    let mut restored = Builder::like_image(&dst).finish();
+    assert!(from_planar_bytes(&planar, &mut restored));
+    assert_eq!(to_planar_bytes(&restored), planar);
+}
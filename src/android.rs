@@ -0,0 +1,300 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the pixel formats of Android bitmaps
+//! and hardware buffers, for JNI glue that renders into them.
+//!
+//! [`AndroidBitmapInfo`] has the same layout as the structure that
+//! `AndroidBitmap_getInfo` fills in, and turns into a [`FramebufferDesc`]
+//! for mapping the pixels that `AndroidBitmap_lockPixels` gives back with
+//! [`map_framebuffer`]. Android stores pixels in little-endian order, and
+//! bitmaps are premultiplied unless their flags say otherwise.
+//!
+//! Android's half float format stores red first, while the float formats
+//! of `genimage` store alpha first, so `RGBA_F16` has no format.
+//!
+//! This requires the `android` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::android::{AndroidBitmapInfo, ANDROID_BITMAP_FORMAT_RGBA_8888};
+//! use genimage::{map_framebuffer, AlphaMode, Format};
+//!
+//! // filled in by AndroidBitmap_getInfo
+//! let info = AndroidBitmapInfo {
+//!     width: 100,
+//!     height: 50,
+//!     stride: 400,
+//!     format: ANDROID_BITMAP_FORMAT_RGBA_8888,
+//!     flags: 0,
+//! };
+//! let desc = info.to_desc().unwrap();
+//! assert_eq!(desc.format, Format::RGBA32.with_alpha_mode(AlphaMode::Premultiplied));
+//!
+//! // the pixels from AndroidBitmap_lockPixels
+//! let mut pixels = vec![0u8; desc.len()];
+//! let bitmap = map_framebuffer(&desc, &mut pixels[..]).unwrap();
+//! ```
+//!
+//! [`AndroidBitmapInfo`]: crate::android::AndroidBitmapInfo
+//! [`FramebufferDesc`]: crate::FramebufferDesc
+//! [`map_framebuffer`]: crate::map_framebuffer
+
+use crate::{AlphaMode, ByteCount, Channel, ColorType, Endianness, Error, Format, FramebufferDesc};
+use core::convert::TryFrom;
+
+/// A bitmap without a format.
+pub const ANDROID_BITMAP_FORMAT_NONE: i32 = 0;
+/// [`Format::RGBA32`](crate::Format::RGBA32).
+pub const ANDROID_BITMAP_FORMAT_RGBA_8888: i32 = 1;
+/// [`Format::RGB565`](crate::Format::RGB565).
+pub const ANDROID_BITMAP_FORMAT_RGB_565: i32 = 4;
+/// [`Format::ABGR16`](crate::Format::ABGR16), which is deprecated.
+pub const ANDROID_BITMAP_FORMAT_RGBA_4444: i32 = 7;
+/// [`Format::A8`](crate::Format::A8).
+pub const ANDROID_BITMAP_FORMAT_A_8: i32 = 8;
+/// Red, green, blue and alpha as half floats, which has no format.
+pub const ANDROID_BITMAP_FORMAT_RGBA_F16: i32 = 9;
+/// Ten bits of red, green and blue, then two bits of alpha.
+pub const ANDROID_BITMAP_FORMAT_RGBA_1010102: i32 = 10;
+
+/// The flags of a bitmap whose colors are premultiplied by alpha.
+pub const ANDROID_BITMAP_FLAGS_ALPHA_PREMUL: u32 = 0;
+/// The flags of a bitmap that is opaque.
+pub const ANDROID_BITMAP_FLAGS_ALPHA_OPAQUE: u32 = 1;
+/// The flags of a bitmap whose colors aren't premultiplied by alpha.
+pub const ANDROID_BITMAP_FLAGS_ALPHA_UNPREMUL: u32 = 2;
+/// The bits of the flags that say how alpha is stored.
+pub const ANDROID_BITMAP_FLAGS_ALPHA_MASK: u32 = 3;
+
+/// [`Format::RGBA32`](crate::Format::RGBA32).
+pub const AHARDWAREBUFFER_FORMAT_R8G8B8A8_UNORM: u32 = 1;
+/// [`Format::XRGB32`](crate::Format::XRGB32).
+pub const AHARDWAREBUFFER_FORMAT_R8G8B8X8_UNORM: u32 = 2;
+/// [`Format::RGB24`](crate::Format::RGB24).
+pub const AHARDWAREBUFFER_FORMAT_R8G8B8_UNORM: u32 = 3;
+/// [`Format::RGB565`](crate::Format::RGB565).
+pub const AHARDWAREBUFFER_FORMAT_R5G6B5_UNORM: u32 = 4;
+/// Red, green, blue and alpha as half floats, which has no format.
+pub const AHARDWAREBUFFER_FORMAT_R16G16B16A16_FLOAT: u32 = 0x16;
+/// Ten bits of red, green and blue, then two bits of alpha.
+pub const AHARDWAREBUFFER_FORMAT_R10G10B10A2_UNORM: u32 = 0x2B;
+/// Gray stored in a red channel, which is [`Format::GRAY8`](crate::Format::GRAY8).
+pub const AHARDWAREBUFFER_FORMAT_R8_UNORM: u32 = 0x38;
+
+/// Ten bits of red, green and blue, then two bits of alpha.
+const RGBA_1010102: Format = Format::new(32, ColorType::Rgba, 2, 10, 10, 10);
+
+/// Every bitmap format with a format.
+const BITMAP_FORMATS: &[(i32, Format)] = &[
+    (ANDROID_BITMAP_FORMAT_RGBA_8888, Format::RGBA32),
+    (ANDROID_BITMAP_FORMAT_RGB_565, Format::RGB565),
+    (ANDROID_BITMAP_FORMAT_RGBA_4444, Format::ABGR16),
+    (ANDROID_BITMAP_FORMAT_A_8, Format::A8),
+    (ANDROID_BITMAP_FORMAT_RGBA_1010102, RGBA_1010102),
+];
+
+/// Every hardware buffer format with a format.
+const BUFFER_FORMATS: &[(u32, Format)] = &[
+    (AHARDWAREBUFFER_FORMAT_R8G8B8A8_UNORM, Format::RGBA32),
+    (AHARDWAREBUFFER_FORMAT_R8G8B8X8_UNORM, Format::XRGB32),
+    (AHARDWAREBUFFER_FORMAT_R8G8B8_UNORM, Format::RGB24),
+    (AHARDWAREBUFFER_FORMAT_R5G6B5_UNORM, Format::RGB565),
+    (AHARDWAREBUFFER_FORMAT_R10G10B10A2_UNORM, RGBA_1010102),
+    (AHARDWAREBUFFER_FORMAT_R8_UNORM, Format::GRAY8),
+];
+
+/// The fields of an `AndroidBitmapInfo`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[repr(C)]
+pub struct AndroidBitmapInfo {
+    /// The width of the bitmap in pixels.
+    pub width: u32,
+    /// The height of the bitmap in pixels.
+    pub height: u32,
+    /// The number of bytes from the start of one row to the next.
+    pub stride: u32,
+    /// The format of the pixels, such as
+    /// [`ANDROID_BITMAP_FORMAT_RGBA_8888`].
+    ///
+    /// [`ANDROID_BITMAP_FORMAT_RGBA_8888`]: crate::android::ANDROID_BITMAP_FORMAT_RGBA_8888
+    pub format: i32,
+    /// How alpha is stored, along with other flags.
+    pub flags: u32,
+}
+
+impl AndroidBitmapInfo {
+    /// The format of the pixels of the bitmap.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unsupported`] if there is no format for the format
+    /// of the bitmap.
+    ///
+    /// [`Error::Unsupported`]: crate::Error::Unsupported
+    pub fn pixel_format(&self) -> Result<Format, Error> {
+        Format::from_android_bitmap_format(self.format, self.flags).ok_or(Error::Unsupported)
+    }
+
+    /// The layout of the pixels of the bitmap, for [`map_framebuffer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`pixel_format`] does, or [`Error::TooLarge`] if
+    /// the size of the bitmap doesn't fit in memory.
+    ///
+    /// [`map_framebuffer`]: crate::map_framebuffer
+    /// [`pixel_format`]: crate::android::AndroidBitmapInfo::pixel_format
+    /// [`Error::TooLarge`]: crate::Error::TooLarge
+    pub fn to_desc(&self) -> Result<FramebufferDesc, Error> {
+        let format = self.pixel_format()?;
+        let too_large = |_| Error::TooLarge;
+        let width = usize::try_from(self.width).map_err(too_large)?;
+        let height = usize::try_from(self.height).map_err(too_large)?;
+        let stride = usize::try_from(self.stride).map_err(too_large)?;
+
+        Ok(FramebufferDesc::new(width, height, format)
+            .with_stride(ByteCount::new(stride))
+            .with_endianness(Endianness::Little))
+    }
+}
+
+impl Format {
+    /// The Android bitmap format and alpha flags for this format, if there
+    /// is a bitmap format with the same layout.
+    ///
+    /// The flags say whether alpha is premultiplied, or that the bitmap is
+    /// opaque for formats without alpha.
+    ///
+    /// This requires the `android` feature.
+    pub fn to_android_bitmap_format(self) -> Option<(i32, u32)> {
+        let straight = self.with_alpha_mode(AlphaMode::Straight);
+        let (code, _) = BITMAP_FORMATS
+            .iter()
+            .find(|&&(_, known)| known == straight)?;
+
+        let flags = if self.channel_mask(Channel::Alpha) == 0 {
+            ANDROID_BITMAP_FLAGS_ALPHA_OPAQUE
+        } else if self.premultiplies() {
+            ANDROID_BITMAP_FLAGS_ALPHA_PREMUL
+        } else {
+            ANDROID_BITMAP_FLAGS_ALPHA_UNPREMUL
+        };
+        Some((*code, flags))
+    }
+
+    /// The format for an Android bitmap format and its flags, if there is
+    /// one.
+    ///
+    /// Formats with alpha are premultiplied unless the flags say that the
+    /// bitmap is unpremultiplied.
+    ///
+    /// This requires the `android` feature.
+    pub fn from_android_bitmap_format(code: i32, flags: u32) -> Option<Format> {
+        let format = BITMAP_FORMATS
+            .iter()
+            .find(|&&(known, _)| known == code)
+            .map(|&(_, format)| format)?;
+
+        let premultiplied = flags & ANDROID_BITMAP_FLAGS_ALPHA_MASK
+            == ANDROID_BITMAP_FLAGS_ALPHA_PREMUL
+            && format.channel_mask(Channel::Alpha) != 0
+            && format.color_type() != ColorType::Alpha;
+        Some(if premultiplied {
+            format.with_alpha_mode(AlphaMode::Premultiplied)
+        } else {
+            format
+        })
+    }
+
+    /// The `AHardwareBuffer_Format` with the same layout as this format, if
+    /// there is one.
+    ///
+    /// Hardware buffers don't say whether alpha is premultiplied, so it is
+    /// ignored.
+    ///
+    /// This requires the `android` feature.
+    pub fn to_ahardware_buffer_format(self) -> Option<u32> {
+        let format = match self.with_alpha_mode(AlphaMode::Straight) {
+            Format::RGBX32 => Format::XRGB32,
+            format => format,
+        };
+        BUFFER_FORMATS
+            .iter()
+            .find(|&&(_, known)| known == format)
+            .map(|&(code, _)| code)
+    }
+
+    /// The format with the same layout as an `AHardwareBuffer_Format`, if
+    /// there is one.
+    ///
+    /// This requires the `android` feature.
+    pub fn from_ahardware_buffer_format(code: u32) -> Option<Format> {
+        BUFFER_FORMATS
+            .iter()
+            .find(|&&(known, _)| known == code)
+            .map(|&(_, format)| format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        for &(code, format) in BITMAP_FORMATS {
+            let flags = if format.channel_mask(Channel::Alpha) == 0 {
+                ANDROID_BITMAP_FLAGS_ALPHA_OPAQUE
+            } else {
+                ANDROID_BITMAP_FLAGS_ALPHA_UNPREMUL
+            };
+            assert_eq!(format.to_android_bitmap_format(), Some((code, flags)));
+            assert_eq!(
+                Format::from_android_bitmap_format(code, flags),
+                Some(format)
+            );
+        }
+        for &(code, format) in BUFFER_FORMATS {
+            assert_eq!(format.to_ahardware_buffer_format(), Some(code));
+            assert_eq!(Format::from_ahardware_buffer_format(code), Some(format));
+        }
+
+        // bitmaps are premultiplied by default, except for masks
+        let premultiplied = Format::RGBA32.with_alpha_mode(AlphaMode::Premultiplied);
+        assert_eq!(
+            Format::from_android_bitmap_format(ANDROID_BITMAP_FORMAT_RGBA_8888, 0),
+            Some(premultiplied)
+        );
+        assert_eq!(
+            premultiplied.to_android_bitmap_format(),
+            Some((
+                ANDROID_BITMAP_FORMAT_RGBA_8888,
+                ANDROID_BITMAP_FLAGS_ALPHA_PREMUL
+            ))
+        );
+        assert_eq!(
+            Format::from_android_bitmap_format(ANDROID_BITMAP_FORMAT_A_8, 0),
+            Some(Format::A8)
+        );
+        assert_eq!(
+            Format::from_android_bitmap_format(ANDROID_BITMAP_FORMAT_RGBA_F16, 0),
+            None
+        );
+        assert_eq!(
+            Format::from_ahardware_buffer_format(AHARDWAREBUFFER_FORMAT_R16G16B16A16_FLOAT),
+            None
+        );
+
+        let info = AndroidBitmapInfo {
+            width: 3,
+            height: 2,
+            stride: 8,
+            format: ANDROID_BITMAP_FORMAT_RGB_565,
+            flags: ANDROID_BITMAP_FLAGS_ALPHA_OPAQUE,
+        };
+        let desc = info.to_desc().unwrap();
+        assert_eq!(desc.format, Format::RGB565);
+        assert_eq!(desc.len(), 14);
+    }
+}
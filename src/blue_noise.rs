@@ -0,0 +1,287 @@
+// BSL 1.0 License
+
+use crate::{Endianness, Format, Image};
+use core::cmp;
+
+/// The side length of the blue noise tile.
+const SIZE: usize = 64;
+
+/// A tile of blue noise, for use as the thresholds of [`ordered_dither`].
+///
+/// Blue noise has no low-frequency structure, so dithering with it
+/// doesn't show the cross-hatched patterns of a Bayer matrix. The tile is
+/// a [`GRAY8`] image that is 64 pixels on each side, and it tiles without
+/// seams. Every value from 0 to 255 appears the same number of times.
+///
+/// The tile was generated with the void-and-cluster method, and is
+/// always the same.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{BlueNoise, Image};
+///
+/// assert_eq!(BlueNoise.dimensions(), (64, 64));
+/// ```
+///
+/// [`ordered_dither`]: crate::ordered_dither
+/// [`GRAY8`]: crate::Format::GRAY8
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BlueNoise;
+
+impl BlueNoise {
+    /// The width and height of the tile.
+    pub const SIZE: usize = SIZE;
+}
+
+impl Image for BlueNoise {
+    fn format(&self) -> Format {
+        Format::GRAY8
+    }
+
+    fn endianness(&self) -> Endianness {
+        Endianness::NATIVE
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (SIZE, SIZE)
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        SIZE
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        if y >= SIZE {
+            return 0;
+        }
+
+        let row = &THRESHOLDS[y * SIZE..(y + 1) * SIZE];
+        let start = cmp::min(x, SIZE);
+        let len = cmp::min(scanline.len(), SIZE - start);
+        scanline[..len].copy_from_slice(&row[start..start + len]);
+        len
+    }
+
+    /// The tile can't be written to, so this does nothing.
+    fn set_scanline(&mut self, _x: usize, _y: usize, _scanline: &[u8]) -> usize {
+        0
+    }
+}
+
+/// The thresholds, in row-major order.
+static THRESHOLDS: [u8; SIZE * SIZE] = [
+    240, 86, 211, 114, 160, 26, 192, 241, 13, 154, 251, 68, 126, 196, 77, 241, 47, 101, 202, 37,
+    67, 188, 24, 208, 226, 63, 235, 51, 208, 64, 33, 188, 148, 123, 202, 156, 78, 52, 100, 213, 62,
+    3, 177, 255, 120, 220, 34, 161, 5, 193, 142, 105, 159, 90, 195, 71, 249, 16, 209, 75, 231, 151,
+    207, 181, 105, 32, 144, 13, 96, 220, 144, 69, 207, 88, 112, 20, 210, 36, 167, 15, 192, 161,
+    231, 116, 214, 151, 55, 89, 140, 163, 14, 183, 133, 107, 216, 90, 246, 29, 58, 234, 33, 194,
+    250, 153, 125, 95, 145, 68, 14, 137, 79, 249, 61, 91, 43, 240, 24, 227, 36, 142, 95, 154, 112,
+    138, 53, 93, 22, 67, 170, 225, 196, 48, 236, 78, 37, 118, 168, 47, 228, 179, 145, 96, 222, 136,
+    87, 62, 25, 139, 17, 100, 242, 194, 32, 77, 122, 92, 241, 7, 141, 48, 161, 114, 183, 95, 143,
+    119, 13, 44, 183, 239, 39, 207, 165, 234, 48, 110, 152, 218, 173, 199, 62, 132, 170, 10, 216,
+    45, 239, 192, 1, 164, 254, 139, 9, 122, 64, 165, 128, 203, 176, 22, 245, 131, 4, 82, 54, 247,
+    23, 118, 207, 253, 171, 78, 223, 177, 0, 117, 170, 255, 209, 44, 158, 75, 193, 230, 13, 72,
+    207, 17, 242, 173, 88, 221, 74, 21, 190, 108, 85, 23, 174, 201, 27, 129, 8, 117, 83, 254, 100,
+    201, 66, 174, 25, 87, 224, 121, 198, 47, 233, 181, 87, 250, 0, 102, 60, 190, 97, 158, 219, 194,
+    124, 171, 72, 156, 3, 42, 111, 199, 53, 129, 73, 225, 52, 100, 21, 177, 222, 29, 121, 174, 103,
+    223, 166, 129, 40, 66, 201, 114, 161, 138, 230, 51, 151, 217, 126, 93, 227, 67, 237, 150, 17,
+    185, 41, 124, 244, 104, 131, 59, 177, 29, 75, 98, 208, 21, 112, 40, 149, 213, 137, 229, 13, 73,
+    43, 106, 27, 205, 46, 229, 187, 94, 236, 147, 35, 249, 158, 26, 145, 196, 128, 69, 143, 95,
+    251, 63, 35, 145, 52, 85, 216, 152, 1, 245, 56, 93, 9, 119, 248, 65, 2, 189, 46, 164, 87, 205,
+    56, 219, 156, 78, 6, 161, 230, 204, 147, 107, 240, 142, 158, 72, 200, 168, 227, 28, 80, 47,
+    150, 205, 254, 176, 142, 237, 88, 113, 139, 60, 166, 12, 88, 194, 102, 181, 78, 219, 12, 236,
+    47, 206, 2, 134, 202, 241, 9, 232, 108, 189, 133, 36, 182, 213, 167, 199, 33, 178, 80, 144,
+    246, 106, 24, 175, 111, 134, 28, 228, 196, 52, 20, 81, 41, 217, 13, 57, 35, 229, 131, 53, 92,
+    121, 174, 241, 101, 126, 23, 91, 63, 9, 162, 20, 210, 30, 225, 130, 217, 56, 7, 237, 37, 112,
+    169, 85, 187, 115, 162, 78, 179, 94, 124, 175, 23, 54, 91, 223, 110, 26, 64, 135, 98, 224, 113,
+    210, 16, 130, 220, 42, 248, 70, 178, 93, 138, 112, 189, 252, 128, 165, 191, 116, 176, 84, 6,
+    245, 193, 12, 211, 34, 187, 57, 165, 201, 224, 128, 189, 244, 82, 180, 68, 104, 177, 146, 117,
+    206, 137, 58, 250, 155, 26, 230, 43, 222, 21, 60, 156, 74, 208, 247, 162, 72, 143, 251, 84,
+    231, 11, 160, 56, 38, 169, 75, 193, 147, 99, 0, 236, 43, 214, 66, 152, 99, 5, 64, 89, 249, 134,
+    215, 110, 156, 70, 145, 105, 159, 77, 235, 4, 146, 38, 75, 103, 52, 149, 118, 238, 41, 18, 246,
+    71, 165, 91, 200, 5, 104, 67, 138, 99, 191, 112, 254, 198, 40, 137, 113, 18, 197, 7, 179, 156,
+    41, 190, 129, 253, 200, 95, 240, 57, 20, 172, 206, 116, 157, 11, 239, 34, 173, 208, 226, 150,
+    18, 66, 44, 186, 29, 221, 49, 252, 18, 124, 215, 94, 115, 247, 174, 215, 28, 201, 1, 140, 199,
+    85, 212, 46, 27, 229, 24, 146, 218, 178, 246, 13, 153, 49, 133, 6, 220, 82, 184, 50, 237, 102,
+    122, 57, 212, 92, 28, 71, 140, 5, 154, 109, 230, 80, 142, 60, 194, 83, 181, 133, 79, 50, 112,
+    37, 202, 160, 244, 92, 127, 175, 84, 137, 204, 46, 173, 30, 195, 60, 11, 132, 157, 253, 97, 60,
+    169, 113, 155, 131, 180, 107, 191, 75, 125, 30, 60, 209, 82, 235, 177, 102, 162, 27, 228, 141,
+    68, 207, 24, 242, 142, 111, 233, 172, 117, 226, 35, 186, 124, 45, 254, 26, 101, 227, 118, 16,
+    244, 188, 141, 237, 98, 122, 12, 210, 61, 231, 3, 190, 99, 70, 244, 131, 79, 163, 230, 89, 40,
+    73, 184, 223, 31, 234, 5, 78, 254, 59, 159, 242, 45, 197, 96, 168, 123, 25, 72, 45, 241, 126,
+    94, 174, 34, 165, 82, 192, 0, 63, 186, 19, 51, 194, 82, 218, 13, 179, 213, 132, 167, 38, 57,
+    217, 160, 27, 84, 0, 179, 76, 195, 139, 24, 159, 118, 35, 164, 224, 20, 153, 211, 43, 112, 190,
+    221, 123, 12, 144, 103, 54, 187, 216, 32, 122, 11, 93, 141, 234, 116, 1, 227, 185, 216, 147,
+    201, 57, 3, 214, 110, 231, 131, 48, 154, 221, 89, 147, 240, 103, 162, 62, 145, 96, 72, 4, 236,
+    200, 148, 97, 70, 127, 197, 220, 52, 154, 39, 236, 105, 71, 245, 215, 143, 56, 116, 89, 2, 251,
+    144, 17, 56, 161, 208, 79, 248, 125, 160, 95, 143, 204, 172, 221, 19, 177, 73, 153, 53, 135,
+    89, 16, 113, 182, 247, 74, 152, 14, 99, 254, 179, 120, 38, 202, 73, 7, 132, 244, 31, 202, 162,
+    111, 64, 123, 12, 176, 253, 45, 166, 101, 249, 111, 175, 87, 206, 180, 44, 91, 12, 188, 240,
+    169, 199, 69, 98, 175, 234, 105, 37, 180, 24, 201, 14, 65, 238, 47, 79, 118, 64, 38, 214, 241,
+    30, 104, 252, 40, 165, 84, 138, 37, 191, 61, 211, 33, 77, 15, 245, 129, 170, 220, 44, 178, 117,
+    225, 49, 248, 186, 85, 213, 32, 112, 203, 17, 142, 29, 225, 16, 54, 151, 7, 132, 198, 112, 74,
+    32, 135, 47, 126, 213, 32, 82, 135, 240, 62, 149, 88, 232, 177, 109, 7, 184, 251, 146, 195,
+    110, 82, 191, 170, 206, 69, 229, 11, 210, 121, 238, 167, 143, 113, 203, 163, 94, 60, 28, 108,
+    199, 88, 10, 76, 142, 18, 39, 154, 234, 138, 67, 227, 87, 58, 209, 70, 136, 246, 116, 218, 64,
+    255, 166, 214, 99, 232, 184, 22, 242, 155, 201, 4, 170, 120, 224, 49, 134, 33, 154, 215, 132,
+    30, 88, 165, 15, 130, 61, 5, 143, 120, 195, 150, 54, 95, 22, 80, 8, 234, 56, 137, 223, 183,
+    237, 145, 56, 251, 153, 192, 124, 209, 173, 105, 51, 190, 10, 179, 153, 123, 184, 162, 94, 193,
+    37, 83, 155, 26, 49, 145, 14, 63, 159, 86, 115, 65, 44, 219, 93, 21, 192, 106, 211, 70, 242,
+    90, 59, 203, 227, 52, 246, 211, 157, 238, 93, 48, 27, 104, 251, 181, 135, 227, 174, 92, 191,
+    41, 5, 119, 81, 17, 166, 112, 35, 228, 54, 83, 239, 1, 77, 127, 243, 101, 39, 247, 3, 47, 235,
+    24, 175, 230, 104, 183, 125, 236, 200, 110, 225, 5, 197, 143, 180, 128, 68, 255, 41, 156, 3,
+    183, 125, 19, 170, 110, 2, 137, 98, 35, 113, 21, 216, 182, 235, 166, 73, 34, 202, 47, 122, 28,
+    249, 110, 156, 209, 43, 202, 232, 71, 182, 99, 22, 161, 118, 219, 150, 206, 30, 167, 75, 200,
+    109, 214, 121, 76, 140, 57, 206, 0, 92, 70, 39, 176, 133, 55, 251, 99, 18, 236, 151, 113, 180,
+    78, 232, 98, 50, 196, 247, 75, 158, 187, 68, 202, 176, 80, 133, 65, 124, 9, 218, 157, 106, 67,
+    217, 143, 76, 181, 63, 244, 125, 91, 135, 3, 219, 143, 247, 200, 64, 36, 180, 91, 61, 120, 223,
+    21, 148, 181, 8, 166, 250, 20, 130, 225, 194, 164, 249, 90, 30, 216, 157, 36, 80, 198, 48, 13,
+    208, 133, 32, 164, 219, 146, 34, 128, 222, 26, 233, 147, 46, 254, 159, 36, 193, 91, 140, 19,
+    242, 197, 0, 165, 228, 15, 99, 30, 168, 190, 54, 162, 33, 79, 126, 15, 97, 134, 255, 20, 232,
+    141, 186, 54, 83, 227, 65, 204, 109, 85, 161, 52, 32, 117, 9, 147, 192, 77, 119, 184, 225, 104,
+    167, 230, 93, 58, 248, 116, 10, 67, 103, 176, 54, 90, 122, 14, 100, 205, 2, 221, 106, 247, 45,
+    84, 177, 127, 94, 54, 111, 198, 137, 218, 72, 13, 255, 101, 204, 174, 47, 190, 226, 168, 55,
+    111, 197, 10, 97, 251, 132, 102, 42, 149, 29, 187, 239, 102, 149, 229, 59, 209, 106, 14, 238,
+    59, 146, 0, 66, 137, 27, 160, 197, 83, 184, 236, 210, 19, 198, 248, 165, 63, 184, 117, 75, 143,
+    55, 179, 208, 152, 61, 27, 188, 253, 33, 158, 51, 238, 115, 150, 213, 123, 61, 237, 109, 154,
+    77, 6, 208, 156, 74, 175, 44, 158, 26, 170, 244, 124, 219, 68, 9, 210, 74, 183, 135, 244, 46,
+    175, 130, 23, 211, 117, 243, 187, 218, 103, 16, 144, 38, 130, 86, 149, 111, 37, 139, 212, 241,
+    21, 172, 236, 25, 128, 5, 114, 234, 213, 145, 70, 125, 208, 85, 22, 178, 42, 81, 24, 147, 10,
+    215, 31, 242, 122, 89, 30, 219, 129, 235, 69, 198, 20, 86, 179, 50, 142, 115, 169, 19, 97, 29,
+    82, 149, 203, 73, 163, 90, 177, 37, 81, 125, 49, 212, 240, 61, 171, 3, 220, 73, 191, 7, 84, 50,
+    153, 89, 196, 64, 222, 74, 167, 40, 101, 12, 171, 230, 6, 183, 140, 96, 200, 233, 169, 194, 69,
+    95, 139, 46, 186, 146, 248, 49, 101, 4, 212, 117, 60, 233, 2, 98, 254, 196, 46, 242, 216, 121,
+    190, 10, 102, 247, 27, 231, 54, 152, 8, 249, 176, 76, 115, 203, 100, 253, 50, 163, 239, 104,
+    179, 128, 217, 40, 135, 107, 154, 252, 92, 184, 134, 243, 87, 55, 108, 222, 61, 245, 4, 108,
+    46, 128, 249, 176, 206, 65, 230, 16, 116, 171, 193, 142, 81, 153, 184, 132, 204, 160, 33, 82,
+    153, 130, 56, 166, 233, 62, 213, 43, 138, 109, 199, 220, 95, 140, 33, 162, 13, 150, 32, 187,
+    119, 22, 135, 61, 232, 26, 109, 248, 9, 210, 32, 194, 17, 58, 204, 34, 119, 191, 151, 39, 166,
+    126, 73, 160, 224, 86, 29, 115, 0, 162, 102, 81, 205, 63, 23, 245, 42, 223, 107, 38, 70, 119,
+    221, 17, 107, 205, 4, 92, 34, 156, 119, 172, 191, 75, 14, 120, 182, 67, 224, 192, 91, 234, 57,
+    139, 216, 91, 225, 35, 149, 205, 73, 157, 180, 84, 53, 140, 120, 237, 79, 157, 218, 20, 251,
+    79, 207, 28, 189, 138, 15, 204, 155, 55, 218, 126, 183, 41, 224, 160, 92, 120, 168, 13, 250,
+    149, 231, 178, 59, 191, 237, 67, 182, 252, 136, 222, 87, 2, 58, 255, 158, 44, 237, 19, 109, 47,
+    127, 206, 80, 173, 8, 68, 195, 171, 95, 0, 190, 57, 100, 240, 170, 220, 96, 149, 1, 179, 50,
+    139, 98, 14, 117, 227, 54, 252, 101, 66, 185, 240, 78, 25, 251, 149, 6, 129, 236, 51, 195, 88,
+    201, 25, 95, 6, 136, 90, 161, 40, 146, 114, 71, 21, 200, 236, 148, 99, 215, 83, 133, 202, 148,
+    252, 2, 154, 28, 247, 104, 157, 122, 49, 255, 142, 121, 233, 26, 127, 11, 70, 31, 190, 228,
+    108, 71, 235, 175, 198, 148, 93, 177, 34, 212, 130, 38, 100, 141, 196, 53, 111, 70, 181, 31,
+    215, 139, 66, 127, 54, 217, 164, 245, 22, 122, 228, 15, 212, 49, 178, 109, 41, 124, 17, 181,
+    31, 170, 58, 89, 175, 72, 225, 118, 184, 42, 233, 18, 208, 82, 23, 219, 45, 151, 193, 210, 160,
+    247, 49, 124, 27, 211, 129, 40, 59, 241, 2, 74, 114, 153, 7, 173, 227, 12, 163, 90, 230, 204,
+    155, 81, 107, 0, 165, 240, 188, 114, 70, 206, 50, 186, 72, 102, 192, 153, 245, 78, 168, 228,
+    199, 64, 116, 246, 9, 213, 34, 106, 194, 55, 88, 137, 198, 62, 151, 115, 183, 66, 169, 110, 82,
+    36, 102, 136, 80, 158, 197, 92, 8, 155, 86, 120, 203, 164, 219, 239, 87, 201, 68, 109, 244, 30,
+    126, 15, 48, 253, 185, 226, 41, 97, 18, 155, 37, 131, 95, 240, 141, 221, 34, 91, 8, 138, 60,
+    29, 143, 86, 223, 154, 101, 128, 239, 145, 16, 163, 240, 12, 169, 100, 217, 38, 236, 96, 201,
+    5, 252, 58, 226, 187, 6, 238, 62, 168, 249, 220, 181, 30, 137, 48, 19, 60, 143, 44, 125, 209,
+    57, 187, 167, 220, 99, 123, 23, 151, 73, 172, 229, 83, 255, 181, 29, 167, 0, 58, 171, 127, 230,
+    184, 218, 97, 243, 14, 191, 36, 71, 184, 48, 205, 69, 219, 38, 128, 76, 244, 4, 136, 163, 20,
+    129, 216, 144, 178, 122, 25, 216, 108, 39, 135, 24, 102, 69, 209, 247, 98, 193, 111, 180, 254,
+    19, 154, 84, 139, 43, 76, 198, 145, 59, 209, 113, 200, 57, 141, 7, 202, 115, 77, 209, 110, 249,
+    67, 22, 115, 45, 163, 121, 173, 53, 136, 219, 158, 22, 119, 96, 140, 190, 108, 204, 48, 184,
+    87, 53, 245, 74, 43, 94, 27, 71, 169, 86, 151, 206, 183, 117, 51, 148, 15, 168, 80, 133, 235,
+    29, 90, 169, 221, 2, 233, 115, 243, 6, 175, 89, 246, 9, 42, 122, 221, 99, 62, 152, 231, 135,
+    40, 197, 144, 211, 82, 195, 2, 69, 207, 104, 250, 5, 92, 231, 171, 254, 1, 61, 226, 23, 157,
+    118, 222, 194, 107, 174, 152, 227, 188, 239, 138, 55, 254, 12, 77, 228, 197, 237, 125, 44, 224,
+    5, 158, 72, 200, 132, 51, 107, 193, 26, 156, 55, 223, 33, 130, 159, 86, 188, 29, 171, 238, 43,
+    19, 93, 182, 11, 101, 50, 156, 255, 140, 230, 35, 150, 74, 125, 201, 57, 81, 37, 152, 180, 85,
+    137, 250, 68, 30, 139, 7, 209, 22, 119, 46, 103, 16, 192, 123, 98, 45, 161, 1, 88, 189, 109,
+    65, 202, 40, 220, 115, 24, 246, 73, 173, 90, 210, 125, 101, 190, 71, 216, 110, 250, 148, 76,
+    126, 187, 214, 158, 69, 234, 167, 222, 25, 103, 43, 88, 189, 20, 214, 170, 32, 145, 188, 212,
+    123, 238, 45, 172, 11, 97, 178, 232, 80, 59, 250, 83, 203, 147, 215, 35, 172, 233, 212, 132,
+    67, 175, 31, 250, 138, 174, 104, 150, 58, 185, 144, 213, 38, 136, 65, 250, 11, 152, 238, 22,
+    139, 15, 53, 210, 3, 106, 52, 252, 115, 36, 131, 74, 186, 123, 217, 176, 115, 242, 59, 100,
+    235, 116, 9, 97, 65, 17, 103, 209, 121, 198, 40, 153, 111, 192, 130, 160, 8, 66, 242, 90, 62,
+    146, 21, 106, 241, 143, 55, 214, 11, 82, 244, 18, 232, 94, 8, 117, 231, 17, 164, 184, 41, 120,
+    52, 173, 69, 199, 162, 90, 244, 178, 78, 137, 6, 192, 92, 243, 12, 65, 161, 7, 77, 135, 159,
+    15, 196, 51, 247, 160, 223, 192, 145, 72, 230, 59, 131, 243, 23, 172, 33, 228, 105, 180, 121,
+    161, 6, 200, 81, 190, 27, 207, 119, 162, 100, 196, 51, 123, 177, 69, 195, 155, 50, 199, 107,
+    84, 226, 195, 97, 211, 34, 105, 228, 132, 39, 150, 26, 207, 168, 228, 52, 147, 205, 134, 239,
+    53, 222, 37, 204, 84, 173, 139, 76, 25, 127, 43, 251, 28, 155, 3, 216, 68, 93, 213, 53, 76,
+    201, 23, 48, 223, 108, 248, 128, 49, 159, 94, 19, 66, 235, 31, 169, 215, 139, 40, 253, 101, 77,
+    241, 142, 24, 59, 146, 1, 233, 128, 185, 10, 62, 198, 109, 234, 95, 63, 120, 16, 174, 103, 31,
+    89, 194, 146, 98, 250, 122, 41, 220, 106, 187, 167, 81, 113, 174, 88, 187, 105, 165, 14, 141,
+    239, 123, 152, 252, 135, 186, 69, 33, 176, 224, 73, 255, 178, 221, 142, 113, 78, 2, 90, 220,
+    18, 167, 129, 4, 218, 169, 125, 253, 76, 158, 50, 243, 81, 168, 224, 16, 182, 43, 157, 217, 84,
+    253, 46, 220, 180, 119, 26, 170, 8, 67, 236, 12, 207, 59, 242, 10, 202, 53, 237, 138, 44, 254,
+    203, 108, 182, 1, 42, 96, 78, 12, 214, 152, 95, 3, 142, 43, 129, 8, 191, 58, 250, 153, 118,
+    182, 65, 206, 37, 189, 68, 97, 205, 39, 179, 94, 214, 142, 30, 125, 91, 144, 71, 248, 134, 30,
+    200, 124, 157, 68, 1, 246, 76, 226, 131, 191, 157, 86, 144, 33, 130, 92, 225, 123, 21, 210, 79,
+    126, 30, 63, 87, 224, 166, 197, 229, 172, 120, 53, 243, 117, 198, 215, 107, 85, 37, 210, 174,
+    24, 229, 47, 104, 149, 234, 112, 46, 243, 13, 111, 198, 18, 163, 100, 189, 252, 46, 208, 117,
+    4, 186, 104, 61, 21, 194, 141, 105, 160, 51, 199, 32, 102, 52, 179, 114, 232, 156, 192, 36,
+    164, 65, 152, 11, 193, 169, 147, 207, 51, 116, 25, 61, 141, 29, 195, 75, 180, 23, 64, 164, 245,
+    148, 124, 97, 72, 193, 136, 248, 25, 83, 134, 180, 154, 80, 141, 235, 66, 41, 227, 3, 67, 177,
+    23, 163, 221, 79, 239, 170, 223, 81, 231, 36, 208, 122, 84, 150, 216, 255, 18, 202, 63, 2, 74,
+    105, 185, 249, 112, 218, 96, 45, 246, 11, 134, 239, 158, 107, 248, 92, 222, 13, 155, 102, 226,
+    27, 186, 55, 14, 238, 42, 161, 5, 76, 174, 217, 8, 229, 32, 211, 51, 172, 120, 86, 131, 205,
+    150, 109, 238, 95, 55, 149, 41, 130, 6, 114, 56, 174, 17, 241, 181, 4, 70, 136, 42, 94, 166,
+    212, 241, 144, 17, 85, 48, 173, 233, 70, 114, 186, 93, 70, 210, 5, 185, 42, 163, 132, 235, 45,
+    140, 76, 113, 217, 166, 198, 108, 221, 126, 199, 57, 152, 99, 66, 123, 183, 96, 7, 248, 28,
+    183, 52, 80, 214, 36, 132, 196, 15, 207, 87, 160, 189, 251, 94, 134, 44, 107, 229, 167, 116,
+    188, 238, 111, 38, 124, 54, 221, 198, 141, 4, 128, 27, 161, 222, 20, 173, 46, 131, 78, 208,
+    112, 61, 85, 203, 171, 242, 0, 134, 87, 62, 143, 28, 90, 239, 116, 35, 204, 165, 255, 19, 223,
+    136, 199, 106, 157, 241, 119, 16, 168, 64, 232, 112, 175, 245, 49, 27, 147, 204, 77, 217, 146,
+    58, 21, 209, 77, 12, 148, 83, 195, 164, 94, 35, 244, 77, 211, 192, 88, 41, 144, 232, 98, 244,
+    146, 18, 178, 253, 7, 122, 32, 93, 194, 45, 231, 17, 246, 171, 49, 182, 11, 228, 133, 46, 85,
+    114, 60, 161, 72, 209, 8, 44, 140, 191, 254, 85, 147, 31, 67, 103, 223, 124, 64, 10, 165, 29,
+    189, 96, 248, 152, 48, 225, 181, 27, 253, 10, 132, 179, 116, 162, 53, 148, 249, 63, 205, 116,
+    26, 195, 57, 224, 37, 100, 213, 185, 67, 144, 222, 106, 150, 187, 118, 73, 214, 106, 158, 81,
+    187, 24, 210, 144, 191, 39, 232, 127, 178, 96, 218, 58, 105, 2, 182, 212, 136, 8, 156, 196, 99,
+    232, 111, 242, 72, 127, 35, 179, 107, 133, 60, 215, 114, 71, 233, 56, 20, 226, 100, 13, 108,
+    136, 7, 188, 73, 155, 121, 88, 162, 133, 50, 153, 245, 17, 176, 63, 31, 84, 207, 4, 138, 39,
+    249, 62, 120, 238, 168, 0, 245, 92, 21, 60, 249, 78, 166, 28, 155, 226, 121, 51, 235, 185, 79,
+    40, 215, 177, 47, 151, 7, 223, 196, 81, 4, 243, 88, 159, 42, 188, 149, 207, 80, 183, 38, 217,
+    175, 235, 83, 166, 45, 218, 1, 240, 197, 23, 226, 83, 113, 44, 126, 211, 252, 173, 53, 155,
+    222, 87, 197, 15, 149, 98, 51, 74, 110, 174, 148, 114, 190, 19, 231, 133, 206, 39, 77, 164, 96,
+    28, 252, 133, 13, 83, 130, 201, 101, 159, 60, 140, 203, 172, 25, 130, 205, 95, 1, 108, 134,
+    252, 154, 123, 55, 28, 211, 128, 255, 106, 182, 75, 53, 109, 176, 4, 203, 234, 90, 159, 10,
+    121, 100, 236, 22, 113, 165, 224, 36, 200, 230, 131, 212, 43, 224, 6, 154, 48, 120, 63, 91,
+    186, 246, 15, 205, 117, 171, 61, 155, 238, 21, 67, 255, 28, 119, 234, 47, 114, 226, 75, 247,
+    31, 164, 237, 48, 15, 90, 71, 189, 151, 102, 64, 14, 148, 39, 129, 215, 147, 251, 71, 135, 188,
+    19, 74, 141, 201, 33, 181, 66, 190, 51, 133, 82, 176, 10, 159, 25, 193, 83, 241, 100, 212, 173,
+    243, 6, 110, 136, 65, 148, 43, 213, 98, 192, 113, 216, 172, 45, 183, 212, 20, 93, 154, 10, 178,
+    56, 122, 185, 69, 214, 172, 200, 225, 3, 247, 41, 185, 232, 93, 204, 172, 11, 86, 36, 164, 55,
+    102, 167, 229, 56, 242, 78, 129, 148, 254, 2, 105, 241, 66, 119, 89, 253, 57, 138, 34, 195, 79,
+    23, 142, 203, 169, 34, 229, 187, 79, 1, 231, 49, 31, 138, 88, 146, 105, 75, 163, 250, 68, 215,
+    102, 147, 227, 89, 18, 129, 103, 33, 140, 116, 84, 206, 137, 163, 30, 60, 245, 104, 221, 185,
+    118, 237, 26, 199, 36, 105, 170, 6, 224, 41, 94, 214, 157, 193, 19, 209, 145, 183, 106, 169,
+    125, 65, 237, 113, 42, 86, 59, 211, 97, 123, 248, 164, 132, 180, 75, 246, 15, 202, 232, 0, 134,
+    189, 33, 131, 195, 14, 44, 202, 153, 233, 57, 163, 237, 63, 177, 18, 110, 73, 217, 121, 156,
+    24, 139, 65, 9, 206, 140, 82, 125, 211, 150, 89, 197, 118, 171, 74, 39, 140, 55, 235, 35, 70,
+    11, 231, 159, 1, 148, 182, 222, 160, 254, 9, 151, 25, 54, 91, 22, 108, 205, 157, 59, 124, 40,
+    171, 58, 111, 223, 52, 165, 76, 255, 108, 29, 191, 85, 9, 186, 38, 132, 244, 50, 229, 4, 193,
+    79, 45, 197, 247, 92, 156, 50, 253, 182, 21, 48, 246, 63, 25, 204, 16, 245, 110, 87, 171, 127,
+    225, 203, 50, 185, 214, 93, 52, 14, 129, 108, 185, 71, 170, 194, 215, 143, 235, 9, 96, 228,
+    189, 79, 243, 208, 87, 8, 239, 94, 213, 126, 176, 72, 134, 246, 112, 218, 96, 210, 160, 89,
+    176, 135, 98, 173, 225, 124, 167, 39, 219, 103, 0, 71, 228, 135, 109, 186, 141, 229, 127, 161,
+    218, 186, 22, 101, 151, 84, 115, 32, 74, 137, 243, 199, 66, 32, 228, 137, 243, 114, 34, 66,
+    188, 50, 136, 166, 18, 103, 151, 29, 138, 181, 118, 22, 145, 59, 3, 226, 44, 158, 25, 144, 72,
+    11, 121, 31, 203, 42, 252, 14, 58, 101, 20, 76, 188, 120, 175, 153, 98, 196, 10, 164, 36, 99,
+    79, 49, 10, 69, 251, 46, 197, 17, 248, 104, 227, 19, 171, 103, 150, 205, 91, 47, 16, 80, 226,
+    159, 118, 253, 85, 38, 219, 121, 52, 195, 225, 64, 155, 204, 39, 240, 163, 196, 91, 206, 56,
+    175, 253, 194, 62, 239, 150, 68, 118, 201, 141, 240, 207, 133, 233, 31, 243, 56, 212, 40, 83,
+    216, 65, 242, 181, 203, 146, 115, 212, 162, 64, 139, 168, 54, 193, 126, 35, 81, 233, 3, 167,
+    220, 130, 200, 97, 3, 177, 24, 213, 185, 69, 249, 172, 12, 99, 42, 250, 80, 178, 103, 77, 117,
+    16, 127, 229, 105, 42, 136, 170, 107, 7, 231, 85, 159, 35, 80, 162, 9, 62, 145, 86, 23, 130,
+    169, 251, 119, 149, 2, 106, 31, 234, 89, 131, 8, 238, 81, 217, 5, 157, 62, 252, 184, 55, 127,
+    104, 61, 182, 37, 147, 234, 56, 107, 152, 127, 5, 146, 86, 236, 131, 168, 110, 6, 129, 199, 28,
+    151, 250, 168, 75, 1, 222, 86, 20, 219, 179, 134, 26, 189, 227, 111, 47, 196, 97, 180, 208,
+    114, 230, 68, 16, 189, 49, 221, 126, 166, 58, 178, 34, 188, 109, 38, 128,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_value_is_equally_common() {
+        let mut counts = [0usize; 256];
+        THRESHOLDS
+            .iter()
+            .for_each(|&value| counts[value as usize] += 1);
+        assert!(counts.iter().all(|&count| count == SIZE * SIZE / 256));
+    }
+}
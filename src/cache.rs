@@ -0,0 +1,253 @@
+// BSL 1.0 License
+
+use crate::Image;
+use alloc::vec::Vec;
+
+/// Identifies one version of a source image.
+///
+/// `id` tells source images apart, and `generation` should be bumped
+/// whenever the contents of the image change, so that images derived from
+/// the old contents are no longer used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ImageKey {
+    /// The source image.
+    pub id: u64,
+    /// The version of the source image.
+    pub generation: u64,
+}
+
+impl ImageKey {
+    /// Create a new key.
+    pub const fn new(id: u64, generation: u64) -> Self {
+        Self { id, generation }
+    }
+
+    /// The key for the next version of the same image.
+    pub const fn next_generation(self) -> Self {
+        Self {
+            id: self.id,
+            generation: self.generation.wrapping_add(1),
+        }
+    }
+}
+
+/// A cache of images derived from other images, such as thumbnails or
+/// converted copies.
+///
+/// Entries are keyed by the [`ImageKey`] of the source and by the
+/// operation that produced them, which can be any type that describes it,
+/// such as an enum of the operations an application performs along with
+/// their parameters.
+///
+/// The cache holds at most `capacity` bytes of images, counting
+/// `height * bytes_per_scanline` for each of them, and evicts the least
+/// recently used images to stay under it. Inserting an image for a newer
+/// generation of a source evicts the images of the older generations.
+///
+/// This requires the `alloc` feature.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{convert, Builder, Format, GeneralImage, ImageKey, TransformCache};
+///
+/// let source = GeneralImage::from_buffer(2, 2, Format::ARGB32, vec![0xFFu8; 16]);
+/// let key = ImageKey::new(1, 0);
+/// let mut cache = TransformCache::new(1024);
+///
+/// let mut conversions = 0;
+/// for _ in 0..3 {
+///     cache.get_or_insert_with(key, Format::RGB24, || {
+///         conversions += 1;
+///         let mut copy = Builder::from_image(&source).finish();
+///         let mut rgb = GeneralImage::from_buffer(2, 2, Format::RGB24, vec![0u8; 12]);
+///         convert(&mut copy, &mut rgb);
+///         rgb
+///     });
+/// }
+/// assert_eq!(conversions, 1);
+/// ```
+///
+/// [`ImageKey`]: crate::ImageKey
+#[derive(Debug)]
+pub struct TransformCache<Op, Img> {
+    /// The entries, from the least to the most recently used.
+    entries: Vec<Entry<Op, Img>>,
+    capacity: usize,
+    used: usize,
+}
+
+#[derive(Debug)]
+struct Entry<Op, Img> {
+    key: ImageKey,
+    op: Op,
+    image: Img,
+    bytes: usize,
+}
+
+impl<Op: PartialEq, Img: Image> TransformCache<Op, Img> {
+    /// Create an empty cache that holds up to `capacity` bytes of images.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity,
+            used: 0,
+        }
+    }
+
+    /// Get the image that `op` derived from the source, if it is cached.
+    ///
+    /// This marks the image as the most recently used.
+    pub fn get(&mut self, key: ImageKey, op: &Op) -> Option<&Img> {
+        let index = self.position(key, op)?;
+        Some(&self.touch(index).image)
+    }
+
+    /// Get the image that `op` derived from the source, creating it with
+    /// `f` if it isn't cached.
+    pub fn get_or_insert_with(&mut self, key: ImageKey, op: Op, f: impl FnOnce() -> Img) -> &Img {
+        match self.position(key, &op) {
+            Some(index) => &self.touch(index).image,
+            None => self.insert(key, op, f()),
+        }
+    }
+
+    /// Cache the image that `op` derived from the source, replacing any
+    /// image that was cached for the same source and operation.
+    ///
+    /// Images of older generations of the source are evicted, as are the
+    /// least recently used images until the cache fits in its capacity.
+    /// The new image is always kept, even if it is larger than the
+    /// capacity on its own.
+    pub fn insert(&mut self, key: ImageKey, op: Op, image: Img) -> &Img {
+        let mut used = self.used;
+        self.entries.retain(|entry| {
+            let stale = entry.key.id == key.id
+                && (entry.key.generation < key.generation
+                    || (entry.key.generation == key.generation && entry.op == op));
+            if stale {
+                used -= entry.bytes;
+            }
+            !stale
+        });
+        self.used = used;
+
+        let bytes = image.height().saturating_mul(image.bytes_per_scanline());
+        self.used = self.used.saturating_add(bytes);
+        self.entries.push(Entry {
+            key,
+            op,
+            image,
+            bytes,
+        });
+        self.shrink_to(self.capacity);
+
+        &self.entries[self.entries.len() - 1].image
+    }
+
+    /// Remove every image derived from the source image with the given
+    /// id.
+    pub fn invalidate(&mut self, id: u64) {
+        let mut used = self.used;
+        self.entries.retain(|entry| {
+            if entry.key.id == id {
+                used -= entry.bytes;
+            }
+            entry.key.id != id
+        });
+        self.used = used;
+    }
+
+    /// Remove every image.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    /// Change the number of bytes that the cache can hold, evicting the
+    /// least recently used images until it fits.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.shrink_to(capacity);
+    }
+
+    /// The number of bytes that the cache can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of bytes of the cached images.
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    /// The number of cached images.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no images are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, key: ImageKey, op: &Op) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.key == key && entry.op == *op)
+    }
+
+    /// Move an entry to the most recently used position.
+    fn touch(&mut self, index: usize) -> &Entry<Op, Img> {
+        let entry = self.entries.remove(index);
+        self.entries.push(entry);
+        &self.entries[self.entries.len() - 1]
+    }
+
+    /// Evict the least recently used entries until the cache holds at
+    /// most `capacity` bytes, keeping the most recent entry.
+    fn shrink_to(&mut self, capacity: usize) {
+        let mut evicted = 0;
+        while self.used > capacity && evicted + 1 < self.entries.len() {
+            self.used -= self.entries[evicted].bytes;
+            evicted += 1;
+        }
+        self.entries.drain(..evicted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, GeneralImage};
+    use alloc::vec;
+
+    fn image(width: usize) -> GeneralImage<Vec<u8>> {
+        GeneralImage::from_buffer(width, 1, Format::A8, vec![0u8; width])
+    }
+
+    #[test]
+    fn eviction() {
+        let mut cache = TransformCache::new(10);
+        let (a, b) = (ImageKey::new(1, 0), ImageKey::new(2, 0));
+        cache.insert(a, "thumbnail", image(4));
+        cache.insert(b, "thumbnail", image(4));
+        assert!(cache.get(a, &"thumbnail").is_some());
+
+        // b is the least recently used
+        cache.insert(a, "gray", image(4));
+        assert!(cache.get(b, &"thumbnail").is_none());
+        assert_eq!((cache.len(), cache.used_bytes()), (2, 8));
+
+        // newer generations replace older ones
+        cache.insert(a.next_generation(), "gray", image(2));
+        assert!(cache.get(a, &"thumbnail").is_none());
+        assert_eq!((cache.len(), cache.used_bytes()), (1, 2));
+
+        // oversized images are still kept
+        cache.insert(b, "thumbnail", image(20));
+        assert_eq!((cache.len(), cache.used_bytes()), (1, 20));
+        cache.invalidate(2);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the formats of cairo image surfaces,
+//! for drawing into the data of `cairo_image_surface_create_for_data`.
+//!
+//! Cairo stores pixels as native-endian quanta, so `CAIRO_FORMAT_ARGB32`
+//! keeps blue in the lowest byte and is [`Format::BGRA32`] with
+//! premultiplied alpha. Its scanlines are padded to a multiple of four
+//! bytes, which [`stride_for_width`] works out the same way that
+//! `cairo_format_stride_for_width` does.
+//!
+//! `CAIRO_FORMAT_RGBA128F` stores red first, while the float formats of
+//! `genimage` store alpha first, so it has no format.
+//!
+//! This requires the `cairo` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::{cairo, AlphaMode, Format, Image};
+//!
+//! let mut data = vec![0u8; cairo::stride_for_width(cairo::CAIRO_FORMAT_A1, 50).unwrap() * 20];
+//! let image = cairo::builder(50, 20, cairo::CAIRO_FORMAT_A1, &mut data[..])
+//!     .unwrap()
+//!     .finish();
+//! assert_eq!(image.bytes_per_scanline(), 8);
+//!
+//! let premultiplied = Format::BGRA32.with_alpha_mode(AlphaMode::Premultiplied);
+//! assert_eq!(premultiplied.to_cairo_format(), Some(cairo::CAIRO_FORMAT_ARGB32));
+//! ```
+//!
+//! [`Format::BGRA32`]: crate::Format::BGRA32
+//! [`stride_for_width`]: crate::cairo::stride_for_width
+
+use crate::{AlphaMode, BitOrder, Builder, Channel, ColorType, Endianness, Error, Format};
+
+/// A format that cairo doesn't know about.
+pub const CAIRO_FORMAT_INVALID: i32 = -1;
+/// [`Format::BGRA32`](crate::Format::BGRA32), premultiplied.
+pub const CAIRO_FORMAT_ARGB32: i32 = 0;
+/// [`Format::XBGR32`](crate::Format::XBGR32).
+pub const CAIRO_FORMAT_RGB24: i32 = 1;
+/// [`Format::A8`](crate::Format::A8).
+pub const CAIRO_FORMAT_A8: i32 = 2;
+/// [`Format::A1`](crate::Format::A1).
+pub const CAIRO_FORMAT_A1: i32 = 3;
+/// [`Format::RGB565`](crate::Format::RGB565).
+pub const CAIRO_FORMAT_RGB16_565: i32 = 4;
+/// [`Format::XRGB2101010`](crate::Format::XRGB2101010).
+pub const CAIRO_FORMAT_RGB30: i32 = 5;
+/// [`Format::RGB_F32`](crate::Format::RGB_F32).
+pub const CAIRO_FORMAT_RGB96F: i32 = 6;
+/// Red, green, blue and alpha as floats, which has no format.
+pub const CAIRO_FORMAT_RGBA128F: i32 = 7;
+
+/// Every cairo format with a format.
+const FORMATS: &[(i32, Format)] = &[
+    (CAIRO_FORMAT_ARGB32, Format::BGRA32),
+    (CAIRO_FORMAT_RGB24, Format::XBGR32),
+    (CAIRO_FORMAT_A8, Format::A8),
+    (CAIRO_FORMAT_A1, Format::A1),
+    (CAIRO_FORMAT_RGB16_565, Format::RGB565),
+    (CAIRO_FORMAT_RGB30, Format::XRGB2101010),
+    (CAIRO_FORMAT_RGB96F, Format::RGB_F32),
+];
+
+/// Formats that have the same layout as a format in [`FORMATS`], and so
+/// map to its code, but that the code doesn't map back to.
+const ALIASES: &[(Format, Format)] = &[(Format::BGRX32, Format::XBGR32)];
+
+/// The order of the pixels in each byte of `CAIRO_FORMAT_A1` surfaces,
+/// which follows the byte order of the machine.
+const BIT_ORDER: BitOrder = match Endianness::NATIVE {
+    Endianness::Little => BitOrder::LsbFirst,
+    Endianness::Big => BitOrder::MsbFirst,
+};
+
+/// The number of bytes per scanline that cairo uses for surfaces with
+/// this format and width, or `None` if the format is unknown or the
+/// scanline is too large.
+///
+/// Scanlines are rounded up to a multiple of four bytes.
+pub fn stride_for_width(code: i32, width: usize) -> Option<usize> {
+    let (_, format) = FORMATS.iter().find(|&&(known, _)| known == code)?;
+    let bits = width.checked_mul(usize::from(format.bpp()))?;
+    let bytes = bits.checked_add(7)? / 8;
+    Some(bytes.checked_add(3)? / 4 * 4)
+}
+
+/// Start building an image over the data of a cairo image surface with
+/// this format, using the same stride that cairo does.
+///
+/// # Errors
+///
+/// Returns [`Error::Unsupported`] if there is no format for the cairo
+/// format, or [`Error::TooLarge`] if the stride doesn't fit in memory.
+///
+/// [`Error::Unsupported`]: crate::Error::Unsupported
+/// [`Error::TooLarge`]: crate::Error::TooLarge
+pub fn builder<Storage>(
+    width: usize,
+    height: usize,
+    code: i32,
+    data: Storage,
+) -> Result<Builder<Storage>, Error> {
+    let format = Format::from_cairo_format(code).ok_or(Error::Unsupported)?;
+    let stride = stride_for_width(code, width).ok_or(Error::TooLarge)?;
+    Builder::from_buffer(width, height, format, data)
+        .with_endianness(Endianness::NATIVE)
+        .with_bit_order(BIT_ORDER)
+        .try_with_bytes_per_scanline(stride)
+}
+
+impl Format {
+    /// The cairo format with the same layout as this format, if there is
+    /// one.
+    ///
+    /// Cairo always premultiplies alpha, so the alpha mode is ignored.
+    ///
+    /// This requires the `cairo` feature.
+    pub fn to_cairo_format(self) -> Option<i32> {
+        let format = self.with_alpha_mode(AlphaMode::Straight);
+        let format = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == format)
+            .map_or(format, |&(_, format)| format);
+
+        FORMATS
+            .iter()
+            .find(|&&(_, known)| known == format)
+            .map(|&(code, _)| code)
+    }
+
+    /// The format for a cairo format, if there is one.
+    ///
+    /// Formats with both color and alpha are premultiplied.
+    ///
+    /// This requires the `cairo` feature.
+    pub fn from_cairo_format(code: i32) -> Option<Format> {
+        let format = FORMATS
+            .iter()
+            .find(|&&(known, _)| known == code)
+            .map(|&(_, format)| format)?;
+
+        if format.channel_mask(Channel::Alpha) != 0 && format.color_type() != ColorType::Alpha {
+            Some(format.with_alpha_mode(AlphaMode::Premultiplied))
+        } else {
+            Some(format)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strides() {
+        assert_eq!(stride_for_width(CAIRO_FORMAT_ARGB32, 3), Some(12));
+        assert_eq!(stride_for_width(CAIRO_FORMAT_RGB16_565, 3), Some(8));
+        assert_eq!(stride_for_width(CAIRO_FORMAT_A8, 5), Some(8));
+        assert_eq!(stride_for_width(CAIRO_FORMAT_A1, 33), Some(8));
+        assert_eq!(stride_for_width(CAIRO_FORMAT_RGBA128F, 1), None);
+        assert_eq!(stride_for_width(CAIRO_FORMAT_ARGB32, usize::MAX), None);
+
+        for &(code, format) in FORMATS {
+            assert_eq!(format.to_cairo_format(), Some(code));
+            let back = Format::from_cairo_format(code).unwrap();
+            assert_eq!(back.with_alpha_mode(AlphaMode::Straight), format);
+        }
+        assert!(Format::from_cairo_format(CAIRO_FORMAT_ARGB32)
+            .unwrap()
+            .premultiplies());
+        assert_eq!(Format::BGRX32.to_cairo_format(), Some(CAIRO_FORMAT_RGB24));
+        assert_eq!(Format::from_cairo_format(CAIRO_FORMAT_INVALID), None);
+    }
+}
@@ -0,0 +1,317 @@
+// BSL 1.0 License
+
+//! A C interface to `genimage`, for graphics frameworks written in C and
+//! C++.
+//!
+//! Images are passed around as opaque [`GenimageImage`] handles that own
+//! their pixels, and formats as the `GENIMAGE_FORMAT_*` codes. The codes
+//! never change meaning between releases; new formats get new codes.
+//!
+//! This requires the `capi` feature.
+//!
+//! [`GenimageImage`]: crate::capi::GenimageImage
+
+#![allow(unsafe_code)]
+
+use crate::{convert, Builder, Format, GeneralImage, Image, Nothing};
+use alloc::boxed::Box;
+use core::{ptr, slice};
+
+macro_rules! format_codes {
+    ($($(#[$meta: meta])* $code_name: ident = $code: literal => $format: ident,)*) => {
+        $(
+            $(#[$meta])*
+            pub const $code_name: u32 = $code;
+        )*
+
+        /// Every format with a code.
+        const FORMATS: &[(u32, Format)] = &[$(($code, Format::$format),)*];
+    };
+}
+
+format_codes! {
+    /// [`Format::ARGB32`](crate::Format::ARGB32).
+    GENIMAGE_FORMAT_ARGB32 = 1 => ARGB32,
+    /// [`Format::XRGB32`](crate::Format::XRGB32).
+    GENIMAGE_FORMAT_XRGB32 = 2 => XRGB32,
+    /// [`Format::ABGR32`](crate::Format::ABGR32).
+    GENIMAGE_FORMAT_ABGR32 = 3 => ABGR32,
+    /// [`Format::XBGR32`](crate::Format::XBGR32).
+    GENIMAGE_FORMAT_XBGR32 = 4 => XBGR32,
+    /// [`Format::RGBA32`](crate::Format::RGBA32).
+    GENIMAGE_FORMAT_RGBA32 = 5 => RGBA32,
+    /// [`Format::RGBX32`](crate::Format::RGBX32).
+    GENIMAGE_FORMAT_RGBX32 = 6 => RGBX32,
+    /// [`Format::BGRA32`](crate::Format::BGRA32).
+    GENIMAGE_FORMAT_BGRA32 = 7 => BGRA32,
+    /// [`Format::BGRX32`](crate::Format::BGRX32).
+    GENIMAGE_FORMAT_BGRX32 = 8 => BGRX32,
+    /// [`Format::RGB24`](crate::Format::RGB24).
+    GENIMAGE_FORMAT_RGB24 = 9 => RGB24,
+    /// [`Format::BGR24`](crate::Format::BGR24).
+    GENIMAGE_FORMAT_BGR24 = 10 => BGR24,
+    /// [`Format::RGB565`](crate::Format::RGB565).
+    GENIMAGE_FORMAT_RGB565 = 11 => RGB565,
+    /// [`Format::RGB555`](crate::Format::RGB555).
+    GENIMAGE_FORMAT_RGB555 = 12 => RGB555,
+    /// [`Format::ARGB1555`](crate::Format::ARGB1555).
+    GENIMAGE_FORMAT_ARGB1555 = 13 => ARGB1555,
+    /// [`Format::ARGB16`](crate::Format::ARGB16).
+    GENIMAGE_FORMAT_ARGB16 = 14 => ARGB16,
+    /// [`Format::XRGB16`](crate::Format::XRGB16).
+    GENIMAGE_FORMAT_XRGB16 = 15 => XRGB16,
+    /// [`Format::ABGR16`](crate::Format::ABGR16).
+    GENIMAGE_FORMAT_ABGR16 = 16 => ABGR16,
+    /// [`Format::XBGR16`](crate::Format::XBGR16).
+    GENIMAGE_FORMAT_XBGR16 = 17 => XBGR16,
+    /// [`Format::A8`](crate::Format::A8).
+    GENIMAGE_FORMAT_A8 = 18 => A8,
+    /// [`Format::A4`](crate::Format::A4).
+    GENIMAGE_FORMAT_A4 = 19 => A4,
+    /// [`Format::A1`](crate::Format::A1).
+    GENIMAGE_FORMAT_A1 = 20 => A1,
+    /// [`Format::GRAY8`](crate::Format::GRAY8).
+    GENIMAGE_FORMAT_GRAY8 = 21 => GRAY8,
+    /// [`Format::GRAY16`](crate::Format::GRAY16).
+    GENIMAGE_FORMAT_GRAY16 = 22 => GRAY16,
+    /// [`Format::LA8`](crate::Format::LA8).
+    GENIMAGE_FORMAT_LA8 = 23 => LA8,
+    /// [`Format::RG8`](crate::Format::RG8).
+    GENIMAGE_FORMAT_RG8 = 24 => RG8,
+    /// [`Format::RG16`](crate::Format::RG16).
+    GENIMAGE_FORMAT_RG16 = 25 => RG16,
+    /// [`Format::RGB48`](crate::Format::RGB48).
+    GENIMAGE_FORMAT_RGB48 = 26 => RGB48,
+    /// [`Format::BGR48`](crate::Format::BGR48).
+    GENIMAGE_FORMAT_BGR48 = 27 => BGR48,
+    /// [`Format::RGBA64`](crate::Format::RGBA64).
+    GENIMAGE_FORMAT_RGBA64 = 28 => RGBA64,
+    /// [`Format::ABGR64`](crate::Format::ABGR64).
+    GENIMAGE_FORMAT_ABGR64 = 29 => ABGR64,
+    /// [`Format::ARGB_F32`](crate::Format::ARGB_F32).
+    GENIMAGE_FORMAT_ARGB_F32 = 30 => ARGB_F32,
+    /// [`Format::RGB_F32`](crate::Format::RGB_F32).
+    GENIMAGE_FORMAT_RGB_F32 = 31 => RGB_F32,
+    /// [`Format::ARGB_F16`](crate::Format::ARGB_F16).
+    GENIMAGE_FORMAT_ARGB_F16 = 32 => ARGB_F16,
+    /// [`Format::RGB_F16`](crate::Format::RGB_F16).
+    GENIMAGE_FORMAT_RGB_F16 = 33 => RGB_F16,
+    /// [`Format::CMYK32`](crate::Format::CMYK32).
+    GENIMAGE_FORMAT_CMYK32 = 34 => CMYK32,
+    /// [`Format::INDEXED8`](crate::Format::INDEXED8).
+    GENIMAGE_FORMAT_INDEXED8 = 35 => INDEXED8,
+    /// [`Format::INDEXED4`](crate::Format::INDEXED4).
+    GENIMAGE_FORMAT_INDEXED4 = 36 => INDEXED4,
+    /// [`Format::INDEXED1`](crate::Format::INDEXED1).
+    GENIMAGE_FORMAT_INDEXED1 = 37 => INDEXED1,
+    /// [`Format::YUYV`](crate::Format::YUYV).
+    GENIMAGE_FORMAT_YUYV = 38 => YUYV,
+    /// [`Format::UYVY`](crate::Format::UYVY).
+    GENIMAGE_FORMAT_UYVY = 39 => UYVY,
+    /// [`Format::YUYV_BT709`](crate::Format::YUYV_BT709).
+    GENIMAGE_FORMAT_YUYV_BT709 = 40 => YUYV_BT709,
+    /// [`Format::UYVY_BT709`](crate::Format::UYVY_BT709).
+    GENIMAGE_FORMAT_UYVY_BT709 = 41 => UYVY_BT709,
+    /// [`Format::ARGB32_PREMUL`](crate::Format::ARGB32_PREMUL).
+    GENIMAGE_FORMAT_ARGB32_PREMUL = 42 => ARGB32_PREMUL,
+}
+
+/// The format with the given code, if there is one.
+pub fn format_from_code(code: u32) -> Option<Format> {
+    FORMATS
+        .iter()
+        .find(|&&(other, _)| other == code)
+        .map(|&(_, format)| format)
+}
+
+/// The code of a format, if it has one.
+pub fn format_to_code(format: Format) -> Option<u32> {
+    FORMATS
+        .iter()
+        .find(|&&(_, other)| other == format)
+        .map(|&(code, _)| code)
+}
+
+/// An image that owns its pixels, in native endianness.
+///
+/// This is opaque to C, which only ever sees pointers to it.
+pub struct GenimageImage(GeneralImage<Nothing>);
+
+/// Create a zeroed image, or return null if the format code is unknown or
+/// the image is too large.
+///
+/// The image must be freed with [`genimage_image_destroy`].
+///
+/// [`genimage_image_destroy`]: crate::capi::genimage_image_destroy
+#[no_mangle]
+pub extern "C" fn genimage_image_create(
+    width: usize,
+    height: usize,
+    format: u32,
+) -> *mut GenimageImage {
+    let format = match format_from_code(format) {
+        Some(format) => format,
+        None => return ptr::null_mut(),
+    };
+
+    match Builder::owned(width, height, format).try_finish() {
+        Ok(image) => Box::into_raw(Box::new(GenimageImage(image))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Free an image. Null is ignored.
+///
+/// # Safety
+///
+/// `image` must be null or come from [`genimage_image_create`], and must
+/// not be used afterwards.
+///
+/// [`genimage_image_create`]: crate::capi::genimage_image_create
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_destroy(image: *mut GenimageImage) {
+    if !image.is_null() {
+        drop(Box::from_raw(image));
+    }
+}
+
+/// The width of an image, in pixels.
+///
+/// # Safety
+///
+/// `image` must be a live image.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_width(image: *const GenimageImage) -> usize {
+    (*image).0.width()
+}
+
+/// The height of an image, in rows.
+///
+/// # Safety
+///
+/// `image` must be a live image.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_height(image: *const GenimageImage) -> usize {
+    (*image).0.height()
+}
+
+/// The number of bytes in a scanline of an image.
+///
+/// # Safety
+///
+/// `image` must be a live image.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_stride(image: *const GenimageImage) -> usize {
+    (*image).0.bytes_per_scanline()
+}
+
+/// The format code of an image.
+///
+/// # Safety
+///
+/// `image` must be a live image.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_format(image: *const GenimageImage) -> u32 {
+    // images can only be created with a format that has a code
+    format_to_code((*image).0.format()).unwrap_or(0)
+}
+
+/// Copy up to `len` bytes of the scanline at `y`, starting at pixel `x`,
+/// into `out`. Returns the number of bytes copied.
+///
+/// # Safety
+///
+/// `image` must be a live image, and `out` must be valid for writing
+/// `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_get_scanline(
+    image: *const GenimageImage,
+    x: usize,
+    y: usize,
+    out: *mut u8,
+    len: usize,
+) -> usize {
+    if out.is_null() {
+        return 0;
+    }
+    (*image)
+        .0
+        .scanline(x, y, slice::from_raw_parts_mut(out, len))
+}
+
+/// Copy up to `len` bytes from `data` into the scanline at `y`, starting
+/// at pixel `x`. Returns the number of bytes copied.
+///
+/// # Safety
+///
+/// `image` must be a live image, and `data` must be valid for reading
+/// `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_image_set_scanline(
+    image: *mut GenimageImage,
+    x: usize,
+    y: usize,
+    data: *const u8,
+    len: usize,
+) -> usize {
+    if data.is_null() {
+        return 0;
+    }
+    (*image)
+        .0
+        .set_scanline(x, y, slice::from_raw_parts(data, len))
+}
+
+/// Copy the pixels of `src` into `dst`, converting them to the format of
+/// `dst`. Only the area where both images overlap is copied.
+///
+/// # Safety
+///
+/// `src` and `dst` must be live images, and must not be the same image.
+#[no_mangle]
+pub unsafe extern "C" fn genimage_convert(src: *const GenimageImage, dst: *mut GenimageImage) {
+    convert(&(*src).0, &mut (*dst).0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifecycle() {
+        assert!(genimage_image_create(1, 1, 0).is_null());
+        assert!(genimage_image_create(usize::MAX, 2, GENIMAGE_FORMAT_RGB24).is_null());
+
+        for &(code, format) in FORMATS {
+            assert_eq!(format_to_code(format), Some(code));
+        }
+
+        unsafe {
+            let src = genimage_image_create(2, 1, GENIMAGE_FORMAT_RGB24);
+            let dst = genimage_image_create(2, 1, GENIMAGE_FORMAT_XBGR32);
+            assert_eq!(genimage_image_stride(src), 6);
+            assert_eq!(genimage_image_format(dst), GENIMAGE_FORMAT_XBGR32);
+
+            let row = [1u8, 2, 3, 4, 5, 6];
+            assert_eq!(
+                genimage_image_set_scanline(src, 0, 0, row.as_ptr(), row.len()),
+                6
+            );
+            genimage_convert(src, dst);
+
+            let mut out = [0u8; 8];
+            assert_eq!(
+                genimage_image_get_scanline(dst, 0, 0, out.as_mut_ptr(), out.len()),
+                8
+            );
+            assert_eq!(
+                (*dst).0.pixel(1, 0).to_rgba(),
+                (*src).0.pixel(1, 0).to_rgba()
+            );
+
+            genimage_image_destroy(src);
+            genimage_image_destroy(dst);
+            genimage_image_destroy(ptr::null_mut());
+        }
+    }
+}
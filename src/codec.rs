@@ -0,0 +1,609 @@
+// BSL 1.0 License
+
+//! Reading and writing the channels of pixels packed into a row of bytes.
+//!
+//! Most bulk operations need to look at individual channels without
+//! going through a full `Pixel` for every value. The `Codec` knows how a
+//! given format and endianness lay pixels out in memory, and can decode
+//! them into normalized components or encode them back.
+
+use crate::{
+    format::ChannelInfo, half_float, BitOrder, Channel, ColorType, Endianness, Format, Palette,
+    TransferFunction, YcbcrMatrix, MAX_BYTES_PER_PIXEL,
+};
+use tinyvec::ArrayVec;
+
+/// The components of a pixel, normalized to the range `0.0..=1.0`.
+///
+/// The components are indexed by `Channel as usize`, so they are in
+/// the order `[red, green, blue, alpha]`.
+pub(crate) type Components = [f32; 4];
+
+/// Decodes and encodes the pixels of a single format and endianness.
+#[derive(Debug, Clone)]
+pub(crate) struct Codec {
+    format: Format,
+    endianness: Endianness,
+    /// The order of pixels within a byte, for sub-byte formats.
+    bit_order: BitOrder,
+    /// How the color channels map to light.
+    transfer_function: TransferFunction,
+    channels: ArrayVec<[ChannelInfo; 4]>,
+    /// Whether the red channel holds a gray level.
+    gray: bool,
+    /// Whether the channels hold cyan, magenta, yellow and black.
+    cmyk: bool,
+    /// The matrix for formats where the red and green channels hold luma
+    /// and chroma shared by pairs of pixels.
+    ycbcr: Option<YcbcrMatrix>,
+    /// Whether the color channels are premultiplied by alpha.
+    premultiplied: bool,
+}
+
+impl Codec {
+    /// Create a new codec for the given format and endianness.
+    pub(crate) fn new(format: Format, endianness: Endianness) -> Self {
+        Self {
+            format,
+            endianness,
+            bit_order: BitOrder::LsbFirst,
+            transfer_function: TransferFunction::Srgb,
+            channels: format.channels().collect(),
+            gray: format.color_type().is_gray(),
+            cmyk: format.color_type().is_cmyk(),
+            ycbcr: format.color_type().ycbcr_matrix(),
+            premultiplied: format.premultiplies(),
+        }
+    }
+
+    /// Use a different order for the pixels within each byte.
+    pub(crate) fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Use a different transfer function for the color channels.
+    ///
+    /// Components are still read and written as they are stored; this is
+    /// only used to re-encode them for another codec.
+    pub(crate) fn with_transfer_function(mut self, transfer_function: TransferFunction) -> Self {
+        self.transfer_function = transfer_function;
+        self
+    }
+
+    /// The format that this codec handles.
+    pub(crate) fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The endianness that this codec handles.
+    pub(crate) fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    /// The order of pixels within a byte that this codec handles.
+    pub(crate) fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// The transfer function of the color channels.
+    pub(crate) fn transfer_function(&self) -> TransferFunction {
+        self.transfer_function
+    }
+
+    /// Information about the given channel, if the format has it.
+    pub(crate) fn channel(&self, channel: Channel) -> Option<ChannelInfo> {
+        self.channels
+            .iter()
+            .find(|info| info.channel == channel)
+            .copied()
+    }
+
+    /// The index of the byte within a pixel that holds the given
+    /// channel, if the channel is exactly one byte wide and aligned
+    /// to a byte boundary.
+    ///
+    /// This is used to find fast paths for 8-bit channels.
+    pub(crate) fn byte_offset(&self, channel: Channel) -> Option<usize> {
+        if self.format.involves_float()
+            || self.format.subbyte()
+            || self.cmyk
+            || self.ycbcr.is_some()
+            || self.premultiplied
+        {
+            return None;
+        }
+
+        let info = self.channel(channel)?;
+        if info.bits != 8 || info.shift % 8 != 0 {
+            return None;
+        }
+
+        let bytes = self.format.bytes() as usize;
+        let index = (info.shift / 8) as usize;
+        match self.endianness {
+            Endianness::Little => Some(index),
+            Endianness::Big => Some(bytes - 1 - index),
+        }
+    }
+
+    /// The byte offset and bit index of the `i`th pixel in a row.
+    fn locate(&self, i: usize) -> (usize, u32) {
+        let bpp = self.format.bpp();
+        let index = self.bit_order.index_in_byte(bpp, i);
+        (i * bpp as usize / 8, index as u32)
+    }
+
+    /// Read the integer quantum holding the `i`th pixel.
+    pub(crate) fn read_quantum(&self, row: &[u8], i: usize) -> u64 {
+        let (offset, index) = self.locate(i);
+        let bytes = &row[offset..offset + self.format.bytes() as usize];
+
+        if self.format.subbyte() {
+            return (bytes[0] >> index) as u64 & low_bits(self.format.bpp());
+        }
+
+        self.endianness.make_u64(bytes)
+    }
+
+    /// Write the integer quantum holding the `i`th pixel.
+    pub(crate) fn write_quantum(&self, row: &mut [u8], i: usize, mut quantum: u64) {
+        let (offset, index) = self.locate(i);
+        let bytes = &mut row[offset..offset + self.format.bytes() as usize];
+
+        if self.format.subbyte() {
+            let mask = (low_bits(self.format.bpp()) as u8) << index;
+            bytes[0] = (bytes[0] & !mask) | (((quantum as u8) << index) & mask);
+            return;
+        }
+
+        let mut store = |byte: &mut u8| {
+            *byte = quantum as u8;
+            quantum >>= 8;
+        };
+
+        match self.endianness {
+            Endianness::Little => bytes.iter_mut().for_each(&mut store),
+            Endianness::Big => bytes.iter_mut().rev().for_each(&mut store),
+        }
+    }
+
+    /// Copy the `i`th pixel of `src` over the `j`th pixel of `dst`.
+    ///
+    /// For formats smaller than a byte, the other pixels that share the
+    /// byte in `dst` are kept.
+    pub(crate) fn copy_pixel(&self, src: &[u8], i: usize, dst: &mut [u8], j: usize) {
+        if self.format.subbyte() {
+            self.write_quantum(dst, j, self.read_quantum(src, i));
+        } else {
+            let bytes = self.format.bytes() as usize;
+            dst[j * bytes..(j + 1) * bytes].copy_from_slice(&src[i * bytes..(i + 1) * bytes]);
+        }
+    }
+
+    /// Fill `pattern` with copies of the given quantum.
+    ///
+    /// `pattern` should be a whole number of pixels long. This is used to
+    /// build byte patterns that can be applied to a row without decoding
+    /// it, for instance to mask certain channels.
+    pub(crate) fn fill_quantum(&self, pattern: &mut [u8], quantum: u64) {
+        let count = pattern.len() * 8 / self.format.bpp() as usize;
+        for i in 0..count {
+            self.write_quantum(pattern, i, quantum);
+        }
+    }
+
+    /// Encode a color into the smallest run of bytes that can be repeated
+    /// to fill a row with it.
+    ///
+    /// This is one pixel for formats of at least one byte, one byte
+    /// containing several pixels for sub-byte formats, or a pair of pixels
+    /// for YCbCr formats. Returns the buffer along with the length of the
+    /// pattern.
+    pub(crate) fn pattern(&self, components: Components) -> ([u8; MAX_BYTES_PER_PIXEL], usize) {
+        let mut pattern = [0u8; MAX_BYTES_PER_PIXEL];
+        let mut len = self.format.bytes() as usize;
+        if self.ycbcr.is_some() {
+            len *= 2;
+        }
+        let count = len * 8 / self.format.bpp() as usize;
+
+        for i in 0..count {
+            self.write(&mut pattern[..len], i, components);
+        }
+
+        (pattern, len)
+    }
+
+    /// Read the raw value of every channel of the `i`th pixel.
+    ///
+    /// For float formats, these are the bits of the floats. Channels
+    /// that the format does not have are zero.
+    pub(crate) fn read_raw(&self, row: &[u8], i: usize) -> [u64; 4] {
+        let mut raw = [0u64; 4];
+
+        if self.format.involves_float() {
+            let (offset, _) = self.locate(i);
+            for info in &self.channels {
+                let start = offset + (info.shift / 8) as usize;
+                let bytes = &row[start..start + (info.bits / 8) as usize];
+                raw[info.channel as usize] = self.endianness.make_u64(bytes);
+            }
+        } else {
+            let quantum = self.read_quantum(row, i);
+            for info in &self.channels {
+                raw[info.channel as usize] = (quantum >> info.shift) & low_bits(info.bits);
+            }
+        }
+
+        raw
+    }
+
+    /// Write the raw value of every channel of the `i`th pixel.
+    ///
+    /// Any bits in the pixel that do not belong to a channel are cleared.
+    pub(crate) fn write_raw(&self, row: &mut [u8], i: usize, raw: [u64; 4]) {
+        if self.format.involves_float() {
+            let (offset, _) = self.locate(i);
+            for info in &self.channels {
+                let start = offset + (info.shift / 8) as usize;
+                let len = (info.bits / 8) as usize;
+                let value = raw[info.channel as usize];
+                let bytes = &mut row[start..start + len];
+                match self.endianness {
+                    Endianness::Little => bytes.copy_from_slice(&value.to_le_bytes()[..len]),
+                    Endianness::Big => bytes.copy_from_slice(&value.to_be_bytes()[8 - len..]),
+                }
+            }
+        } else {
+            let quantum = self.channels.iter().fold(0, |quantum, info| {
+                quantum | ((raw[info.channel as usize] & low_bits(info.bits)) << info.shift)
+            });
+            self.write_quantum(row, i, quantum);
+        }
+    }
+
+    /// Read the normalized components of the `i`th pixel.
+    ///
+    /// Color channels that the format does not have read as zero, and a
+    /// missing alpha channel reads as fully opaque. Premultiplied colors
+    /// are divided by alpha.
+    pub(crate) fn read(&self, row: &[u8], i: usize) -> Components {
+        let raw = self.read_raw(row, i);
+        let mut components = [0.0, 0.0, 0.0, 1.0];
+
+        for info in &self.channels {
+            let index = info.channel as usize;
+            components[index] = if self.format.involves_float() {
+                float_from_raw(raw[index], info.bits)
+            } else {
+                raw[index] as f32 / low_bits(info.bits) as f32
+            };
+        }
+
+        if self.gray {
+            let gray = components[Channel::Red as usize];
+            components[Channel::Green as usize] = gray;
+            components[Channel::Blue as usize] = gray;
+        }
+        if self.cmyk {
+            components = cmyk_to_rgb(components);
+        }
+        if let Some(matrix) = self.ycbcr {
+            let pair = i - i % 2;
+            let blue = self.chroma(row, pair);
+            let red = self.chroma(row, pair + 1);
+            components = ycbcr_to_rgb(matrix, [components[Channel::Red as usize], blue, red]);
+        }
+        if self.premultiplied {
+            components = unpremultiply(components);
+        }
+
+        components
+    }
+
+    /// Read the normalized components of the `i`th pixel, looking indices
+    /// up in `palette`.
+    ///
+    /// Indices past the end of the palette read as transparent black.
+    /// Other formats, or indices without a palette, are read as in
+    /// [`read`].
+    ///
+    /// [`read`]: Codec::read
+    pub(crate) fn read_resolved(
+        &self,
+        row: &[u8],
+        i: usize,
+        palette: Option<&Palette>,
+    ) -> Components {
+        match palette {
+            Some(palette) if self.format.color_type() == ColorType::Indexed => palette
+                .get(self.read_quantum(row, i) as usize)
+                .unwrap_or_default()
+                .components(),
+            _ => self.read(row, i),
+        }
+    }
+
+    /// The normalized chroma sample of the `i`th pixel of a YCbCr format,
+    /// or neutral chroma if the row ends before it.
+    fn chroma(&self, row: &[u8], i: usize) -> f32 {
+        let bits = self.format.green_bits();
+        if (i + 1) * self.format.bytes() as usize > row.len() {
+            return NEUTRAL_CHROMA;
+        }
+
+        self.read_raw(row, i)[Channel::Green as usize] as f32 / low_bits(bits) as f32
+    }
+
+    /// Write the normalized components of the `i`th pixel.
+    ///
+    /// Components are clamped to `0.0..=1.0` for integer formats.
+    pub(crate) fn write(&self, row: &mut [u8], i: usize, components: Components) {
+        self.write_dithered(row, i, components, [0.5; 4]);
+    }
+
+    /// Write the normalized components of the `i`th pixel, adding the
+    /// threshold for each channel before rounding down.
+    ///
+    /// Thresholds are expected to be in `0.0..1.0`, where `0.5` rounds to
+    /// the nearest value. They don't affect float formats.
+    pub(crate) fn write_dithered(
+        &self,
+        row: &mut [u8],
+        i: usize,
+        mut components: Components,
+        thresholds: Components,
+    ) {
+        let mut raw = [0u64; 4];
+
+        if self.premultiplied {
+            components = premultiply(components);
+        }
+        if self.gray {
+            components[Channel::Red as usize] = luma(components);
+        }
+        if self.cmyk {
+            components = rgb_to_cmyk(components);
+        }
+        if let Some(matrix) = self.ycbcr {
+            let [luma, blue, red] = rgb_to_ycbcr(matrix, components);
+            components[Channel::Red as usize] = luma;
+            components[Channel::Green as usize] = if i % 2 == 0 { blue } else { red };
+        }
+
+        for info in &self.channels {
+            let index = info.channel as usize;
+            raw[index] = if self.format.involves_float() {
+                float_to_raw(components[index], info.bits)
+            } else {
+                quantize_with_threshold(components[index], info.bits, thresholds[index])
+            };
+        }
+
+        self.write_raw(row, i, raw);
+    }
+}
+
+/// The mask for the lowest `bits` bits of a `u64`.
+pub(crate) const fn low_bits(bits: u8) -> u64 {
+    if bits >= 64 {
+        !0
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Convert cyan, magenta, yellow and black, stored in the slots of red,
+/// green, blue and alpha, to an opaque color.
+///
+/// This is the naive conversion, and is where a color profile would be
+/// applied.
+pub(crate) fn cmyk_to_rgb(cmyk: Components) -> Components {
+    let [cyan, magenta, yellow, black] = cmyk;
+    let white = 1.0 - black;
+    [
+        (1.0 - cyan) * white,
+        (1.0 - magenta) * white,
+        (1.0 - yellow) * white,
+        1.0,
+    ]
+}
+
+/// Convert a color to cyan, magenta, yellow and black, using as much
+/// black as possible. Alpha is ignored.
+///
+/// This is the inverse of [`cmyk_to_rgb`].
+pub(crate) fn rgb_to_cmyk(components: Components) -> Components {
+    let red = clamp_unit(components[Channel::Red as usize]);
+    let green = clamp_unit(components[Channel::Green as usize]);
+    let blue = clamp_unit(components[Channel::Blue as usize]);
+
+    let white = red.max(green).max(blue);
+    if white <= 0.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+
+    [
+        (white - red) / white,
+        (white - green) / white,
+        (white - blue) / white,
+        1.0 - white,
+    ]
+}
+
+/// Multiply the color of a set of components by their alpha.
+pub(crate) fn premultiply(components: Components) -> Components {
+    let [red, green, blue, alpha] = components;
+    let alpha = clamp_unit(alpha);
+    [red * alpha, green * alpha, blue * alpha, alpha]
+}
+
+/// Divide the color of a set of premultiplied components by their alpha.
+///
+/// Fully transparent components become transparent black. This is the
+/// inverse of [`premultiply`].
+pub(crate) fn unpremultiply(components: Components) -> Components {
+    let [red, green, blue, alpha] = components;
+    if alpha <= 0.0 {
+        return [0.0; 4];
+    }
+
+    [
+        clamp_unit(red / alpha),
+        clamp_unit(green / alpha),
+        clamp_unit(blue / alpha),
+        alpha,
+    ]
+}
+
+/// The normalized chroma that has no color.
+pub(crate) const NEUTRAL_CHROMA: f32 = 128.0 / 255.0;
+
+/// Convert normalized luma, blue-difference and red-difference chroma in
+/// the limited video range to an opaque color.
+pub(crate) fn ycbcr_to_rgb(matrix: YcbcrMatrix, ycbcr: [f32; 3]) -> Components {
+    let (kr, kb) = matrix.weights();
+    let [luma, blue, red] = ycbcr;
+    let luma = (luma - 16.0 / 255.0) * (255.0 / 219.0);
+    let blue = (blue - NEUTRAL_CHROMA) * (255.0 / 224.0);
+    let red = (red - NEUTRAL_CHROMA) * (255.0 / 224.0);
+
+    let r = luma + 2.0 * (1.0 - kr) * red;
+    let b = luma + 2.0 * (1.0 - kb) * blue;
+    let g = (luma - kr * r - kb * b) / (1.0 - kr - kb);
+    [clamp_unit(r), clamp_unit(g), clamp_unit(b), 1.0]
+}
+
+/// Convert a color to normalized luma, blue-difference and
+/// red-difference chroma in the limited video range. Alpha is ignored.
+///
+/// This is the inverse of [`ycbcr_to_rgb`].
+pub(crate) fn rgb_to_ycbcr(matrix: YcbcrMatrix, components: Components) -> [f32; 3] {
+    let (kr, kb) = matrix.weights();
+    let r = clamp_unit(components[Channel::Red as usize]);
+    let g = clamp_unit(components[Channel::Green as usize]);
+    let b = clamp_unit(components[Channel::Blue as usize]);
+
+    let luma = kr * r + (1.0 - kr - kb) * g + kb * b;
+    let blue = (b - luma) / (2.0 * (1.0 - kb));
+    let red = (r - luma) / (2.0 * (1.0 - kr));
+    [
+        (16.0 + 219.0 * luma) / 255.0,
+        NEUTRAL_CHROMA + blue * (224.0 / 255.0),
+        NEUTRAL_CHROMA + red * (224.0 / 255.0),
+    ]
+}
+
+/// The value of a float channel with the given number of bits, from
+/// its raw bits.
+pub(crate) fn float_from_raw(raw: u64, bits: u8) -> f32 {
+    match bits {
+        16 => half_float::f16_to_f32(raw as u16),
+        _ => f32::from_bits(raw as u32),
+    }
+}
+
+/// The raw bits of a float channel with the given number of bits.
+pub(crate) fn float_to_raw(value: f32, bits: u8) -> u64 {
+    match bits {
+        16 => half_float::f32_to_f16(value) as u64,
+        _ => value.to_bits() as u64,
+    }
+}
+
+/// The BT.601 luma of a set of components.
+pub(crate) fn luma(components: Components) -> f32 {
+    0.299 * components[0] + 0.587 * components[1] + 0.114 * components[2]
+}
+
+/// Clamp a component to the range `0.0..=1.0`.
+pub(crate) fn clamp_unit(value: f32) -> f32 {
+    // also catches NaN
+    if value > 0.0 {
+        if value < 1.0 {
+            value
+        } else {
+            1.0
+        }
+    } else {
+        0.0
+    }
+}
+
+/// Convert a normalized component to an integer with the given number
+/// of bits, rounding to the nearest value.
+pub(crate) fn quantize(value: f32, bits: u8) -> u64 {
+    quantize_with_threshold(value, bits, 0.5)
+}
+
+/// Convert a normalized component to an integer with the given number
+/// of bits, adding `threshold` before rounding down.
+pub(crate) fn quantize_with_threshold(value: f32, bits: u8, threshold: f32) -> u64 {
+    let max = low_bits(bits);
+    let value = (clamp_unit(value) * max as f32 + threshold) as u64;
+    if value < max {
+        value
+    } else {
+        max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_raw() {
+        let formats = [
+            Format::ARGB32,
+            Format::RGB24,
+            Format::XBGR16,
+            Format::A8,
+            Format::A4,
+            Format::A1,
+            Format::ARGB_F32,
+        ];
+
+        for &format in formats.iter() {
+            for &endianness in [Endianness::Little, Endianness::Big].iter() {
+                let codec = Codec::new(format, endianness);
+                let mut row = [0u8; 64];
+                let values = [0.0, 0.2, 0.6, 1.0];
+
+                for i in 0..4 {
+                    codec.write(&mut row, i, [values[i], values[3 - i], 1.0, values[i]]);
+                }
+
+                for i in 0..4 {
+                    let components = codec.read(&row, i);
+                    let original = [values[i], values[3 - i], 1.0, values[i]];
+                    for info in format.channels() {
+                        let index = info.channel as usize;
+                        let tolerance = 1.0 / low_bits(info.bits) as f32;
+                        assert!(
+                            (components[index] - original[index]).abs() <= tolerance,
+                            "{:?} {:?} pixel {}",
+                            format,
+                            endianness,
+                            i
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn byte_offsets() {
+        let codec = Codec::new(Format::ARGB32, Endianness::Little);
+        assert_eq!(codec.byte_offset(Channel::Alpha), Some(0));
+        assert_eq!(codec.byte_offset(Channel::Blue), Some(3));
+
+        let codec = Codec::new(Format::ARGB32, Endianness::Big);
+        assert_eq!(codec.byte_offset(Channel::Alpha), Some(3));
+
+        let codec = Codec::new(Format::ARGB16, Endianness::Little);
+        assert_eq!(codec.byte_offset(Channel::Alpha), None);
+    }
+}
@@ -2,7 +2,13 @@
 
 use tinyvec::ArrayVec;
 
-use crate::{Channel, ChannelValue};
+use crate::{
+    codec::{self, Components},
+    Channel, ChannelValue, Error,
+};
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String};
+use core::iter;
 
 /// An RGBA color tuple.
 ///
@@ -26,14 +32,233 @@ impl Rgba {
         use Channel::*;
 
         ArrayVec::from([
-            ChannelValue::new(Alpha, shift(self.alpha)),
-            ChannelValue::new(Red, shift(self.red)),
-            ChannelValue::new(Green, shift(self.green)),
-            ChannelValue::new(Blue, shift(self.blue)),
+            ChannelValue::from_u16(Alpha, self.alpha),
+            ChannelValue::from_u16(Red, self.red),
+            ChannelValue::from_u16(Green, self.green),
+            ChannelValue::from_u16(Blue, self.blue),
         ])
     }
+
+    /// Parse a color the way CSS writes it, as `#rgb`, `#rgba`, `#rrggbb`,
+    /// `#rrggbbaa`, `rgb(...)` or `rgba(...)`.
+    ///
+    /// The functions take red, green and blue as numbers from `0` to `255`
+    /// or as percentages, and an optional alpha as a number from `0` to
+    /// `1` or as a percentage. Their arguments can be separated by commas,
+    /// or by spaces with a `/` before alpha. Values out of range are
+    /// clamped, and names and hex digits ignore ASCII case.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidColor`] for any other string.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::Rgba;
+    ///
+    /// let teal = Rgba::parse_css("rgba(0, 128, 128, 50%)").unwrap();
+    /// assert_eq!(teal, Rgba::parse_css("rgb(0 128 128 / 0.5)").unwrap());
+    /// assert_eq!((teal.green, teal.alpha), (0x8080, 0x8000));
+    /// ```
+    ///
+    /// [`Error::InvalidColor`]: crate::Error::InvalidColor
+    pub fn parse_css(s: &str) -> Result<Rgba, Error> {
+        let s = s.trim();
+        if let Some(digits) = s.strip_prefix('#') {
+            return parse_hex(digits).ok_or(Error::InvalidColor);
+        }
+
+        let open = s.find('(').ok_or(Error::InvalidColor)?;
+        let name = s[..open].trim_end();
+        if !name.eq_ignore_ascii_case("rgb") && !name.eq_ignore_ascii_case("rgba") {
+            return Err(Error::InvalidColor);
+        }
+        let args = s[open + 1..].strip_suffix(')').ok_or(Error::InvalidColor)?;
+        parse_args(args).ok_or(Error::InvalidColor)
+    }
+
+    /// Write this color the way CSS writes it, as `#rrggbb`, or as
+    /// `#rrggbbaa` if it isn't opaque.
+    ///
+    /// Channels are rounded to 8 bits, so [`parse_css`] gives back the
+    /// same color for colors that came from 8-bit channels.
+    ///
+    /// This requires the `alloc` feature.
+    ///
+    /// [`parse_css`]: crate::Rgba::parse_css
+    #[cfg(feature = "alloc")]
+    pub fn to_css_string(self) -> String {
+        let [red, green, blue, alpha] = self.components();
+        let byte = |value| codec::quantize(value, 8);
+        if self.alpha == u16::MAX {
+            format!("#{:02x}{:02x}{:02x}", byte(red), byte(green), byte(blue))
+        } else {
+            format!(
+                "#{:02x}{:02x}{:02x}{:02x}",
+                byte(red),
+                byte(green),
+                byte(blue),
+                byte(alpha)
+            )
+        }
+    }
+
+    /// Get the normalized components of this color.
+    pub(crate) fn components(self) -> Components {
+        [
+            normalize(self.red),
+            normalize(self.green),
+            normalize(self.blue),
+            normalize(self.alpha),
+        ]
+    }
+}
+
+fn normalize(value: u16) -> f32 {
+    value as f32 / u16::MAX as f32
+}
+
+fn denormalize(value: f32) -> u16 {
+    codec::quantize(value, 16) as u16
+}
+
+/// Parse the digits of a hex color, with one or two digits per channel.
+fn parse_hex(digits: &str) -> Option<Rgba> {
+    let (width, scale) = match digits.len() {
+        3 | 4 => (1, 0x1111),
+        6 | 8 => (2, 0x0101),
+        _ => return None,
+    };
+    // from_str_radix would accept a sign
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut channels = [u16::MAX; 4];
+    for (channel, start) in channels.iter_mut().zip((0..digits.len()).step_by(width)) {
+        *channel = u16::from_str_radix(&digits[start..start + width], 16).ok()? * scale;
+    }
+    let [red, green, blue, alpha] = channels;
+    Some(Rgba {
+        red,
+        green,
+        blue,
+        alpha,
+    })
 }
 
-const fn shift(value: u16) -> u8 {
-    (value >> 8) as u8
+/// Parse the arguments of `rgb()` or `rgba()`.
+fn parse_args(args: &str) -> Option<Rgba> {
+    let mut values = [""; 4];
+    let count = if args.contains(',') {
+        fill(&mut values, args.split(',').map(str::trim))?
+    } else {
+        let mut halves = args.splitn(2, '/');
+        let count = fill(&mut values, halves.next()?.split_whitespace())?;
+        match halves.next() {
+            Some(alpha) if count == 3 => fill(&mut values[3..], iter::once(alpha.trim()))? + 3,
+            Some(_) => return None,
+            None => count,
+        }
+    };
+    if count < 3 {
+        return None;
+    }
+
+    let alpha = if count == 4 {
+        parse_number(values[3], 1.0)?
+    } else {
+        1.0
+    };
+    Some(Rgba {
+        red: denormalize(parse_number(values[0], 255.0)?),
+        green: denormalize(parse_number(values[1], 255.0)?),
+        blue: denormalize(parse_number(values[2], 255.0)?),
+        alpha: denormalize(alpha),
+    })
+}
+
+/// Put the values into `slots`, returning how many there are, or `None`
+/// if they don't fit.
+fn fill<'a>(slots: &mut [&'a str], values: impl Iterator<Item = &'a str>) -> Option<usize> {
+    let mut count = 0;
+    for value in values {
+        *slots.get_mut(count)? = value;
+        count += 1;
+    }
+    Some(count)
+}
+
+/// Parse a number or a percentage, as a fraction of `max`.
+fn parse_number(value: &str, max: f32) -> Option<f32> {
+    let (value, max) = match value.strip_suffix('%') {
+        Some(value) => (value, 100.0),
+        None => (value, max),
+    };
+    let value = value.parse::<f32>().ok()?;
+    // parse also accepts infinities and NaN, which CSS doesn't
+    if value.is_finite() {
+        Some(value / max)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css() {
+        let orange = Rgba {
+            red: 0xFFFF,
+            green: 0x8080,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        for &s in [
+            "#ff8000",
+            "#FF8000ff",
+            "rgb(255, 128, 0)",
+            " RGBA(100%,128,-5) ",
+            "rgb(255 128 0 / 1)",
+            "rgba(255 128 0 / 100%)",
+        ]
+        .iter()
+        {
+            assert_eq!(Rgba::parse_css(s), Ok(orange), "{}", s);
+        }
+        assert_eq!(Rgba::parse_css("#f80").unwrap().green, 0x8888);
+        assert_eq!(Rgba::parse_css("#f808").unwrap().alpha, 0x8888);
+        assert_eq!(Rgba::parse_css("rgba(0, 0, 0, 0.5)").unwrap().alpha, 0x8000);
+
+        for &s in [
+            "",
+            "#ff800",
+            "#+f8080",
+            "#gg0000",
+            "rgb(1, 2)",
+            "rgb(1, 2, 3, 4, 5)",
+            "rgb(1 2 / 3)",
+            "rgb(1, 2, 3",
+            "hsl(1, 2, 3)",
+            "rgb(1, 2, inf)",
+            "rgb(1,, 2, 3)",
+        ]
+        .iter()
+        {
+            assert_eq!(Rgba::parse_css(s), Err(Error::InvalidColor), "{}", s);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn css_strings() {
+        let color = Rgba::parse_css("rgb(255, 128, 0)").unwrap();
+        assert_eq!(color.to_css_string(), "#ff8000");
+        let color = Rgba::parse_css("#12345678").unwrap();
+        assert_eq!(color.to_css_string(), "#12345678");
+        assert_eq!(Rgba::parse_css(&color.to_css_string()), Ok(color));
+    }
 }
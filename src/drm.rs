@@ -0,0 +1,205 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the fourcc codes of the Linux DRM
+//! subsystem, for use with KMS, GBM and dma-bufs.
+//!
+//! DRM codes describe the layout of a little-endian quantum from its most
+//! significant bits down, so `DRM_FORMAT_XRGB8888` has blue in its lowest
+//! byte and is [`Format::XBGR32`]. Formats with big-endian quanta have
+//! [`DRM_FORMAT_BIG_ENDIAN`] set in their code.
+//!
+//! Only formats with a single plane are covered.
+//!
+//! This requires the `drm` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::{drm, Endianness, Format};
+//!
+//! let code = Format::XBGR32.to_drm_fourcc(Endianness::Little);
+//! assert_eq!(code, Some(drm::DRM_FORMAT_XRGB8888));
+//! assert_eq!(
+//!     Format::from_drm_fourcc(drm::DRM_FORMAT_XRGB8888),
+//!     Some((Format::XBGR32, Endianness::Little)),
+//! );
+//! ```
+//!
+//! [`Format::XBGR32`]: crate::Format::XBGR32
+//! [`DRM_FORMAT_BIG_ENDIAN`]: crate::drm::DRM_FORMAT_BIG_ENDIAN
+
+use crate::{AlphaMode, ColorType, Endianness, Format};
+
+/// The flag that is set in the codes of formats with big-endian quanta.
+pub const DRM_FORMAT_BIG_ENDIAN: u32 = 1 << 31;
+
+/// Build a fourcc code out of its four characters.
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+macro_rules! drm_codes {
+    ($($(#[$meta: meta])* $code_name: ident = $code: literal => $format: expr,)*) => {
+        $(
+            $(#[$meta])*
+            pub const $code_name: u32 = fourcc($code);
+        )*
+
+        /// Every format with a code, in the order that codes are looked
+        /// up in.
+        const FORMATS: &[(u32, Format)] = &[$((fourcc($code), $format),)*];
+    };
+}
+
+drm_codes! {
+    /// Gray stored in a red channel, which is [`Format::GRAY8`](crate::Format::GRAY8).
+    DRM_FORMAT_R8 = b"R8  " => Format::GRAY8,
+    /// Gray stored in a red channel, which is [`Format::GRAY16`](crate::Format::GRAY16).
+    DRM_FORMAT_R16 = b"R16 " => Format::GRAY16,
+    /// [`Format::RG8`](crate::Format::RG8).
+    DRM_FORMAT_GR88 = b"GR88" => Format::RG8,
+    /// [`Format::RG16`](crate::Format::RG16).
+    DRM_FORMAT_GR1616 = b"GR32" => Format::RG16,
+    /// [`Format::INDEXED8`](crate::Format::INDEXED8).
+    DRM_FORMAT_C8 = b"C8  " => Format::INDEXED8,
+
+    /// [`Format::XBGR16`](crate::Format::XBGR16).
+    DRM_FORMAT_XRGB4444 = b"XR12" => Format::XBGR16,
+    /// [`Format::XRGB16`](crate::Format::XRGB16).
+    DRM_FORMAT_XBGR4444 = b"XB12" => Format::XRGB16,
+    /// Alpha in the top four bits, then red, green and blue.
+    DRM_FORMAT_ARGB4444 = b"AR12" => Format::new(16, ColorType::Bgra, 4, 4, 4, 4),
+    /// [`Format::ABGR16`](crate::Format::ABGR16).
+    DRM_FORMAT_RGBA4444 = b"RA12" => Format::ABGR16,
+    /// [`Format::ARGB16`](crate::Format::ARGB16).
+    DRM_FORMAT_BGRA4444 = b"BA12" => Format::ARGB16,
+    /// [`Format::RGB555`](crate::Format::RGB555).
+    DRM_FORMAT_XRGB1555 = b"XR15" => Format::RGB555,
+    /// [`Format::ARGB1555`](crate::Format::ARGB1555).
+    DRM_FORMAT_ARGB1555 = b"AR15" => Format::ARGB1555,
+    /// [`Format::RGB565`](crate::Format::RGB565).
+    DRM_FORMAT_RGB565 = b"RG16" => Format::RGB565,
+    /// Blue in the top five bits, then green and red.
+    DRM_FORMAT_BGR565 = b"BG16" => Format::new(16, ColorType::Argb, 0, 5, 6, 5),
+
+    /// [`Format::BGR24`](crate::Format::BGR24).
+    DRM_FORMAT_RGB888 = b"RG24" => Format::BGR24,
+    /// [`Format::RGB24`](crate::Format::RGB24).
+    DRM_FORMAT_BGR888 = b"BG24" => Format::RGB24,
+
+    /// [`Format::XBGR32`](crate::Format::XBGR32).
+    DRM_FORMAT_XRGB8888 = b"XR24" => Format::XBGR32,
+    /// [`Format::XRGB32`](crate::Format::XRGB32).
+    DRM_FORMAT_XBGR8888 = b"XB24" => Format::XRGB32,
+    /// [`Format::BGRA32`](crate::Format::BGRA32).
+    DRM_FORMAT_ARGB8888 = b"AR24" => Format::BGRA32,
+    /// [`Format::RGBA32`](crate::Format::RGBA32).
+    DRM_FORMAT_ABGR8888 = b"AB24" => Format::RGBA32,
+    /// [`Format::ABGR32`](crate::Format::ABGR32).
+    DRM_FORMAT_RGBA8888 = b"RA24" => Format::ABGR32,
+    /// [`Format::ARGB32`](crate::Format::ARGB32).
+    DRM_FORMAT_BGRA8888 = b"BA24" => Format::ARGB32,
+
+    /// [`Format::XRGB2101010`](crate::Format::XRGB2101010).
+    DRM_FORMAT_XRGB2101010 = b"XR30" => Format::XRGB2101010,
+    /// Two unused bits on top, then ten bits of blue, green and red.
+    DRM_FORMAT_XBGR2101010 = b"XB30" => Format::new(32, ColorType::Rgba, 0, 10, 10, 10),
+    /// [`Format::ARGB2101010`](crate::Format::ARGB2101010).
+    DRM_FORMAT_ARGB2101010 = b"AR30" => Format::ARGB2101010,
+    /// Two bits of alpha on top, then ten bits of blue, green and red.
+    DRM_FORMAT_ABGR2101010 = b"AB30" => Format::new(32, ColorType::Rgba, 2, 10, 10, 10),
+
+    /// [`Format::RGBA64`](crate::Format::RGBA64).
+    DRM_FORMAT_ABGR16161616 = b"AB48" => Format::RGBA64,
+
+    /// [`Format::YUYV`](crate::Format::YUYV).
+    DRM_FORMAT_YUYV = b"YUYV" => Format::YUYV,
+    /// [`Format::UYVY`](crate::Format::UYVY).
+    DRM_FORMAT_UYVY = b"UYVY" => Format::UYVY,
+}
+
+/// Formats that have the same layout as a format in [`FORMATS`], and so
+/// map to its code, but that the code doesn't map back to.
+const ALIASES: &[(Format, Format)] = &[
+    (Format::RGBX32, Format::XRGB32),
+    (Format::BGRX32, Format::XBGR32),
+];
+
+impl Format {
+    /// The DRM fourcc code for this format with the given endianness, if
+    /// there is one.
+    ///
+    /// DRM doesn't say whether alpha is premultiplied, so it is ignored.
+    ///
+    /// This requires the `drm` feature.
+    pub fn to_drm_fourcc(self, endianness: Endianness) -> Option<u32> {
+        let format = self.with_alpha_mode(AlphaMode::Straight);
+        let format = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == format)
+            .map_or(format, |&(_, format)| format);
+
+        let (code, _) = FORMATS.iter().find(|(_, known)| *known == format)?;
+        Some(match endianness {
+            Endianness::Little => *code,
+            Endianness::Big => code | DRM_FORMAT_BIG_ENDIAN,
+        })
+    }
+
+    /// The format and endianness for a DRM fourcc code, if it is a
+    /// single-plane code that has a format.
+    ///
+    /// This requires the `drm` feature.
+    pub fn from_drm_fourcc(code: u32) -> Option<(Format, Endianness)> {
+        let endianness = if code & DRM_FORMAT_BIG_ENDIAN != 0 {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        };
+
+        let code = code & !DRM_FORMAT_BIG_ENDIAN;
+        FORMATS
+            .iter()
+            .find(|&&(known, _)| known == code)
+            .map(|&(_, format)| (format, endianness))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Channel, Pixel, Rgba};
+
+    #[test]
+    fn layouts() {
+        // DRM_FORMAT_ARGB8888 has blue in its lowest byte and alpha in the
+        // highest
+        let (format, endianness) = Format::from_drm_fourcc(DRM_FORMAT_ARGB8888).unwrap();
+        assert_eq!(format.shift_for(Channel::Blue), Some(0));
+        assert_eq!(format.shift_for(Channel::Alpha), Some(24));
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        assert_eq!(
+            Pixel::from_rgba(red, format, endianness).raw_u32(),
+            0xFFFF_0000
+        );
+
+        for &(code, format) in FORMATS {
+            assert_eq!(format.to_drm_fourcc(Endianness::Little), Some(code));
+            assert_eq!(
+                Format::from_drm_fourcc(code | DRM_FORMAT_BIG_ENDIAN),
+                Some((format, Endianness::Big))
+            );
+        }
+        assert_eq!(
+            Format::RGBX32.to_drm_fourcc(Endianness::Big),
+            Some(DRM_FORMAT_XBGR8888 | DRM_FORMAT_BIG_ENDIAN)
+        );
+        assert_eq!(Format::CMYK32.to_drm_fourcc(Endianness::Little), None);
+        assert_eq!(Format::from_drm_fourcc(fourcc(b"NV12")), None);
+    }
+}
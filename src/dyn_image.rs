@@ -0,0 +1,221 @@
+// BSL 1.0 License
+
+use crate::{
+    blit_bits, convert, BitOrder, Endianness, Format, Image, Palette, Pixel, Rect, Rgba,
+    TransferFunction,
+};
+use alloc::boxed::Box;
+use core::{any::Any, fmt};
+
+/// An [`Image`] that can be downcast back to its concrete type.
+///
+/// This is implemented for every `'static` image, and is what
+/// [`DynImage`] stores.
+///
+/// [`Image`]: crate::Image
+/// [`DynImage`]: crate::DynImage
+pub trait ImageAny: Image + Any {
+    /// Get the image as an [`Any`].
+    fn as_any(&self) -> &dyn Any;
+    /// Get the image as a mutable [`Any`].
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Convert the boxed image into a boxed [`Any`].
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<I: Image + Any> ImageAny for I {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A boxed image of any type.
+///
+/// Plugin-style systems can pass these around without being generic over
+/// the type of the image. The common operations are available as
+/// inherent methods, and the original image can be recovered with
+/// [`downcast`].
+///
+/// This requires the `alloc` feature.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{DynImage, Format, GeneralImage, Image};
+///
+/// let image = DynImage::new(GeneralImage::from_buffer(2, 1, Format::A8, [1u8, 2]));
+/// assert_eq!(image.sample(1, 0).map(|pixel| pixel.raw_u32()), Some(2));
+/// assert_eq!(image.sample(2, 0), None);
+///
+/// let inner: GeneralImage<[u8; 2]> = image.downcast().ok().unwrap();
+/// assert_eq!(inner.dimensions(), (2, 1));
+/// ```
+///
+/// [`downcast`]: crate::DynImage::downcast
+pub struct DynImage(pub Box<dyn ImageAny>);
+
+impl DynImage {
+    /// Box an image.
+    pub fn new<I: Image + 'static>(image: I) -> Self {
+        DynImage(Box::new(image))
+    }
+
+    /// Get a reference to the image if it has the type `I`.
+    pub fn downcast_ref<I: Image + 'static>(&self) -> Option<&I> {
+        self.0.as_any().downcast_ref()
+    }
+
+    /// Get a mutable reference to the image if it has the type `I`.
+    pub fn downcast_mut<I: Image + 'static>(&mut self) -> Option<&mut I> {
+        self.0.as_any_mut().downcast_mut()
+    }
+
+    /// Take the image back out if it has the type `I`.
+    pub fn downcast<I: Image + 'static>(self) -> Result<I, Self> {
+        if self.0.as_any().is::<I>() {
+            Ok(*self.0.into_any().downcast().ok().unwrap())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Read the pixel at the given location.
+    ///
+    /// Locations outside of the image give its [`border_pixel`], or `None`
+    /// if it doesn't have one.
+    ///
+    /// [`border_pixel`]: crate::Image::border_pixel
+    pub fn sample(&self, x: usize, y: usize) -> Option<Pixel> {
+        let (width, height) = self.dimensions();
+        if x < width && y < height {
+            Some(self.0.pixel(x, y))
+        } else {
+            let color = self.border_pixel()?;
+            Some(Pixel::from_rgba(color, self.format(), self.endianness()))
+        }
+    }
+
+    /// Copy this image into `dst`, converting it to the format of `dst`.
+    ///
+    /// See [`convert`] for details.
+    ///
+    /// [`convert`]: crate::convert
+    pub fn convert_to(&self, dst: &mut dyn Image) {
+        convert(&*self.0, dst);
+    }
+
+    /// Copy `src` into this image, converting it to the format of this
+    /// image.
+    ///
+    /// See [`convert`] for details.
+    ///
+    /// [`convert`]: crate::convert
+    pub fn convert_from(&mut self, src: &dyn Image) {
+        convert(src, &mut *self.0);
+    }
+
+    /// Copy a rectangle of this image onto `dst`.
+    ///
+    /// See [`blit_bits`] for details.
+    ///
+    /// [`blit_bits`]: crate::blit_bits
+    pub fn blit_to(&self, src_rect: Rect, dst: &mut dyn Image, dst_point: (usize, usize)) {
+        blit_bits(&*self.0, src_rect, dst, dst_point);
+    }
+}
+
+impl fmt::Debug for DynImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynImage")
+            .field("format", &self.0.format())
+            .field("endianness", &self.0.endianness())
+            .field("dimensions", &self.0.dimensions())
+            .finish()
+    }
+}
+
+impl Image for DynImage {
+    fn format(&self) -> Format {
+        self.0.format()
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.0.endianness()
+    }
+
+    fn bit_order(&self) -> BitOrder {
+        self.0.bit_order()
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.0.transfer_function()
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        self.0.border_pixel()
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        self.0.dimensions()
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        self.0.bytes_per_scanline()
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        self.0.scanline(x, y, scanline)
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        self.0.set_scanline(x, y, scanline)
+    }
+
+    fn set_scanline_clipped(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        self.0.set_scanline_clipped(x, y, scanline)
+    }
+
+    fn column(&self, x: usize, y: usize, column: &mut [u8]) -> usize {
+        self.0.column(x, y, column)
+    }
+
+    fn palette(&self) -> Option<&Palette> {
+        self.0.palette()
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        self.0.pixel(x, y)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.0.set_pixel(x, y, pixel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeneralImage;
+
+    #[test]
+    fn dynamic_dispatch() {
+        let mut image = DynImage::new(GeneralImage::from_buffer(2, 2, Format::RGB24, [0u8; 12]));
+        assert!(image.downcast_ref::<GeneralImage<[u8; 4]>>().is_none());
+
+        let src = GeneralImage::from_buffer(2, 2, Format::XRGB32, [0x7Fu8; 16]);
+        image.convert_from(&src);
+
+        let mut dst = GeneralImage::from_buffer(2, 2, Format::XRGB32, [0u8; 16]);
+        image.blit_to(Rect::new(0, 0, 1, 1), &mut dst, (1, 1));
+        assert_eq!(dst.pixel(1, 1).raw_u32() & 0xFFFFFF, 0x7F7F7F);
+        assert_eq!(dst.pixel(0, 0).raw_u32(), 0);
+    }
+}
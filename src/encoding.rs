@@ -0,0 +1,350 @@
+// BSL 1.0 License
+
+//! Plugging in encoders and decoders for file formats.
+//!
+//! `genimage` doesn't parse PNG or JPEG itself. Companion crates implement
+//! [`ImageEncoder`] and [`ImageDecoder`] for their formats, and
+//! applications gather them in a [`CodecRegistry`].
+
+use crate::{DynImage, Error, ImageAny};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+/// Encodes images into a file format.
+///
+/// [`Error::Unsupported`] should be returned for images that the format
+/// can't hold, such as a format without alpha being asked to store an
+/// image with alpha.
+///
+/// This requires the `alloc` feature.
+///
+/// [`Error::Unsupported`]: crate::Error::Unsupported
+pub trait ImageEncoder {
+    /// Encode an image, appending the bytes to `out`.
+    fn encode(&mut self, image: &dyn ImageAny, out: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+/// Decodes images from a file format.
+///
+/// This requires the `alloc` feature.
+pub trait ImageDecoder {
+    /// Whether `bytes` look like they are in this format, usually by
+    /// checking a magic number at the start.
+    fn can_decode(&self, bytes: &[u8]) -> bool;
+
+    /// Decode an image.
+    ///
+    /// [`Error::InvalidData`] should be returned if the bytes are
+    /// malformed.
+    ///
+    /// [`Error::InvalidData`]: crate::Error::InvalidData
+    fn decode(&mut self, bytes: &[u8]) -> Result<DynImage, Error>;
+}
+
+struct Entry {
+    name: &'static str,
+    encoder: Option<Box<dyn ImageEncoder>>,
+    decoder: Option<Box<dyn ImageDecoder>>,
+}
+
+/// A set of encoders and decoders, looked up by the name of their
+/// encoding.
+///
+/// Names are compared ignoring ASCII case, and are usually the file
+/// extension of the format, like `"png"`. Registering a codec for a name
+/// that already has one replaces it.
+///
+/// This requires the `alloc` feature.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{
+///     CodecRegistry, DynImage, Error, Format, GeneralImage, Image, ImageAny, ImageDecoder,
+///     ImageEncoder,
+/// };
+///
+/// // a toy encoding of a one-byte width, then rows of 8-bit gray
+/// struct Gray;
+///
+/// impl ImageEncoder for Gray {
+///     fn encode(&mut self, image: &dyn ImageAny, out: &mut Vec<u8>) -> Result<(), Error> {
+///         if image.format() != Format::GRAY8 || image.width() > 255 {
+///             return Err(Error::Unsupported);
+///         }
+///         out.push(image.width() as u8);
+///         let mut row = vec![0u8; image.width()];
+///         for y in 0..image.height() {
+///             image.scanline(0, y, &mut row);
+///             out.extend_from_slice(&row);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// impl ImageDecoder for Gray {
+///     fn can_decode(&self, bytes: &[u8]) -> bool {
+///         bytes.first().map_or(false, |&width| width > 0)
+///     }
+///
+///     fn decode(&mut self, bytes: &[u8]) -> Result<DynImage, Error> {
+///         let width = bytes[0] as usize;
+///         let pixels = bytes[1..].to_vec();
+///         if pixels.len() % width != 0 {
+///             return Err(Error::InvalidData);
+///         }
+///         let height = pixels.len() / width;
+///         Ok(DynImage::new(GeneralImage::from_buffer(width, height, Format::GRAY8, pixels)))
+///     }
+/// }
+///
+/// let mut registry = CodecRegistry::new();
+/// registry.register_encoder("gray", Gray);
+/// registry.register_decoder("gray", Gray);
+///
+/// let image = GeneralImage::from_buffer(2, 2, Format::GRAY8, [1u8, 2, 3, 4]);
+/// let mut bytes = Vec::new();
+/// registry.encode("GRAY", &image, &mut bytes).unwrap();
+/// assert_eq!(bytes, [2, 1, 2, 3, 4]);
+///
+/// let decoded = registry.decode(&bytes).unwrap();
+/// assert_eq!(decoded.dimensions(), (2, 2));
+/// assert_eq!(decoded.pixel(1, 1).raw_u32(), 4);
+/// ```
+#[derive(Default)]
+pub struct CodecRegistry {
+    entries: Vec<Entry>,
+}
+
+impl CodecRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the encoder for an encoding.
+    pub fn register_encoder<E: ImageEncoder + 'static>(&mut self, name: &'static str, encoder: E) {
+        self.entry_mut(name).encoder = Some(Box::new(encoder));
+    }
+
+    /// Register the decoder for an encoding.
+    pub fn register_decoder<D: ImageDecoder + 'static>(&mut self, name: &'static str, decoder: D) {
+        self.entry_mut(name).decoder = Some(Box::new(decoder));
+    }
+
+    /// The names of the encodings that can be encoded.
+    pub fn encodings(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.entries
+            .iter()
+            .filter(|entry| entry.encoder.is_some())
+            .map(|entry| entry.name)
+    }
+
+    /// The names of the encodings that can be decoded.
+    pub fn decodings(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.entries
+            .iter()
+            .filter(|entry| entry.decoder.is_some())
+            .map(|entry| entry.name)
+    }
+
+    /// Encode an image with the encoder for `name`, appending the bytes to
+    /// `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownEncoding`] if there is no encoder for
+    /// `name`, or the error of the encoder.
+    ///
+    /// [`Error::UnknownEncoding`]: crate::Error::UnknownEncoding
+    pub fn encode(
+        &mut self,
+        name: &str,
+        image: &dyn ImageAny,
+        out: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .and_then(|entry| entry.encoder.as_mut())
+            .ok_or(Error::UnknownEncoding)?
+            .encode(image, out)
+    }
+
+    /// Decode an image with the first decoder, in the order they were
+    /// registered in, that recognizes the bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownEncoding`] if no decoder recognizes the
+    /// bytes, or the error of the decoder.
+    ///
+    /// [`Error::UnknownEncoding`]: crate::Error::UnknownEncoding
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<DynImage, Error> {
+        self.entries
+            .iter_mut()
+            .filter_map(|entry| entry.decoder.as_mut())
+            .find(|decoder| decoder.can_decode(bytes))
+            .ok_or(Error::UnknownEncoding)?
+            .decode(bytes)
+    }
+
+    /// Decode an image with the decoder for `name`, without checking
+    /// whether it recognizes the bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownEncoding`] if there is no decoder for
+    /// `name`, or the error of the decoder.
+    ///
+    /// [`Error::UnknownEncoding`]: crate::Error::UnknownEncoding
+    pub fn decode_as(&mut self, name: &str, bytes: &[u8]) -> Result<DynImage, Error> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .and_then(|entry| entry.decoder.as_mut())
+            .ok_or(Error::UnknownEncoding)?
+            .decode(bytes)
+    }
+
+    fn entry_mut(&mut self, name: &'static str) -> &mut Entry {
+        let index = match self
+            .entries
+            .iter()
+            .position(|entry| entry.name.eq_ignore_ascii_case(name))
+        {
+            Some(index) => index,
+            None => {
+                self.entries.push(Entry {
+                    name,
+                    encoder: None,
+                    decoder: None,
+                });
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[index]
+    }
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodecRegistry")
+            .field("encodings", &self.encodings().collect::<Vec<_>>())
+            .field("decodings", &self.decodings().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, GeneralImage, Image};
+    use alloc::vec;
+
+    /// Decodes any bytes starting with `magic` into a blank image that is
+    /// `width` pixels wide.
+    struct Blank {
+        magic: u8,
+        width: usize,
+    }
+
+    impl ImageDecoder for Blank {
+        fn can_decode(&self, bytes: &[u8]) -> bool {
+            bytes.first() == Some(&self.magic)
+        }
+
+        fn decode(&mut self, _: &[u8]) -> Result<DynImage, Error> {
+            let image = GeneralImage::from_buffer(self.width, 1, Format::A8, vec![0u8; self.width]);
+            Ok(DynImage::new(image))
+        }
+    }
+
+    /// Encodes an image as `tag` followed by its width, and refuses images
+    /// wider than 255 pixels.
+    struct Tag(u8);
+
+    impl ImageEncoder for Tag {
+        fn encode(&mut self, image: &dyn ImageAny, out: &mut Vec<u8>) -> Result<(), Error> {
+            if image.width() > 255 {
+                return Err(Error::Unsupported);
+            }
+            out.extend_from_slice(&[self.0, image.width() as u8]);
+            Ok(())
+        }
+    }
+
+    fn width(result: Result<DynImage, Error>) -> Result<usize, Error> {
+        result.map(|image| image.width())
+    }
+
+    #[test]
+    fn decoders() {
+        let mut registry = CodecRegistry::new();
+        registry.register_decoder("one", Blank { magic: 1, width: 1 });
+        registry.register_decoder("two", Blank { magic: 2, width: 2 });
+        registry.register_decoder("also-two", Blank { magic: 2, width: 3 });
+
+        // the first decoder to recognize the bytes wins
+        assert_eq!(width(registry.decode(&[2])), Ok(2));
+        assert_eq!(width(registry.decode(&[3])), Err(Error::UnknownEncoding));
+        assert_eq!(width(registry.decode(&[])), Err(Error::UnknownEncoding));
+
+        // the decoder is used even if it doesn't recognize the bytes
+        assert_eq!(width(registry.decode_as("one", &[2])), Ok(1));
+        assert_eq!(width(registry.decode_as("TWO", &[])), Ok(2));
+        assert_eq!(
+            width(registry.decode_as("three", &[1])),
+            Err(Error::UnknownEncoding)
+        );
+    }
+
+    #[test]
+    fn encoders() {
+        let mut registry = CodecRegistry::new();
+        registry.register_encoder("tag", Tag(7));
+        registry.register_decoder("blank", Blank { magic: 1, width: 1 });
+
+        let image = GeneralImage::from_buffer(3, 1, Format::A8, [0u8; 3]);
+        let mut out = vec![9];
+        assert_eq!(registry.encode("TAG", &image, &mut out), Ok(()));
+        assert_eq!(out, [9, 7, 3]);
+
+        // names without an encoder, even if they have a decoder
+        assert_eq!(
+            registry.encode("png", &image, &mut out),
+            Err(Error::UnknownEncoding)
+        );
+        assert_eq!(
+            registry.encode("blank", &image, &mut out),
+            Err(Error::UnknownEncoding)
+        );
+
+        let wide = GeneralImage::from_buffer(256, 1, Format::A8, vec![0u8; 256]);
+        assert_eq!(
+            registry.encode("tag", &wide, &mut out),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(out, [9, 7, 3]);
+    }
+
+    #[test]
+    fn registering_again_replaces() {
+        let mut registry = CodecRegistry::new();
+        registry.register_decoder("png", Blank { magic: 1, width: 1 });
+        registry.register_decoder("PNG", Blank { magic: 1, width: 5 });
+        registry.register_encoder("Png", Tag(1));
+        registry.register_encoder("pNG", Tag(2));
+
+        // the name it was first registered with is kept
+        assert_eq!(registry.decodings().collect::<Vec<_>>(), ["png"]);
+        assert_eq!(registry.encodings().collect::<Vec<_>>(), ["png"]);
+        assert_eq!(width(registry.decode(&[1])), Ok(5));
+        assert_eq!(width(registry.decode_as("Png", &[1])), Ok(5));
+
+        let image = GeneralImage::from_buffer(1, 1, Format::A8, [0u8]);
+        let mut out = Vec::new();
+        registry.encode("png", &image, &mut out).unwrap();
+        assert_eq!(out, [2, 1]);
+    }
+}
@@ -0,0 +1,132 @@
+// BSL 1.0 License
+
+use crate::Channel;
+use core::fmt;
+
+/// An error that occurs when bytes or image metadata can't be used as
+/// they are described.
+///
+/// The fallible constructors return this instead of panicking, so that
+/// descriptions of pixels and images that come from outside of the
+/// program can be checked safely.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// A buffer is too small for the data that it is supposed to hold.
+    BufferTooSmall {
+        /// The number of bytes that are needed.
+        expected: usize,
+        /// The number of bytes in the buffer.
+        actual: usize,
+    },
+    /// The number of bytes in the image doesn't fit in a `usize`.
+    TooLarge,
+    /// The number of bytes per scanline is too small to hold a row, or
+    /// is not a multiple of the number of bytes per pixel.
+    InvalidStride,
+    /// The bit index of a pixel smaller than a byte doesn't fit in the
+    /// byte.
+    InvalidIndex,
+    /// The views of an image overlap, or there are too many of them.
+    InvalidViews,
+    /// A palette has more colors than an index can refer to.
+    TooManyColors,
+    /// The description of an image asks for a layout that isn't
+    /// supported.
+    Unsupported,
+    /// No encoder or decoder is registered for an encoding.
+    UnknownEncoding,
+    /// Encoded image data is malformed or truncated.
+    InvalidData,
+    /// A string is not the name of a format.
+    UnknownFormat,
+    /// More than one number of bytes per scanline fits the length of a
+    /// buffer.
+    AmbiguousStride,
+    /// A string is not a color that [`Rgba::parse_css`] understands.
+    ///
+    /// [`Rgba::parse_css`]: crate::Rgba::parse_css
+    InvalidColor,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BufferTooSmall { expected, actual } => write!(
+                f,
+                "buffer is {} bytes long, but {} bytes are needed",
+                actual, expected
+            ),
+            Error::TooLarge => f.write_str("image is too large to be addressed"),
+            Error::InvalidStride => f.write_str("number of bytes per scanline is invalid"),
+            Error::InvalidIndex => f.write_str("bit index does not fit in the byte"),
+            Error::InvalidViews => f.write_str("views overlap or are too many"),
+            Error::TooManyColors => f.write_str("palette has more than 256 colors"),
+            Error::Unsupported => f.write_str("image layout is not supported"),
+            Error::UnknownEncoding => f.write_str("no codec is registered for the encoding"),
+            Error::InvalidData => f.write_str("encoded image data is malformed"),
+            Error::UnknownFormat => f.write_str("unknown format name"),
+            Error::AmbiguousStride => {
+                f.write_str("more than one number of bytes per scanline fits the buffer")
+            }
+            Error::InvalidColor => f.write_str("string is not a CSS color"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// An error that occurs when the description of a format can't be used.
+///
+/// This is returned by [`Format::try_new`].
+///
+/// [`Format::try_new`]: crate::Format::try_new
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FormatError {
+    /// The number of bits per pixel isn't 1, 4, 8, 16, 24, 32, 48, 64, 96
+    /// or 128.
+    InvalidBpp(u8),
+    /// A channel has a number of bits that isn't supported for it.
+    ///
+    /// Integer channels can have 0 through 8, 10, 16 or 32 bits, float
+    /// channels must be as wide as their floats, and channels that the
+    /// color type doesn't have must have no bits.
+    InvalidChannelBits {
+        /// The channel.
+        channel: Channel,
+        /// The number of bits given for it.
+        bits: u8,
+    },
+    /// The channels need more bits than a pixel has.
+    ChannelsTooWide {
+        /// The number of bits per pixel.
+        bpp: u8,
+        /// The number of bits in all of the channels together.
+        bits: u32,
+    },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::InvalidBpp(bpp) => write!(f, "{} bits per pixel is not supported", bpp),
+            FormatError::InvalidChannelBits { channel, bits } => {
+                write!(
+                    f,
+                    "{} bits is not supported for the {:?} channel",
+                    bits, channel
+                )
+            }
+            FormatError::ChannelsTooWide { bpp, bits } => write!(
+                f,
+                "channels need {} bits, but pixels only have {}",
+                bits, bpp
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FormatError {}
@@ -1,7 +1,7 @@
 // BSL 1.0 License
 
-use crate::assert_exact_size::AssertExactSize;
-use core::{fmt, iter::FusedIterator};
+use crate::{codec::low_bits, Error, FormatError};
+use core::{fmt, iter::FusedIterator, str::FromStr};
 use tinyvec::ArrayVec;
 
 // hack that allows us to "panic" in const calls prior to const panics
@@ -18,6 +18,73 @@ macro_rules! const_panic {
     }}
 }
 
+// like `assert!`, but usable in const calls prior to const panics being
+// stabilized, where indexing out of bounds stops compilation instead
+macro_rules! const_assert {
+    ($cond: expr, $($tt: tt)*) => {{
+        cfg_if::cfg_if! {
+            if #[cfg(rustc_1_57)] {
+                if !$cond {
+                    panic!($($tt)*)
+                }
+            } else {
+                let _ = [()][!$cond as usize];
+            }
+        }
+    }}
+}
+
+/// Define a format from its channels, checking it while compiling.
+///
+/// Channels are listed from the lowest bits of the pixel up, as in
+/// [`Format::custom`], each as a letter followed by its number of bits:
+/// `R`, `G`, `B` and `A` for the channels, and `X` for padding, which can
+/// only come after every channel. The bits per pixel are the sum of the
+/// bits of the list. If a named color type has the same order, the format
+/// uses it, so the result compares equal to the constants on [`Format`].
+///
+/// The macro expands to a constant expression. A channel with a number of
+/// bits that [`Format::new`] doesn't support, other letters, a channel
+/// that appears more than once, padding before a channel and bits that
+/// don't add up to a valid number of bits per pixel fail to compile.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{format_spec, Format};
+///
+/// const BGRA: Format = format_spec!(B8 G8 R8 A8);
+/// assert_eq!(BGRA, Format::BGRA32);
+/// assert_eq!(format_spec!(B8 G8 R8 X8), Format::XBGR32);
+/// assert_eq!(format_spec!(B5 G6 R5), Format::RGB565);
+/// ```
+///
+/// Bits that don't add up to a valid size don't compile:
+///
+/// ```compile_fail
+/// # use genimage::format_spec;
+/// let format = format_spec!(R8 G8 B8 A4);
+/// ```
+///
+/// Neither do channels with an unsupported number of bits:
+///
+/// ```compile_fail
+/// # use genimage::format_spec;
+/// let format = format_spec!(R9 G8 B7);
+/// ```
+///
+/// [`Format::custom`]: crate::Format::custom
+/// [`Format`]: crate::Format
+/// [`Format::new`]: crate::Format::new
+#[macro_export]
+macro_rules! format_spec {
+    ($($channel: ident)+) => {{
+        const FORMAT: $crate::Format =
+            $crate::Format::__from_spec(&[$($crate::Format::__spec_channel(stringify!($channel))),+]);
+        FORMAT
+    }};
+}
+
 /// The format for an image.
 ///
 /// Images can be encoded in many different formats. This structure provides
@@ -26,7 +93,7 @@ macro_rules! const_panic {
 ///
 /// - The number of bits per pixel for an image. This is the number of bits
 ///   that are used to encode a single pixel. The valid values for bits per
-///   pixel are 1, 4, 8, 16, 24, 32, 96 and 128.
+///   pixel are 1, 4, 8, 16, 24, 32, 48, 64, 96 and 128.
 /// - The ordering of colors within the image. For instance, an image may
 ///   be of the `ARGB` format, which means the order of colors in a pixel
 ///   is first alpha, then red, then green, finally blue.
@@ -74,22 +141,44 @@ macro_rules! const_panic {
 /// let my_format = Format::ARGB_F32;
 /// # let _ = my_format;
 /// ```
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// ## Serialization
+///
+/// With the `serde` feature, formats are serialized as a struct with the
+/// fields `bpp`, `color_type`, `alpha_bits`, `red_bits`, `green_bits`,
+/// `blue_bits` and `alpha_mode`. Color types, alpha modes and channels
+/// are serialized as the names of their variants, and the order of a
+/// [`ColorType::Custom`] as its channels from the lowest bits up. This
+/// representation won't change.
+///
+/// [`ColorType::Custom`]: crate::ColorType::Custom
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Format {
-    /// The bits per pixel for this image.
+    /// The bits per pixel for this image, along with the
+    /// [`PREMULTIPLIED`] flag.
+    ///
+    /// Every valid number of bits per pixel is either one or a multiple of
+    /// four, so the bit worth two is free. Keeping the flag here keeps
+    /// `Format` within 32 bits.
     bpp: u8,
     /// The color type for this image.
     color_type: ColorType,
     /// The bits for each channel of the image.
-    channels: Channels,
+    channels: ChannelBits,
 }
 
+/// Set in `Format::bpp` if the color channels are premultiplied by alpha.
+const PREMULTIPLIED: u8 = 0b10;
+
 pub(crate) const MAX_BITS_PER_PIXEL: usize = 32 * 4;
 pub(crate) const MAX_BYTES_PER_PIXEL: usize = MAX_BITS_PER_PIXEL / 8;
 
 impl Format {
     /// The ARGB32 format.
     pub const ARGB32: Format = Format::new(32, ColorType::Argb, 8, 8, 8, 8);
+    /// The ARGB32 format with premultiplied alpha, as used by most
+    /// compositors.
+    pub const ARGB32_PREMUL: Format = Format::ARGB32.with_alpha_mode(AlphaMode::Premultiplied);
     /// The XRGB32 format.
     pub const XRGB32: Format = Format::new(32, ColorType::Argb, 0, 8, 8, 8);
     /// The ABGR32 format.
@@ -110,6 +199,123 @@ impl Format {
     /// The BGR24 format.
     pub const BGR24: Format = Format::new(24, ColorType::Abgr, 0, 8, 8, 8);
 
+    /// RGB with 16 bits per channel.
+    ///
+    /// Red is in the lowest bits of the 48-bit quantum. 16-bit PNG and
+    /// TIFF images store each channel big-endian in RGB order, which is
+    /// [`BGR48`] with [`Endianness::Big`].
+    ///
+    /// [`BGR48`]: crate::Format::BGR48
+    /// [`Endianness::Big`]: crate::Endianness::Big
+    pub const RGB48: Format = Format::new(48, ColorType::Argb, 0, 16, 16, 16);
+    /// BGR with 16 bits per channel.
+    pub const BGR48: Format = Format::new(48, ColorType::Abgr, 0, 16, 16, 16);
+
+    /// RGBA with 16 bits per channel, as produced by scanners and medical
+    /// imaging devices.
+    ///
+    /// Like [`RGB48`], red is in the lowest bits of the 64-bit quantum.
+    /// 16-bit PNG images with alpha are [`ABGR64`] with
+    /// [`Endianness::Big`].
+    ///
+    /// [`RGB48`]: crate::Format::RGB48
+    /// [`ABGR64`]: crate::Format::ABGR64
+    /// [`Endianness::Big`]: crate::Endianness::Big
+    pub const RGBA64: Format = Format::new(64, ColorType::Rgba, 16, 16, 16, 16);
+    /// ABGR with 16 bits per channel.
+    pub const ABGR64: Format = Format::new(64, ColorType::Abgr, 16, 16, 16, 16);
+
+    /// Two 8-bit channels, red then green.
+    pub const RG8: Format = Format::new(16, ColorType::Rg, 0, 8, 8, 0);
+    /// Two 16-bit channels, red then green.
+    pub const RG16: Format = Format::new(32, ColorType::Rg, 0, 16, 16, 0);
+    /// 8-bit gray followed by 8-bit alpha, as used by text atlases.
+    ///
+    /// See [`ColorType::Gray`] for how colors are converted to gray.
+    ///
+    /// [`ColorType::Gray`]: crate::ColorType::Gray
+    pub const LA8: Format = Format::new(16, ColorType::GrayAlpha, 8, 8, 0, 0);
+
+    /// The GRAY8 format, for luminance-only framebuffers.
+    ///
+    /// See [`ColorType::Gray`] for how colors are converted to gray.
+    ///
+    /// [`ColorType::Gray`]: crate::ColorType::Gray
+    pub const GRAY8: Format = Format::new(8, ColorType::Gray, 0, 8, 0, 0);
+    /// The GRAY16 format, with a single 16-bit gray channel.
+    pub const GRAY16: Format = Format::new(16, ColorType::Gray, 0, 16, 0, 0);
+    /// The GRAYA16 format, which is the same as [`LA8`].
+    ///
+    /// [`LA8`]: crate::Format::LA8
+    pub const GRAYA16: Format = Format::LA8;
+
+    /// 8-bit indices into a [`Palette`], for up to 256 colors.
+    ///
+    /// [`Palette`]: crate::Palette
+    pub const INDEXED8: Format = Format::new(8, ColorType::Indexed, 0, 8, 0, 0);
+    /// 4-bit indices into a [`Palette`], for up to 16 colors.
+    ///
+    /// [`Palette`]: crate::Palette
+    pub const INDEXED4: Format = Format::new(4, ColorType::Indexed, 0, 4, 0, 0);
+    /// 1-bit indices into a [`Palette`], for two colors.
+    ///
+    /// [`Palette`]: crate::Palette
+    pub const INDEXED1: Format = Format::new(1, ColorType::Indexed, 0, 1, 0, 0);
+
+    /// The RGB565 format used by most embedded displays.
+    ///
+    /// Red is in the top five bits of the 16-bit quantum and blue is in
+    /// the bottom five, with six bits of green in between.
+    pub const RGB565: Format = Format::new(16, ColorType::Abgr, 0, 5, 6, 5);
+    /// The RGB555 format, which is [`RGB565`] with five bits of green and
+    /// an unused top bit.
+    ///
+    /// [`RGB565`]: crate::Format::RGB565
+    pub const RGB555: Format = Format::new(16, ColorType::Abgr, 0, 5, 5, 5);
+    /// The ARGB1555 format, which is [`RGB555`] with a one-bit alpha
+    /// channel in the top bit.
+    ///
+    /// [`RGB555`]: crate::Format::RGB555
+    pub const ARGB1555: Format = Format::new(16, ColorType::Bgra, 1, 5, 5, 5);
+    /// The XRGB2101010 format used for HDR scanout.
+    ///
+    /// Red is in bits 20 to 29 of the 32-bit quantum and blue is in the
+    /// bottom ten, with ten bits of green in between and two unused bits
+    /// on top.
+    pub const XRGB2101010: Format = Format::new(32, ColorType::Bgra, 0, 10, 10, 10);
+    /// The ARGB2101010 format, which is [`XRGB2101010`] with a two-bit
+    /// alpha channel in the top bits.
+    ///
+    /// [`XRGB2101010`]: crate::Format::XRGB2101010
+    pub const ARGB2101010: Format = Format::new(32, ColorType::Bgra, 2, 10, 10, 10);
+
+    /// Cyan, magenta, yellow and black with 8 bits each, in that order.
+    ///
+    /// See [`ColorType::Cmyk`] for how these are converted to colors.
+    ///
+    /// [`ColorType::Cmyk`]: crate::ColorType::Cmyk
+    pub const CMYK32: Format = Format::new(32, ColorType::Cmyk, 8, 8, 8, 8);
+
+    /// Packed 4:2:2 YCbCr using BT.601, as produced by most webcams.
+    ///
+    /// In little endian, the bytes of each pair of pixels are Y0, Cb, Y1
+    /// and Cr. See [`ColorType::Yuyv`] for how these are converted to
+    /// colors.
+    ///
+    /// [`ColorType::Yuyv`]: crate::ColorType::Yuyv
+    pub const YUYV: Format = Format::new(16, ColorType::Yuyv, 0, 8, 8, 0);
+    /// Packed 4:2:2 YCbCr using BT.601, where the bytes of each pair of
+    /// pixels are Cb, Y0, Cr and Y1 in little endian.
+    pub const UYVY: Format = Format::new(16, ColorType::Uyvy, 0, 8, 8, 0);
+    /// [`YUYV`] using BT.709, as used by high definition video.
+    ///
+    /// [`YUYV`]: crate::Format::YUYV
+    pub const YUYV_BT709: Format = Format::new(16, ColorType::YuyvBt709, 0, 8, 8, 0);
+    /// [`UYVY`] using BT.709, as used by high definition video.
+    ///
+    /// [`UYVY`]: crate::Format::UYVY
+    pub const UYVY_BT709: Format = Format::new(16, ColorType::UyvyBt709, 0, 8, 8, 0);
+
     /// The ARGB16 format.
     pub const ARGB16: Format = Format::new(16, ColorType::Argb, 4, 4, 4, 4);
     /// The XRGB16 format.
@@ -133,17 +339,26 @@ impl Format {
     /// RGB with 32-bit floats.
     pub const RGB_F32: Format = Format::new(32 * 3, ColorType::ArgbFloat, 0, 32, 32, 32);
 
+    /// ARGB with 16-bit half floats, as used by GPU render targets.
+    pub const ARGB_F16: Format = Format::new(16 * 4, ColorType::ArgbFloat16, 16, 16, 16, 16);
+    /// RGB with 16-bit half floats.
+    pub const RGB_F16: Format = Format::new(16 * 3, ColorType::ArgbFloat16, 0, 16, 16, 16);
+
     /// Create a new format with the given specifications.
     ///
     /// ## Parameters
     ///
     /// - `bpp` is the bits per pixel for the format. The valid values for this
-    ///   parameter are 1, 4, 8, 16, 24, 32, 96 and 128. If the value is not
+    ///   parameter are 1, 4, 8, 16, 24, 32, 48, 64, 96 and 128. If the value is not
     ///   valid, it will be rounded up or down to another entry.
     /// - `color_type` is the color type for the format.
     /// - `*_bits` is used to encode the number of bits used for each channel.
     ///   Valid values are 0 through 8, 10, 16 and 32. If the value is not
     ///   one of these, logic errors will occur, up to and including panics.
+    ///
+    /// See [`try_new`] for a version that checks its parameters.
+    ///
+    /// [`try_new`]: crate::Format::try_new
     pub const fn new(
         bpp: u8,
         color_type: ColorType,
@@ -160,27 +375,428 @@ impl Format {
             9..=16 => 16,
             17..=24 => 24,
             25..=32 => 32,
-            33..=96 => 96,
+            33..=48 => 48,
+            49..=64 => 64,
+            65..=96 => 96,
             _ => 128,
         };
 
         Self {
             bpp,
             color_type,
-            channels: Channels::new(alpha_bits, red_bits, green_bits, blue_bits),
+            channels: ChannelBits::new(alpha_bits, red_bits, green_bits, blue_bits),
         }
     }
 
+    /// Like [`new`], but checks that the format can be used instead of
+    /// rounding or panicking.
+    ///
+    /// This is for descriptions of formats that come from outside of the
+    /// program, such as from a file or a socket.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{ColorType, Format, FormatError};
+    ///
+    /// assert_eq!(Format::try_new(16, ColorType::Abgr, 0, 5, 6, 5), Ok(Format::RGB565));
+    /// assert_eq!(
+    ///     Format::try_new(8, ColorType::Abgr, 0, 5, 6, 5),
+    ///     Err(FormatError::ChannelsTooWide { bpp: 8, bits: 16 })
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bpp` isn't one of the valid values, if a
+    /// channel has an unsupported number of bits, or if the channels don't
+    /// fit in `bpp` bits. Channels that `color_type` doesn't have must have
+    /// no bits, and the channels of float color types must be as wide as
+    /// their floats.
+    ///
+    /// [`new`]: crate::Format::new
+    pub fn try_new(
+        bpp: u8,
+        color_type: ColorType,
+        alpha_bits: u8,
+        red_bits: u8,
+        green_bits: u8,
+        blue_bits: u8,
+    ) -> Result<Self, FormatError> {
+        if !core::matches!(bpp, 1 | 4 | 8 | 16 | 24 | 32 | 48 | 64 | 96 | 128) {
+            return Err(FormatError::InvalidBpp(bpp));
+        }
+
+        let channels = [
+            (Channel::Alpha, alpha_bits),
+            (Channel::Red, red_bits),
+            (Channel::Green, green_bits),
+            (Channel::Blue, blue_bits),
+        ];
+        let mut total = 0;
+        for &(channel, bits) in channels.iter() {
+            let valid = if !color_type.channels().any(|other| other == channel) {
+                bits == 0
+            } else {
+                match color_type {
+                    ColorType::ArgbFloat => core::matches!(bits, 0 | 32),
+                    ColorType::ArgbFloat16 => core::matches!(bits, 0 | 16),
+                    _ => core::matches!(bits, 0..=8 | 10 | 16 | 32),
+                }
+            };
+            if !valid {
+                return Err(FormatError::InvalidChannelBits { channel, bits });
+            }
+            total += u32::from(bits);
+        }
+        if total > u32::from(bpp) {
+            return Err(FormatError::ChannelsTooWide { bpp, bits: total });
+        }
+
+        Ok(Self::new(
+            bpp, color_type, alpha_bits, red_bits, green_bits, blue_bits,
+        ))
+    }
+
+    /// Create a new format with the channels in any order.
+    ///
+    /// `order` lists the channels from the lowest bits of the pixel to the
+    /// highest, and `bits` is the number of bits for each of them, in the
+    /// same order. See [`new`] for the valid values.
+    ///
+    /// This is an escape hatch for device formats that `genimage` doesn't
+    /// have a constant for.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Channel::*, Format};
+    ///
+    /// // three bits of red and green, then two bits of blue
+    /// let bgr233 = Format::custom(8, [Red, Green, Blue, Alpha], [3, 3, 2, 0]);
+    /// assert_eq!(bgr233.blue_bits(), 2);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a channel appears more than once in `order`.
+    ///
+    /// [`new`]: crate::Format::new
+    pub const fn custom(bpp: u8, order: [Channel; 4], bits: [u8; 4]) -> Self {
+        let mut channel_bits = [0u8; 4];
+        let mut i = 0;
+        while i < 4 {
+            channel_bits[order[i] as usize] = bits[i];
+            i += 1;
+        }
+
+        Self::new(
+            bpp,
+            ColorType::Custom(ChannelOrder::new(order)),
+            channel_bits[Channel::Alpha as usize],
+            channel_bits[Channel::Red as usize],
+            channel_bits[Channel::Green as usize],
+            channel_bits[Channel::Blue as usize],
+        )
+    }
+
+    /// Parse an entry of [`format_spec!`], such as `R8`, into its channel
+    /// and number of bits.
+    ///
+    /// [`format_spec!`]: crate::format_spec
+    #[doc(hidden)]
+    pub const fn __spec_channel(name: &str) -> (Option<Channel>, u8) {
+        let name = name.as_bytes();
+        let channel = match name[0] {
+            b'R' => Some(Channel::Red),
+            b'G' => Some(Channel::Green),
+            b'B' => Some(Channel::Blue),
+            b'A' => Some(Channel::Alpha),
+            _ => None,
+        };
+        const_assert!(
+            matches!(name[0], b'R' | b'G' | b'B' | b'A' | b'X'),
+            "Channels must be one of R, G, B, A or X"
+        );
+
+        let mut bits = 0u32;
+        let mut i = 1;
+        while i < name.len() {
+            let digit = name[i];
+            const_assert!(
+                digit >= b'0' && digit <= b'9' && bits < 10,
+                "Invalid number of bits"
+            );
+            bits = bits * 10 + (digit - b'0') as u32;
+            i += 1;
+        }
+        const_assert!(
+            matches!(bits, 1..=8 | 10 | 16 | 32),
+            "Invalid number of bits"
+        );
+
+        (channel, bits as u8)
+    }
+
+    /// Build a format from the entries of [`format_spec!`].
+    ///
+    /// [`format_spec!`]: crate::format_spec
+    #[doc(hidden)]
+    pub const fn __from_spec(spec: &[(Option<Channel>, u8)]) -> Self {
+        use Channel::*;
+
+        let mut listed = [Alpha; 4];
+        let mut count = 0;
+        let mut bits = [0u8; 4];
+        let mut seen = [false; 4];
+        let mut total = 0u32;
+        let mut padded = false;
+
+        let mut i = 0;
+        while i < spec.len() {
+            let (channel, width) = spec[i];
+            match channel {
+                Some(channel) => {
+                    const_assert!(!padded, "Padding must come after every channel");
+                    const_assert!(
+                        !seen[channel as usize],
+                        "Every channel must appear at most once"
+                    );
+                    seen[channel as usize] = true;
+                    bits[channel as usize] = width;
+                    listed[count] = channel;
+                    count += 1;
+                }
+                None => padded = true,
+            }
+            total += width as u32;
+            i += 1;
+        }
+
+        const_assert!(
+            matches!(total, 1 | 4 | 8 | 16 | 24 | 32 | 48 | 64 | 96 | 128),
+            "The channels must add up to a valid number of bits per pixel"
+        );
+        let bpp = total as u8;
+
+        // channels without bits go first, so that formats without alpha
+        // match the named color types
+        let mut order = [Alpha; 4];
+        let mut filled = 0;
+        let all = [Red, Green, Blue, Alpha];
+        let mut i = 0;
+        while i < 4 {
+            if !seen[all[i] as usize] {
+                order[filled] = all[i];
+                filled += 1;
+            }
+            i += 1;
+        }
+        let mut i = 0;
+        while i < count {
+            order[filled + i] = listed[i];
+            i += 1;
+        }
+
+        let named = [
+            (ColorType::Argb, [Alpha, Red, Green, Blue]),
+            (ColorType::Rgba, [Red, Green, Blue, Alpha]),
+            (ColorType::Abgr, [Alpha, Blue, Green, Red]),
+            (ColorType::Bgra, [Blue, Green, Red, Alpha]),
+        ];
+        let index = permutation_index(order);
+        let mut color_type = ColorType::Custom(ChannelOrder::new(order));
+        let mut i = 0;
+        while i < named.len() {
+            if permutation_index(named[i].1) == index {
+                color_type = named[i].0;
+            }
+            i += 1;
+        }
+        if count == 1 && listed[0] as u8 == Alpha as u8 {
+            color_type = ColorType::Alpha;
+        }
+
+        Self::new(
+            bpp,
+            color_type,
+            bits[Alpha as usize],
+            bits[Red as usize],
+            bits[Green as usize],
+            bits[Blue as usize],
+        )
+    }
+
+    /// Create a format from the bit masks of its channels, as X11 visuals
+    /// and DRM describe them.
+    ///
+    /// The masks are over a pixel read as an integer, so `0x00FF_0000` is
+    /// the third byte in little endian. Channels with an empty mask are
+    /// left out. A named color type is used if one has the channels in the
+    /// same order, and a [`Custom`] color type otherwise.
+    ///
+    /// Returns `None` if `bpp` isn't 1, 4, 8, 16, 24 or 32, or if the masks
+    /// overlap, leave gaps between the channels or below the lowest one,
+    /// don't fit in `bpp`, or have a number of bits that [`new`] doesn't
+    /// support.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Channel, Format};
+    ///
+    /// // a 24-bit TrueColor visual
+    /// let format = Format::from_masks(32, 0xFF_0000, 0xFF00, 0xFF, 0).unwrap();
+    /// assert_eq!(format, Format::XBGR32);
+    /// assert_eq!(format.channel_mask(Channel::Red), 0xFF_0000);
+    /// ```
+    ///
+    /// [`Custom`]: crate::ColorType::Custom
+    /// [`new`]: crate::Format::new
+    pub fn from_masks(
+        bpp: u8,
+        red_mask: u32,
+        green_mask: u32,
+        blue_mask: u32,
+        alpha_mask: u32,
+    ) -> Option<Format> {
+        if !core::matches!(bpp, 1 | 4 | 8 | 16 | 24 | 32) {
+            return None;
+        }
+
+        // sort the channels from the lowest bits up, with missing channels
+        // at the end
+        let mut masks = [
+            (Channel::Red, red_mask),
+            (Channel::Green, green_mask),
+            (Channel::Blue, blue_mask),
+            (Channel::Alpha, alpha_mask),
+        ];
+        masks.sort_unstable_by_key(|&(_, mask)| match mask {
+            0 => 32,
+            mask => mask.trailing_zeros(),
+        });
+
+        let mut order = [Channel::Alpha; 4];
+        let mut bits = [0u8; 4];
+        let mut present = 0;
+        let mut shift = 0;
+        for (i, &(channel, mask)) in masks.iter().enumerate() {
+            order[i] = channel;
+            if mask == 0 {
+                continue;
+            }
+
+            // each channel has to start right where the last one ended
+            let width = mask.count_ones();
+            if mask.trailing_zeros() != shift || u64::from(mask >> shift) != low_bits(width as u8) {
+                return None;
+            }
+            if !core::matches!(width, 1..=8 | 10 | 16 | 32) {
+                return None;
+            }
+
+            bits[i] = width as u8;
+            present += 1;
+            shift += width;
+        }
+        if present == 0 || shift > u32::from(bpp) {
+            return None;
+        }
+
+        let mut channel_bits = [0u8; 4];
+        for (&channel, &bits) in order.iter().zip(bits.iter()) {
+            channel_bits[channel as usize] = bits;
+        }
+
+        let named = [
+            ColorType::Alpha,
+            ColorType::Argb,
+            ColorType::Abgr,
+            ColorType::Rgba,
+            ColorType::Bgra,
+        ];
+        let color_type = named
+            .iter()
+            .copied()
+            .find(|color_type| {
+                color_type
+                    .channels()
+                    .filter(|&channel| channel_bits[channel as usize] != 0)
+                    .eq(order[..present].iter().copied())
+            })
+            .unwrap_or(ColorType::Custom(ChannelOrder::new(order)));
+
+        Some(Self::new(
+            bpp,
+            color_type,
+            channel_bits[Channel::Alpha as usize],
+            channel_bits[Channel::Red as usize],
+            channel_bits[Channel::Green as usize],
+            channel_bits[Channel::Blue as usize],
+        ))
+    }
+
+    /// The bit mask of a channel over a pixel read as an integer.
+    ///
+    /// This is the reverse of [`from_masks`]. Channels that the format
+    /// doesn't have, or that don't fit in the lowest 32 bits of the pixel,
+    /// give an empty mask.
+    ///
+    /// [`from_masks`]: crate::Format::from_masks
+    pub fn channel_mask(&self, channel: Channel) -> u32 {
+        match self.mask_for(channel) {
+            mask if mask > u64::from(u32::MAX) => 0,
+            mask => mask as u32,
+        }
+    }
+
+    /// The number of bits that a channel is shifted up by within a pixel
+    /// read as an integer, or `None` if the format doesn't have it.
+    pub fn shift_for(&self, channel: Channel) -> Option<u8> {
+        self.channel_info(channel).map(|info| info.shift)
+    }
+
+    /// The bit mask of a channel within a pixel read as an integer.
+    ///
+    /// Channels that the format doesn't have, or that don't fit in the
+    /// lowest 64 bits of the pixel, give an empty mask.
+    pub fn mask_for(&self, channel: Channel) -> u64 {
+        self.channel_info(channel)
+            .filter(|info| u32::from(info.shift) + u32::from(info.bits) <= 64)
+            .map_or(0, |info| low_bits(info.bits) << info.shift)
+    }
+
+    /// The largest raw value of a channel, or zero if the format doesn't
+    /// have it.
+    ///
+    /// This is the value that a fully saturated integer channel holds. For
+    /// float channels, it is the bits of the largest finite float.
+    pub fn max_value(&self, channel: Channel) -> u64 {
+        self.channel_info(channel).map_or(0, |info| {
+            match (self.color_type().involves_float(), info.bits) {
+                (false, bits) => low_bits(bits),
+                // the largest finite half float, 65504
+                (true, 16) => 0x7BFF,
+                (true, _) => u64::from(f32::MAX.to_bits()),
+            }
+        })
+    }
+
+    fn channel_info(&self, channel: Channel) -> Option<ChannelInfo> {
+        self.channels().find(|info| info.channel == channel)
+    }
+
     /// The bits per pixel for this image.
     pub const fn bpp(&self) -> u8 {
-        self.bpp
+        self.bpp & !PREMULTIPLIED
     }
 
     /// Number of bytes per pixel.
     ///
     /// This is the number of bytes required to encode a pixel.
     pub const fn bytes(&self) -> u8 {
-        match self.bpp {
+        match self.bpp() {
             1 | 4 => 1,
             bpp => bpp / 8,
         }
@@ -188,7 +804,38 @@ impl Format {
 
     /// If the size of a pixel is less than a byte.
     pub const fn subbyte(&self) -> bool {
-        self.bpp < 8
+        self.bpp() < 8
+    }
+
+    /// Whether the color channels are stored premultiplied by alpha.
+    pub const fn alpha_mode(&self) -> AlphaMode {
+        if self.bpp & PREMULTIPLIED != 0 {
+            AlphaMode::Premultiplied
+        } else {
+            AlphaMode::Straight
+        }
+    }
+
+    /// This format, with the color channels stored straight or
+    /// premultiplied by alpha.
+    ///
+    /// Formats start out straight. The alpha mode only affects formats with
+    /// an alpha channel, other than CMYK formats, which store black in its
+    /// place.
+    pub const fn with_alpha_mode(self, mode: AlphaMode) -> Self {
+        let bpp = match mode {
+            AlphaMode::Straight => self.bpp & !PREMULTIPLIED,
+            AlphaMode::Premultiplied => self.bpp | PREMULTIPLIED,
+        };
+        Self { bpp, ..self }
+    }
+
+    /// Whether converting to or from this format multiplies or divides
+    /// colors by alpha.
+    pub(crate) fn premultiplies(&self) -> bool {
+        self.alpha_mode() == AlphaMode::Premultiplied
+            && self.alpha_bits() > 0
+            && !self.color_type.is_cmyk()
     }
 
     /// The color type for this image.
@@ -198,7 +845,7 @@ impl Format {
 
     /// The number of bits used in the alpha channel.
     pub const fn alpha_bits(&self) -> u8 {
-        self.channels.alpha() as u8
+        self.channels.alpha()
     }
 
     pub(crate) fn involves_float(&self) -> bool {
@@ -207,17 +854,17 @@ impl Format {
 
     /// The number of bits used in the red channel.
     pub const fn red_bits(&self) -> u8 {
-        self.channels.red() as u8
+        self.channels.red()
     }
 
     /// The number of bits used in the green channel.
     pub const fn green_bits(&self) -> u8 {
-        self.channels.green() as u8
+        self.channels.green()
     }
 
     /// The number of bits used in the blue channel.
     pub const fn blue_bits(&self) -> u8 {
-        self.channels.blue() as u8
+        self.channels.blue()
     }
 
     const fn bits_for_channel(&self, channel: Channel) -> u8 {
@@ -237,40 +884,51 @@ impl Format {
     /// were encoded in a primitive value.
     ///
     /// The channels are presented in order.
-    pub fn channels(
-        self,
-    ) -> impl FusedIterator<Item = ChannelInfo> + ExactSizeIterator + DoubleEndedIterator {
-        ChannelIter::new(self, self.color_type().channels())
+    pub fn channels(self) -> ChannelInfos {
+        ChannelInfos::new(self)
     }
 }
 
-/// Iterator over the channels of this format.
+/// An iterator over the channels of a format, along with where they are.
+///
+/// This is returned by [`Format::channels`]. It is a named type rather
+/// than an `impl Iterator` so that it can be stored in structures.
 ///
 /// I could've used an STD iterator, but this let me make it
 /// `ExactSizeIterator` and `DoubleEndedIterator`, which a standard
 /// `filter_map()` wouldn't let me do.
-#[derive(Debug)]
-struct ChannelIter<I: ?Sized> {
+///
+/// [`Format::channels`]: crate::Format::channels
+#[derive(Debug, Clone)]
+pub struct ChannelInfos {
     format: Format,
     /// Current shift from the front.
     shift: u8,
     /// Current shift from the back.
     shift_back: u8,
-    channels: I,
+    channels: Channels,
 }
 
-impl<I> ChannelIter<I> {
-    fn new(format: Format, channels: I) -> Self {
+impl ChannelInfos {
+    fn new(format: Format) -> Self {
+        // padding bits come after the channels, so the back of the
+        // iterator is where the last channel ends
+        let shift_back = format
+            .color_type()
+            .channels()
+            .map(|channel| format.bits_for_channel(channel))
+            .sum();
+
         Self {
             format,
             shift: 0,
-            shift_back: format.bpp,
-            channels,
+            shift_back,
+            channels: format.color_type().channels(),
         }
     }
 }
 
-impl<I: ExactSizeIterator<Item = Channel>> Iterator for ChannelIter<I> {
+impl Iterator for ChannelInfos {
     type Item = ChannelInfo;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -319,8 +977,8 @@ impl<I: ExactSizeIterator<Item = Channel>> Iterator for ChannelIter<I> {
     }
 }
 
-impl<I: FusedIterator<Item = Channel> + ExactSizeIterator> FusedIterator for ChannelIter<I> {}
-impl<I: ExactSizeIterator<Item = Channel>> ExactSizeIterator for ChannelIter<I> {
+impl FusedIterator for ChannelInfos {}
+impl ExactSizeIterator for ChannelInfos {
     fn len(&self) -> usize {
         // determine which channels we need to work with from
         // this point on
@@ -331,16 +989,15 @@ impl<I: ExactSizeIterator<Item = Channel>> ExactSizeIterator for ChannelIter<I>
             .channels()
             .filter(|channel| {
                 let bits = self.format.bits_for_channel(*channel);
+                let start = current_shift;
                 current_shift += bits;
-                current_shift > self.shift && current_shift < self.shift_back
+                bits != 0 && start >= self.shift && current_shift <= self.shift_back
             })
             .count()
     }
 }
 
-impl<I: DoubleEndedIterator<Item = Channel> + ExactSizeIterator> DoubleEndedIterator
-    for ChannelIter<I>
-{
+impl DoubleEndedIterator for ChannelInfos {
     fn next_back(&mut self) -> Option<Self::Item> {
         loop {
             // if we've met the other end of the iterator, return
@@ -359,19 +1016,212 @@ impl<I: DoubleEndedIterator<Item = Channel> + ExactSizeIterator> DoubleEndedIter
                 continue;
             }
 
-            // create the channel info
-            let info = ChannelInfo {
+            // decrement the shift
+            self.shift_back = self.shift_back.saturating_sub(bits);
+
+            // create and return the channel info
+            return Some(ChannelInfo {
                 bits,
                 shift: self.shift_back,
                 channel,
-            };
+            });
+        }
+    }
+}
 
-            // decrement the shift
-            self.shift = self.shift_back.saturating_sub(bits);
+/// An iterator over the channels of a color type.
+///
+/// This is returned by [`ColorType::channels`]. It is a named type rather
+/// than an `impl Iterator` so that it can be stored in structures.
+///
+/// [`ColorType::channels`]: crate::ColorType::channels
+#[derive(Debug, Clone)]
+pub struct Channels {
+    channels: [Channel; 4],
+    /// The index of the next channel from the front.
+    front: u8,
+    /// The index after the next channel from the back.
+    back: u8,
+}
 
-            // return the channel info
-            return Some(info);
+impl Iterator for Channels {
+    type Item = Channel;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
         }
+
+        self.front += 1;
+        Some(self.channels[self.front as usize - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let l = self.len();
+        (l, Some(l))
+    }
+}
+
+impl FusedIterator for Channels {}
+impl ExactSizeIterator for Channels {
+    fn len(&self) -> usize {
+        (self.back - self.front) as usize
+    }
+}
+
+impl DoubleEndedIterator for Channels {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.channels[self.back as usize])
+    }
+}
+
+impl fmt::Debug for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Format")
+            .field("bpp", &self.bpp())
+            .field("color_type", &self.color_type)
+            .field("channels", &self.channels)
+            .field("alpha_mode", &self.alpha_mode())
+            .finish()
+    }
+}
+
+/// The names of the standard formats.
+///
+/// Formats with more than one name are shown with the first one.
+const NAMED: &[(&str, Format)] = &[
+    ("ARGB32", Format::ARGB32),
+    ("ARGB32_PREMUL", Format::ARGB32_PREMUL),
+    ("XRGB32", Format::XRGB32),
+    ("ABGR32", Format::ABGR32),
+    ("XBGR32", Format::XBGR32),
+    ("RGBA32", Format::RGBA32),
+    ("RGBX32", Format::RGBX32),
+    ("BGRA32", Format::BGRA32),
+    ("BGRX32", Format::BGRX32),
+    ("RGB24", Format::RGB24),
+    ("BGR24", Format::BGR24),
+    ("RGB48", Format::RGB48),
+    ("BGR48", Format::BGR48),
+    ("RGBA64", Format::RGBA64),
+    ("ABGR64", Format::ABGR64),
+    ("RG8", Format::RG8),
+    ("RG16", Format::RG16),
+    ("LA8", Format::LA8),
+    ("GRAY8", Format::GRAY8),
+    ("GRAY16", Format::GRAY16),
+    ("GRAYA16", Format::GRAYA16),
+    ("INDEXED8", Format::INDEXED8),
+    ("INDEXED4", Format::INDEXED4),
+    ("INDEXED1", Format::INDEXED1),
+    ("RGB565", Format::RGB565),
+    ("RGB555", Format::RGB555),
+    ("ARGB1555", Format::ARGB1555),
+    ("CMYK32", Format::CMYK32),
+    ("YUYV", Format::YUYV),
+    ("UYVY", Format::UYVY),
+    ("YUYV_BT709", Format::YUYV_BT709),
+    ("UYVY_BT709", Format::UYVY_BT709),
+    ("ARGB16", Format::ARGB16),
+    ("XRGB16", Format::XRGB16),
+    ("ABGR16", Format::ABGR16),
+    ("XBGR16", Format::XBGR16),
+    ("A8", Format::A8),
+    ("A4", Format::A4),
+    ("A1", Format::A1),
+    ("ARGB_F32", Format::ARGB_F32),
+    ("RGB_F32", Format::RGB_F32),
+    ("ARGB_F16", Format::ARGB_F16),
+    ("RGB_F16", Format::RGB_F16),
+];
+
+/// Formats are shown with the name of their constant, like `ARGB32`.
+/// Other formats are shown with their bits per pixel and the number of
+/// bits in each channel, from the lowest bits up, like
+/// `custom(bpp=16, r=5 g=6 b=5)`.
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(&(name, _)) = NAMED.iter().find(|&&(_, format)| format == *self) {
+            return f.write_str(name);
+        }
+
+        write!(f, "custom(bpp={}", self.bpp())?;
+        let kind = match self.color_type {
+            ColorType::Gray | ColorType::GrayAlpha => Some("gray"),
+            ColorType::Cmyk => Some("cmyk"),
+            ColorType::Indexed => Some("indexed"),
+            ColorType::Yuyv | ColorType::Uyvy => Some("ycbcr"),
+            ColorType::YuyvBt709 | ColorType::UyvyBt709 => Some("ycbcr-bt709"),
+            ColorType::ArgbFloat | ColorType::ArgbFloat16 => Some("float"),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            write!(f, ", {}", kind)?;
+        }
+
+        let mut separator = ", ";
+        for info in self.channels() {
+            let letter = match info.channel {
+                Channel::Alpha => 'a',
+                Channel::Red => 'r',
+                Channel::Green => 'g',
+                Channel::Blue => 'b',
+            };
+            write!(f, "{}{}={}", separator, letter, info.bits)?;
+            separator = " ";
+        }
+
+        if self.alpha_mode() == AlphaMode::Premultiplied {
+            f.write_str(", premultiplied")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Parses the name of one of the format constants, like `ARGB32`,
+/// ignoring ASCII case.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownFormat`] for any other string.
+///
+/// [`Error::UnknownFormat`]: crate::Error::UnknownFormat
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        NAMED
+            .iter()
+            .find(|&&(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|&(_, format)| format)
+            .ok_or(Error::UnknownFormat)
+    }
+}
+
+/// Whether the color channels of a format are premultiplied by alpha.
+///
+/// Compositors usually work with premultiplied colors, since blending
+/// them is cheaper and doesn't bleed the color of transparent pixels.
+/// `genimage` converts between the two whenever pixels change format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlphaMode {
+    /// The color channels are independent of alpha.
+    ///
+    /// This is the default.
+    Straight,
+    /// The color channels have been multiplied by alpha.
+    Premultiplied,
+}
+
+impl Default for AlphaMode {
+    fn default() -> Self {
+        AlphaMode::Straight
     }
 }
 
@@ -380,7 +1230,7 @@ impl<I: DoubleEndedIterator<Item = Channel> + ExactSizeIterator> DoubleEndedIter
 /// This defines the channels that appear for the format, as well
 /// as the order that they appear in.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ColorType {
     /// Packed ARGB tuple.
     Argb,
@@ -392,12 +1242,91 @@ pub enum ColorType {
     Bgra,
     /// Single alpha channel.
     Alpha,
+    /// Packed RG tuple, for two-channel textures such as normal maps.
+    ///
+    /// Converting to formats with more channels gives zero blue and full
+    /// alpha.
+    Rg,
+    /// Single gray channel.
+    ///
+    /// The gray level is stored in the bits of the red channel, and
+    /// reads back as a color where red, green and blue are all equal to
+    /// it. Colors are converted to gray by their BT.601 luma.
+    Gray,
+    /// Packed gray and alpha tuple, starting with gray.
+    ///
+    /// The gray level is treated the same way as in [`Gray`].
+    ///
+    /// [`Gray`]: crate::ColorType::Gray
+    GrayAlpha,
+    /// Packed CMYK tuple, for print.
+    ///
+    /// Cyan, magenta, yellow and black are stored in the bits of the red,
+    /// green, blue and alpha channels, respectively. They read back as an
+    /// opaque color using the naive conversion, where red is
+    /// `(1 - cyan) * (1 - black)` and so on, and colors are converted to
+    /// CMYK by taking as much black as possible. Color profiles are not
+    /// applied.
+    Cmyk,
+    /// Index into a [`Palette`], as used by legacy framebuffers and X11
+    /// `PseudoColor` visuals.
+    ///
+    /// The index is stored in the bits of the red channel. Without a
+    /// palette it reads back as a level of red, so indices should be
+    /// resolved with [`Pixel::resolve`] or [`IndexedImage`] instead of
+    /// being converted.
+    ///
+    /// [`Palette`]: crate::Palette
+    /// [`Pixel::resolve`]: crate::Pixel::resolve
+    /// [`IndexedImage`]: crate::IndexedImage
+    Indexed,
+    /// Packed 4:2:2 YCbCr using BT.601, where each pair of pixels shares
+    /// its chroma, in the order luma then chroma.
+    ///
+    /// Each pixel stores its luma in the bits of the red channel and one
+    /// chroma sample in the bits of the green channel: the blue-difference
+    /// chroma for pixels at even x, and the red-difference chroma for
+    /// pixels at odd x. Values are in the limited range used by video, and
+    /// read back as opaque colors.
+    ///
+    /// Writing a pixel replaces the chroma of both pixels in its pair.
+    /// Bulk operations keep to whole pairs as long as they start at an
+    /// even x.
+    Yuyv,
+    /// Packed 4:2:2 YCbCr using BT.601, in the order chroma then luma.
+    ///
+    /// This is otherwise the same as [`Yuyv`].
+    ///
+    /// [`Yuyv`]: crate::ColorType::Yuyv
+    Uyvy,
+    /// [`Yuyv`] using BT.709.
+    ///
+    /// [`Yuyv`]: crate::ColorType::Yuyv
+    YuyvBt709,
+    /// [`Uyvy`] using BT.709.
+    ///
+    /// [`Uyvy`]: crate::ColorType::Uyvy
+    UyvyBt709,
     /// Tuple of 32-bit floats.
     ///
     /// This implies that the bit count for each component is either 32
     /// or 0. If either of these are not the case, this may lead to panics
     /// or rounding, but never unsafe behavior.
     ArgbFloat,
+    /// Tuple of 16-bit IEEE half floats.
+    ///
+    /// This is like [`ArgbFloat`], but the bit count for each component
+    /// is either 16 or 0.
+    ///
+    /// [`ArgbFloat`]: crate::ColorType::ArgbFloat
+    ArgbFloat16,
+    /// Packed tuple with the channels in any order.
+    ///
+    /// This can be used for formats that `genimage` doesn't have a
+    /// color type for. See [`Format::custom`].
+    ///
+    /// [`Format::custom`]: crate::Format::custom
+    Custom(ChannelOrder),
 }
 
 impl ColorType {
@@ -405,9 +1334,7 @@ impl ColorType {
     ///
     /// This will iterate over the channels in the order that they
     /// appear in the color type.
-    pub fn channels(
-        self,
-    ) -> impl ExactSizeIterator<Item = Channel> + DoubleEndedIterator + FusedIterator {
+    pub fn channels(self) -> Channels {
         use Channel::*;
 
         let item = match self {
@@ -416,15 +1343,168 @@ impl ColorType {
             ColorType::Abgr => ArrayVec::from_array_len([Alpha, Blue, Green, Red], 4),
             ColorType::Bgra => ArrayVec::from_array_len([Blue, Green, Red, Alpha], 4),
             ColorType::Alpha => ArrayVec::from_array_len([Alpha, Alpha, Alpha, Alpha], 1),
-            ColorType::ArgbFloat => ArrayVec::from_array_len([Alpha, Red, Green, Blue], 4),
+            ColorType::Rg => ArrayVec::from_array_len([Red, Green, Red, Red], 2),
+            ColorType::Gray => ArrayVec::from_array_len([Red, Red, Red, Red], 1),
+            ColorType::GrayAlpha => ArrayVec::from_array_len([Red, Alpha, Red, Red], 2),
+            ColorType::Cmyk => ArrayVec::from_array_len([Red, Green, Blue, Alpha], 4),
+            ColorType::Indexed => ArrayVec::from_array_len([Red, Red, Red, Red], 1),
+            ColorType::Yuyv | ColorType::YuyvBt709 => {
+                ArrayVec::from_array_len([Red, Green, Red, Red], 2)
+            }
+            ColorType::Uyvy | ColorType::UyvyBt709 => {
+                ArrayVec::from_array_len([Green, Red, Red, Red], 2)
+            }
+            ColorType::ArgbFloat | ColorType::ArgbFloat16 => {
+                ArrayVec::from_array_len([Alpha, Red, Green, Blue], 4)
+            }
+            ColorType::Custom(order) => ArrayVec::from_array_len(order.channels(), 4),
         };
 
-        AssertExactSize(item.into_iter())
+        Channels {
+            front: 0,
+            back: item.len() as u8,
+            channels: item.into_inner(),
+        }
     }
 
     /// Whether or not this color type involves floats.
     pub fn involves_float(self) -> bool {
-        core::matches!(self, ColorType::ArgbFloat)
+        core::matches!(self, ColorType::ArgbFloat | ColorType::ArgbFloat16)
+    }
+
+    /// Whether or not this color type stores a gray level in place of red,
+    /// green and blue.
+    pub fn is_gray(self) -> bool {
+        core::matches!(self, ColorType::Gray | ColorType::GrayAlpha)
+    }
+
+    /// Whether or not this color type stores cyan, magenta, yellow and
+    /// black in place of red, green, blue and alpha.
+    pub fn is_cmyk(self) -> bool {
+        core::matches!(self, ColorType::Cmyk)
+    }
+
+    /// The matrix that this color type converts YCbCr with, if it stores
+    /// YCbCr.
+    pub fn ycbcr_matrix(self) -> Option<YcbcrMatrix> {
+        match self {
+            ColorType::Yuyv | ColorType::Uyvy => Some(YcbcrMatrix::Bt601),
+            ColorType::YuyvBt709 | ColorType::UyvyBt709 => Some(YcbcrMatrix::Bt709),
+            _ => None,
+        }
+    }
+}
+
+/// The matrix used to convert between YCbCr and RGB.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum YcbcrMatrix {
+    /// ITU-R BT.601, used by standard definition video and most webcams.
+    Bt601,
+    /// ITU-R BT.709, used by high definition video.
+    Bt709,
+}
+
+impl YcbcrMatrix {
+    /// The weights of red and blue in the luma.
+    pub(crate) fn weights(self) -> (f32, f32) {
+        match self {
+            YcbcrMatrix::Bt601 => (0.299, 0.114),
+            YcbcrMatrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+}
+
+/// The order of the channels of a [`Custom`] color type.
+///
+/// Every channel appears exactly once, starting from the lowest bits of
+/// the pixel. Channels that a format doesn't have can be given zero bits.
+///
+/// [`Custom`]: crate::ColorType::Custom
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChannelOrder(Permutation);
+
+macro_rules! permutations {
+    ($($name: ident => [$($channel: ident),*],)*) => {
+        /// One of the orders that four channels can be in.
+        ///
+        /// This is an enum rather than an index so that `ColorType` can
+        /// be niched into a single byte.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        enum Permutation {
+            $($name,)*
+        }
+
+        const PERMUTATIONS: [(Permutation, [Channel; 4]); 24] = [
+            $((Permutation::$name, [$(Channel::$channel),*]),)*
+        ];
+    };
+}
+
+permutations! {
+    Rgba => [Red, Green, Blue, Alpha],
+    Rgab => [Red, Green, Alpha, Blue],
+    Rbga => [Red, Blue, Green, Alpha],
+    Rbag => [Red, Blue, Alpha, Green],
+    Ragb => [Red, Alpha, Green, Blue],
+    Rabg => [Red, Alpha, Blue, Green],
+    Grba => [Green, Red, Blue, Alpha],
+    Grab => [Green, Red, Alpha, Blue],
+    Gbra => [Green, Blue, Red, Alpha],
+    Gbar => [Green, Blue, Alpha, Red],
+    Garb => [Green, Alpha, Red, Blue],
+    Gabr => [Green, Alpha, Blue, Red],
+    Brga => [Blue, Red, Green, Alpha],
+    Brag => [Blue, Red, Alpha, Green],
+    Bgra => [Blue, Green, Red, Alpha],
+    Bgar => [Blue, Green, Alpha, Red],
+    Barg => [Blue, Alpha, Red, Green],
+    Bagr => [Blue, Alpha, Green, Red],
+    Argb => [Alpha, Red, Green, Blue],
+    Arbg => [Alpha, Red, Blue, Green],
+    Agrb => [Alpha, Green, Red, Blue],
+    Agbr => [Alpha, Green, Blue, Red],
+    Abrg => [Alpha, Blue, Red, Green],
+    Abgr => [Alpha, Blue, Green, Red],
+}
+
+impl ChannelOrder {
+    /// Create a new channel order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a channel appears more than once.
+    pub const fn new(order: [Channel; 4]) -> Self {
+        ChannelOrder(PERMUTATIONS[permutation_index(order)].0)
+    }
+
+    /// The channels, from the lowest bits to the highest.
+    pub const fn channels(self) -> [Channel; 4] {
+        PERMUTATIONS[self.0 as usize].1
+    }
+}
+
+/// Find the index of the permutation in `PERMUTATIONS`.
+const fn permutation_index(order: [Channel; 4]) -> usize {
+    let mut i = 0;
+    while i < PERMUTATIONS.len() {
+        let channels = PERMUTATIONS[i].1;
+        if channels[0] as u8 == order[0] as u8
+            && channels[1] as u8 == order[1] as u8
+            && channels[2] as u8 == order[2] as u8
+            && channels[3] as u8 == order[3] as u8
+        {
+            return i;
+        }
+        i += 1;
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(rustc_1_57)] {
+            panic!("Every channel must appear exactly once")
+        } else {
+            // out of bounds for `PERMUTATIONS`, which stops compilation
+            i
+        }
     }
 }
 
@@ -451,6 +1531,7 @@ pub struct ChannelInfo {
 ///
 /// [`channels()`]: crate::Format::channels
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Channel {
     /// Red channel.
     Red,
@@ -470,9 +1551,9 @@ impl Default for Channel {
 
 /// The number of bits each channel has.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct Channels(u16);
+struct ChannelBits(u16);
 
-/// Convert the real number of bits to its representation in `Channels`.
+/// Convert the real number of bits to its representation in `ChannelBits`.
 #[inline]
 const fn convert_to_channels_repr(num_bits: u8) -> u16 {
     match num_bits {
@@ -486,7 +1567,7 @@ const fn convert_to_channels_repr(num_bits: u8) -> u16 {
     }
 }
 
-/// Convert the representation of `Channels` to the real number of bits.
+/// Convert the representation of `ChannelBits` to the real number of bits.
 #[inline]
 const fn convert_from_channels_repr(num_bits: u16) -> u8 {
     match num_bits {
@@ -506,9 +1587,9 @@ const GREEN_SHIFT: u16 = 4;
 const BLUE_SHIFT: u16 = 0;
 const CHANNEL_MASK: u16 = 0x0f;
 
-impl Channels {
-    const fn new(alpha: u8, red: u8, green: u8, blue: u8) -> Channels {
-        Channels(
+impl ChannelBits {
+    const fn new(alpha: u8, red: u8, green: u8, blue: u8) -> ChannelBits {
+        ChannelBits(
             (convert_to_channels_repr(alpha) << ALPHA_SHIFT)
                 | (convert_to_channels_repr(red) << RED_SHIFT)
                 | (convert_to_channels_repr(green) << GREEN_SHIFT)
@@ -533,7 +1614,7 @@ impl Channels {
     }
 }
 
-impl fmt::Debug for Channels {
+impl fmt::Debug for ChannelBits {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Channels")
             .field("alpha", &self.alpha())
@@ -546,7 +1627,7 @@ impl fmt::Debug for Channels {
 
 #[cfg(test)]
 mod tests {
-    use super::Format;
+    use super::{Channel, ChannelInfos, Channels, ColorType, Format};
     use core::mem::size_of;
 
     #[test]
@@ -556,4 +1637,213 @@ mod tests {
         assert!(size_of::<Format>() <= size_of::<i32>());
         assert_eq!(size_of::<Format>(), size_of::<Option<Format>>());
     }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn names() {
+        use super::{AlphaMode, Channel::*, ColorType, NAMED};
+        use crate::Error;
+        use alloc::string::ToString;
+
+        for &(name, format) in NAMED {
+            assert_eq!(name.parse(), Ok(format));
+        }
+        assert_eq!(Format::GRAYA16.to_string(), "LA8");
+        assert_eq!("xrgb32".parse(), Ok(Format::XRGB32));
+        assert_eq!("RGB32".parse::<Format>(), Err(Error::UnknownFormat));
+
+        let bgr233 = Format::custom(8, [Red, Green, Blue, Alpha], [3, 3, 2, 0]);
+        assert_eq!(bgr233.to_string(), "custom(bpp=8, r=3 g=3 b=2)");
+        let gray4 = Format::new(4, ColorType::Gray, 0, 4, 0, 0);
+        assert_eq!(gray4.to_string(), "custom(bpp=4, gray, r=4)");
+        assert_eq!(
+            Format::ARGB16
+                .with_alpha_mode(AlphaMode::Premultiplied)
+                .to_string(),
+            "custom(bpp=16, a=4 r=4 g=4 b=4, premultiplied)"
+        );
+    }
+
+    #[test]
+    fn validation() {
+        use super::{Channel, ColorType, NAMED};
+        use crate::FormatError;
+
+        for &(name, format) in NAMED {
+            let checked = Format::try_new(
+                format.bpp(),
+                format.color_type(),
+                format.alpha_bits(),
+                format.red_bits(),
+                format.green_bits(),
+                format.blue_bits(),
+            );
+            assert_eq!(
+                checked.map(|checked| checked.with_alpha_mode(format.alpha_mode())),
+                Ok(format),
+                "{}",
+                name
+            );
+        }
+
+        assert_eq!(
+            Format::try_new(12, ColorType::Argb, 0, 4, 4, 4),
+            Err(FormatError::InvalidBpp(12))
+        );
+        assert_eq!(
+            Format::try_new(8, ColorType::Gray, 0, 8, 8, 0),
+            Err(FormatError::InvalidChannelBits {
+                channel: Channel::Green,
+                bits: 8
+            })
+        );
+        assert_eq!(
+            Format::try_new(64, ColorType::ArgbFloat, 0, 16, 16, 16),
+            Err(FormatError::InvalidChannelBits {
+                channel: Channel::Red,
+                bits: 16
+            })
+        );
+    }
+
+    #[test]
+    fn masks() {
+        use super::Channel::*;
+
+        let rgb565 = Format::from_masks(16, 0xF800, 0x07E0, 0x001F, 0).unwrap();
+        assert_eq!(rgb565, Format::RGB565);
+        assert_eq!(Format::from_masks(8, 0, 0, 0, 0xFF), Some(Format::A8));
+        assert_eq!(
+            Format::from_masks(32, 0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000),
+            Some(Format::RGBA32)
+        );
+
+        let odd = Format::from_masks(16, 0x000F, 0xF000, 0x00F0, 0x0F00).unwrap();
+        assert!(odd
+            .channels()
+            .map(|info| info.channel)
+            .eq([Red, Blue, Alpha, Green].iter().copied()));
+        for &channel in [Red, Green, Blue, Alpha].iter() {
+            let mask = odd.channel_mask(channel);
+            assert_eq!(mask.count_ones(), 4);
+        }
+        assert_eq!(odd.channel_mask(Green), 0xF000);
+
+        // gaps, overlaps and masks that don't fit
+        assert_eq!(Format::from_masks(32, 0xFF_0000, 0xFF, 0, 0), None);
+        assert_eq!(Format::from_masks(16, 0xFF, 0x0FF0, 0, 0), None);
+        assert_eq!(Format::from_masks(8, 0xF00, 0xF0, 0xF, 0), None);
+        assert_eq!(Format::from_masks(32, 0, 0, 0, 0), None);
+        assert_eq!(Format::RGB_F32.channel_mask(Blue), 0);
+
+        assert_eq!(Format::RGB565.mask_for(Green), 0x07E0);
+        assert_eq!(Format::RGB565.mask_for(Alpha), 0);
+        assert_eq!(Format::RGBA64.mask_for(Alpha), 0xFFFF << 48);
+        assert_eq!(Format::RGBA64.channel_mask(Alpha), 0);
+        assert_eq!(Format::ARGB_F32.mask_for(Blue), 0);
+    }
+
+    #[test]
+    fn shifts_and_maximums() {
+        use super::Channel::*;
+
+        assert_eq!(Format::RGB565.shift_for(Red), Some(11));
+        assert_eq!(Format::RGB565.shift_for(Blue), Some(0));
+        assert_eq!(Format::RGB565.shift_for(Alpha), None);
+        assert_eq!(Format::RGB565.max_value(Red), 31);
+        assert_eq!(Format::RGB565.max_value(Green), 63);
+        assert_eq!(Format::RGB565.max_value(Alpha), 0);
+
+        // sub-byte and wide channels
+        assert_eq!(Format::A1.max_value(Alpha), 1);
+        assert_eq!(Format::A4.max_value(Alpha), 15);
+        assert_eq!(Format::RGBA64.shift_for(Alpha), Some(48));
+        assert_eq!(Format::RGBA64.max_value(Alpha), 0xFFFF);
+        assert_eq!(Format::GRAY8.shift_for(Red), Some(0));
+        assert_eq!(Format::GRAY8.max_value(Blue), 0);
+
+        // float channels past the lowest 64 bits still have a shift, and
+        // their maximum is the largest finite float rather than a NaN
+        assert_eq!(Format::ARGB_F32.shift_for(Blue), Some(96));
+        let max = Format::ARGB_F32.max_value(Blue);
+        assert_eq!(f32::from_bits(max as u32), f32::MAX);
+        assert_eq!(Format::RGB_F32.max_value(Alpha), 0);
+        let max = Format::ARGB_F16.max_value(Red);
+        assert_eq!(crate::half_float::f16_to_f32(max as u16), 65504.0);
+    }
+
+    #[test]
+    fn custom_formats() {
+        use super::{Channel::*, ColorType};
+
+        let rgba4444_reversed = Format::custom(16, [Alpha, Blue, Green, Red], [4, 4, 4, 4]);
+        assert!(rgba4444_reversed.channels().map(|info| info.channel).eq([
+            Alpha, Blue, Green, Red
+        ]
+        .iter()
+        .copied()));
+        assert_eq!(size_of::<ColorType>(), 1);
+
+        let bgr233 = Format::custom(8, [Red, Green, Blue, Alpha], [3, 3, 2, 0]);
+        let shifts = bgr233.channels().map(|info| info.shift);
+        assert!(shifts.eq([0, 3, 6].iter().copied()));
+    }
+
+    #[test]
+    fn format_spec() {
+        use super::Channel::*;
+
+        const GBR: Format = format_spec!(G8 B8 R8 X8);
+        assert_eq!(
+            GBR,
+            Format::custom(32, [Alpha, Green, Blue, Red], [0, 8, 8, 8])
+        );
+        assert_eq!(format_spec!(R8 G8 B8), Format::RGB24);
+        assert_eq!(
+            format_spec!(A1),
+            Format::A1.with_alpha_mode(Default::default())
+        );
+        assert_eq!(format_spec!(R16 G16 B16 A16), Format::RGBA64);
+        assert_eq!(format_spec!(B5 G5 R5 A1), Format::ARGB1555);
+    }
+
+    #[test]
+    fn channels_from_both_ends() {
+        for &format in [Format::XRGB32, Format::ARGB_F32, Format::A8].iter() {
+            let len = format.channels().fold(0, |len, _| len + 1);
+            assert_eq!(format.channels().len(), len);
+
+            let backward = format
+                .channels()
+                .rev()
+                .collect::<tinyvec::ArrayVec<[_; 4]>>();
+            assert!(
+                format.channels().eq(backward.into_iter().rev()),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn stored_channel_iterators() {
+        struct Walker {
+            infos: ChannelInfos,
+            channels: Channels,
+        }
+
+        let mut walker = Walker {
+            infos: Format::RGB565.channels(),
+            channels: ColorType::Rg.channels(),
+        };
+        assert_eq!(walker.infos.len(), 3);
+        let last = walker.infos.next_back().unwrap();
+        assert_eq!((last.channel, last.shift, last.bits), (Channel::Red, 11, 5));
+        assert_eq!(walker.infos.clone().count(), 2);
+
+        assert_eq!(walker.channels.len(), 2);
+        assert_eq!(walker.channels.next_back(), Some(Channel::Green));
+        assert_eq!(walker.channels.next(), Some(Channel::Red));
+        assert_eq!(walker.channels.next(), None);
+    }
 }
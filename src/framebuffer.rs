@@ -0,0 +1,224 @@
+// BSL 1.0 License
+
+//! Descriptions of framebuffers handed over by windowing systems and
+//! display drivers.
+
+use crate::{Builder, ByteCount, Endianness, Error, Format, GeneralImage, Image, PixelCount};
+
+/// Where the first row of a framebuffer is shown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    /// The first row is the top of the picture, as in most framebuffers.
+    ///
+    /// This is the default.
+    TopLeft,
+    /// The first row is the bottom of the picture, as in Windows DIBs and
+    /// OpenGL readbacks.
+    BottomLeft,
+}
+
+impl Default for Origin {
+    fn default() -> Self {
+        Origin::TopLeft
+    }
+}
+
+/// A description of a framebuffer, for handing pixels between a platform
+/// backend and `genimage`.
+///
+/// Backends for minifb, DRM or fbdev fill this in from whatever their
+/// platform reports, and [`map_framebuffer`] turns it into an image over
+/// the platform's bytes. Going the other way, [`from_image`] describes an
+/// image so that it can be handed to the platform.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{map_framebuffer, ByteCount, Format, FramebufferDesc, Image};
+///
+/// // a 640x480 XRGB framebuffer with 64-byte aligned rows
+/// let desc = FramebufferDesc::new(640, 480, Format::XRGB32).with_stride(ByteCount::new(2560));
+/// let mut bytes = vec![0u8; desc.len()];
+///
+/// let image = map_framebuffer(&desc, &mut bytes[..]).unwrap();
+/// assert_eq!(image.dimensions(), (640, 480));
+/// assert_eq!(FramebufferDesc::from_image(&image), desc);
+/// ```
+///
+/// [`map_framebuffer`]: crate::map_framebuffer
+/// [`from_image`]: crate::FramebufferDesc::from_image
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FramebufferDesc {
+    /// The width of the framebuffer, in pixels.
+    pub width: PixelCount,
+    /// The height of the framebuffer, in rows.
+    pub height: usize,
+    /// The number of bytes from the start of one row to the next.
+    pub stride: ByteCount,
+    /// The format of the pixels.
+    pub format: Format,
+    /// The endianness of the pixels.
+    pub endianness: Endianness,
+    /// Where the first row is shown.
+    pub origin: Origin,
+}
+
+impl FramebufferDesc {
+    /// Describe a top-down framebuffer of native-endian pixels with no
+    /// padding between rows.
+    pub fn new(width: usize, height: usize, format: Format) -> Self {
+        let width = PixelCount::new(width);
+        Self {
+            width,
+            height,
+            stride: width.to_bytes(format),
+            format,
+            endianness: Endianness::NATIVE,
+            origin: Origin::TopLeft,
+        }
+    }
+
+    /// Describe the layout of an image.
+    pub fn from_image<Img: Image + ?Sized>(image: &Img) -> Self {
+        Self {
+            width: image.pixel_width(),
+            height: image.height(),
+            stride: image.stride(),
+            format: image.format(),
+            endianness: image.endianness(),
+            origin: Origin::TopLeft,
+        }
+    }
+
+    /// Use a different stride.
+    pub const fn with_stride(self, stride: ByteCount) -> Self {
+        Self { stride, ..self }
+    }
+
+    /// Use a different endianness.
+    pub const fn with_endianness(self, endianness: Endianness) -> Self {
+        Self { endianness, ..self }
+    }
+
+    /// Use a different origin.
+    pub const fn with_origin(self, origin: Origin) -> Self {
+        Self { origin, ..self }
+    }
+
+    /// The number of bytes that the framebuffer takes up.
+    ///
+    /// The last row is not padded to the full stride.
+    pub fn len(&self) -> usize {
+        match self.height {
+            0 => 0,
+            height => (height - 1) * self.stride.get() + self.width.to_bytes(self.format).get(),
+        }
+    }
+
+    /// Whether the framebuffer has no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width.get() == 0 || self.height == 0
+    }
+}
+
+/// Create an image over the bytes of a framebuffer.
+///
+/// No pixels are copied, so writes to the image go straight to the
+/// framebuffer.
+///
+/// # Errors
+///
+/// Returns an error if the stride can't hold a row of pixels, or if
+/// `bytes` is shorter than [`len`].
+///
+/// [`len`]: crate::FramebufferDesc::len
+pub fn map_framebuffer<Storage: AsRef<[u8]>>(
+    desc: &FramebufferDesc,
+    bytes: Storage,
+) -> Result<GeneralImage<Storage>, Error> {
+    let actual = bytes.as_ref().len();
+    if actual < desc.len() {
+        return Err(Error::BufferTooSmall {
+            expected: desc.len(),
+            actual,
+        });
+    }
+
+    let builder = Builder::from_buffer(desc.width.get(), desc.height, desc.format, bytes)
+        .with_endianness(desc.endianness)
+        .with_origin(desc.origin)
+        .try_with_stride(desc.stride)?;
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_rows() {
+        // two pixels of padding after every row but the last
+        let desc = FramebufferDesc::new(2, 3, Format::RGB24).with_stride(ByteCount::new(12));
+        assert_eq!(desc.len(), 2 * 12 + 6);
+        assert!(!desc.is_empty());
+
+        let mut bytes = [0u8; 30];
+        let mut image = map_framebuffer(&desc, &mut bytes[..]).unwrap();
+        image.set_scanline(0, 1, &[1, 2, 3, 4, 5, 6]);
+        image.set_scanline(0, 2, &[7, 8, 9, 10, 11, 12]);
+        assert_eq!(FramebufferDesc::from_image(&image), desc);
+        drop(image);
+        assert_eq!(&bytes[12..24], &[1, 2, 3, 4, 5, 6, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&bytes[24..], &[7, 8, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn origin_and_endianness() {
+        let desc = FramebufferDesc::new(1, 2, Format::RGB565).with_endianness(Endianness::Big);
+        let image = map_framebuffer(&desc, [0xF8u8, 0x00, 0x07, 0xE0]).unwrap();
+        assert_eq!(image.endianness(), Endianness::Big);
+
+        let mut pixel = [0u16];
+        image.scanline_u16(0, 0, &mut pixel);
+        assert_eq!(pixel, [0xF800]);
+        image.scanline_u16(0, 1, &mut pixel);
+        assert_eq!(pixel, [0x07E0]);
+
+        // the first row of bytes is the bottom of the picture
+        let flipped = desc.with_origin(Origin::BottomLeft);
+        let image = map_framebuffer(&flipped, [0xF8u8, 0x00, 0x07, 0xE0]).unwrap();
+        image.scanline_u16(0, 0, &mut pixel);
+        assert_eq!(pixel, [0x07E0]);
+        image.scanline_u16(0, 1, &mut pixel);
+        assert_eq!(pixel, [0xF800]);
+    }
+
+    #[test]
+    fn errors() {
+        let desc = FramebufferDesc::new(4, 2, Format::RGB24);
+        assert_eq!(
+            map_framebuffer(&desc, [0u8; 23]).err(),
+            Some(Error::BufferTooSmall {
+                expected: 24,
+                actual: 23,
+            })
+        );
+
+        // rows overlap, or don't start on a pixel
+        let narrow = desc.with_stride(ByteCount::new(9));
+        assert_eq!(
+            map_framebuffer(&narrow, [0u8; 24]).err(),
+            Some(Error::InvalidStride)
+        );
+        let unaligned = desc.with_stride(ByteCount::new(13));
+        assert_eq!(
+            map_framebuffer(&unaligned, [0u8; 25]).err(),
+            Some(Error::InvalidStride)
+        );
+
+        let empty = FramebufferDesc::new(4, 0, Format::RGB24);
+        assert_eq!(empty.len(), 0);
+        assert!(empty.is_empty());
+        assert!(map_framebuffer(&empty, [0u8; 0]).is_ok());
+    }
+}
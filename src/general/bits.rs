@@ -1,7 +1,8 @@
 // BSL 1.0 License
 
-use crate::{Endianness, Format};
-use core::cmp;
+use super::OobPolicy;
+use crate::{codec::Codec, divide_rounding_up, BitOrder, Endianness, Format, Origin, Rgba};
+use core::{cmp, mem, ops::Range};
 
 /// An image that stores all of its bits in a buffer, like a traditional
 /// image.
@@ -11,8 +12,11 @@ pub(crate) struct BitsImage<Storage: ?Sized> {
     height: usize,
     format: Format,
     endianness: Endianness,
+    bit_order: BitOrder,
+    origin: Origin,
     bytes_per_scanline: usize,
     repeat: bool,
+    oob_policy: OobPolicy,
     storage: Storage,
 }
 
@@ -31,25 +35,39 @@ impl<Storage> BitsImage<Storage> {
             height,
             format,
             endianness,
+            bit_order: BitOrder::LsbFirst,
+            origin: Origin::TopLeft,
             bytes_per_scanline,
             repeat,
+            oob_policy: OobPolicy::Empty,
             storage,
         }
     }
+
+    pub(crate) fn with_oob_policy(mut self, oob_policy: OobPolicy) -> Self {
+        self.oob_policy = oob_policy;
+        self
+    }
+
+    pub(crate) fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    pub(crate) fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
 }
 
-impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
+impl<Storage: AsRef<[u8]> + ?Sized> BitsImage<Storage> {
     fn storage(&self) -> &[u8] {
         self.storage.as_ref()
     }
 
-    fn storage_mut(&mut self) -> &mut [u8] {
-        self.storage.as_mut()
-    }
-
     fn reduce_y(&self, mut y: usize) -> Result<usize, ()> {
         if y >= self.height {
-            if self.repeat {
+            if self.repeat && self.height > 0 {
                 y %= self.height;
             } else {
                 return Err(());
@@ -63,22 +81,82 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
         self.repeat
     }
 
+    /// The index of the row of the buffer that holds the scanline `y`.
+    fn buffer_row(&self, y: usize) -> usize {
+        match self.origin {
+            Origin::TopLeft => y,
+            Origin::BottomLeft => self.height.saturating_sub(1).saturating_sub(y),
+        }
+    }
+
     fn calculate_posn(&self, x: usize, y: usize, len: usize) -> (usize, usize) {
-        let line_start = y.saturating_mul(self.bytes_per_scanline);
-        let index_start = x
-            .saturating_mul(self.format.bpp() as usize)
-            .saturating_div(8);
+        let line_start = self.buffer_row(y).saturating_mul(self.bytes_per_scanline);
+        let index_start = x.saturating_mul(self.format.bpp() as usize) / 8;
         let index_start = cmp::min(index_start, self.bytes_per_scanline);
         let index_end = index_start.saturating_add(len);
         let index_end = cmp::min(index_end, self.bytes_per_scanline);
 
-        let begin = line_start.saturating_add(index_start);
-        let end = line_start.saturating_add(index_end);
+        // never go past the end of the storage, even if it is too short
+        let len = self.storage.as_ref().len();
+        let begin = cmp::min(line_start.saturating_add(index_start), len);
+        let end = cmp::min(line_start.saturating_add(index_end), len);
 
         (begin, end)
     }
 
+    pub(crate) fn oob_policy(&self) -> OobPolicy {
+        self.oob_policy
+    }
+
+    pub(crate) fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    fn codec(&self) -> Codec {
+        Codec::new(self.format, self.endianness).with_bit_order(self.bit_order)
+    }
+
     pub(crate) fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        if self.repeat || self.oob_policy == OobPolicy::Empty {
+            return self.scanline_unbounded(x, y, scanline);
+        }
+
+        let y = if y < self.height {
+            y
+        } else if self.oob_policy == OobPolicy::Clamp && self.height > 0 {
+            self.height - 1
+        } else {
+            scanline.iter_mut().for_each(|byte| *byte = 0);
+            return scanline.len();
+        };
+
+        // copy the part of the scanline that is within the image
+        let bpp = self.format.bpp() as usize;
+        let row_len = divide_rounding_up(self.width.saturating_mul(bpp), 8);
+        let line_start = self.buffer_row(y).saturating_mul(self.bytes_per_scanline);
+        let line = self.storage().get(line_start..).unwrap_or(&[]);
+        let line = &line[..cmp::min(row_len, line.len())];
+        let start = cmp::min(x.saturating_mul(bpp) / 8, line.len());
+        let len = cmp::min(scanline.len(), line.len() - start);
+        scanline[..len].copy_from_slice(&line[start..start + len]);
+        scanline[len..].iter_mut().for_each(|byte| *byte = 0);
+
+        // and then fill in the rest
+        let codec = self.codec();
+        let edge = match self.oob_policy {
+            OobPolicy::Clamp if self.width > 0 && line.len() == row_len => {
+                Some(codec.read_raw(line, self.width - 1))
+            }
+            _ => None,
+        };
+        fill_past_edge(&codec, scanline, x, self.width, edge);
+
+        scanline.len()
+    }
+
+    /// Read a scanline, without filling in pixels that are outside of
+    /// the image.
+    fn scanline_unbounded(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
         // calculate the index into the bytes we need to go
         let y = match self.reduce_y(y) {
             Ok(y) => y,
@@ -91,15 +169,19 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
         loop {
             // memcpy the slice over
             let bytes = &self.storage()[begin..end];
-            scanline.copy_from_slice(bytes);
-            bytes_written += end.saturating_sub(begin);
+            scanline[bytes_written..bytes_written + bytes.len()].copy_from_slice(bytes);
+            bytes_written += bytes.len();
 
             let remaining = scanline.len() - bytes_written;
 
-            if self.repeat && remaining > 0 {
+            if self.repeat && remaining > 0 && !bytes.is_empty() {
                 // start over at the beginning of the line
-                begin = 0;
-                end = cmp::min(self.bytes_per_scanline, remaining);
+                let (line_begin, _) = self.calculate_posn(0, y, 0);
+                begin = line_begin;
+                end = cmp::min(
+                    begin.saturating_add(cmp::min(self.bytes_per_scanline, remaining)),
+                    self.storage().len(),
+                );
                 continue;
             }
 
@@ -109,21 +191,6 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
         bytes_written
     }
 
-    pub(crate) fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
-        // calculate the index into the bytes we need to go
-        // TODO: handle repeating on x axis
-        let y = match self.reduce_y(y) {
-            Ok(y) => y,
-            Err(()) => return 0,
-        };
-        let (begin, end) = self.calculate_posn(x, y, scanline.len());
-
-        // memcpy the slice over
-        let bytes = &mut self.storage_mut()[begin..end];
-        bytes.copy_from_slice(scanline);
-        end.saturating_sub(begin)
-    }
-
     #[inline]
     pub(crate) fn format(&self) -> Format {
         self.format
@@ -144,3 +211,453 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
         self.bytes_per_scanline
     }
 }
+
+impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
+    fn storage_mut(&mut self) -> &mut [u8] {
+        self.storage.as_mut()
+    }
+
+    /// Fill every scanline with a color, or clear the entire buffer to
+    /// zero if there is no color.
+    pub(crate) fn fill(&mut self, color: Option<Rgba>) {
+        let (height, bytes_per_scanline) = (self.height, self.bytes_per_scanline);
+        let codec = self.codec();
+        let storage = self.storage_mut();
+
+        match color {
+            None => storage.iter_mut().for_each(|byte| *byte = 0),
+            // there are no rows to fill, and `chunks_mut` can't take 0
+            Some(_) if bytes_per_scanline == 0 || height == 0 => {}
+            Some(color) => {
+                let (pattern, len) = codec.pattern(color.components());
+                let pattern = &pattern[..len];
+                storage
+                    .chunks_mut(bytes_per_scanline)
+                    .take(height)
+                    .for_each(|row| {
+                        row.iter_mut()
+                            .zip(pattern.iter().cycle())
+                            .for_each(|(byte, value)| *byte = *value)
+                    })
+            }
+        }
+    }
+
+    pub(crate) fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        // calculate the index into the bytes we need to go
+        // TODO: handle repeating on x axis
+        let y = match self.reduce_y(y) {
+            Ok(y) => y,
+            Err(()) => return 0,
+        };
+        let (begin, end) = self.calculate_posn(x, y, scanline.len());
+
+        // memcpy the slice over, dropping anything past the end of the line
+        let bytes = &mut self.storage_mut()[begin..end];
+        let len = bytes.len();
+        if len < scanline.len() {
+            slow_path!(
+                "clipped a scanline write from {} to {} bytes",
+                scanline.len(),
+                len
+            );
+        }
+        bytes.copy_from_slice(&scanline[..len]);
+        len
+    }
+}
+
+impl<Storage: ?Sized> BitsImage<Storage> {
+    /// The same image over other storage, `height` rows tall and without
+    /// repeating.
+    fn with_storage<Other>(&self, height: usize, storage: Other) -> BitsImage<Other> {
+        BitsImage {
+            width: self.width,
+            height,
+            format: self.format,
+            endianness: self.endianness,
+            bit_order: self.bit_order,
+            origin: self.origin,
+            bytes_per_scanline: self.bytes_per_scanline,
+            repeat: false,
+            oob_policy: self.oob_policy,
+            storage,
+        }
+    }
+
+    /// The height of the `index`th band of an image split into bands
+    /// `band_height` rows tall, and the range of a buffer `len` bytes long
+    /// that holds it, or `None` if there is no such band.
+    fn band(&self, index: usize, band_height: usize, len: usize) -> Option<(usize, Range<usize>)> {
+        let top = index
+            .checked_mul(band_height)
+            .filter(|&top| top < self.height)?;
+        let height = cmp::min(band_height, self.height - top);
+        let first_row = match self.origin {
+            Origin::TopLeft => top,
+            Origin::BottomLeft => self.height - top - height,
+        };
+
+        let start = cmp::min(first_row.saturating_mul(self.bytes_per_scanline), len);
+        let end = cmp::min(
+            (first_row + height).saturating_mul(self.bytes_per_scanline),
+            len,
+        );
+        Some((height, start..end))
+    }
+}
+
+impl<Storage: AsRef<[u8]> + ?Sized> BitsImage<Storage> {
+    /// This image, over a slice of its storage.
+    pub(crate) fn as_bytes(&self) -> BitsImage<&[u8]> {
+        self.with_storage(self.height, self.storage())
+    }
+}
+
+impl<Storage: AsRef<[u8]> + AsMut<[u8]> + ?Sized> BitsImage<Storage> {
+    /// This image, over a mutable slice of its storage.
+    pub(crate) fn as_bytes_mut(&mut self) -> BitsImage<&mut [u8]> {
+        let shape = self.with_storage(self.height, ());
+        shape.with_storage(self.height, self.storage.as_mut())
+    }
+}
+
+impl<'a> BitsImage<&'a [u8]> {
+    /// Split this image into bands `band_height` rows tall, from top to
+    /// bottom.
+    pub(crate) fn into_bands(
+        self,
+        band_height: usize,
+    ) -> impl Iterator<Item = BitsImage<&'a [u8]>> {
+        (0..)
+            .map(move |index| self.band(index, band_height, self.storage.len()))
+            .take_while(Option::is_some)
+            .flatten()
+            .map(move |(height, range)| self.with_storage(height, &self.storage[range]))
+    }
+}
+
+impl<'a> BitsImage<&'a mut [u8]> {
+    /// Like `into_bands`, but over mutable storage.
+    pub(crate) fn into_bands_mut(
+        self,
+        band_height: usize,
+    ) -> impl Iterator<Item = BitsImage<&'a mut [u8]>> {
+        let shape = self.with_storage(self.height, ());
+        let mut rest = self.storage;
+        // where `rest` starts in the storage
+        let mut offset = 0;
+        let len = rest.len();
+
+        (0..)
+            .map(move |index| shape.band(index, band_height, len))
+            .take_while(Option::is_some)
+            .flatten()
+            .map(move |(height, range)| {
+                // bands come from the start of the storage when it is top
+                // down, and from the end when it is bottom up
+                let (before, band) = mem::take(&mut rest).split_at_mut(range.start - offset);
+                let (band, after) = band.split_at_mut(range.end - range.start);
+                match shape.origin {
+                    Origin::TopLeft => {
+                        rest = after;
+                        offset = range.end;
+                    }
+                    Origin::BottomLeft => rest = before,
+                }
+                shape.with_storage(height, band)
+            })
+    }
+}
+
+/// Paint the pixels of `scanline` that are outside of an image with the
+/// given dimensions with `color`, where the scanline starts at the pixel
+/// at `(x, y)` and was read with the [`Border`] policy.
+///
+/// [`Border`]: crate::OobPolicy::Border
+pub(crate) fn paint_border(
+    codec: &Codec,
+    color: Rgba,
+    scanline: &mut [u8],
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+) {
+    let mut pixel = [0u8; crate::MAX_BYTES_PER_PIXEL];
+    codec.write(&mut pixel, 0, color.components());
+    let raw = codec.read_raw(&pixel, 0);
+
+    // rows past the bottom of the image are border all the way across
+    let width = if y < height { width } else { 0 };
+    fill_past_edge(codec, scanline, x, width, Some(raw));
+}
+
+/// Overwrite the pixels of `scanline` that are past the right edge of an
+/// image that is `width` pixels wide, where the scanline starts at the
+/// pixel at `x`.
+///
+/// The pixels are set to the raw channel values in `edge`, or cleared if
+/// there are none.
+pub(crate) fn fill_past_edge(
+    codec: &Codec,
+    scanline: &mut [u8],
+    x: usize,
+    width: usize,
+    edge: Option<[u64; 4]>,
+) {
+    let bpp = codec.format().bpp() as usize;
+    // sub-byte scanlines start with the pixels before `x` in its byte
+    let first = x.saturating_mul(bpp) % 8 / bpp;
+    let count = scanline.len() * 8 / bpp;
+    let from = first.saturating_add(width.saturating_sub(x));
+
+    for i in from..count {
+        codec.write_raw(scanline, i, edge.unwrap_or([0; 4]));
+    }
+
+    // clear any bytes that don't make up a whole pixel
+    let whole = cmp::max(from, count).saturating_mul(bpp) / 8;
+    if let Some(rest) = scanline.get_mut(whole..) {
+        rest.iter_mut().for_each(|byte| *byte = 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Builder, Endianness, Format, GeneralImage, Image, OobPolicy, Origin, Pixel, Rect, Rgba,
+    };
+
+    #[test]
+    fn overlong_scanlines_are_clipped() {
+        let mut buffer = [0u8; 12];
+        let mut image = GeneralImage::from_buffer(2, 2, Format::RGB24, &mut buffer[..]);
+
+        assert_eq!(image.set_scanline_clipped(1, 0, &[1; 9]), 3);
+        assert_eq!(image.set_scanline_clipped(2, 0, &[1; 9]), 0);
+        assert_eq!(image.set_scanline(0, 1, &[2; 9]), 6);
+        drop(image);
+
+        assert_eq!(buffer, [0, 0, 0, 1, 1, 1, 2, 2, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn fill_empty_images() {
+        let white = Rgba {
+            red: 0xFFFF,
+            green: 0xFFFF,
+            blue: 0xFFFF,
+            alpha: 0xFFFF,
+        };
+
+        let mut image = GeneralImage::from_buffer(0, 3, Format::RGB24, [0u8; 0]);
+        image.fill(white);
+        assert_eq!(image.dimensions(), (0, 3));
+
+        let mut buffer = [0u8; 3];
+        let mut image = GeneralImage::from_buffer(1, 0, Format::RGB24, &mut buffer[..]);
+        image.fill(white);
+        drop(image);
+        assert_eq!(buffer, [0; 3]);
+
+        let image = Builder::from_buffer(0, 2, Format::A4, [0u8; 0])
+            .fill_on_build(white)
+            .finish();
+        assert_eq!(image.width(), 0);
+    }
+
+    #[test]
+    fn rect_payloads() {
+        let mut buffer = [0u8; 4 * 3];
+        let mut image = GeneralImage::from_buffer(2, 3, Format::ARGB16, &mut buffer[..]);
+        let rect = Rect::new(1, 1, 1, 2);
+
+        // the stride must hold a row, and the last row needs no padding
+        assert!(!image.put_rect(rect, &[1, 2, 0, 3, 4], 1));
+        assert!(!image.put_rect(rect, &[1, 2, 0, 3], 3));
+        assert!(image.put_rect(rect, &[1, 2, 0, 3, 4], 3));
+        drop(image);
+        assert_eq!(buffer, [0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4]);
+    }
+
+    #[test]
+    fn wide_quantums() {
+        for &endianness in [Endianness::Little, Endianness::Big].iter() {
+            let mut image = Builder::from_buffer(2, 1, Format::RGBA64, [0u8; 16])
+                .with_endianness(endianness)
+                .finish();
+            let pixel = Pixel::with_index(
+                &0x1234_5678_9ABC_DEF0u64.to_be_bytes(),
+                0,
+                Endianness::Big,
+                Format::RGBA64,
+            );
+            image.set_pixel(1, 0, pixel);
+
+            let read = image.pixel(1, 0);
+            assert_eq!(read.raw_u64(), 0x1234_5678_9ABC_DEF0);
+            assert_eq!(read, pixel);
+            assert_eq!(
+                read.to_rgba(),
+                Rgba {
+                    red: 0xDEF0,
+                    green: 0x9ABC,
+                    blue: 0x5678,
+                    alpha: 0x1234,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn native_quantums() {
+        let mut bytes = [0u8; 300 * 4];
+        for (i, quad) in bytes.chunks_exact_mut(4).enumerate() {
+            quad.copy_from_slice(&(i as u32).to_be_bytes());
+        }
+        let image = Builder::from_buffer(300, 1, Format::ARGB32, bytes)
+            .with_endianness(Endianness::Big)
+            .finish();
+
+        let mut quantums = [0u32; 310];
+        assert_eq!(image.scanline_u32(0, 0, &mut quantums), 300);
+        assert!((0..300).eq(quantums[..300].iter().copied()));
+
+        let mut halves = [0u16; 4];
+        assert_eq!(image.scanline_u16(299, 0, &mut halves), 2);
+        assert_eq!(halves[..2], [0, 299][..]);
+    }
+
+    #[test]
+    fn out_of_bounds_policies() {
+        let buffer = [1u8, 2, 3, 4, 5, 6, 0xAA, 0xAA];
+        let image = |policy| {
+            Builder::from_buffer(2, 2, Format::A8, buffer)
+                .with_bytes_per_scanline(4)
+                .with_oob_policy(policy)
+                .finish()
+        };
+
+        let mut row = [0xFFu8; 4];
+        assert_eq!(image(OobPolicy::Empty).scanline(1, 0, &mut row), 3);
+        assert_eq!(image(OobPolicy::Empty).scanline(0, 2, &mut row), 0);
+
+        assert_eq!(image(OobPolicy::Transparent).scanline(1, 0, &mut row), 4);
+        assert_eq!(row, [2, 0, 0, 0]);
+        image(OobPolicy::Transparent).scanline(0, 5, &mut row);
+        assert_eq!(row, [0; 4]);
+
+        image(OobPolicy::Clamp).scanline(0, 7, &mut row);
+        assert_eq!(row, [5, 6, 6, 6]);
+        image(OobPolicy::Clamp).scanline(9, 0, &mut row);
+        assert_eq!(row, [2; 4]);
+
+        let bits = Builder::from_buffer(3, 1, Format::A1, [0b1111_1100u8])
+            .with_oob_policy(OobPolicy::Clamp)
+            .finish();
+        assert_eq!(bits.pixel(20, 3), bits.pixel(2, 0));
+        let mut byte = [0u8];
+        bits.scanline(0, 0, &mut byte);
+        assert_eq!(byte, [0b1111_1100]);
+    }
+
+    #[test]
+    fn border_pixels() {
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let image = Builder::from_buffer(1, 1, Format::RGB24, [1u8, 2, 3])
+            .with_border_pixel(red)
+            .finish();
+        assert_eq!(image.border_pixel(), Some(red));
+        assert_eq!(image.oob_policy(), OobPolicy::Border);
+
+        let mut row = [0u8; 6];
+        image.scanline(0, 0, &mut row);
+        assert_eq!(row, [1, 2, 3, 0xFF, 0, 0]);
+        image.scanline(0, 4, &mut row);
+        assert_eq!(row, [0xFF, 0, 0, 0xFF, 0, 0]);
+
+        let solid = Builder::from_solid_color_rgba(1, 1, Format::A1, Rgba::default())
+            .with_border_pixel(red)
+            .finish();
+        let mut byte = [0u8];
+        solid.scanline(0, 0, &mut byte);
+        assert_eq!(byte, [0b1111_1110]);
+
+        let bordered = crate::BorderedImage::new(image, 1, crate::Border::Inherit);
+        bordered.scanline(0, 1, &mut row[..]);
+        assert_eq!(row, [0xFF, 0, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn bands() {
+        // five padded rows, stored bottom up, with the last row unpadded
+        let mut buffer = [0u8; 14];
+        for (y, row) in buffer.chunks_mut(3).enumerate() {
+            row[0] = 4 - y as u8;
+        }
+        let mut image = Builder::from_buffer(1, 5, Format::A8, &mut buffer[..])
+            .with_bytes_per_scanline(3)
+            .with_origin(Origin::BottomLeft)
+            .finish();
+
+        // the height of each band, and its rows from top to bottom
+        let bands = |image: &GeneralImage<&mut [u8]>| {
+            let mut bands = [(0, [0u8; 2]); 3];
+            assert_eq!(image.bands(2).count(), 3);
+            for (band, (height, rows)) in image.bands(2).zip(bands.iter_mut()) {
+                *height = band.height();
+                band.scanline(0, 0, &mut rows[..1]);
+                band.scanline(0, 1, &mut rows[1..]);
+            }
+            bands
+        };
+        assert_eq!(bands(&image), [(2, [0, 1]), (2, [2, 3]), (1, [4, 0])]);
+
+        for (i, mut band) in image.bands_mut(2).enumerate() {
+            let y = band.height() - 1;
+            band.set_scanline(0, y, &[0x10 * (i as u8 + 1)]);
+        }
+        assert_eq!(
+            bands(&image),
+            [(2, [0, 0x10]), (2, [2, 0x20]), (1, [0x30, 0])]
+        );
+    }
+
+    #[test]
+    fn scanline_past_the_end_of_the_row() {
+        let bytes = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let image = GeneralImage::from_buffer(2, 1, Format::ARGB32, bytes);
+
+        let mut scanline = [0u8; 8];
+        assert_eq!(image.scanline(1, 0, &mut scanline), 4);
+        assert_eq!(scanline, [5, 6, 7, 8, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn repeated_scanlines_stay_on_their_row() {
+        let bytes = [1u8, 2, 3, 4];
+        let image = Builder::from_buffer(2, 2, Format::A8, bytes)
+            .repeat()
+            .finish();
+
+        let mut scanline = [0u8; 5];
+        assert_eq!(image.scanline(0, 1, &mut scanline), 5);
+        assert_eq!(scanline, [3, 4, 3, 4, 3]);
+    }
+
+    #[test]
+    fn pixel_at_the_end_of_the_row() {
+        let bytes = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let image = Builder::from_buffer(2, 1, Format::ARGB32, bytes)
+            .with_endianness(Endianness::Little)
+            .finish();
+        assert_eq!(
+            image.pixel(1, 0),
+            Pixel::new(&[5, 6, 7, 8], Endianness::Little, Format::ARGB32)
+        );
+    }
+}
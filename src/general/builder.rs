@@ -1,9 +1,17 @@
 // BSL 1.0 License
 
-use super::{BitsImage, GeneralImage, Innards, SolidColorImage};
-use crate::{divide_rounding_up, Endianness, Format, Nothing, Pixel, Rgba};
+use super::{BitsImage, ColumnsImage, GeneralImage, Innards, Layout, OobPolicy, SolidColorImage};
+use crate::{
+    divide_rounding_up, BitOrder, ByteCount, Endianness, Error, Format, Nothing, Origin, Pixel,
+    Rgba, TransferFunction,
+};
 use const_fn::const_fn;
 
+#[cfg(feature = "alloc")]
+use crate::{codec::Codec, ops::convert_row, Image, U32Buf};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
 /// A builder that allows the user to construct images.
 #[derive(Debug)]
 pub struct Builder<Storage> {
@@ -11,9 +19,29 @@ pub struct Builder<Storage> {
     height: usize,
     bytes_per_scanline: usize,
     repeat: bool,
+    oob_policy: OobPolicy,
+    bit_order: BitOrder,
+    transfer_function: TransferFunction,
+    border_pixel: Option<Rgba>,
+    origin: Origin,
+    layout: Layout,
+    fill: Option<Fill<Storage>>,
     variant: Variant<Storage>,
 }
 
+/// How to initialize the image once it is built.
+#[derive(Debug)]
+struct Fill<Storage> {
+    /// The color to fill the image with, or `None` to clear it.
+    color: Option<Rgba>,
+    /// Fills or clears the buffer.
+    ///
+    /// `finish()` doesn't require the storage to be mutable, so the
+    /// function is picked in `clear_on_build()` and `fill_on_build()`
+    /// where it is known to be.
+    apply: fn(&mut BitsImage<Storage>, Option<Rgba>),
+}
+
 #[derive(Debug)]
 enum Variant<Storage> {
     Bits {
@@ -24,6 +52,15 @@ enum Variant<Storage> {
     SolidColor {
         pixel: Pixel,
     },
+    /// A buffer that is allocated when the image is built.
+    #[cfg(feature = "alloc")]
+    Owned {
+        format: Format,
+        endianness: Endianness,
+        /// The contents of the image, tightly packed, along with the
+        /// endianness they are stored in.
+        contents: Option<(Vec<u8>, Endianness)>,
+    },
 }
 
 impl<Storage> Builder<Storage> {
@@ -38,6 +75,13 @@ impl<Storage> Builder<Storage> {
             height,
             bytes_per_scanline: bytes_per_scanline(width, format.bpp()),
             repeat: false,
+            oob_policy: OobPolicy::Empty,
+            bit_order: BitOrder::LsbFirst,
+            transfer_function: TransferFunction::Srgb,
+            border_pixel: None,
+            origin: Origin::TopLeft,
+            layout: Layout::RowMajor,
+            fill: None,
             variant,
         }
     }
@@ -62,10 +106,19 @@ impl<Storage> Builder<Storage> {
         )
     }
 
+    /// The number of pixels in each line of the buffer.
+    const fn line_len(&self) -> usize {
+        match self.layout {
+            Layout::RowMajor => self.width,
+            Layout::ColumnMajor => self.height,
+        }
+    }
+
     /// Use a custom number of bytes per scanline.
     ///
     /// Sometimes, it is useful to use a custom number of bytes per scanline.
     /// For instance, if extra padding is expected by the system for each line.
+    /// For column-major images, this is the number of bytes per column.
     ///
     /// # Panics
     ///
@@ -75,18 +128,91 @@ impl<Storage> Builder<Storage> {
     ///   will panic, since the scanline is not aligned to the pixel.
     #[const_fn("1.57")]
     pub const fn with_bytes_per_scanline(mut self, value: usize) -> Self {
+        let format = self.variant.format();
         assert!(
-            value >= self.width * self.bytes_per_scanline,
+            value >= bytes_per_scanline(self.line_len(), format.bpp()),
             "The number of bytes per scanline must be at least the number of bytes per pixel times the width of the image."
         );
         assert!(
-            value % self.bytes_per_scanline == 0,
+            value % format.bytes() as usize == 0,
             "The number of bytes per scanline must be a multiple of the number of bytes per pixel."
         );
         self.bytes_per_scanline = value;
         self
     }
 
+    /// Like [`with_bytes_per_scanline`], but returns an error instead of
+    /// panicking if the value is invalid.
+    ///
+    /// [`with_bytes_per_scanline`]: crate::Builder::with_bytes_per_scanline
+    pub fn try_with_bytes_per_scanline(mut self, value: usize) -> Result<Self, Error> {
+        let format = self.variant.format();
+        if value < bytes_per_scanline(self.line_len(), format.bpp())
+            || value % format.bytes() as usize != 0
+        {
+            return Err(Error::InvalidStride);
+        }
+
+        self.bytes_per_scanline = value;
+        Ok(self)
+    }
+
+    /// Use a custom stride, which is the number of bytes per scanline.
+    ///
+    /// This is [`with_bytes_per_scanline`], but the stride can't be
+    /// mistaken for the width of the image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stride is too small to hold a scanline, or is not a
+    /// multiple of the number of bytes per pixel. See [`try_with_stride`]
+    /// for a version that doesn't panic.
+    ///
+    /// [`with_bytes_per_scanline`]: crate::Builder::with_bytes_per_scanline
+    /// [`try_with_stride`]: crate::Builder::try_with_stride
+    #[const_fn("1.57")]
+    pub const fn with_stride(self, stride: ByteCount) -> Self {
+        self.with_bytes_per_scanline(stride.get())
+    }
+
+    /// Like [`with_stride`], but returns an error instead of panicking if
+    /// the stride is invalid.
+    ///
+    /// [`with_stride`]: crate::Builder::with_stride
+    pub fn try_with_stride(self, stride: ByteCount) -> Result<Self, Error> {
+        self.try_with_bytes_per_scanline(stride.get())
+    }
+
+    /// Round the number of bytes per scanline up to a multiple of
+    /// `alignment`.
+    ///
+    /// GPUs and windowing systems often want each scanline to start on
+    /// a 4, 64 or 256 byte boundary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` is zero, or if the aligned number of bytes
+    /// is not a multiple of the number of bytes per pixel.
+    #[const_fn("1.57")]
+    pub const fn with_scanline_alignment(self, alignment: usize) -> Self {
+        assert!(alignment > 0, "The alignment must be greater than zero.");
+        let value = divide_rounding_up(self.bytes_per_scanline, alignment) * alignment;
+        self.with_bytes_per_scanline(value)
+    }
+
+    /// Choose the order that the pixels are stored in.
+    ///
+    /// This resets the number of bytes per scanline, so it should be
+    /// called before [`with_bytes_per_scanline`]. Only images built from a
+    /// buffer can be column-major; other images are always row-major.
+    ///
+    /// [`with_bytes_per_scanline`]: crate::Builder::with_bytes_per_scanline
+    pub const fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self.bytes_per_scanline = bytes_per_scanline(self.line_len(), self.variant.format().bpp());
+        self
+    }
+
     /// Use a different endianness for the image.
     pub fn with_endianness(mut self, endianness: Endianness) -> Self {
         self.variant = self.variant.with_endianness(endianness);
@@ -99,6 +225,74 @@ impl<Storage> Builder<Storage> {
         self
     }
 
+    /// Choose what reads outside of the bounds of the image give back.
+    pub const fn with_oob_policy(mut self, oob_policy: OobPolicy) -> Self {
+        self.oob_policy = oob_policy;
+        self
+    }
+
+    /// Choose the order of the pixels within each byte, for formats that
+    /// are smaller than a byte.
+    ///
+    /// Solid color images ignore this, since every pixel is the same.
+    pub const fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Choose how the values of the color channels map to light.
+    ///
+    /// This is [`Srgb`] by default. Set it for frames that come from HDR
+    /// video, so that converting them doesn't treat them as sRGB.
+    ///
+    /// [`Srgb`]: crate::TransferFunction::Srgb
+    pub const fn with_transfer_function(mut self, transfer_function: TransferFunction) -> Self {
+        self.transfer_function = transfer_function;
+        self
+    }
+
+    /// Give the image a color for the pixels outside of it, and read them
+    /// as that color with the [`Border`] policy.
+    ///
+    /// The color is kept as the [`border_pixel`] of the image, which
+    /// [`BorderedImage`]s with [`Border::Inherit`] draw their border with.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Builder, Format, Image, Rgba};
+    ///
+    /// let white = Rgba { red: 0xFFFF, green: 0xFFFF, blue: 0xFFFF, alpha: 0xFFFF };
+    /// let image = Builder::from_buffer(1, 1, Format::GRAY8, [0u8])
+    ///     .with_border_pixel(white)
+    ///     .finish();
+    ///
+    /// let mut row = [0u8; 3];
+    /// image.scanline(0, 0, &mut row);
+    /// assert_eq!(row, [0, 0xFF, 0xFF]);
+    /// ```
+    ///
+    /// [`Border`]: crate::OobPolicy::Border
+    /// [`border_pixel`]: crate::Image::border_pixel
+    /// [`BorderedImage`]: crate::BorderedImage
+    /// [`Border::Inherit`]: crate::Border::Inherit
+    pub const fn with_border_pixel(mut self, color: Rgba) -> Self {
+        self.border_pixel = Some(color);
+        self.oob_policy = OobPolicy::Border;
+        self
+    }
+
+    /// Choose which row of the buffer holds the first scanline.
+    ///
+    /// Bottom-up buffers, such as Windows DIBs, store the last scanline
+    /// first. Scanlines are still numbered from the top, so callers don't
+    /// need to flip their coordinates. Only buffers that are stored row by
+    /// row can be bottom-up.
+    pub const fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+
     /// Finish building the image.
     pub fn finish(self) -> GeneralImage<Storage> {
         // disassemble the builder
@@ -106,41 +300,298 @@ impl<Storage> Builder<Storage> {
             width,
             height,
             repeat,
-            bytes_per_scanline,
+            oob_policy,
+            bit_order,
+            transfer_function,
+            border_pixel,
+            origin,
+            layout,
+            mut bytes_per_scanline,
+            fill,
             variant,
         } = self;
 
+        // only buffers can be stored column by column
+        let column_major = match (layout, &variant) {
+            (Layout::ColumnMajor, Variant::Bits { .. }) => true,
+            (Layout::ColumnMajor, _) => {
+                bytes_per_scanline = self::bytes_per_scanline(width, variant.format().bpp());
+                false
+            }
+            (Layout::RowMajor, _) => false,
+        };
+
         let innards = match variant {
             Variant::Bits {
                 storage,
                 format,
                 endianness,
             } => {
-                // create the bits image
-                let bits = BitsImage::with_bytes_per_line(
-                    width,
-                    height,
+                // create the bits image, storing the transpose of
+                // column-major images so that its scanlines are the columns
+                let (lines, line_len) = if column_major {
+                    (width, height)
+                } else {
+                    (height, width)
+                };
+                let mut bits = BitsImage::with_bytes_per_line(
+                    line_len,
+                    lines,
                     format,
                     endianness,
                     bytes_per_scanline,
                     repeat,
                     storage,
-                );
-                Innards::Bits(bits)
+                )
+                .with_oob_policy(oob_policy)
+                .with_bit_order(bit_order);
+                if !column_major {
+                    bits = bits.with_origin(origin);
+                }
+                if let Some(fill) = fill {
+                    (fill.apply)(&mut bits, fill.color);
+                }
+                if column_major {
+                    Innards::Columns(ColumnsImage::new(bits))
+                } else {
+                    Innards::Bits(bits)
+                }
             }
-            Variant::SolidColor { pixel } => {
+            Variant::SolidColor { mut pixel } => {
+                if let Some(fill) = fill {
+                    let (format, endianness) = (pixel.format(), pixel.endianness());
+                    pixel = match fill.color {
+                        Some(color) => Pixel::from_rgba(color, format, endianness),
+                        None => Pixel::new(&[0; 16], endianness, format),
+                    };
+                }
+
                 let solid = SolidColorImage::with_bytes_per_line(
                     width,
                     height,
                     bytes_per_scanline,
                     repeat,
                     pixel,
-                );
+                )
+                .with_oob_policy(oob_policy);
                 Innards::Solid(solid)
             }
+            #[cfg(feature = "alloc")]
+            Variant::Owned {
+                format,
+                endianness,
+                contents,
+            } => {
+                // allocate a buffer aligned to 32 bits
+                let len = divide_rounding_up(height * bytes_per_scanline, 4);
+                let mut bits = BitsImage::with_bytes_per_line(
+                    width,
+                    height,
+                    format,
+                    endianness,
+                    bytes_per_scanline,
+                    repeat,
+                    U32Buf(vec![0u32; len]),
+                )
+                .with_oob_policy(oob_policy)
+                .with_bit_order(bit_order)
+                .with_origin(origin);
+
+                if let Some(fill) = fill {
+                    bits.fill(fill.color);
+                }
+
+                if let Some((contents, source_endianness)) = contents {
+                    let row_len = divide_rounding_up(width * format.bpp() as usize, 8);
+                    let source = Codec::new(format, source_endianness).with_bit_order(bit_order);
+                    let target = Codec::new(format, endianness).with_bit_order(bit_order);
+                    let mut row = vec![0u8; row_len];
+
+                    for (y, source_row) in contents.chunks_exact(row_len).enumerate() {
+                        convert_row(&source, source_row, None, &target, &mut row, width);
+                        bits.set_scanline(0, y, &row);
+                    }
+                }
+
+                Innards::Buffered(bits)
+            }
+        };
+
+        GeneralImage {
+            innards,
+            transfer_function,
+            border_pixel,
+        }
+    }
+}
+
+/// The alignments that scanlines of foreign buffers are commonly padded
+/// to, on top of being unpadded.
+const COMMON_ALIGNMENTS: [usize; 3] = [4, 8, 16];
+
+impl<Storage: AsRef<[u8]>> Builder<Storage> {
+    /// Work out the number of bytes per scanline from the length of the
+    /// buffer given to [`from_buffer`].
+    ///
+    /// This is for buffers from APIs that only report their total size.
+    /// The scanlines are assumed to be unpadded, or padded to a multiple
+    /// of 4, 8 or 16 bytes that holds a whole number of pixels, and the
+    /// buffer is assumed to end either after
+    /// the last pixel or after the padding of the last scanline. Builders
+    /// that don't wrap a buffer are left as they are.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Builder, Format, Image};
+    ///
+    /// // three RGB24 pixels per row, padded to 12 bytes
+    /// let image = Builder::from_buffer(3, 2, Format::RGB24, [0u8; 24])
+    ///     .infer_stride_from_len()
+    ///     .unwrap()
+    ///     .finish();
+    /// assert_eq!(image.bytes_per_scanline(), 12);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BufferTooSmall`] if the buffer can't hold every
+    /// pixel, [`Error::InvalidStride`] if no common padding explains its
+    /// length, or [`Error::AmbiguousStride`] if more than one does.
+    ///
+    /// [`from_buffer`]: crate::Builder::from_buffer
+    /// [`Error::BufferTooSmall`]: crate::Error::BufferTooSmall
+    /// [`Error::InvalidStride`]: crate::Error::InvalidStride
+    /// [`Error::AmbiguousStride`]: crate::Error::AmbiguousStride
+    pub fn infer_stride_from_len(mut self) -> Result<Self, Error> {
+        let len = match &self.variant {
+            Variant::Bits { storage, .. } => storage.as_ref().len(),
+            _ => return Ok(self),
+        };
+
+        let format = self.variant.format();
+        let row_len = self
+            .line_len()
+            .checked_mul(format.bpp() as usize)
+            .map(|bits| divide_rounding_up(bits, 8))
+            .ok_or(Error::TooLarge)?;
+        let lines = match self.layout {
+            Layout::RowMajor => self.height,
+            Layout::ColumnMajor => self.width,
+        };
+        if lines == 0 {
+            return Ok(self);
+        }
+
+        let expected = (lines - 1)
+            .checked_mul(row_len)
+            .and_then(|len| len.checked_add(row_len))
+            .ok_or(Error::TooLarge)?;
+        if len < expected {
+            return Err(Error::BufferTooSmall {
+                expected,
+                actual: len,
+            });
+        }
+        if lines == 1 {
+            // a single scanline has no padding to speak of
+            return Ok(self);
+        }
+
+        let candidates = core::iter::once(row_len).chain(
+            COMMON_ALIGNMENTS
+                .iter()
+                .map(|&alignment| divide_rounding_up(row_len, alignment) * alignment),
+        );
+        let mut found = None;
+        for stride in candidates {
+            if stride % format.bytes() as usize != 0 || found == Some(stride) {
+                continue;
+            }
+
+            let padded = stride.checked_mul(lines);
+            let unpadded = stride
+                .checked_mul(lines - 1)
+                .and_then(|len| len.checked_add(row_len));
+            if padded == Some(len) || unpadded == Some(len) {
+                if found.is_some() {
+                    return Err(Error::AmbiguousStride);
+                }
+                found = Some(stride);
+            }
+        }
+
+        self.bytes_per_scanline = found.ok_or(Error::InvalidStride)?;
+        Ok(self)
+    }
+
+    /// Finish building the image, or return an error if it can't be used
+    /// safely.
+    ///
+    /// This checks that the size of the image in bytes fits in a `usize`,
+    /// and that a buffer given to [`from_buffer`] holds every scanline.
+    /// Images built by [`finish`] from a buffer that is too small don't
+    /// panic, but their last rows come up short.
+    ///
+    /// [`from_buffer`]: crate::Builder::from_buffer
+    /// [`finish`]: crate::Builder::finish
+    pub fn try_finish(self) -> Result<GeneralImage<Storage>, Error> {
+        let row_len = self
+            .line_len()
+            .checked_mul(self.variant.format().bpp() as usize)
+            .map(|bits| divide_rounding_up(bits, 8))
+            .ok_or(Error::TooLarge)?;
+        if self.bytes_per_scanline < row_len {
+            return Err(Error::InvalidStride);
+        }
+
+        let lines = match self.layout {
+            Layout::RowMajor => self.height,
+            Layout::ColumnMajor => self.width,
+        };
+        let expected = lines
+            .checked_mul(self.bytes_per_scanline)
+            .ok_or(Error::TooLarge)?;
+        if let Variant::Bits { storage, .. } = &self.variant {
+            let actual = storage.as_ref().len();
+            if actual < expected {
+                return Err(Error::BufferTooSmall { expected, actual });
+            }
+        }
+
+        Ok(self.finish())
+    }
+}
+
+impl<Storage: AsRef<[u8]> + AsMut<[u8]>> Builder<Storage> {
+    /// Clear the image to zero when it is built.
+    ///
+    /// When wrapping a buffer that is being recycled, this wipes any stale
+    /// data as part of construction. Passing `false` leaves the buffer
+    /// as-is, which is the default.
+    pub fn clear_on_build(mut self, clear: bool) -> Self {
+        self.fill = if clear {
+            Some(Fill {
+                color: None,
+                apply: BitsImage::fill,
+            })
+        } else {
+            None
         };
+        self
+    }
 
-        innards.into()
+    /// Fill every pixel of the image with a color when it is built.
+    ///
+    /// This overrides [`clear_on_build`].
+    ///
+    /// [`clear_on_build`]: crate::Builder::clear_on_build
+    pub fn fill_on_build(mut self, color: Rgba) -> Self {
+        self.fill = Some(Fill {
+            color: Some(color),
+            apply: BitsImage::fill,
+        });
+        self
     }
 }
 
@@ -162,7 +613,83 @@ impl Builder<Nothing> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl Builder<Nothing> {
+    /// Create a new image builder for a buffer-backed copy of an image.
+    ///
+    /// The new image has the same dimensions, format, endianness, bit
+    /// order, transfer function and border pixel as `src`, and contains
+    /// the same pixels. Its buffer is allocated when the image is built, so
+    /// the number of bytes per scanline can still be changed, for instance
+    /// to add the padding that a GPU expects.
+    ///
+    /// The repeat setting of `src` is not copied.
+    pub fn from_image<Img: Image + ?Sized>(src: &Img) -> Self {
+        let (width, height) = src.dimensions();
+        let format = src.format();
+        let row_len = bytes_per_scanline(width, format.bpp());
+
+        let mut contents = vec![0u8; row_len * height];
+        for (y, row) in contents.chunks_exact_mut(row_len).enumerate() {
+            src.scanline(0, y, row);
+        }
+
+        let builder = Self::new_with_variant(
+            width,
+            height,
+            format,
+            Variant::Owned {
+                format,
+                endianness: src.endianness(),
+                contents: Some((contents, src.endianness())),
+            },
+        )
+        .with_bit_order(src.bit_order())
+        .with_transfer_function(src.transfer_function());
+        match src.border_pixel() {
+            Some(color) => builder.with_border_pixel(color),
+            None => builder,
+        }
+    }
+
+    /// Create a new image builder for a buffer-backed image with the
+    /// same dimensions, format and endianness as `src`.
+    ///
+    /// Unlike [`from_image`], the pixels of `src` are not copied, and
+    /// the new image is zeroed.
+    ///
+    /// [`from_image`]: crate::Builder::from_image
+    pub fn like_image<Img: Image + ?Sized>(src: &Img) -> Self {
+        let (width, height) = src.dimensions();
+        Self::owned(width, height, src.format()).with_endianness(src.endianness())
+    }
+
+    /// Create a new image builder for a zeroed image whose buffer is
+    /// allocated when the image is built.
+    pub fn owned(width: usize, height: usize, format: Format) -> Self {
+        Self::new_with_variant(
+            width,
+            height,
+            format,
+            Variant::Owned {
+                format,
+                endianness: Endianness::NATIVE,
+                contents: None,
+            },
+        )
+    }
+}
+
 impl<Storage> Variant<Storage> {
+    const fn format(&self) -> Format {
+        match self {
+            Variant::Bits { format, .. } => *format,
+            Variant::SolidColor { pixel } => pixel.format(),
+            #[cfg(feature = "alloc")]
+            Variant::Owned { format, .. } => *format,
+        }
+    }
+
     fn with_endianness(mut self, endian: Endianness) -> Self {
         match self {
             Variant::Bits {
@@ -173,6 +700,12 @@ impl<Storage> Variant<Storage> {
             Variant::SolidColor { ref mut pixel, .. } => {
                 *pixel = pixel.into_new_format(endian, pixel.format());
             }
+            #[cfg(feature = "alloc")]
+            Variant::Owned {
+                ref mut endianness, ..
+            } => {
+                *endianness = endian;
+            }
         }
 
         self
@@ -180,5 +713,189 @@ impl<Storage> Variant<Storage> {
 }
 
 const fn bytes_per_scanline(width: usize, bpp: u8) -> usize {
-    divide_rounding_up(width * bpp as usize, 8)
+    divide_rounding_up(width.saturating_mul(bpp as usize), 8)
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::GeneralImage;
+
+    #[test]
+    fn from_image_with_alignment() {
+        let bytes: Vec<u8> = (0..5 * 3 * 4).collect();
+        let src = GeneralImage::from_buffer(5, 3, Format::ARGB32, bytes);
+
+        let copy = Builder::from_image(&src)
+            .with_scanline_alignment(64)
+            .with_endianness(Endianness::Big)
+            .finish();
+        assert_eq!(copy.bytes_per_scanline(), 64);
+        assert_eq!(copy.endianness(), Endianness::Big);
+
+        let mut copied_row = [0u8; 4];
+        copy.scanline(1, 2, &mut copied_row);
+        assert_eq!(copied_row, [47, 46, 45, 44]);
+    }
+
+    #[test]
+    fn from_image_keeps_settings() {
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let src = Builder::from_buffer(10, 1, Format::A1, [0b1000_0000u8, 0b0100_0000])
+            .with_bit_order(BitOrder::MsbFirst)
+            .with_transfer_function(TransferFunction::Linear)
+            .with_border_pixel(red)
+            .finish();
+
+        let copy = Builder::from_image(&src).finish();
+        assert_eq!(copy.bit_order(), BitOrder::MsbFirst);
+        assert_eq!(copy.transfer_function(), TransferFunction::Linear);
+        assert_eq!(copy.border_pixel(), Some(red));
+
+        // the copied bits are still read in the same order
+        for x in 0..10 {
+            assert_eq!(copy.pixel(x, 0), src.pixel(x, 0), "pixel {}", x);
+        }
+        assert_eq!(copy.pixel(0, 0).raw_u32(), 1);
+        assert_eq!(copy.pixel(9, 0).raw_u32(), 1);
+
+        // and round trip through another copy, past whose edge the
+        // border pixel is read
+        let mut row = [0u8; 2];
+        Builder::from_image(&copy).finish().scanline(0, 0, &mut row);
+        assert_eq!(row, [0b1000_0000, 0b0111_1111]);
+    }
+
+    #[test]
+    fn fill_recycled_buffer() {
+        let mut buffer = [0xAAu8; 2 * 2 * 2];
+        let color = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+
+        Builder::from_buffer(2, 2, Format::ARGB16, &mut buffer[..])
+            .fill_on_build(color)
+            .finish();
+        let expected = u16::to_ne_bytes(0x00FF);
+        assert_eq!(buffer[..2], expected);
+        assert_eq!(buffer[6..], expected);
+
+        Builder::from_buffer(2, 2, Format::ARGB16, &mut buffer[..])
+            .clear_on_build(true)
+            .finish();
+        assert_eq!(buffer, [0; 8]);
+    }
+
+    #[test]
+    fn bit_order() {
+        let mut bytes = [0b1000_0000u8, 0b0000_1111];
+        let mut image = Builder::from_buffer(16, 1, Format::A1, &mut bytes[..])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        assert_eq!(image.bit_order(), BitOrder::MsbFirst);
+        assert_eq!(image.pixel(0, 0).raw_u32(), 1);
+        assert_eq!(image.pixel(7, 0).raw_u32(), 0);
+        assert_eq!(image.pixel(15, 0).raw_u32(), 1);
+
+        let opaque = image.pixel(0, 0);
+        image.set_pixel(1, 0, opaque);
+        let mut lsb_first = GeneralImage::from_buffer(16, 1, Format::A1, [0u8; 2]);
+        crate::convert(&image, &mut lsb_first);
+        let mut row = [0u8; 2];
+        lsb_first.scanline(0, 0, &mut row);
+        assert_eq!(row, [0b0000_0011, 0b1111_0000]);
+
+        let mut nibbles = [0x12u8];
+        let image = Builder::from_buffer(2, 1, Format::A4, &mut nibbles[..])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        assert_eq!(image.pixel(0, 0).raw_u32(), 1);
+        assert_eq!(image.pixel(1, 0).raw_u32(), 2);
+    }
+
+    #[test]
+    fn bottom_up() {
+        let desc = crate::FramebufferDesc::new(2, 3, Format::A8)
+            .with_stride(ByteCount::new(4))
+            .with_origin(Origin::BottomLeft);
+        let mut bytes = [0u8; 10];
+        let mut image = crate::map_framebuffer(&desc, &mut bytes[..]).unwrap();
+
+        image.set_scanline(0, 0, &[1, 2]);
+        image.set_scanline(0, 2, &[5, 6]);
+        let mut row = [0u8; 2];
+        image.scanline(0, 0, &mut row);
+        assert_eq!(row, [1, 2]);
+        assert_eq!(bytes, [5, 6, 0, 0, 0, 0, 0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn infer_stride() {
+        let infer = |width, height, format, len| {
+            Builder::from_buffer(width, height, format, vec![0u8; len])
+                .infer_stride_from_len()
+                .map(|builder| builder.finish().bytes_per_scanline())
+        };
+
+        assert_eq!(infer(5, 3, Format::A8, 15), Ok(5));
+        assert_eq!(infer(5, 3, Format::A8, 24), Ok(8));
+        assert_eq!(infer(5, 3, Format::A8, 37), Ok(16));
+        assert_eq!(infer(3, 3, Format::RGB24, 33), Ok(12));
+        assert_eq!(infer(5, 1, Format::A8, 9), Ok(5));
+        assert_eq!(infer(5, 3, Format::A8, 16), Err(Error::InvalidStride));
+        assert_eq!(
+            infer(5, 3, Format::A8, 14),
+            Err(Error::BufferTooSmall {
+                expected: 15,
+                actual: 14
+            })
+        );
+    }
+
+    #[test]
+    fn hostile_metadata() {
+        let buffer = [1u8; 10];
+        let result = Builder::from_buffer(3, 4, Format::A8, buffer).try_finish();
+        assert_eq!(
+            result.err(),
+            Some(Error::BufferTooSmall {
+                expected: 12,
+                actual: 10
+            })
+        );
+        let result = Builder::from_buffer(usize::MAX, 2, Format::ARGB32, buffer).try_finish();
+        assert_eq!(result.err(), Some(Error::TooLarge));
+        let result =
+            Builder::from_buffer(3, 1, Format::RGB24, buffer).try_with_bytes_per_scanline(10);
+        assert_eq!(result.unwrap_err(), Error::InvalidStride);
+
+        // reading the missing rows comes up short instead of panicking
+        let image = Builder::from_buffer(3, 4, Format::A8, buffer).finish();
+        let mut row = [0u8; 8];
+        assert_eq!(image.scanline(0, 3, &mut row), 1);
+        let image = Builder::from_buffer(3, 0, Format::A8, buffer)
+            .repeat()
+            .finish();
+        assert_eq!(image.scanline(0, 1, &mut row), 0);
+
+        assert_eq!(
+            Pixel::try_new(&buffer[..2], Endianness::Little, Format::RGB24).unwrap_err(),
+            Error::BufferTooSmall {
+                expected: 3,
+                actual: 2
+            }
+        );
+        assert_eq!(
+            Pixel::try_with_index(&buffer, 6, Endianness::Little, Format::A4).unwrap_err(),
+            Error::InvalidIndex
+        );
+    }
 }
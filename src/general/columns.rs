@@ -0,0 +1,238 @@
+// BSL 1.0 License
+
+use super::{BitsImage, OobPolicy};
+use crate::{
+    codec::Codec, divide_rounding_up, BitOrder, Endianness, Format, Rgba, MAX_BYTES_PER_PIXEL,
+};
+
+/// An image whose buffer stores each column contiguously.
+///
+/// This is stored as the transpose of the image, so that the scanlines of
+/// the inner image are the columns of this one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct ColumnsImage<Storage> {
+    columns: BitsImage<Storage>,
+}
+
+impl<Storage> ColumnsImage<Storage> {
+    pub(crate) fn new(columns: BitsImage<Storage>) -> Self {
+        ColumnsImage { columns }
+    }
+}
+
+impl<Storage: AsRef<[u8]> + AsMut<[u8]>> ColumnsImage<Storage> {
+    pub(crate) fn repeat(&self) -> bool {
+        self.columns.repeat()
+    }
+
+    pub(crate) fn oob_policy(&self) -> OobPolicy {
+        self.columns.oob_policy()
+    }
+
+    pub(crate) fn bit_order(&self) -> BitOrder {
+        self.columns.bit_order()
+    }
+
+    fn codec(&self) -> Codec {
+        Codec::new(self.format(), self.endianness()).with_bit_order(self.bit_order())
+    }
+
+    /// The scanline to read from when reading the scanline `y`, or `None`
+    /// if it is outside of the image.
+    fn reduce_y(&self, y: usize) -> Option<usize> {
+        let (_, height) = self.dimensions();
+        if y < height {
+            Some(y)
+        } else if height == 0 {
+            None
+        } else if self.repeat() {
+            Some(y % height)
+        } else if self.oob_policy() == OobPolicy::Clamp {
+            Some(height - 1)
+        } else {
+            None
+        }
+    }
+
+    /// The index of the pixel at `x` within the first byte of a
+    /// scanline, for formats smaller than a byte.
+    fn index_in_byte(&self, x: usize) -> usize {
+        let bpp = self.format().bpp() as usize;
+        x.saturating_mul(bpp) % 8 / bpp
+    }
+
+    pub(crate) fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        let y = match self.reduce_y(y) {
+            Some(y) => y,
+            None if self.repeat() || self.oob_policy() == OobPolicy::Empty => return 0,
+            None => {
+                scanline.iter_mut().for_each(|byte| *byte = 0);
+                return scanline.len();
+            }
+        };
+
+        // gather the pixels one column at a time; the inner image takes
+        // care of columns that are out of bounds
+        let codec = self.codec();
+        let (bpp, bytes) = (self.format().bpp() as usize, self.format().bytes() as usize);
+        let first = self.index_in_byte(x);
+        let mut pixel = [0u8; MAX_BYTES_PER_PIXEL];
+        let mut end = first;
+
+        while end < scanline.len() * 8 / bpp {
+            let column = x.saturating_add(end - first);
+            if self.columns.scanline(y, column, &mut pixel[..bytes]) < bytes {
+                break;
+            }
+            codec.copy_pixel(&pixel, self.index_in_byte(y), scanline, end);
+            end += 1;
+        }
+
+        if end == first {
+            0
+        } else {
+            divide_rounding_up(end * bpp, 8)
+        }
+    }
+
+    pub(crate) fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        let (width, height) = self.dimensions();
+        let y = if y < height {
+            y
+        } else if self.repeat() && height > 0 {
+            y % height
+        } else {
+            return 0;
+        };
+
+        // scatter the pixels, reading back the ones that share a byte
+        let codec = self.codec();
+        let (bpp, bytes) = (self.format().bpp() as usize, self.format().bytes() as usize);
+        let first = self.index_in_byte(x);
+        let mut pixel = [0u8; MAX_BYTES_PER_PIXEL];
+        let mut end = first;
+
+        while end < scanline.len() * 8 / bpp {
+            let column = x.saturating_add(end - first);
+            if column >= width {
+                break;
+            }
+            if self.format().subbyte() {
+                self.columns.scanline(y, column, &mut pixel[..bytes]);
+            }
+            codec.copy_pixel(scanline, end, &mut pixel, self.index_in_byte(y));
+            self.columns.set_scanline(y, column, &pixel[..bytes]);
+            end += 1;
+        }
+
+        if end == first {
+            0
+        } else {
+            divide_rounding_up(end * bpp, 8)
+        }
+    }
+
+    /// Fill every pixel with a color.
+    pub(crate) fn fill(&mut self, color: Rgba) {
+        let codec = self.codec();
+        let (pattern, len) = codec.pattern(color.components());
+
+        // a multiple of the length of every pattern
+        let mut row = [0u8; MAX_BYTES_PER_PIXEL * 3];
+        row.iter_mut()
+            .zip(pattern[..len].iter().cycle())
+            .for_each(|(byte, &value)| *byte = value);
+
+        let bpp = self.format().bpp() as usize;
+        let pixels_per_row = row.len() * 8 / bpp;
+        let (width, height) = self.dimensions();
+        for y in 0..height {
+            let mut x = 0;
+            while x < width {
+                let count = core::cmp::min(pixels_per_row, width - x);
+                self.set_scanline(x, y, &row[..divide_rounding_up(count * bpp, 8)]);
+                x += count;
+            }
+        }
+    }
+
+    /// Read part of a column straight out of the buffer.
+    pub(crate) fn column(&self, x: usize, y: usize, column: &mut [u8]) -> usize {
+        self.columns.scanline(y, x, column)
+    }
+
+    #[inline]
+    pub(crate) fn format(&self) -> Format {
+        self.columns.format()
+    }
+
+    #[inline]
+    pub(crate) fn endianness(&self) -> Endianness {
+        self.columns.endianness()
+    }
+
+    #[inline]
+    pub(crate) fn dimensions(&self) -> (usize, usize) {
+        let (height, width) = self.columns.dimensions();
+        (width, height)
+    }
+
+    /// The number of bytes in a scanline without padding, since the
+    /// scanlines aren't stored.
+    #[inline]
+    pub(crate) fn bytes_per_scanline(&self) -> usize {
+        let (width, _) = self.dimensions();
+        divide_rounding_up(width.saturating_mul(self.format().bpp() as usize), 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BitOrder, Builder, Format, Image, Layout, OobPolicy};
+
+    #[test]
+    fn gather_and_scatter() {
+        // three columns of two pixels each
+        let mut buffer = [1u8, 2, 3, 4, 5, 6];
+        let mut image = Builder::from_buffer(3, 2, Format::A8, &mut buffer[..])
+            .with_layout(Layout::ColumnMajor)
+            .finish();
+        assert_eq!(image.bytes_per_scanline(), 3);
+
+        let mut row = [0u8; 4];
+        assert_eq!(image.scanline(0, 1, &mut row), 3);
+        assert_eq!(row[..3], [2, 4, 6]);
+        assert_eq!(image.scanline(0, 2, &mut row), 0);
+
+        let mut column = [0u8; 2];
+        assert_eq!(image.column(1, 0, &mut column), 2);
+        assert_eq!(column, [3, 4]);
+
+        assert_eq!(image.set_scanline(1, 0, &[7, 8, 9]), 2);
+        drop(image);
+        assert_eq!(buffer, [1, 2, 7, 4, 8, 6]);
+
+        // row-major images gather their columns instead
+        let image = Builder::from_buffer(2, 3, Format::A8, buffer).finish();
+        assert_eq!(image.column(1, 1, &mut column), 2);
+        assert_eq!(column, [4, 6]);
+
+        // nibbles are packed along each column
+        let image = Builder::from_buffer(2, 2, Format::A4, [0x21u8, 0x43])
+            .with_layout(Layout::ColumnMajor)
+            .with_oob_policy(OobPolicy::Transparent)
+            .finish();
+        let mut row = [0xFFu8; 2];
+        assert_eq!(image.scanline(0, 1, &mut row), 2);
+        assert_eq!(row, [0x42, 0]);
+        assert_eq!(image.pixel(1, 0).raw_u32(), 3);
+
+        // gathered bits keep the bit order of the image
+        let image = Builder::from_buffer(8, 2, Format::A1, [0b0100_0000u8, 0b0100_0000])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let mut column = [0u8];
+        assert_eq!(image.column(1, 0, &mut column), 1);
+        assert_eq!(column, [0b1100_0000]);
+    }
+}
@@ -3,22 +3,32 @@
 // TODO: builder pattern
 
 mod bits;
-use bits::BitsImage;
+use bits::{paint_border, BitsImage};
 
 mod builder;
 pub use builder::Builder;
 
+mod columns;
+use columns::ColumnsImage;
+
 mod solid;
 use solid::SolidColorImage;
 
+mod sub_image;
+pub use sub_image::SubImage;
+
+#[cfg(feature = "alloc")]
+use crate::U32Buf;
 #[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-use crate::{Endianness, Format, Image, Rgba, U32Buf};
+use crate::{codec::Codec, BitOrder, Endianness, Format, Image, Rgba, TransferFunction};
 
 /// A general-purpose image that fits many use cases.
 pub struct GeneralImage<Storage> {
     innards: Innards<Storage>,
+    transfer_function: TransferFunction,
+    border_pixel: Option<Rgba>,
 }
 
 /// A storage type that evaluates to nothing.
@@ -31,6 +41,80 @@ pub struct GeneralImage<Storage> {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Nothing;
 
+impl AsRef<[u8]> for Nothing {
+    fn as_ref(&self) -> &[u8] {
+        &[]
+    }
+}
+
+impl AsMut<[u8]> for Nothing {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+}
+
+/// What reading outside of the bounds of an image gives back.
+///
+/// This applies to [`scanline`] and [`pixel`] reads of images that
+/// don't [`repeat`]. The padding at the end of each scanline is
+/// considered to be outside of the image, except by [`Empty`].
+///
+/// [`scanline`]: crate::Image::scanline
+/// [`pixel`]: crate::Image::pixel
+/// [`repeat`]: crate::Builder::repeat
+/// [`Empty`]: crate::OobPolicy::Empty
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OobPolicy {
+    /// Nothing is written, and reads stop at the end of the scanline.
+    ///
+    /// This is the default.
+    Empty,
+    /// Pixels outside of the image are transparent black, which are all
+    /// zero bytes.
+    Transparent,
+    /// Pixels outside of the image repeat the nearest pixel on the edge
+    /// of the image.
+    Clamp,
+    /// Pixels outside of the image are the [`border_pixel`] of the image,
+    /// or transparent black if it doesn't have one.
+    ///
+    /// [`border_pixel`]: crate::Image::border_pixel
+    Border,
+}
+
+impl Default for OobPolicy {
+    fn default() -> Self {
+        OobPolicy::Empty
+    }
+}
+
+/// The order that the pixels of an image are stored in.
+///
+/// See [`with_layout`].
+///
+/// [`with_layout`]: crate::Builder::with_layout
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layout {
+    /// Each scanline is stored contiguously, one after the other.
+    ///
+    /// This is the default.
+    RowMajor,
+    /// Each column is stored contiguously, one after the other, as in
+    /// Fortran arrays and some hardware buffers.
+    ///
+    /// Reading a scanline gathers its pixels from every column, so
+    /// reading columns with [`column`] is much faster.
+    ///
+    /// [`column`]: crate::Image::column
+    ColumnMajor,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::RowMajor
+    }
+}
+
 /// Keeping this enum internal means that any changes do not become
 /// breaking changes.
 enum Innards<Storage> {
@@ -42,6 +126,8 @@ enum Innards<Storage> {
     /// the image to an editable form.
     #[cfg(feature = "alloc")]
     Buffered(BitsImage<U32Buf<Vec<u32>>>),
+    /// An image made up of bits that are stored column by column.
+    Columns(ColumnsImage<Storage>),
     /// An image that is a solid color.
     Solid(SolidColorImage),
 }
@@ -52,6 +138,7 @@ macro_rules! dispatch {
             Innards::Bits(ref bits) => bits.$fnname $($args)*,
             #[cfg(feature = "alloc")]
             Innards::Buffered(ref bits) => bits.$fnname $($args)*,
+            Innards::Columns(ref columns) => columns.$fnname $($args)*,
             Innards::Solid(ref solid) => solid.$fnname $($args)*,
         }
     }};
@@ -61,6 +148,7 @@ macro_rules! dispatch {
                 Innards::Bits(ref mut bits) => return bits.$fnname $($args)*,
                 #[cfg(feature = "alloc")]
                 Innards::Buffered(ref mut bits) => return bits.$fnname $($args)*,
+                Innards::Columns(ref mut columns) => return columns.$fnname $($args)*,
                 _ => {
                     cfg_if::cfg_if! {
                         if #[cfg(feature = "alloc")] {
@@ -83,7 +171,11 @@ macro_rules! dispatch {
 
 impl<Storage> From<Innards<Storage>> for GeneralImage<Storage> {
     fn from(innards: Innards<Storage>) -> Self {
-        GeneralImage { innards }
+        GeneralImage {
+            innards,
+            transfer_function: TransferFunction::Srgb,
+            border_pixel: None,
+        }
     }
 }
 
@@ -99,11 +191,130 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]>> GeneralImage<Storage> {
         dispatch!(&self, repeat())
     }
 
+    /// What reading outside of the bounds of this image gives back.
+    pub fn oob_policy(&self) -> OobPolicy {
+        dispatch!(&self, oob_policy())
+    }
+
+    /// Fill every pixel of this image with a color.
+    ///
+    /// Solid color images stay solid, so this never copies them into a
+    /// buffer.
+    pub fn fill(&mut self, color: Rgba) {
+        match self.innards {
+            Innards::Bits(ref mut bits) => bits.fill(Some(color)),
+            #[cfg(feature = "alloc")]
+            Innards::Buffered(ref mut bits) => bits.fill(Some(color)),
+            Innards::Columns(ref mut columns) => columns.fill(color),
+            Innards::Solid(ref mut solid) => solid.set_color(color),
+        }
+    }
+
+    /// Split this image into views of bands that are `band_height` rows
+    /// tall, from top to bottom.
+    ///
+    /// The bands don't overlap, and the last one is shorter if the height
+    /// of the image isn't a multiple of `band_height`. Bands are the
+    /// natural unit of work for processing an image in stages, or on
+    /// several threads, since each one can be read on its own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `band_height` is zero, or if the image isn't stored in a
+    /// buffer, such as a solid color image or one stored column by column
+    /// that hasn't been written to.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Format, GeneralImage, Image};
+    ///
+    /// let image = GeneralImage::from_buffer(2, 5, Format::GRAY8, [0u8; 10]);
+    /// let heights = image.bands(2).map(|band| band.height()).collect::<Vec<_>>();
+    /// assert_eq!(heights, [2, 2, 1]);
+    /// ```
+    pub fn bands(&self, band_height: usize) -> impl Iterator<Item = SubImage<'_>> {
+        assert!(band_height > 0, "Bands must be at least one row tall");
+        let bits = match self.innards {
+            Innards::Bits(ref bits) => bits.as_bytes(),
+            #[cfg(feature = "alloc")]
+            Innards::Buffered(ref bits) => bits.as_bytes(),
+            _ => panic!("Cannot split a non-buffered image into bands"),
+        };
+
+        let (transfer_function, border_pixel) = (self.transfer_function, self.border_pixel);
+        bits.into_bands(band_height)
+            .map(move |band| SubImage::new(band, transfer_function, border_pixel))
+    }
+
+    /// Like [`bands`], but giving images that can be written to.
+    ///
+    /// Images that aren't stored in a buffer are copied into one first,
+    /// which requires the `alloc` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `band_height` is zero, or if the image isn't stored in a
+    /// buffer and the `alloc` feature is disabled.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Format, GeneralImage, Image};
+    ///
+    /// let mut image = GeneralImage::from_buffer(1, 3, Format::GRAY8, [0u8; 3]);
+    /// for (i, mut band) in image.bands_mut(2).enumerate() {
+    ///     band.set_scanline(0, 0, &[i as u8 + 1]);
+    /// }
+    ///
+    /// let mut row = [0u8];
+    /// image.scanline(0, 2, &mut row);
+    /// assert_eq!(row, [2]);
+    /// ```
+    ///
+    /// [`bands`]: crate::GeneralImage::bands
+    pub fn bands_mut(
+        &mut self,
+        band_height: usize,
+    ) -> impl Iterator<Item = GeneralImage<&mut [u8]>> {
+        assert!(band_height > 0, "Bands must be at least one row tall");
+        if matches!(self.innards, Innards::Columns(_) | Innards::Solid(_)) {
+            cfg_if::cfg_if! {
+                if #[cfg(feature = "alloc")] {
+                    self.make_buffered();
+                } else {
+                    panic!("Cannot split a non-buffered image into bands");
+                }
+            }
+        }
+
+        let (transfer_function, border_pixel) = (self.transfer_function, self.border_pixel);
+        let bits = match self.innards {
+            Innards::Bits(ref mut bits) => bits.as_bytes_mut(),
+            #[cfg(feature = "alloc")]
+            Innards::Buffered(ref mut bits) => bits.as_bytes_mut(),
+            _ => unreachable!(),
+        };
+
+        bits.into_bands_mut(band_height)
+            .map(move |band| GeneralImage {
+                innards: Innards::Bits(band),
+                transfer_function,
+                border_pixel,
+            })
+    }
+
     /// Make this buffered.
     #[cfg(feature = "alloc")]
     fn make_buffered(&mut self) {
         use crate::divide_rounding_up;
 
+        slow_path!(
+            "copied a {}x{} image into a buffer to edit it",
+            self.width(),
+            self.height()
+        );
+
         // create a heap buffer with enough space to store the
         // current image data
         let heap_buffer_size = self.height() * self.bytes_per_scanline();
@@ -121,7 +332,9 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]>> GeneralImage<Storage> {
             self.bytes_per_scanline(),
             self.repeat(),
             U32Buf(heap_buffer),
-        );
+        )
+        .with_oob_policy(self.oob_policy())
+        .with_bit_order(self.bit_order());
 
         let mut line_buffer = alloc::vec![0u8; self.bytes_per_scanline()];
 
@@ -152,6 +365,18 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]>> Image for GeneralImage<Storage> {
         dispatch!(&self, endianness())
     }
 
+    fn bit_order(&self) -> BitOrder {
+        dispatch!(&self, bit_order())
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.transfer_function
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        self.border_pixel
+    }
+
     fn dimensions(&self) -> (usize, usize) {
         dispatch!(&self, dimensions())
     }
@@ -161,10 +386,35 @@ impl<Storage: AsRef<[u8]> + AsMut<[u8]>> Image for GeneralImage<Storage> {
     }
 
     fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
-        dispatch!(&self, scanline(x, y, scanline))
+        let written = dispatch!(&self, scanline(x, y, scanline));
+
+        // the inner images leave the outside transparent
+        if let Some(color) = self.border_pixel {
+            if self.oob_policy() == OobPolicy::Border && !self.repeat() {
+                let codec =
+                    Codec::new(self.format(), self.endianness()).with_bit_order(self.bit_order());
+                paint_border(&codec, color, scanline, (x, y), self.dimensions());
+            }
+        }
+
+        written
     }
 
     fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        // writes that keep a solid color image solid don't need a buffer
+        if let Innards::Solid(ref mut solid) = self.innards {
+            if let Some(written) = solid.try_set_scanline(x, y, scanline) {
+                return written;
+            }
+        }
+
         dispatch!(&mut self, set_scanline(x, y, scanline))
     }
+
+    fn column(&self, x: usize, y: usize, column: &mut [u8]) -> usize {
+        match self.innards {
+            Innards::Columns(ref columns) => columns.column(x, y, column),
+            _ => crate::gather_column(self, x, y, column),
+        }
+    }
 }
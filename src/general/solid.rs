@@ -1,8 +1,15 @@
 // BSL 1.0 License
 
-use crate::{Endianness, Format, Pixel};
+use super::{bits::fill_past_edge, OobPolicy};
+use crate::{codec::Codec, divide_rounding_up, BitOrder, Endianness, Format, Pixel, Rgba};
 use core::cmp;
 
+/// The number of bytes compared at once when checking whether a write
+/// keeps the image a solid color.
+///
+/// This is a multiple of the length of every pixel and pair of pixels.
+const COMPARE_CHUNK: usize = 48;
+
 /// An image made up entirely of a solid color.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct SolidColorImage {
@@ -10,6 +17,7 @@ pub(crate) struct SolidColorImage {
     height: usize,
     bytes_per_scanline: usize,
     repeat: bool,
+    oob_policy: OobPolicy,
     pixel: Pixel,
 }
 
@@ -26,28 +34,111 @@ impl SolidColorImage {
             height,
             bytes_per_scanline,
             repeat,
+            oob_policy: OobPolicy::Empty,
             pixel,
         }
     }
 
+    pub(crate) fn with_oob_policy(mut self, oob_policy: OobPolicy) -> Self {
+        self.oob_policy = oob_policy;
+        self
+    }
+
+    pub(crate) fn oob_policy(&self) -> OobPolicy {
+        self.oob_policy
+    }
+
+    /// Every pixel is the same, so the order within a byte doesn't matter.
+    pub(crate) fn bit_order(&self) -> BitOrder {
+        BitOrder::LsbFirst
+    }
+
     pub(crate) fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        // every pixel is the same, so clamping never leaves the image
+        let clamp = self.oob_policy == OobPolicy::Clamp && self.width > 0 && self.height > 0;
+
         // if we're logically outside of the image bounds, return
-        if !self.repeat && y >= self.height {
-            return 0;
+        if !self.repeat && !clamp && y >= self.height {
+            return match self.oob_policy {
+                OobPolicy::Empty => 0,
+                _ => {
+                    scanline.iter_mut().for_each(|byte| *byte = 0);
+                    scanline.len()
+                }
+            };
         }
 
         // determine how many bytes to fill
-        let fill = if self.repeat {
+        let bpp = self.format().bpp() as usize;
+        let fill = if self.repeat || clamp {
             scanline.len()
         } else {
-            let byte_index = x
-                .saturating_mul(self.format().bpp() as usize)
-                .saturating_div(8);
-            cmp::min(scanline.len(), self.bytes_per_scanline() - byte_index)
+            let row_len = match self.oob_policy {
+                OobPolicy::Empty => self.bytes_per_scanline(),
+                _ => divide_rounding_up(self.width.saturating_mul(bpp), 8),
+            };
+            let byte_index = x.saturating_mul(bpp) / 8;
+            cmp::min(scanline.len(), row_len.saturating_sub(byte_index))
         };
 
         // fill the scanline with the solid color
-        self.pixel.fill_row(&mut scanline[..fill])
+        let written = self.pixel.fill_row(&mut scanline[..fill]);
+
+        let transparent = matches!(self.oob_policy, OobPolicy::Transparent | OobPolicy::Border);
+        if transparent && !self.repeat {
+            let codec = Codec::new(self.format(), self.endianness());
+            fill_past_edge(&codec, scanline, x, self.width, None);
+            return scanline.len();
+        }
+
+        written
+    }
+
+    /// Change the color of every pixel.
+    pub(crate) fn set_color(&mut self, color: Rgba) {
+        self.pixel = Pixel::from_rgba(color, self.format(), self.endianness());
+    }
+
+    /// Write a scanline if the image stays a solid color afterwards, which
+    /// is when it writes the color that the image already has, or when it
+    /// writes a single color over the entire image.
+    ///
+    /// Returns the number of bytes written, like a buffered image would,
+    /// or `None` if the image needs to be buffered to hold the scanline.
+    pub(crate) fn try_set_scanline(
+        &mut self,
+        x: usize,
+        y: usize,
+        scanline: &[u8],
+    ) -> Option<usize> {
+        if y >= self.height && !(self.repeat && self.height > 0) {
+            // writes outside of the image are dropped
+            return Some(0);
+        }
+
+        let bpp = self.format().bpp() as usize;
+        if self.format().color_type().ycbcr_matrix().is_some() && x % 2 != 0 {
+            // the bytes don't start at the beginning of a pattern
+            return None;
+        }
+
+        let start = cmp::min(x.saturating_mul(bpp) / 8, self.bytes_per_scanline);
+        let len = cmp::min(scanline.len(), self.bytes_per_scanline - start);
+        let scanline = &scanline[..len];
+        if repeats(self.pixel, scanline) {
+            return Some(len);
+        }
+
+        let row_len = divide_rounding_up(self.width.saturating_mul(bpp), 8);
+        if self.height == 1 && x == 0 && len >= row_len {
+            let pixel = Pixel::try_new(scanline, self.endianness(), self.format()).ok()?;
+            if repeats(pixel, scanline) {
+                self.pixel = pixel;
+                return Some(len);
+            }
+        }
+
+        None
     }
 
     pub(crate) fn format(&self) -> Format {
@@ -70,3 +161,38 @@ impl SolidColorImage {
         self.bytes_per_scanline
     }
 }
+
+/// Whether `bytes` are a row of copies of `pixel`.
+fn repeats(pixel: Pixel, bytes: &[u8]) -> bool {
+    let mut expected = [0u8; COMPARE_CHUNK];
+    bytes.chunks(COMPARE_CHUNK).all(|chunk| {
+        let expected = &mut expected[..chunk.len()];
+        pixel.fill_row(expected) == chunk.len() && expected == chunk
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_writes() {
+        let pixel = Pixel::from_rgba(Rgba::default(), Format::RGB24, Endianness::Little);
+        let mut solid = SolidColorImage::with_bytes_per_line(2, 1, 6, false, pixel);
+
+        // writing the same color keeps the image as it is
+        assert_eq!(solid.try_set_scanline(1, 0, &[0, 0, 0]), Some(3));
+        // so does writing a new color over the whole image
+        assert_eq!(solid.try_set_scanline(0, 0, &[1, 2, 3, 1, 2, 3]), Some(6));
+        let mut row = [0u8; 6];
+        solid.scanline(0, 0, &mut row);
+        assert_eq!(row, [1, 2, 3, 1, 2, 3]);
+
+        // but anything else needs a buffer
+        assert_eq!(solid.try_set_scanline(0, 0, &[1, 2, 3, 4, 5, 6]), None);
+        assert_eq!(solid.try_set_scanline(1, 0, &[4, 5, 6]), None);
+        let mut taller = SolidColorImage::with_bytes_per_line(2, 2, 6, false, pixel);
+        assert_eq!(taller.try_set_scanline(0, 0, &[1, 2, 3, 1, 2, 3]), None);
+        assert_eq!(taller.try_set_scanline(0, 5, &[1, 2, 3]), Some(0));
+    }
+}
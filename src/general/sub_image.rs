@@ -0,0 +1,84 @@
+// BSL 1.0 License
+
+use super::{
+    bits::{paint_border, BitsImage},
+    OobPolicy,
+};
+use crate::{codec::Codec, BitOrder, Endianness, Format, Image, Rgba, TransferFunction};
+
+/// A read-only view of some of the rows of a [`GeneralImage`].
+///
+/// This is what [`GeneralImage::bands`] splits an image into. Views only
+/// borrow the buffer of the image, so they can be handed to other threads
+/// and read at the same time. Writes to a view are dropped; use
+/// [`GeneralImage::bands_mut`] to write to the bands of an image.
+///
+/// [`GeneralImage`]: crate::GeneralImage
+/// [`GeneralImage::bands`]: crate::GeneralImage::bands
+/// [`GeneralImage::bands_mut`]: crate::GeneralImage::bands_mut
+#[derive(Debug, Copy, Clone)]
+pub struct SubImage<'a> {
+    bits: BitsImage<&'a [u8]>,
+    transfer_function: TransferFunction,
+    border_pixel: Option<Rgba>,
+}
+
+impl<'a> SubImage<'a> {
+    pub(crate) fn new(
+        bits: BitsImage<&'a [u8]>,
+        transfer_function: TransferFunction,
+        border_pixel: Option<Rgba>,
+    ) -> Self {
+        SubImage {
+            bits,
+            transfer_function,
+            border_pixel,
+        }
+    }
+}
+
+impl Image for SubImage<'_> {
+    fn format(&self) -> Format {
+        self.bits.format()
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.bits.endianness()
+    }
+
+    fn bit_order(&self) -> BitOrder {
+        self.bits.bit_order()
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.transfer_function
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        self.border_pixel
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        self.bits.dimensions()
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        self.bits.bytes_per_scanline()
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        let written = self.bits.scanline(x, y, scanline);
+        if let Some(color) = self.border_pixel {
+            if self.bits.oob_policy() == OobPolicy::Border {
+                let codec =
+                    Codec::new(self.format(), self.endianness()).with_bit_order(self.bit_order());
+                paint_border(&codec, color, scanline, (x, y), self.dimensions());
+            }
+        }
+        written
+    }
+
+    fn set_scanline(&mut self, _x: usize, _y: usize, _scanline: &[u8]) -> usize {
+        0
+    }
+}
@@ -0,0 +1,98 @@
+// BSL 1.0 License
+
+//! Conversions between `f32` and IEEE 754 half-precision floats.
+//!
+//! `f16` isn't a stable primitive, so half floats are carried around as
+//! their bits in a `u16`.
+
+/// Convert the bits of a half float to an `f32`.
+///
+/// Every half float, including subnormals, infinities and NaNs, is
+/// represented exactly.
+pub(crate) fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // subnormal, so move the leading one into the implicit bit
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3FF;
+            sign | ((113 - shift) << 23) | (mantissa << 13)
+        }
+        0x1F => sign | 0x7F80_0000 | (mantissa << 13),
+        exponent => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+/// Convert an `f32` to the bits of the nearest half float.
+///
+/// Ties round to even. Values that are too large become infinity, and
+/// NaNs stay NaNs.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    if exponent == 0xFF {
+        let nan = if mantissa == 0 { 0 } else { 0x200 };
+        return sign | 0x7C00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // the bits of the half float followed by the bits that get rounded
+    // away, and how many of those there are
+    let (full, shift) = if exponent > 0 {
+        (((exponent as u32) << 23) | mantissa, 13)
+    } else if exponent >= -10 {
+        // subnormal, so the implicit bit becomes explicit
+        (mantissa | 0x80_0000, (14 - exponent) as u32)
+    } else {
+        return sign;
+    };
+
+    let half = full >> shift;
+    let rest = full & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let round_up = rest > halfway || (rest == halfway && half & 1 == 1);
+
+    // a carry out of the mantissa correctly bumps the exponent
+    sign | (half + round_up as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(f32_to_f16(1.0), 0x3C00);
+        assert_eq!(f32_to_f16(-2.0), 0xC000);
+        assert_eq!(f32_to_f16(65504.0), 0x7BFF);
+        assert_eq!(f32_to_f16(65520.0), 0x7C00);
+        assert_eq!(f32_to_f16(1.0 / 3.0), 0x3555);
+        assert_eq!(f32_to_f16(5.960_464_5e-8), 0x0001);
+        assert_eq!(f32_to_f16(2.980_232_2e-8), 0x0000);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+
+        // every half float survives a trip through f32
+        for half in 0..=u16::MAX {
+            let back = f32_to_f16(f16_to_f32(half));
+            if half & 0x7C00 == 0x7C00 && half & 0x3FF != 0 {
+                assert_eq!(back & 0x7C00, 0x7C00);
+                assert_ne!(back & 0x3FF, 0);
+            } else {
+                assert_eq!(back, half, "{:04X}", half);
+            }
+        }
+    }
+}
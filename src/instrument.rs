@@ -0,0 +1,42 @@
+// BSL 1.0 License
+
+//! Reporting of hidden slow paths, and of the time spent in operations.
+//!
+//! Some operations quietly fall back to much slower code, such as when a
+//! solid color image is copied into a buffer to be edited. With the `log`
+//! or `defmt` feature enabled, these fallbacks are logged at the debug
+//! level so that they can be found in production pipelines. With the
+//! `tracing` feature enabled, the bulk operations run inside spans.
+//! Otherwise, the messages and spans compile to nothing.
+
+/// Log that a slow path has been taken.
+///
+/// The message is a format string for both `log` and `defmt`, so its
+/// arguments should be plain integers.
+macro_rules! slow_path {
+    ($($arg: tt)*) => {{
+        #[cfg(feature = "log")]
+        log::debug!(target: "genimage::slow_path", $($arg)*);
+        #[cfg(feature = "defmt")]
+        defmt::debug!($($arg)*);
+    }};
+}
+
+/// Enter a span for a bulk operation, which is exited when the returned
+/// guard is dropped.
+///
+/// The fields use the syntax of `tracing`, so formats should be recorded
+/// with `%`.
+#[cfg(feature = "tracing")]
+macro_rules! op_span {
+    ($name: literal, $($field: tt)*) => {
+        tracing::debug_span!(target: "genimage::ops", $name, $($field)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! op_span {
+    ($($arg: tt)*) => {
+        ()
+    };
+}
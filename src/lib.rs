@@ -58,6 +58,13 @@
 //! backed by a buffer. Without the `alloc` feature, mutating this image will
 //! panic.
 //!
+//! ## Avoiding allocations
+//!
+//! Image operations that need a buffer each have an `_into` variant that
+//! takes the buffer from the caller instead, such as [`convert_into`] or
+//! [`diff_image_into`]. These variants are available without the `alloc`
+//! feature, and never allocate behind the caller's back.
+//!
 //! ## [`image`] compatibility
 //!
 //! With the `image` feature enabled, types that implement
@@ -65,7 +72,37 @@
 //! sake. However, it will only be implemented if the image's `Pixel`
 //! implements the [`CompatiblePixel`] trait.
 //!
+//! ## [`ndarray`] compatibility
+//!
+//! With the `ndarray` feature enabled, arrays of bytes can be wrapped in
+//! an [`NdImage`], so that they can be used with the rest of this crate
+//! without copying them.
+//!
+//! ## Finding slow paths
+//!
+//! With the `log` or `defmt` feature enabled, operations that quietly fall
+//! back to a slow path are logged at the debug level. This includes
+//! solid color images being copied into a buffer to be edited, pixels
+//! being converted one at a time, columns being read one pixel at a time,
+//! and writes being clipped.
+//!
+//! ## Profiling
+//!
+//! With the `tracing` feature enabled, the bulk operations that take the
+//! most time, such as [`convert`], [`blit_bits`] and [`Scene::render`],
+//! run inside debug level spans of the `genimage::ops` target. The spans
+//! record the dimensions and formats that the operations work on, so the
+//! time spent in them can be told apart by existing `tracing`
+//! subscribers.
+//!
+//! [`convert`]: crate::convert
+//! [`blit_bits`]: crate::blit_bits
+//! [`Scene::render`]: crate::Scene::render
+//! [`convert_into`]: crate::convert_into
+//! [`diff_image_into`]: crate::diff_image_into
 //! [`image`]: https://crates.io/crates/image
+//! [`ndarray`]: https://crates.io/crates/ndarray
+//! [`NdImage`]: crate::NdImage
 //! [`imageproc`]: https://crates.io/crates/imageproc
 //! [`ARGB32`]: crate::Format::ARGB32
 //! [`Image`]: crate::Image
@@ -82,31 +119,165 @@
 //! [`Vec`]: std::vec::Vec
 //! [`GenericImageView`]: image::GenericImageView
 
-#![forbid(unsafe_code, future_incompatible, rust_2018_idioms)]
+#![forbid(future_incompatible)]
+// serde's derives allow lints that can't be allowed once forbidden
+#![cfg_attr(not(feature = "serde"), forbid(rust_2018_idioms))]
+#![cfg_attr(feature = "serde", deny(rust_2018_idioms))]
+// only the C interface needs unsafe code
+#![cfg_attr(not(feature = "capi"), forbid(unsafe_code))]
+#![cfg_attr(feature = "capi", deny(unsafe_code))]
 #![no_std]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::cmp;
+
+#[macro_use]
+mod instrument;
 
-pub(crate) mod array;
 pub(crate) mod assert_exact_size;
+pub(crate) mod codec;
+pub(crate) mod half_float;
+
+mod blue_noise;
+pub use blue_noise::BlueNoise;
+
+#[cfg(feature = "android")]
+pub mod android;
+
+#[cfg(feature = "cairo")]
+pub mod cairo;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "alloc")]
+mod cache;
+#[cfg(feature = "alloc")]
+pub use cache::{ImageKey, TransformCache};
 
 mod color;
 pub use color::Rgba;
 
+#[cfg(feature = "drm")]
+pub mod drm;
+
+#[cfg(feature = "alloc")]
+mod dyn_image;
+#[cfg(feature = "alloc")]
+pub use dyn_image::{DynImage, ImageAny};
+
+#[cfg(feature = "alloc")]
+mod encoding;
+#[cfg(feature = "alloc")]
+pub use encoding::{CodecRegistry, ImageDecoder, ImageEncoder};
+
+mod error;
+pub use error::{Error, FormatError};
+
 mod format;
 pub(crate) use format::MAX_BYTES_PER_PIXEL;
-pub use format::{Channel, ColorType, Format};
+pub use format::{
+    AlphaMode, Channel, ChannelInfo, ChannelInfos, ChannelOrder, Channels, ColorType, Format,
+    YcbcrMatrix,
+};
+
+mod framebuffer;
+pub use framebuffer::{map_framebuffer, FramebufferDesc, Origin};
+
+mod palette;
+pub use palette::Palette;
 
 mod pixel;
 pub use pixel::{ChannelValue, Pixel};
 
 mod general;
-pub use general::{Builder, GeneralImage, Nothing};
+pub use general::{Builder, GeneralImage, Layout, Nothing, OobPolicy, SubImage};
+
+#[cfg(feature = "ndarray")]
+mod ndimage;
+#[cfg(feature = "ndarray")]
+pub use ndimage::{NdImage, NdStorage};
+
+mod ops;
+#[cfg(all(feature = "std", rustc_1_63))]
+pub use ops::convert_tiled;
+pub use ops::{
+    abs_diff, accumulate, accumulate_with_policy, apply_color_matrix,
+    apply_color_matrix_with_policy, blend_row, blend_row_with_policy, blit_bits,
+    blit_bits_with_hints, column_sums_into, convert, convert_into, convert_with_hints,
+    count_pixels_matching, daltonize, demosaic_nearest_into, demosaic_scratch_len, desaturate,
+    desaturate_with_policy, diff_image_into, diff_scratch_len, edge_detect_into, edge_scratch_len,
+    eink_dither_into, eink_scratch_len, fade, find_pixel, from_bitplanes, from_planar_bytes_into,
+    images_equal, invert, ordered_dither, per_pixel_max, per_pixel_min, planar_len, read_id_at,
+    roundtrip_lossless, row_hashes_into, row_sums_into, simulate_color_blindness, swizzle_row,
+    tint, tint_with_policy, to_bitplanes_into, to_planar_bytes_into, zip_scanlines,
+    ArithmeticPolicy, BlendOp, CfaPattern, ChunkingHints, ColorBlindness, ColorMatrix, CopyTask,
+    EinkOptions, ImagesEqualOptions,
+};
+#[cfg(feature = "alloc")]
+pub use ops::{
+    column_sums, demosaic_nearest, diff_image, edge_detect, eink_dither, from_planar_bytes,
+    row_hashes, row_sums, to_bitplanes, to_planar_bytes,
+};
+
+#[cfg(feature = "pixman")]
+pub mod pixman;
+
+mod planar;
+pub use planar::{ChromaSubsampling, PlanarImage, Plane};
+
+pub mod prelude;
+
+mod report;
+pub use report::{ChannelSet, ConversionReport, OpReport};
+
+#[cfg(feature = "alloc")]
+mod scene;
+#[cfg(feature = "alloc")]
+pub use scene::{LayerId, Scene};
+
+#[cfg(feature = "serde")]
+mod serde_support;
+
+#[cfg(feature = "alloc")]
+mod sparse;
+#[cfg(feature = "alloc")]
+pub use sparse::SparseImage;
+
+mod rect;
+pub use rect::Rect;
+
+mod transfer;
+pub use transfer::TransferFunction;
 
 mod u32_buf;
 pub use u32_buf::U32Buf;
 
+mod units;
+pub use units::{ByteCount, PixelCount};
+
+mod view;
+pub use view::{Border, BorderedImage, ConvertedImage, IndexedImage, MultiViewImage};
+
+#[cfg(feature = "v4l2")]
+pub mod v4l2;
+
+#[cfg(feature = "vulkan")]
+pub mod vulkan;
+
+#[cfg(feature = "win32")]
+pub mod win32;
+
+#[cfg(feature = "wgpu")]
+pub mod wgpu;
+
+#[cfg(feature = "x11")]
+pub mod x11;
+
 /// The centerpiece trait for this library.
 ///
 /// This trait represents a byte-oriented two-dimensional array of
@@ -146,6 +317,38 @@ pub trait Image {
     /// if they are sent from another computer.
     fn endianness(&self) -> Endianness;
 
+    /// The order of the pixels within each byte, for formats that are
+    /// smaller than a byte.
+    ///
+    /// This is [`LsbFirst`] by default.
+    ///
+    /// [`LsbFirst`]: crate::BitOrder::LsbFirst
+    fn bit_order(&self) -> BitOrder {
+        BitOrder::LsbFirst
+    }
+
+    /// How the values of the color channels map to light.
+    ///
+    /// This is [`Srgb`] by default.
+    ///
+    /// [`Srgb`]: crate::TransferFunction::Srgb
+    fn transfer_function(&self) -> TransferFunction {
+        TransferFunction::Srgb
+    }
+
+    /// The color of the pixels outside of the image, if it has one.
+    ///
+    /// Images built with [`with_border_pixel`] read as this color past
+    /// their edges, and [`BorderedImage`]s with [`Border::Inherit`] draw
+    /// their border with it. This is `None` by default.
+    ///
+    /// [`with_border_pixel`]: crate::Builder::with_border_pixel
+    /// [`BorderedImage`]: crate::BorderedImage
+    /// [`Border::Inherit`]: crate::Border::Inherit
+    fn border_pixel(&self) -> Option<Rgba> {
+        None
+    }
+
     /// Logical dimensions of the image.
     ///
     /// This describes the width and the height for the image.
@@ -180,6 +383,52 @@ pub trait Image {
     /// [`self.format().bytes()`]: crate::Image::format
     fn bytes_per_scanline(&self) -> usize;
 
+    /// The number of bytes per scanline, as a [`ByteCount`].
+    ///
+    /// [`ByteCount`]: crate::ByteCount
+    fn stride(&self) -> ByteCount {
+        ByteCount::new(self.bytes_per_scanline())
+    }
+
+    /// The width of the image, as a [`PixelCount`].
+    ///
+    /// [`PixelCount`]: crate::PixelCount
+    fn pixel_width(&self) -> PixelCount {
+        PixelCount::new(self.width())
+    }
+
+    /// The number of bytes that each pixel takes up.
+    ///
+    /// This is one for formats smaller than a byte, even though several
+    /// pixels share that byte.
+    fn bytes_per_pixel(&self) -> usize {
+        self.format().bytes() as usize
+    }
+
+    /// The number of pixels that fit in a scanline, including the padding
+    /// at the end of it.
+    ///
+    /// Unlike dividing [`bytes_per_scanline`] by [`bytes_per_pixel`], this
+    /// counts every pixel of formats smaller than a byte.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Builder, Format, Image};
+    ///
+    /// let image = Builder::from_buffer(5, 2, Format::A4, [0u8; 8])
+    ///     .with_bytes_per_scanline(4)
+    ///     .finish();
+    /// assert_eq!(image.bytes_per_pixel(), 1);
+    /// assert_eq!(image.pixels_per_scanline(), 8);
+    /// ```
+    ///
+    /// [`bytes_per_scanline`]: crate::Image::bytes_per_scanline
+    /// [`bytes_per_pixel`]: crate::Image::bytes_per_pixel
+    fn pixels_per_scanline(&self) -> usize {
+        self.stride().to_pixels(self.format()).get()
+    }
+
     /// Fill a scanline with bytes from this image.
     ///
     /// The `y` coordinate is the logical scanline index. The `x` coordinate
@@ -203,37 +452,315 @@ pub trait Image {
     ///   bits are extracted.
     /// - For formats where the pixels' size is one byte, no further interpretation
     ///   is necessary.
-    /// - For formats where the pixels' size is 16 or 32 bytes, the array should
-    ///   be cast to an array of `u16`s or `u32`s, respectively. `bytemuck`'s
-    ///   [`cast_slice`] function provides a safe way of doing this. Note that
-    ///   the `scanline` should be aligned to the `u16` or `u32` boundary in this
-    ///   case. This can be done be instantiating it as an array of `u32`, and then
-    ///   using [`bytes_of`] to cast it into an array of bytes for this function.
-    /// - For formats where the pixels are floats, the above steps should be taken,
-    ///   but the array should be cast to an array of `f32`s.
+    /// - For formats where the pixels' size is 16 or 32 bits, the bytes should
+    ///   be read as `u16`s or `u32`s of the image's endianness, respectively.
+    ///   [`scanline_u16`] and [`scanline_u32`] do this, and give back the
+    ///   values in native order.
+    /// - For formats where the pixels are 32-bit floats, the above steps should
+    ///   be taken, and the `u32`s turned into `f32`s with `f32::from_bits`.
     ///
     /// The [`pixel()`] function does all of the above.
     ///
     /// [`pixel()`]: crate::Image::pixel
     /// [`format`]: crate::Image::format
     /// [`endianness`]: crate::Image::endianness
-    /// [`bytes_of`]: bytemuck::bytes_of
-    /// [`cast_slice`]: bytemuck::cast_slice
+    /// [`scanline_u16`]: crate::Image::scanline_u16
+    /// [`scanline_u32`]: crate::Image::scanline_u32
     fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize;
+
+    /// Fill a buffer with the 16-bit quantums of a scanline, in native
+    /// order.
+    ///
+    /// This reads `out.len() * 2` bytes starting at the pixel at `x`, like
+    /// [`scanline`], and converts every two of them from the endianness of
+    /// the image. It is meant for formats whose pixels or channels are 16
+    /// bits wide, such as [`RGB565`] or [`RGB48`], and takes care of the
+    /// alignment and byte swapping that casting the bytes would need.
+    ///
+    /// Returns the number of quantums written to `out`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Builder, Endianness, Format, Image};
+    ///
+    /// let image = Builder::from_buffer(2, 1, Format::RGB565, [0xF8u8, 0x00, 0x07, 0xE0])
+    ///     .with_endianness(Endianness::Big)
+    ///     .finish();
+    /// let mut pixels = [0u16; 2];
+    /// assert_eq!(image.scanline_u16(0, 0, &mut pixels), 2);
+    /// assert_eq!(pixels, [0xF800, 0x07E0]);
+    /// ```
+    ///
+    /// [`scanline`]: crate::Image::scanline
+    /// [`RGB565`]: crate::Format::RGB565
+    /// [`RGB48`]: crate::Format::RGB48
+    fn scanline_u16(&self, x: usize, y: usize, out: &mut [u16]) -> usize {
+        let endianness = self.endianness();
+        let mut written = 0;
+        read_quantums(self, x, y, out.len() * 2, |bytes| {
+            for (value, pair) in out[written..].iter_mut().zip(bytes.chunks_exact(2)) {
+                let pair = [pair[0], pair[1]];
+                *value = match endianness {
+                    Endianness::Little => u16::from_le_bytes(pair),
+                    Endianness::Big => u16::from_be_bytes(pair),
+                };
+            }
+            written += bytes.len() / 2;
+        });
+        written
+    }
+
+    /// Fill a buffer with the 32-bit quantums of a scanline, in native
+    /// order.
+    ///
+    /// This is [`scanline_u16`] for formats whose pixels or channels are 32
+    /// bits wide, such as [`ARGB32`] or [`ARGB_F32`].
+    ///
+    /// Returns the number of quantums written to `out`.
+    ///
+    /// [`scanline_u16`]: crate::Image::scanline_u16
+    /// [`ARGB32`]: crate::Format::ARGB32
+    /// [`ARGB_F32`]: crate::Format::ARGB_F32
+    fn scanline_u32(&self, x: usize, y: usize, out: &mut [u32]) -> usize {
+        let endianness = self.endianness();
+        let mut written = 0;
+        read_quantums(self, x, y, out.len() * 4, |bytes| {
+            for (value, quad) in out[written..].iter_mut().zip(bytes.chunks_exact(4)) {
+                let quad = [quad[0], quad[1], quad[2], quad[3]];
+                *value = match endianness {
+                    Endianness::Little => u32::from_le_bytes(quad),
+                    Endianness::Big => u32::from_be_bytes(quad),
+                };
+            }
+            written += bytes.len() / 4;
+        });
+        written
+    }
     /// Store a scanline into this image.
+    ///
+    /// This is the inverse of [`scanline`]: the bytes of `scanline` are
+    /// written starting at the pixel at `x` on the logical scanline `y`.
+    /// It should return the number of bytes that were written.
+    ///
+    /// [`scanline`]: crate::Image::scanline
     fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize;
 
+    /// Store a scanline into this image, clipping it at the right edge.
+    ///
+    /// Any bytes of `scanline` that would land past the last pixel of the
+    /// row are ignored, and bytes that land in the row's padding are never
+    /// written. This never panics due to the length of `scanline`, and
+    /// starting past the end of the row writes nothing.
+    ///
+    /// Returns the number of bytes of `scanline` that were consumed.
+    fn set_scanline_clipped(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        let bpp = self.format().bpp() as usize;
+        let row_len = divide_rounding_up(self.width().saturating_mul(bpp), 8);
+        let start = x.saturating_mul(bpp) / 8;
+
+        if start >= row_len {
+            return 0;
+        }
+
+        let len = cmp::min(scanline.len(), row_len - start);
+        if len < scanline.len() {
+            slow_path!(
+                "clipped a scanline write from {} to {} bytes",
+                scanline.len(),
+                len
+            );
+        }
+        self.set_scanline(x, y, &scanline[..len])
+    }
+
+    /// Store a scanline whose bytes are in the given endianness into this
+    /// image.
+    ///
+    /// This is like [`set_scanline`], but if `src_endian` isn't the
+    /// endianness of the image, the bytes of every pixel are swapped on
+    /// the way in. Pixel data from the network or from files in a fixed
+    /// byte order can be written without first swapping it in a separate
+    /// buffer. Only whole pixels are written, and formats with pixels of
+    /// one byte or less are written as they are.
+    ///
+    /// Returns the number of bytes that were written.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Builder, Endianness, Format, Image};
+    ///
+    /// let mut image = Builder::from_buffer(2, 1, Format::RGB565, [0u8; 4])
+    ///     .with_endianness(Endianness::Little)
+    ///     .finish();
+    /// image.set_scanline_with_endianness(0, 0, &[0xF8, 0x00, 0x07, 0xE0], Endianness::Big);
+    ///
+    /// let mut pixels = [0u16; 2];
+    /// image.scanline_u16(0, 0, &mut pixels);
+    /// assert_eq!(pixels, [0xF800, 0x07E0]);
+    /// ```
+    ///
+    /// [`set_scanline`]: crate::Image::set_scanline
+    fn set_scanline_with_endianness(
+        &mut self,
+        mut x: usize,
+        y: usize,
+        scanline: &[u8],
+        src_endian: Endianness,
+    ) -> usize {
+        let format = self.format();
+        if src_endian == self.endianness() || format.bpp() <= 8 {
+            return self.set_scanline(x, y, scanline);
+        }
+
+        let src_codec = codec::Codec::new(format, src_endian);
+        let dst_codec = codec::Codec::new(format, self.endianness());
+        let pixel_len = format.bytes() as usize;
+        let mut batch = [0u8; ops::CHUNK_BYTES];
+        let mut written = 0;
+        for src in scanline.chunks(ops::CHUNK_BYTES) {
+            let count = src.len() / pixel_len;
+            if count == 0 {
+                break;
+            }
+
+            let len = count * pixel_len;
+            ops::convert_row(&src_codec, src, None, &dst_codec, &mut batch[..len], count);
+
+            let done = self.set_scanline(x, y, &batch[..len]);
+            written += done;
+            if done < len {
+                break;
+            }
+            x += count;
+        }
+        written
+    }
+
+    /// Fill a buffer with part of a column of this image.
+    ///
+    /// This is like [`scanline`], but it reads down the column `x`
+    /// starting at the pixel at `y`, and packs the pixels the same way
+    /// as a scanline that starts at `y` would be. It returns the number of
+    /// bytes written to `column`.
+    ///
+    /// The default implementation reads the pixels one at a time, but
+    /// column-major images can copy them straight out of their buffer.
+    ///
+    /// [`scanline`]: crate::Image::scanline
+    fn column(&self, x: usize, y: usize, column: &mut [u8]) -> usize {
+        gather_column(self, x, y, column)
+    }
+
+    /// The palette that the indices of this image refer to, if it has one.
+    ///
+    /// This is only meaningful for [`Indexed`] formats. Images don't have
+    /// a palette by default; see [`IndexedImage`] for attaching one.
+    /// Conversions out of an image with a palette look its indices up,
+    /// while indices without one are read as red levels.
+    ///
+    /// [`Indexed`]: crate::ColorType::Indexed
+    /// [`IndexedImage`]: crate::IndexedImage
+    fn palette(&self) -> Option<&Palette> {
+        None
+    }
+
+    /// Copy a rectangle of this image into `dst`, one row after another.
+    ///
+    /// Each row starts `dst_stride` bytes after the previous one, like the
+    /// payloads of X11's `GetImage` or OpenGL's `glTexSubImage2D`. The rows
+    /// are read with [`scanline`], so they are packed the same way, and
+    /// pixels outside of the image follow its out-of-bounds behavior.
+    ///
+    /// Returns `false` without writing anything if `dst_stride` is too
+    /// small to hold a row, or if `dst` is too small to hold every row.
+    /// The last row doesn't need to be followed by padding.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Format, GeneralImage, Image, Rect};
+    ///
+    /// let image = GeneralImage::from_buffer(3, 3, Format::A8, [1u8, 2, 3, 4, 5, 6, 7, 8, 9]);
+    /// let mut dst = [0u8; 6];
+    /// assert!(image.get_rect(Rect::new(1, 1, 2, 2), &mut dst, 4));
+    /// assert_eq!(dst, [5, 6, 0, 0, 8, 9]);
+    /// ```
+    ///
+    /// [`scanline`]: crate::Image::scanline
+    fn get_rect(&self, rect: Rect, dst: &mut [u8], dst_stride: usize) -> bool {
+        let row_len = match rect_row_len(self.format(), rect, dst.len(), dst_stride) {
+            Some(row_len) => row_len,
+            None => return false,
+        };
+
+        for row in 0..rect.height {
+            let start = row * dst_stride;
+            self.scanline(
+                rect.x,
+                rect.y.saturating_add(row),
+                &mut dst[start..start + row_len],
+            );
+        }
+
+        true
+    }
+
+    /// Copy rows of bytes from `src` into a rectangle of this image.
+    ///
+    /// This is the inverse of [`get_rect`]: each row starts `src_stride`
+    /// bytes after the previous one, and is written with
+    /// [`set_scanline_clipped`]. For formats smaller than a byte, the
+    /// first and last byte of every row are written whole.
+    ///
+    /// Returns `false` without writing anything if `src_stride` is too
+    /// small to hold a row, or if `src` is too small to hold every row.
+    ///
+    /// [`get_rect`]: crate::Image::get_rect
+    /// [`set_scanline_clipped`]: crate::Image::set_scanline_clipped
+    fn put_rect(&mut self, rect: Rect, src: &[u8], src_stride: usize) -> bool {
+        let row_len = match rect_row_len(self.format(), rect, src.len(), src_stride) {
+            Some(row_len) => row_len,
+            None => return false,
+        };
+
+        for row in 0..rect.height {
+            let start = row * src_stride;
+            self.set_scanline_clipped(
+                rect.x,
+                rect.y.saturating_add(row),
+                &src[start..start + row_len],
+            );
+        }
+
+        true
+    }
+
     /// Fetch the pixel at the given location.
+    ///
+    /// Pixels of YCbCr formats are read along with the other pixel of
+    /// their pair, so that they hold both chroma samples and convert to
+    /// RGBA on their own.
     fn pixel(&self, x: usize, y: usize) -> Pixel {
+        if self.format().color_type().ycbcr_matrix().is_some() {
+            let mut pair = [0u8; MAX_BYTES_PER_PIXEL];
+            let len = self.format().bytes() as usize * 2;
+            let read = self.scanline(x - x % 2, y, &mut pair[..len]);
+            return Pixel::with_index(
+                &pair[..read],
+                (x % 2) as u8,
+                self.endianness(),
+                self.format(),
+            );
+        }
+
         // read into a buffer
         let mut bytes = [0u32; MAX_BYTES_PER_PIXEL / 4];
-        let index = match self.format().bpp() {
-            1 => x % 8,
-            4 => (x % 2) * 4,
-            _ => 0,
-        };
+        let index = self.bit_order().index_in_byte(self.format().bpp(), x);
 
-        let read = self.scanline(x, y, bytemuck::bytes_of_mut(&mut bytes));
+        let len: usize = self.format().bytes().into();
+        let read = self.scanline(x, y, &mut bytemuck::bytes_of_mut(&mut bytes)[..len]);
         debug_assert_eq!(
             read,
             self.format().bytes() as usize,
@@ -244,8 +771,8 @@ pub trait Image {
             Pixel::from_float_bytes(bytemuck::cast(bytes), self.endianness(), self.format())
         } else {
             Pixel::from_bytes(
-                bytes[0].to_ne_bytes(),
-                index as u8,
+                bytemuck::cast::<_, [[u8; 8]; 2]>(bytes)[0],
+                index,
                 self.endianness(),
                 self.format(),
             )
@@ -255,17 +782,87 @@ pub trait Image {
     /// Set the pixel at the given location.
     fn set_pixel(&mut self, x: usize, y: usize, pixel: Pixel) {
         // read one pixel's worth to a buffer, insert it, and then write it back
-        // TODO: convert pixel to this format
+        if pixel.format() != self.format() {
+            slow_path!("converted a single pixel at ({}, {})", x, y);
+        }
+        let pixel = pixel.into_new_format(self.endianness(), self.format());
         let mut buffer = [0u8; MAX_BYTES_PER_PIXEL];
+
+        if self.format().color_type().ycbcr_matrix().is_some() {
+            // the pixel shares its chroma with the other pixel of its pair
+            let len = self.format().bytes() as usize * 2;
+            let read = self.scanline(x - x % 2, y, &mut buffer[..len]);
+            pixel.insert_pair(&mut buffer[..read], (x % 2) as u8);
+            self.set_scanline(x - x % 2, y, &buffer[..read]);
+            return;
+        }
+
         let len: usize = self.format().bytes().into();
+        let index = self.bit_order().index_in_byte(self.format().bpp(), x);
+
         self.scanline(x, y, &mut buffer[..len]);
-        pixel.insert(&mut buffer[..len]);
+        pixel.insert(&mut buffer[..len], index);
         self.set_scanline(x, y, &buffer[..len]);
     }
 }
 
+impl<I: Image + ?Sized> Image for &mut I {
+    fn format(&self) -> Format {
+        (**self).format()
+    }
+
+    fn endianness(&self) -> Endianness {
+        (**self).endianness()
+    }
+
+    fn bit_order(&self) -> BitOrder {
+        (**self).bit_order()
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        (**self).transfer_function()
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        (**self).border_pixel()
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (**self).dimensions()
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        (**self).bytes_per_scanline()
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        (**self).scanline(x, y, scanline)
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        (**self).set_scanline(x, y, scanline)
+    }
+
+    fn column(&self, x: usize, y: usize, column: &mut [u8]) -> usize {
+        (**self).column(x, y, column)
+    }
+
+    fn palette(&self) -> Option<&Palette> {
+        (**self).palette()
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        (**self).pixel(x, y)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, pixel: Pixel) {
+        (**self).set_pixel(x, y, pixel)
+    }
+}
+
 /// The endianness for an image.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Endianness {
     /// Little endian.
     Little,
@@ -287,23 +884,212 @@ impl Endianness {
         self == Self::NATIVE
     }
 
-    /// Create a u32 from bytes of this endianness.
-    pub(crate) fn make_u32(self, bytes: [u8; 4]) -> u32 {
+    /// Create a u64 from up to eight bytes of this endianness.
+    ///
+    /// This is how integer quantums and float channels of any size are
+    /// read.
+    pub(crate) fn make_u64(self, bytes: &[u8]) -> u64 {
+        let mut buffer = [0u8; 8];
         match self {
-            Endianness::Little => u32::from_le_bytes(bytes),
-            Endianness::Big => u32::from_be_bytes(bytes),
+            Endianness::Little => {
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                u64::from_le_bytes(buffer)
+            }
+            Endianness::Big => {
+                buffer[8 - bytes.len()..].copy_from_slice(bytes);
+                u64::from_be_bytes(buffer)
+            }
         }
     }
+}
+
+/// The order of pixels that share a byte.
+///
+/// Formats that are smaller than a byte pack several pixels into each
+/// byte, and platforms disagree about which end of the byte the first
+/// pixel goes in. X11's `XYBitmap` images and many monochrome panels put
+/// it in the most significant bits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitOrder {
+    /// The first pixel is in the least significant bits.
+    ///
+    /// This is the default.
+    LsbFirst,
+    /// The first pixel is in the most significant bits.
+    MsbFirst,
+}
+
+impl Default for BitOrder {
+    fn default() -> Self {
+        BitOrder::LsbFirst
+    }
+}
 
-    /// Create a u16 from bytes of this endianness.
-    pub(crate) fn make_u16(self, bytes: [u8; 2]) -> u16 {
+impl BitOrder {
+    /// The index of the lowest bit of the pixel at `x` within its byte.
+    ///
+    /// This is always zero for formats of a byte or more.
+    pub(crate) fn index_in_byte(self, bpp: u8, x: usize) -> u8 {
+        if bpp >= 8 {
+            return 0;
+        }
+
+        let index = (x.wrapping_mul(bpp as usize) % 8) as u8;
         match self {
-            Endianness::Little => u16::from_le_bytes(bytes),
-            Endianness::Big => u16::from_be_bytes(bytes),
+            BitOrder::LsbFirst => index,
+            BitOrder::MsbFirst => 8 - bpp - index,
         }
     }
 }
 
+/// Read `len` bytes of a scanline in batches, passing each batch to `f`.
+///
+/// Every batch but the last is a multiple of four bytes and of the size
+/// of a pixel.
+fn read_quantums<Img: Image + ?Sized>(
+    image: &Img,
+    mut x: usize,
+    y: usize,
+    mut len: usize,
+    mut f: impl FnMut(&[u8]),
+) {
+    let bpp = image.format().bpp() as usize;
+    let mut batch = [0u8; ops::CHUNK_BYTES];
+    while len > 0 {
+        let want = cmp::min(len, ops::CHUNK_BYTES);
+        let read = image.scanline(x, y, &mut batch[..want]);
+        f(&batch[..read]);
+        if read < want {
+            return;
+        }
+
+        len -= read;
+        x += read * 8 / bpp;
+    }
+}
+
+/// The number of bytes in each row of a rectangle, if a buffer of `len`
+/// bytes with the given stride can hold all of its rows.
+fn rect_row_len(format: Format, rect: Rect, len: usize, stride: usize) -> Option<usize> {
+    let bpp = format.bpp() as usize;
+    let skip = rect.x.saturating_mul(bpp) % 8;
+    let row_len = divide_rounding_up(rect.width.checked_mul(bpp)?.checked_add(skip)?, 8);
+
+    if rect.height == 0 {
+        return Some(row_len);
+    }
+
+    let needed = (rect.height - 1)
+        .checked_mul(stride)?
+        .checked_add(row_len)?;
+    if stride < row_len || len < needed {
+        None
+    } else {
+        Some(row_len)
+    }
+}
+
+/// Read a column one pixel at a time.
+fn gather_column<Img: Image + ?Sized>(image: &Img, x: usize, y: usize, column: &mut [u8]) -> usize {
+    slow_path!("read column {} one pixel at a time", x);
+    let codec =
+        codec::Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    let format = image.format();
+    let (bpp, bytes) = (format.bpp() as usize, format.bytes() as usize);
+    let index = |i: usize| i.saturating_mul(bpp) % 8 / bpp;
+    let first = index(y);
+    let mut pixel = [0u8; MAX_BYTES_PER_PIXEL];
+    let mut end = first;
+
+    while end < column.len() * 8 / bpp {
+        let row = y.saturating_add(end - first);
+        if image.scanline(x, row, &mut pixel[..bytes]) < bytes {
+            break;
+        }
+        codec.copy_pixel(&pixel, index(x), column, end);
+        end += 1;
+    }
+
+    if end == first {
+        0
+    } else {
+        divide_rounding_up(end * bpp, 8)
+    }
+}
+
 const fn divide_rounding_up(n: usize, d: usize) -> usize {
-    (n + d - 1) / d
+    // avoid overflowing on very large values of `n`
+    n / d + (n % d != 0) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Builder, Endianness, Format, Image};
+
+    #[test]
+    fn set_scanline_swapping_bytes() {
+        // long enough to take more than one batch
+        let mut storage = [0u8; 1200];
+        let mut image = Builder::from_buffer(600, 1, Format::RGB565, &mut storage[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        let mut src = [0u8; 1201];
+        for (i, pixel) in src.chunks_exact_mut(2).enumerate() {
+            pixel.copy_from_slice(&(i as u16).to_be_bytes());
+        }
+
+        // the odd byte at the end isn't a whole pixel
+        assert_eq!(
+            image.set_scanline_with_endianness(0, 0, &src, Endianness::Big),
+            1200
+        );
+        let mut pixels = [0u16; 600];
+        image.scanline_u16(0, 0, &mut pixels);
+        assert!(pixels.iter().enumerate().all(|(i, &p)| p == i as u16));
+
+        // clipped at the right edge
+        let src = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC];
+        assert_eq!(
+            image.set_scanline_with_endianness(598, 0, &src, Endianness::Big),
+            4
+        );
+        image.scanline_u16(598, 0, &mut pixels[..2]);
+        assert_eq!(pixels[..2], [0x1234, 0x5678]);
+    }
+
+    #[test]
+    fn set_scanline_without_swapping() {
+        let mut storage = [0u8; 8];
+        let mut image = Builder::from_buffer(2, 1, Format::ARGB32, &mut storage[..])
+            .with_endianness(Endianness::Big)
+            .finish();
+        let src = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            image.set_scanline_with_endianness(0, 0, &src, Endianness::Big),
+            8
+        );
+        let mut bytes = [0u8; 8];
+        image.scanline(0, 0, &mut bytes);
+        assert_eq!(bytes, src);
+
+        assert_eq!(
+            image.set_scanline_with_endianness(0, 0, &src, Endianness::Little),
+            8
+        );
+        image.scanline(0, 0, &mut bytes);
+        assert_eq!(bytes, [4, 3, 2, 1, 8, 7, 6, 5]);
+
+        // single bytes have no order to swap
+        let mut storage = [0u8; 3];
+        let mut image = Builder::from_buffer(3, 1, Format::GRAY8, &mut storage[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        assert_eq!(
+            image.set_scanline_with_endianness(0, 0, &[1, 2, 3], Endianness::Big),
+            3
+        );
+        image.scanline(0, 0, &mut bytes[..3]);
+        assert_eq!(bytes[..3], [1, 2, 3]);
+    }
 }
@@ -0,0 +1,289 @@
+// BSL 1.0 License
+
+//! Images backed by `ndarray` arrays.
+
+use crate::{Endianness, Format, Image};
+use core::{cmp, fmt};
+use ndarray::{
+    ArrayBase, ArrayViewMut, Axis, Data, Dimension, Ix2, Ix3, OwnedArcRepr, OwnedRepr,
+    RawDataClone, RemoveAxis, ViewRepr,
+};
+
+/// Array storage that an [`NdImage`] can be backed by.
+///
+/// Storage that can be written to returns a mutable view of the array,
+/// while read-only storage such as an `ArrayView` returns `None`.
+///
+/// [`NdImage`]: crate::NdImage
+pub trait NdStorage: Data<Elem = u8> {
+    /// Get a mutable view of the array, if the storage allows it.
+    fn view_mut<D: Dimension>(array: &mut ArrayBase<Self, D>) -> Option<ArrayViewMut<'_, u8, D>>;
+}
+
+impl NdStorage for OwnedRepr<u8> {
+    fn view_mut<D: Dimension>(array: &mut ArrayBase<Self, D>) -> Option<ArrayViewMut<'_, u8, D>> {
+        Some(array.view_mut())
+    }
+}
+
+impl NdStorage for OwnedArcRepr<u8> {
+    fn view_mut<D: Dimension>(array: &mut ArrayBase<Self, D>) -> Option<ArrayViewMut<'_, u8, D>> {
+        Some(array.view_mut())
+    }
+}
+
+impl NdStorage for ViewRepr<&mut u8> {
+    fn view_mut<D: Dimension>(array: &mut ArrayBase<Self, D>) -> Option<ArrayViewMut<'_, u8, D>> {
+        Some(array.view_mut())
+    }
+}
+
+impl NdStorage for ViewRepr<&u8> {
+    fn view_mut<D: Dimension>(_array: &mut ArrayBase<Self, D>) -> Option<ArrayViewMut<'_, u8, D>> {
+        None
+    }
+}
+
+/// An image that wraps around an `ndarray` array of bytes.
+///
+/// Two layouts are supported:
+///
+/// - Two-dimensional arrays are indexed by `[y, byte]`, where each row of
+///   the array holds the packed bytes of one scanline.
+/// - Three-dimensional arrays are indexed by `[y, x, byte]`, where the last
+///   axis holds the bytes of a single pixel. This is the usual `H x W x C`
+///   layout for images with 8-bit channels.
+///
+/// The array can have any strides, including negative or non-contiguous
+/// ones; contiguous rows are copied in one go. Images over read-only
+/// arrays silently ignore writes.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Endianness, Format, Image, NdImage};
+/// use ndarray::Array3;
+///
+/// let array = Array3::from_shape_fn((2, 3, 4), |(y, x, c)| (y * 12 + x * 4 + c) as u8);
+/// let image = NdImage::from_array3(array.view(), Format::RGBA32)
+///     .with_endianness(Endianness::Little);
+/// assert_eq!(image.dimensions(), (3, 2));
+///
+/// let mut row = [0u8; 8];
+/// image.scanline(1, 1, &mut row);
+/// assert_eq!(row, [16, 17, 18, 19, 20, 21, 22, 23]);
+/// ```
+pub struct NdImage<S: NdStorage, D: Dimension> {
+    array: ArrayBase<S, D>,
+    format: Format,
+    endianness: Endianness,
+}
+
+impl<S: NdStorage, D: Dimension> fmt::Debug for NdImage<S, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NdImage")
+            .field("array", &self.array)
+            .field("format", &self.format)
+            .field("endianness", &self.endianness)
+            .finish()
+    }
+}
+
+impl<S: NdStorage + RawDataClone, D: Dimension> Clone for NdImage<S, D> {
+    fn clone(&self) -> Self {
+        Self {
+            array: self.array.clone(),
+            format: self.format,
+            endianness: self.endianness,
+        }
+    }
+}
+
+impl<S: NdStorage> NdImage<S, Ix2> {
+    /// Wrap around an array of scanlines.
+    ///
+    /// The width of the image is the number of pixels that fit into each
+    /// row of the array.
+    pub fn from_array2(array: ArrayBase<S, Ix2>, format: Format) -> Self {
+        Self {
+            array,
+            format,
+            endianness: Endianness::NATIVE,
+        }
+    }
+}
+
+impl<S: NdStorage> NdImage<S, Ix3> {
+    /// Wrap around an array of pixels.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the last axis of the array is not as long as a pixel of
+    /// the format, or if the format is smaller than a byte.
+    pub fn from_array3(array: ArrayBase<S, Ix3>, format: Format) -> Self {
+        assert!(!format.subbyte(), "Pixels must be at least one byte");
+        assert_eq!(
+            array.len_of(Axis(2)),
+            format.bytes() as usize,
+            "The last axis must hold a single pixel"
+        );
+
+        Self {
+            array,
+            format,
+            endianness: Endianness::NATIVE,
+        }
+    }
+}
+
+impl<S: NdStorage, D: Dimension> NdImage<S, D> {
+    /// Use a different endianness for the image.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Get a reference to the inner array.
+    pub fn array(&self) -> &ArrayBase<S, D> {
+        &self.array
+    }
+
+    /// Get a mutable reference to the inner array.
+    pub fn array_mut(&mut self) -> &mut ArrayBase<S, D> {
+        &mut self.array
+    }
+
+    /// Get the inner array back.
+    pub fn into_array(self) -> ArrayBase<S, D> {
+        self.array
+    }
+}
+
+impl<S: NdStorage, D: RemoveAxis> NdImage<S, D> {
+    /// The index of the first byte of the pixel at `x`, if it is on the row.
+    fn byte_start(&self, x: usize, y: usize) -> Option<usize> {
+        let start = x.saturating_mul(self.format.bpp() as usize) / 8;
+        if y < self.height() && start < self.bytes_per_scanline() {
+            Some(start)
+        } else {
+            None
+        }
+    }
+}
+
+impl<S: NdStorage, D: RemoveAxis> Image for NdImage<S, D> {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        let shape = self.array.shape();
+        let width = match shape.len() {
+            3 => shape[1],
+            _ => shape[1] * 8 / self.format.bpp() as usize,
+        };
+
+        (width, shape[0])
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        self.array.shape()[1..].iter().product()
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        let start = match self.byte_start(x, y) {
+            Some(start) => start,
+            None => return 0,
+        };
+        let row = self.array.index_axis(Axis(0), y);
+
+        match row.as_slice() {
+            Some(row) => {
+                let len = cmp::min(scanline.len(), row.len() - start);
+                scanline[..len].copy_from_slice(&row[start..start + len]);
+                len
+            }
+            None => row
+                .iter()
+                .skip(start)
+                .zip(scanline.iter_mut())
+                .map(|(src, dst)| *dst = *src)
+                .count(),
+        }
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        let start = match self.byte_start(x, y) {
+            Some(start) => start,
+            None => return 0,
+        };
+        let mut array = match S::view_mut(&mut self.array) {
+            Some(array) => array,
+            None => return 0,
+        };
+        let mut row = array.index_axis_mut(Axis(0), y);
+
+        match row.as_slice_mut() {
+            Some(row) => {
+                let len = cmp::min(scanline.len(), row.len() - start);
+                row[start..start + len].copy_from_slice(&scanline[..len]);
+                len
+            }
+            None => row
+                .iter_mut()
+                .skip(start)
+                .zip(scanline.iter())
+                .map(|(dst, src)| *dst = *src)
+                .count(),
+        }
+    }
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{convert, Builder};
+    use ndarray::{Array2, Array3, Slice};
+
+    #[test]
+    fn strided_views() {
+        let mut array = Array3::<u8>::zeros((4, 6, 3));
+        array
+            .indexed_iter_mut()
+            .for_each(|((y, x, c), value)| *value = (y * 18 + x * 3 + c) as u8);
+
+        // every other column, flipped vertically
+        let mut view = array.slice_axis(Axis(1), Slice::new(0, None, 2));
+        view.invert_axis(Axis(0));
+        let image = NdImage::from_array3(view, Format::RGB24);
+        assert_eq!(image.dimensions(), (3, 4));
+        assert_eq!(image.bytes_per_scanline(), 9);
+
+        let mut row = [0u8; 9];
+        assert_eq!(image.scanline(1, 0, &mut row), 6);
+        assert_eq!(&row[..6], &[60, 61, 62, 66, 67, 68]);
+
+        let mut target = Array3::<u8>::zeros((4, 3, 3));
+        let mut converted = NdImage::from_array3(target.view_mut(), Format::BGR24)
+            .with_endianness(Endianness::Little);
+        convert(&image, &mut converted);
+        assert_eq!(target[[3, 2, 0]], array[[0, 4, 2]]);
+        assert_eq!(target[[3, 2, 2]], array[[0, 4, 0]]);
+    }
+
+    #[test]
+    fn read_only_and_subbyte() {
+        let array = Array2::from_shape_vec((2, 1), alloc::vec![0b1010_0101, 0xFF]).unwrap();
+        let mut image = NdImage::from_array2(array.view(), Format::A1);
+        assert_eq!(image.dimensions(), (8, 2));
+        assert_eq!(image.set_scanline(0, 0, &[0]), 0);
+
+        let copy = Builder::from_image(&image).finish();
+        assert_eq!(copy.pixel(2, 0), image.pixel(2, 0));
+        assert_eq!(array[[0, 0]], 0b1010_0101);
+    }
+}
@@ -0,0 +1,524 @@
+// BSL 1.0 License
+
+use super::{for_each_chunk_mut, ArithmeticPolicy};
+use crate::{
+    codec::{self, luma, Codec},
+    AlphaMode, Channel, ColorType, Image, Rgba,
+};
+use core::cmp;
+
+/// Multiply the alpha channel of every pixel in the image by `opacity`.
+///
+/// `opacity` is expected to be between `0.0` and `1.0`, and is clamped
+/// to that range. Images without an alpha channel are left unchanged.
+///
+/// For images with an 8-bit alpha channel, this uses a lookup table and
+/// never decodes the other channels, which makes it cheap enough to run
+/// on every frame of a fade animation. Premultiplied 8-bit colors go
+/// through the same table as alpha.
+pub fn fade<Img: Image + ?Sized>(image: &mut Img, opacity: f32) {
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    if codec.channel(Channel::Alpha).is_none() {
+        return;
+    }
+
+    let opacity = codec::clamp_unit(opacity);
+
+    // premultiplied colors are scaled along with alpha, which only works
+    // on bytes if every channel is one
+    let format = image.format();
+    let straight = Codec::new(
+        format.with_alpha_mode(AlphaMode::Straight),
+        image.endianness(),
+    );
+    let every_byte = format.premultiplies()
+        && format.channels().count() * 8 == format.bpp() as usize
+        && format
+            .channels()
+            .all(|info| info.bits == 8 && info.shift % 8 == 0);
+
+    match straight.byte_offset(Channel::Alpha) {
+        Some(offset) if every_byte || !format.premultiplies() => {
+            // build a lookup table for the alpha values
+            let mut lut = [0u8; 256];
+            for (i, value) in lut.iter_mut().enumerate() {
+                *value = (i as f32 * opacity + 0.5) as u8;
+            }
+
+            let stride = codec.format().bytes() as usize;
+            for_each_chunk_mut(image, |chunk, _| {
+                if every_byte {
+                    chunk
+                        .iter_mut()
+                        .for_each(|byte| *byte = lut[*byte as usize]);
+                } else {
+                    chunk
+                        .chunks_exact_mut(stride)
+                        .for_each(|pixel| pixel[offset] = lut[pixel[offset] as usize]);
+                }
+            });
+        }
+        _ => for_each_chunk_mut(image, |chunk, count| {
+            for i in 0..count {
+                let mut components = codec.read(chunk, i);
+                components[Channel::Alpha as usize] *= opacity;
+                codec.write(chunk, i, components);
+            }
+        }),
+    }
+}
+
+/// Multiply the color channels of every pixel in the image by a color.
+///
+/// `strength` controls how much of the tint is applied, from `0.0`
+/// (the image is left as-is) to `1.0` (the channels are multiplied by
+/// the color outright). The alpha channel and the alpha of `color` are
+/// ignored.
+///
+/// Images where the color channels are 8-bit use a lookup table for
+/// each channel.
+pub fn tint<Img: Image + ?Sized>(image: &mut Img, color: Rgba, strength: f32) {
+    tint_with_policy(image, color, strength, ArithmeticPolicy::default());
+}
+
+/// Like [`tint`], but with a choice of what is stored when a tinted
+/// channel doesn't fit, such as a float channel brighter than `1.0`.
+///
+/// [`tint`]: crate::tint
+pub fn tint_with_policy<Img: Image + ?Sized>(
+    image: &mut Img,
+    color: Rgba,
+    strength: f32,
+    policy: ArithmeticPolicy,
+) {
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    let strength = codec::clamp_unit(strength);
+
+    // the factor each color channel is multiplied by
+    let mut factors = color.components();
+    for factor in &mut factors[..3] {
+        *factor = 1.0 - strength + *factor * strength;
+    }
+    factors[Channel::Alpha as usize] = 1.0;
+
+    let offsets = [
+        codec.byte_offset(Channel::Red),
+        codec.byte_offset(Channel::Green),
+        codec.byte_offset(Channel::Blue),
+    ];
+
+    if let [Some(red), Some(green), Some(blue)] = offsets {
+        let mut luts = [[0u8; 256]; 3];
+        for (lut, factor) in luts.iter_mut().zip(factors.iter()) {
+            for (i, value) in lut.iter_mut().enumerate() {
+                *value = (i as f32 * factor + 0.5) as u8;
+            }
+        }
+
+        let stride = codec.format().bytes() as usize;
+        for_each_chunk_mut(image, |chunk, _| {
+            chunk.chunks_exact_mut(stride).for_each(|pixel| {
+                for (&offset, lut) in [red, green, blue].iter().zip(luts.iter()) {
+                    pixel[offset] = lut[pixel[offset] as usize];
+                }
+            });
+        });
+    } else {
+        for_each_chunk_mut(image, |chunk, count| {
+            for i in 0..count {
+                let mut components = codec.read(chunk, i);
+                for (component, factor) in components.iter_mut().zip(factors.iter()) {
+                    *component *= factor;
+                }
+                codec.write(chunk, i, policy.apply(&codec, components));
+            }
+        });
+    }
+}
+
+/// Invert the colors of every pixel in the image.
+///
+/// The alpha channel is left unchanged, unless it is the only channel in
+/// the image. In that case, the image is treated as a mask and the alpha
+/// channel is inverted instead.
+///
+/// Premultiplied colors are inverted before being multiplied by alpha,
+/// so that they stay within it. Indices are replaced by the index of the palette color closest to
+/// the inverse of their color. Indexed images without a [`palette`] are
+/// left unchanged.
+///
+/// [`palette`]: crate::Image::palette
+pub fn invert<Img: Image + ?Sized>(image: &mut Img) {
+    let format = image.format();
+    let color_type = format.color_type();
+    let codec = Codec::new(format, image.endianness()).with_bit_order(image.bit_order());
+
+    // figure out which channels to invert
+    let color_channels = format
+        .channels()
+        .filter(|info| info.channel != Channel::Alpha)
+        .count();
+    let inverted = |channel: Channel| color_channels == 0 || channel != Channel::Alpha;
+
+    // the bits of these formats aren't levels of their own channel
+    let per_component = format.involves_float()
+        || color_type.is_cmyk()
+        || color_type.ycbcr_matrix().is_some()
+        || color_type == ColorType::Indexed
+        || format.premultiplies();
+    if per_component {
+        let palette = match color_type {
+            ColorType::Indexed => match image.palette() {
+                Some(palette) => Some(palette.clone()),
+                None => return,
+            },
+            _ => None,
+        };
+
+        for_each_chunk_mut(image, |chunk, count| {
+            // both pixels of a YCbCr pair are read before either of them
+            // is written, as they share their chroma
+            let mut i = 0;
+            while i < count {
+                let n = cmp::min(2, count - i);
+                let mut pixels = [[0.0; 4]; 2];
+                for (j, pixel) in pixels[..n].iter_mut().enumerate() {
+                    *pixel = codec.read_resolved(chunk, i + j, palette.as_ref());
+                    for (index, component) in pixel.iter_mut().enumerate() {
+                        if inverted(CHANNELS[index]) {
+                            *component = 1.0 - *component;
+                        }
+                    }
+                }
+
+                for (j, &pixel) in pixels[..n].iter().enumerate() {
+                    match palette {
+                        Some(ref palette) => {
+                            codec.write_quantum(chunk, i + j, palette.nearest(pixel) as u64)
+                        }
+                        None => codec.write(chunk, i + j, pixel),
+                    }
+                }
+                i += n;
+            }
+        });
+        return;
+    }
+
+    // inverting an integer channel is the same as flipping its bits, so
+    // build a pattern of bits to XOR with the image
+    let mask = format
+        .channels()
+        .filter(|info| inverted(info.channel))
+        .fold(0, |mask, info| {
+            mask | (codec::low_bits(info.bits) << info.shift)
+        });
+    let mut pattern = [0u8; 8];
+    let pattern = &mut pattern[..format.bytes() as usize];
+    codec.fill_quantum(pattern, mask);
+
+    for_each_chunk_mut(image, |chunk, _| {
+        chunk
+            .iter_mut()
+            .zip(pattern.iter().cycle())
+            .for_each(|(byte, mask)| *byte ^= mask);
+    });
+}
+
+/// Remove the color from every pixel in the image.
+///
+/// `amount` is how much color to remove, from `0.0` (the image is left
+/// as-is) to `1.0` (the image becomes entirely gray). The gray level of
+/// a pixel is its luma, using the BT.601 coefficients.
+///
+/// Images where the color channels are 8-bit are desaturated using only
+/// integer arithmetic.
+pub fn desaturate<Img: Image + ?Sized>(image: &mut Img, amount: f32) {
+    desaturate_with_policy(image, amount, ArithmeticPolicy::default());
+}
+
+/// Like [`desaturate`], but with a choice of what is stored when a
+/// channel doesn't fit, such as a float channel brighter than `1.0`.
+///
+/// [`desaturate`]: crate::desaturate
+pub fn desaturate_with_policy<Img: Image + ?Sized>(
+    image: &mut Img,
+    amount: f32,
+    policy: ArithmeticPolicy,
+) {
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    let amount = codec::clamp_unit(amount);
+
+    let offsets = [
+        codec.byte_offset(Channel::Red),
+        codec.byte_offset(Channel::Green),
+        codec.byte_offset(Channel::Blue),
+    ];
+
+    if let [Some(red), Some(green), Some(blue)] = offsets {
+        // amount and luma coefficients in 8.8 fixed point
+        let amount = (amount * 256.0 + 0.5) as u32;
+        let stride = codec.format().bytes() as usize;
+
+        for_each_chunk_mut(image, |chunk, _| {
+            chunk.chunks_exact_mut(stride).for_each(|pixel| {
+                let luma = (77 * pixel[red] as u32
+                    + 150 * pixel[green] as u32
+                    + 29 * pixel[blue] as u32
+                    + 128)
+                    >> 8;
+                for &offset in [red, green, blue].iter() {
+                    let value = pixel[offset] as u32;
+                    pixel[offset] = ((value * (256 - amount) + luma * amount + 128) >> 8) as u8;
+                }
+            });
+        });
+    } else {
+        for_each_chunk_mut(image, |chunk, count| {
+            for i in 0..count {
+                let mut components = codec.read(chunk, i);
+                let gray = luma(components);
+                for component in &mut components[..3] {
+                    *component += (gray - *component) * amount;
+                }
+                codec.write(chunk, i, policy.apply(&codec, components));
+            }
+        });
+    }
+}
+
+/// All of the channels, indexed by `Channel as usize`.
+const CHANNELS: [Channel; 4] = [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, Endianness, Format, GeneralImage, Pixel};
+
+    #[test]
+    fn invert_twice_is_identity() {
+        let mut bytes = [0x12u8, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0];
+        let mut image = GeneralImage::from_buffer(2, 1, Format::ARGB32, &mut bytes[..]);
+
+        invert(&mut image);
+        let first = image.pixel(0, 0);
+        assert_eq!(
+            first,
+            Pixel::new(
+                &[0x12, 0xCB, 0xA9, 0x87],
+                Endianness::NATIVE,
+                Format::ARGB32
+            )
+        );
+
+        invert(&mut image);
+        drop(image);
+        assert_eq!(bytes, [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0]);
+    }
+
+    #[test]
+    fn invert_mask() {
+        let mut bytes = [0b1010_0101u8];
+        let mut image = GeneralImage::from_buffer(8, 1, Format::A1, &mut bytes[..]);
+        invert(&mut image);
+        drop(image);
+        assert_eq!(bytes, [0b0101_1010]);
+    }
+
+    #[test]
+    fn invert_premultiplied() {
+        // half transparent black
+        let mut image = GeneralImage::from_buffer(1, 1, Format::ARGB32_PREMUL, [0u8; 4]);
+        image.set_pixel(
+            0,
+            0,
+            Pixel::from_rgba(
+                Rgba {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                    alpha: 0x8080,
+                },
+                Format::ARGB32_PREMUL,
+                Endianness::NATIVE,
+            ),
+        );
+        invert(&mut image);
+        assert_eq!(image.pixel(0, 0).raw_u32(), 0x8080_8080);
+    }
+
+    #[test]
+    fn invert_decoded_colors() {
+        use crate::{IndexedImage, Palette};
+
+        let opaque = |red, green, blue| Rgba {
+            red,
+            green,
+            blue,
+            alpha: 0xFFFF,
+        };
+
+        // black has full K, which has to be inverted along with the rest
+        let mut cmyk = GeneralImage::from_buffer(1, 1, Format::CMYK32, [0u8, 0, 0, 0xFF]);
+        invert(&mut cmyk);
+        assert_eq!(cmyk.pixel(0, 0).to_rgba(), opaque(0xFFFF, 0xFFFF, 0xFFFF));
+
+        // indices map to the color closest to the inverse of theirs
+        let palette = Palette::from_colors(&[
+            opaque(0, 0, 0),
+            opaque(0xFFFF, 0, 0),
+            opaque(0xFFFF, 0xFFFF, 0xFFFF),
+            opaque(0, 0xFFFF, 0xFFFF),
+        ])
+        .unwrap();
+        let indices = GeneralImage::from_buffer(4, 1, Format::INDEXED8, [0u8, 1, 2, 3]);
+        let mut indexed = IndexedImage::new(indices, palette);
+        invert(&mut indexed);
+        let mut row = [0u8; 4];
+        indexed.scanline(0, 0, &mut row);
+        assert_eq!(row, [2, 3, 0, 1]);
+
+        // both pixels of a pair are inverted from the same chroma
+        let mut rgb = GeneralImage::from_buffer(2, 1, Format::RGB24, [0xFFu8, 0, 0, 0xFF, 0, 0]);
+        let mut yuyv = GeneralImage::from_buffer(2, 1, Format::YUYV, [0u8; 4]);
+        crate::convert(&rgb, &mut yuyv);
+        invert(&mut yuyv);
+        crate::convert(&yuyv, &mut rgb);
+        let mut row = [0u8; 6];
+        rgb.scanline(0, 0, &mut row);
+        let expected = [0u8, 0xFF, 0xFF, 0, 0xFF, 0xFF];
+        for (&actual, &expected) in row.iter().zip(expected.iter()) {
+            assert!((actual as i32 - expected as i32).abs() < 0x10, "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn desaturate_fully_is_gray() {
+        let mut bytes = [0xFFu8, 0xFF, 0x00, 0x00];
+        let mut image = GeneralImage::from_buffer(1, 1, Format::ARGB32, &mut bytes[..]);
+        desaturate(&mut image, 1.0);
+        drop(image);
+        assert_eq!(bytes[1], bytes[2]);
+        assert_eq!(bytes[2], bytes[3]);
+        assert_eq!(bytes[0], 0xFF);
+    }
+
+    #[test]
+    fn fade_8bit_alpha() {
+        // wide enough to take more than one batch
+        let mut bytes = [0u8; 300 * 2 * 4];
+        for pixel in bytes.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[0x80, 0x11, 0x22, 0x33]);
+        }
+        let mut image = Builder::from_buffer(300, 2, Format::ARGB32, &mut bytes[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        fade(&mut image, 0.5);
+        drop(image);
+        assert!(bytes
+            .chunks_exact(4)
+            .all(|pixel| pixel == [0x40, 0x11, 0x22, 0x33]));
+
+        // alpha is the last byte in big endian
+        let mut bytes = [0x11u8, 0x22, 0x33, 0xFF];
+        let mut image = Builder::from_buffer(1, 1, Format::ARGB32, &mut bytes[..])
+            .with_endianness(Endianness::Big)
+            .finish();
+        fade(&mut image, 0.25);
+        drop(image);
+        assert_eq!(bytes, [0x11, 0x22, 0x33, 0x40]);
+    }
+
+    #[test]
+    fn fade_premultiplied() {
+        // every byte of a premultiplied pixel is scaled
+        let mut bytes = [0x80u8, 0x40, 0x20, 0x11];
+        let mut image = Builder::from_buffer(1, 1, Format::ARGB32_PREMUL, &mut bytes[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        fade(&mut image, 0.5);
+        drop(image);
+        assert_eq!(bytes, [0x40, 0x20, 0x10, 0x09]);
+    }
+
+    #[test]
+    fn fade_clamps_opacity() {
+        let mut bytes = [0x80u8, 0x11, 0x22, 0x33];
+        let mut image = Builder::from_buffer(1, 1, Format::ARGB32, &mut bytes[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        fade(&mut image, 2.0);
+        drop(image);
+        assert_eq!(bytes, [0x80, 0x11, 0x22, 0x33]);
+
+        for &opacity in [-1.0, f32::NAN].iter() {
+            let mut bytes = [0x80u8, 0x11, 0x22, 0x33];
+            let mut image = Builder::from_buffer(1, 1, Format::ARGB32, &mut bytes[..])
+                .with_endianness(Endianness::Little)
+                .finish();
+            fade(&mut image, opacity);
+            drop(image);
+            assert_eq!(bytes, [0x00, 0x11, 0x22, 0x33]);
+        }
+    }
+
+    #[test]
+    fn fade_other_formats() {
+        // 4-bit alpha goes through the decoded components
+        let mut bytes = [0xFFu8, 0xFF];
+        let mut image = Builder::from_buffer(1, 1, Format::ARGB16, &mut bytes[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        fade(&mut image, 0.0);
+        drop(image);
+        assert_eq!(bytes, [0xF0, 0xFF]);
+
+        // no alpha channel to fade
+        let mut bytes = [0x12u8, 0x34, 0x56, 0x78];
+        let mut image = GeneralImage::from_buffer(1, 1, Format::XRGB32, &mut bytes[..]);
+        fade(&mut image, 0.5);
+        drop(image);
+        assert_eq!(bytes, [0x12, 0x34, 0x56, 0x78]);
+    }
+
+    #[test]
+    fn tint_8bit_channels() {
+        // the alpha of the color is ignored
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0,
+        };
+        let tinted = |strength| {
+            let mut bytes = [0x40u8, 0xFF, 0xFF, 0x80];
+            let mut image = Builder::from_buffer(1, 1, Format::ARGB32, &mut bytes[..])
+                .with_endianness(Endianness::Little)
+                .finish();
+            tint(&mut image, red, strength);
+            drop(image);
+            bytes
+        };
+
+        assert_eq!(tinted(0.5), [0x40, 0xFF, 0x80, 0x40]);
+        assert_eq!(tinted(0.0), [0x40, 0xFF, 0xFF, 0x80]);
+        assert_eq!(tinted(3.0), [0x40, 0xFF, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn tint_narrow_channels() {
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let mut bytes = [0xFFu8, 0xFF];
+        let mut image = Builder::from_buffer(1, 1, Format::ARGB16, &mut bytes[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        tint(&mut image, red, 1.0);
+        drop(image);
+        assert_eq!(bytes, [0xFF, 0x00]);
+    }
+}
@@ -0,0 +1,135 @@
+// BSL 1.0 License
+
+use crate::{
+    codec::{self, low_bits, Codec, Components},
+    Channel,
+};
+
+/// What channel math does with results that don't fit in a channel.
+///
+/// Blending and adjustments work on channels as values between `0.0` and
+/// `1.0`, and a color matrix or a bright float pixel can push them past
+/// either end. The policy decides what is stored instead.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{apply_color_matrix_with_policy, ArithmeticPolicy, ColorMatrix, Format, GeneralImage};
+///
+/// let brighten = ColorMatrix::from_rgb([[2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 2.0]]);
+/// let mut bytes = [0x40u8, 0x80, 0xC0];
+/// let mut image = GeneralImage::from_buffer(1, 1, Format::RGB24, &mut bytes[..]);
+/// apply_color_matrix_with_policy(&mut image, &brighten, ArithmeticPolicy::Wrapping);
+/// drop(image);
+/// assert_eq!(bytes, [0x80, 0x00, 0x80]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ArithmeticPolicy {
+    /// Results are clamped to the range of their channel, and float
+    /// channels to `0.0..=1.0`.
+    ///
+    /// This is the default.
+    Saturating,
+    /// Integer channels keep the low bits of results that don't fit, the
+    /// way wrapping integer arithmetic does, so `0xFF + 1` becomes zero.
+    ///
+    /// Float channels have nothing to wrap around, and saturate.
+    Wrapping,
+    /// Float channels keep results above `1.0`, so that pipelines with
+    /// high dynamic range don't lose highlights between operations.
+    /// Results below zero are still clamped to zero.
+    ///
+    /// Integer channels can't hold these results, and saturate.
+    Hdr,
+}
+
+impl Default for ArithmeticPolicy {
+    fn default() -> Self {
+        ArithmeticPolicy::Saturating
+    }
+}
+
+impl ArithmeticPolicy {
+    /// Bring components back into the range that `codec` stores, before
+    /// they are written.
+    pub(crate) fn apply(self, codec: &Codec, mut components: Components) -> Components {
+        let format = codec.format();
+        match self {
+            ArithmeticPolicy::Hdr if format.involves_float() => {
+                for component in &mut components {
+                    if component.is_nan() || *component < 0.0 {
+                        *component = 0.0;
+                    }
+                }
+            }
+            ArithmeticPolicy::Wrapping if !format.involves_float() => {
+                // channels that the format doesn't store directly, like the
+                // green of a gray format, wrap with the widest channel
+                let widest = format.channels().map(|info| info.bits).max().unwrap_or(8);
+                for (component, &channel) in components.iter_mut().zip(CHANNELS.iter()) {
+                    let bits = codec.channel(channel).map_or(widest, |info| info.bits);
+                    *component = wrap(*component, bits);
+                }
+            }
+            _ => {
+                for component in &mut components {
+                    *component = codec::clamp_unit(*component);
+                }
+            }
+        }
+        components
+    }
+}
+
+/// Wrap a component around the range of an integer channel with the
+/// given number of bits.
+fn wrap(value: f32, bits: u8) -> f32 {
+    let max = low_bits(bits);
+    let scaled = value * max as f32 + 0.5;
+
+    // round down, even for negative values
+    let mut raw = scaled as i64;
+    if raw as f32 > scaled {
+        raw -= 1;
+    }
+
+    (raw as u64 & max) as f32 / max as f32
+}
+
+/// All of the channels, indexed by `Channel as usize`.
+const CHANNELS: [Channel; 4] = [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Endianness, Format};
+
+    #[test]
+    fn out_of_range() {
+        let bytes = Codec::new(Format::RGB24, Endianness::NATIVE);
+        let components = [256.0 / 255.0, -1.0 / 255.0, 0.5, 1.0];
+        assert_eq!(
+            ArithmeticPolicy::Saturating.apply(&bytes, components),
+            [1.0, 0.0, 0.5, 1.0]
+        );
+        assert_eq!(
+            ArithmeticPolicy::Wrapping.apply(&bytes, components),
+            [0.0, 1.0, 128.0 / 255.0, 1.0]
+        );
+        assert_eq!(
+            ArithmeticPolicy::Hdr.apply(&bytes, components),
+            [1.0, 0.0, 0.5, 1.0]
+        );
+
+        let floats = Codec::new(Format::ARGB_F32, Endianness::NATIVE);
+        let components = [256.0 / 255.0, -1.0 / 255.0, 0.5, 2.0];
+        assert_eq!(
+            ArithmeticPolicy::Hdr.apply(&floats, components),
+            [256.0 / 255.0, 0.0, 0.5, 2.0]
+        );
+        assert_eq!(
+            ArithmeticPolicy::Wrapping.apply(&floats, components),
+            [1.0, 0.0, 0.5, 1.0]
+        );
+    }
+}
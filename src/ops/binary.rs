@@ -0,0 +1,249 @@
+// BSL 1.0 License
+
+use super::{convert, zip_scanlines, ArithmeticPolicy};
+use crate::{
+    codec::{self, Codec},
+    ColorType, Image,
+};
+
+/// Store the smaller value of each channel of `a` and `b` into `dst`.
+///
+/// `a` and `b` are converted to the format of `dst`. All three images
+/// must have the same dimensions.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn per_pixel_min<A, B, Dst>(a: &A, b: &B, dst: &mut Dst)
+where
+    A: Image + ?Sized,
+    B: Image + ?Sized,
+    Dst: Image + ?Sized,
+{
+    combine(a, b, dst, |x, y| if x < y { x } else { y }, f32::min);
+}
+
+/// Store the larger value of each channel of `a` and `b` into `dst`.
+///
+/// See [`per_pixel_min`] for details.
+///
+/// [`per_pixel_min`]: crate::per_pixel_min
+pub fn per_pixel_max<A, B, Dst>(a: &A, b: &B, dst: &mut Dst)
+where
+    A: Image + ?Sized,
+    B: Image + ?Sized,
+    Dst: Image + ?Sized,
+{
+    combine(a, b, dst, |x, y| if x > y { x } else { y }, f32::max);
+}
+
+/// Store the absolute difference of each channel of `a` and `b` into
+/// `dst`.
+///
+/// This includes the alpha channel, so the difference of two opaque
+/// images is transparent unless `dst` has no alpha channel. See
+/// [`per_pixel_min`] for details.
+///
+/// [`per_pixel_min`]: crate::per_pixel_min
+pub fn abs_diff<A, B, Dst>(a: &A, b: &B, dst: &mut Dst)
+where
+    A: Image + ?Sized,
+    B: Image + ?Sized,
+    Dst: Image + ?Sized,
+{
+    combine(
+        a,
+        b,
+        dst,
+        |x, y| if x > y { x - y } else { y - x },
+        |x, y| if x > y { x - y } else { y - x },
+    );
+}
+
+/// Blend `src` into `dst` with the given weight.
+///
+/// Every channel becomes `dst * (1 - weight) + src * weight`, where
+/// `weight` is clamped to `0.0..=1.0`. Calling this once per frame with
+/// a small weight gives a running average of a stream of frames, which
+/// can be used for temporal smoothing or motion blur.
+///
+/// If both images have the same format with 8-bit channels, this uses
+/// integer arithmetic.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn accumulate<Dst: Image + ?Sized, Src: Image + ?Sized>(dst: &mut Dst, src: &Src, weight: f32) {
+    accumulate_with_policy(dst, src, weight, ArithmeticPolicy::default());
+}
+
+/// Like [`accumulate`], but with a choice of what is stored when an
+/// averaged channel doesn't fit, such as a float channel brighter than
+/// `1.0`.
+///
+/// [`accumulate`]: crate::accumulate
+pub fn accumulate_with_policy<Dst: Image + ?Sized, Src: Image + ?Sized>(
+    dst: &mut Dst,
+    src: &Src,
+    weight: f32,
+    policy: ArithmeticPolicy,
+) {
+    let weight = codec::clamp_unit(weight);
+    let src_codec = Codec::new(src.format(), src.endianness()).with_bit_order(src.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+
+    if src_codec.format() == dst_codec.format()
+        && src_codec.endianness() == dst_codec.endianness()
+        && bytewise(&dst_codec)
+    {
+        // weights out of 256
+        let w = (weight * 256.0 + 0.5) as u32;
+        zip_scanlines(src, dst, |_, _, src_bytes, dst_bytes, _| {
+            dst_bytes
+                .iter_mut()
+                .zip(src_bytes.iter())
+                .for_each(|(d, &s)| {
+                    *d = ((*d as u32 * (256 - w) + s as u32 * w + 128) >> 8) as u8;
+                });
+        });
+    } else {
+        zip_scanlines(src, dst, |_, _, src_bytes, dst_bytes, count| {
+            for i in 0..count {
+                let src_components = src_codec.read(src_bytes, i);
+                let mut components = dst_codec.read(dst_bytes, i);
+                for (d, &s) in components.iter_mut().zip(src_components.iter()) {
+                    *d += (s - *d) * weight;
+                }
+                dst_codec.write(dst_bytes, i, policy.apply(&dst_codec, components));
+            }
+        });
+    }
+}
+
+/// Combine the channels of `a` and `b` into `dst`.
+///
+/// `bytes` is used when every byte of `b` and `dst` is a whole 8-bit
+/// channel or padding, and `floats` is used on normalized components
+/// otherwise.
+fn combine<A, B, Dst>(
+    a: &A,
+    b: &B,
+    dst: &mut Dst,
+    bytes: impl Fn(u8, u8) -> u8,
+    floats: impl Fn(f32, f32) -> f32,
+) where
+    A: Image + ?Sized,
+    B: Image + ?Sized,
+    Dst: Image + ?Sized,
+{
+    assert_eq!(
+        a.dimensions(),
+        dst.dimensions(),
+        "Both images must have the same dimensions"
+    );
+
+    // start with `a` in the destination, then fold `b` into it
+    convert(a, dst);
+
+    let b_codec = Codec::new(b.format(), b.endianness()).with_bit_order(b.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+
+    if b_codec.format() == dst_codec.format()
+        && b_codec.endianness() == dst_codec.endianness()
+        && bytewise(&dst_codec)
+    {
+        zip_scanlines(b, dst, |_, _, b_bytes, dst_bytes, _| {
+            dst_bytes
+                .iter_mut()
+                .zip(b_bytes.iter())
+                .for_each(|(d, &b)| *d = bytes(*d, b));
+        });
+    } else {
+        zip_scanlines(b, dst, |_, _, b_bytes, dst_bytes, count| {
+            for i in 0..count {
+                let b_components = b_codec.read(b_bytes, i);
+                let mut components = dst_codec.read(dst_bytes, i);
+                for (d, &b) in components.iter_mut().zip(b_components.iter()) {
+                    *d = floats(*d, b);
+                }
+                dst_codec.write(dst_bytes, i, components);
+            }
+        });
+    }
+}
+
+/// Whether every channel of the format is a whole byte that holds the
+/// level of that channel.
+fn bytewise(codec: &Codec) -> bool {
+    let format = codec.format();
+    let color_type = format.color_type();
+    !format.involves_float()
+        && !format.subbyte()
+        && !color_type.is_cmyk()
+        && color_type.ycbcr_matrix().is_none()
+        && color_type != ColorType::Indexed
+        && !format.premultiplies()
+        && format
+            .channels()
+            .all(|info| info.bits == 8 && info.shift % 8 == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, GeneralImage};
+
+    #[test]
+    fn min_max_diff() {
+        let a = GeneralImage::from_buffer(2, 1, Format::RGB24, [10u8, 20, 30, 40, 50, 60]);
+        let b = GeneralImage::from_buffer(2, 1, Format::RGB24, [30u8, 20, 10, 0, 255, 60]);
+        let mut buffer = [0u8; 6];
+
+        per_pixel_min(
+            &a,
+            &b,
+            &mut GeneralImage::from_buffer(2, 1, Format::RGB24, &mut buffer[..]),
+        );
+        assert_eq!(buffer, [10, 20, 10, 0, 50, 60]);
+        per_pixel_max(
+            &a,
+            &b,
+            &mut GeneralImage::from_buffer(2, 1, Format::RGB24, &mut buffer[..]),
+        );
+        assert_eq!(buffer, [30, 20, 30, 40, 255, 60]);
+
+        // goes through components, since the formats differ
+        let mut nibbles = [0u8; 4];
+        let mut dst = GeneralImage::from_buffer(2, 1, Format::XRGB16, &mut nibbles[..]);
+        abs_diff(&a, &b, &mut dst);
+        drop(dst);
+        assert_eq!(nibbles, [0x01, 0x01, 0xC2, 0x00]);
+
+        // the darker color has more ink, so it can't be compared bytewise
+        let black = GeneralImage::from_buffer(1, 1, Format::CMYK32, [0u8, 0, 0, 0xFF]);
+        let white = GeneralImage::from_buffer(1, 1, Format::CMYK32, [0u8; 4]);
+        let mut buffer = [0u8; 4];
+        per_pixel_min(
+            &black,
+            &white,
+            &mut GeneralImage::from_buffer(1, 1, Format::CMYK32, &mut buffer[..]),
+        );
+        assert_eq!(buffer, [0, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn running_average() {
+        let src = GeneralImage::from_buffer(2, 1, Format::A8, [200u8, 0]);
+        let mut buffer = [100u8, 255];
+        let mut dst = GeneralImage::from_buffer(2, 1, Format::A8, &mut buffer[..]);
+        accumulate(&mut dst, &src, 0.25);
+        drop(dst);
+        assert_eq!(buffer, [125, 191]);
+
+        let mut nibbles = [0x0Fu8];
+        let mut dst = GeneralImage::from_buffer(2, 1, Format::A4, &mut nibbles[..]);
+        accumulate(&mut dst, &src, 1.0);
+        drop(dst);
+        assert_eq!(nibbles, [0x0C]);
+    }
+}
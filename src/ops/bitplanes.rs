@@ -0,0 +1,203 @@
+// BSL 1.0 License
+
+use super::{for_each_chunk, CHUNK_BYTES};
+use crate::{codec::Codec, divide_rounding_up, Image};
+#[cfg(feature = "alloc")]
+use crate::{Builder, Format, GeneralImage, Nothing};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp;
+
+/// Split the pixels of an image into `depth` separate bit planes.
+///
+/// The `k`th plane is an [`A1`] image that holds bit `k` of every pixel,
+/// counting from the lowest bit of the pixel's value. This is the layout
+/// used by planar framebuffers on retro and embedded hardware. The image
+/// can be restored with [`from_bitplanes`].
+///
+/// # Panics
+///
+/// Panics if the format of the image involves floats, or if `depth` is
+/// larger than the bits per pixel of its format.
+///
+/// [`A1`]: crate::Format::A1
+/// [`from_bitplanes`]: crate::from_bitplanes
+#[cfg(feature = "alloc")]
+pub fn to_bitplanes<Img: Image + ?Sized>(image: &Img, depth: u8) -> Vec<GeneralImage<Nothing>> {
+    let format = image.format();
+    assert!(
+        !format.involves_float() && depth <= format.bpp(),
+        "The depth must fit into an integer pixel"
+    );
+
+    let (width, height) = image.dimensions();
+    let mut planes: Vec<_> = (0..depth)
+        .map(|_| Builder::owned(width, height, Format::A1).finish())
+        .collect();
+    to_bitplanes_into(image, &mut planes);
+    planes
+}
+
+/// Like [`to_bitplanes`], but writing the planes into `planes`.
+///
+/// The depth is the number of planes. Every plane must have one bit per
+/// pixel and the same dimensions as the image. Returns `false` without
+/// writing anything if they don't, if the format of the image involves
+/// floats, or if there are more planes than bits per pixel.
+///
+/// [`to_bitplanes`]: crate::to_bitplanes
+pub fn to_bitplanes_into<Img: Image + ?Sized, Plane: Image>(
+    image: &Img,
+    planes: &mut [Plane],
+) -> bool {
+    if !planes_fit(image, planes) {
+        return false;
+    }
+
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    let mut bits = [0u8; CHUNK_BYTES];
+
+    // batches of integer pixels always start on a byte of the planes
+    for_each_chunk(image, |x, y, chunk, count| {
+        let bits = &mut bits[..divide_rounding_up(count, 8)];
+
+        for (k, plane) in planes.iter_mut().enumerate() {
+            let order = plane.bit_order();
+            bits.iter_mut().for_each(|byte| *byte = 0);
+            for i in 0..count {
+                let bit = (codec.read_quantum(chunk, i) >> k) as u8 & 1;
+                bits[i / 8] |= bit << order.index_in_byte(1, i);
+            }
+            plane.set_scanline(x, y, bits);
+        }
+
+        true
+    });
+
+    true
+}
+
+/// Combine bit planes into the pixels of an image.
+///
+/// This is the inverse of [`to_bitplanes`]. The `k`th plane gives bit `k`
+/// of every pixel, and bits past the last plane are cleared. Returns
+/// `false` without modifying the image under the same conditions as
+/// [`to_bitplanes_into`].
+///
+/// [`to_bitplanes`]: crate::to_bitplanes
+/// [`to_bitplanes_into`]: crate::to_bitplanes_into
+pub fn from_bitplanes<Plane: Image, Img: Image + ?Sized>(
+    planes: &[Plane],
+    image: &mut Img,
+) -> bool {
+    if !planes_fit(image, planes) {
+        return false;
+    }
+
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    let bpp = codec.format().bpp() as usize;
+    let (width, height) = image.dimensions();
+    let pixels_per_chunk = CHUNK_BYTES * 8 / bpp;
+    let mut chunk = [0u8; CHUNK_BYTES];
+    let mut bits = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let chunk = &mut chunk[..divide_rounding_up(count * bpp, 8)];
+            let bits = &mut bits[..divide_rounding_up(count, 8)];
+            chunk.iter_mut().for_each(|byte| *byte = 0);
+
+            for (k, plane) in planes.iter().enumerate() {
+                let order = plane.bit_order();
+                plane.scanline(x, y, bits);
+                for i in 0..count {
+                    if bits[i / 8] >> order.index_in_byte(1, i) & 1 != 0 {
+                        let quantum = codec.read_quantum(chunk, i);
+                        codec.write_quantum(chunk, i, quantum | 1 << k);
+                    }
+                }
+            }
+
+            image.set_scanline(x, y, chunk);
+            x += count;
+        }
+    }
+
+    true
+}
+
+/// Whether the planes can hold the bits of the image.
+fn planes_fit<Img: Image + ?Sized, Plane: Image>(image: &Img, planes: &[Plane]) -> bool {
+    let format = image.format();
+    !format.involves_float()
+        && planes.len() <= format.bpp() as usize
+        && planes
+            .iter()
+            .all(|plane| plane.format().bpp() == 1 && plane.dimensions() == image.dimensions())
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::BitOrder;
+
+    #[test]
+    fn roundtrip() {
+        let image = GeneralImage::from_buffer(3, 1, Format::A8, [0b101u8, 0b010, 0b1111]);
+        let planes = to_bitplanes(&image, 3);
+        assert_eq!(planes.len(), 3);
+
+        let bits = |plane: &GeneralImage<Nothing>| {
+            let mut byte = [0u8];
+            plane.scanline(0, 0, &mut byte);
+            byte[0] & 0b111
+        };
+        assert_eq!(bits(&planes[0]), 0b101);
+        assert_eq!(bits(&planes[1]), 0b110);
+        assert_eq!(bits(&planes[2]), 0b101);
+
+        // the fourth bit is lost
+        let mut restored = GeneralImage::from_buffer(3, 1, Format::A8, [0xFFu8; 3]);
+        assert!(from_bitplanes(&planes, &mut restored));
+        assert_eq!(restored.pixel(2, 0).raw_u32(), 0b111);
+        assert_eq!(restored.pixel(0, 0), image.pixel(0, 0));
+
+        let wrong = [Builder::owned(2, 1, Format::A1).finish()];
+        assert!(!from_bitplanes(&wrong, &mut restored));
+    }
+
+    #[test]
+    fn msb_first() {
+        let image = Builder::from_buffer(2, 1, Format::A4, [0b0001_0010u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let first = |plane: &GeneralImage<Nothing>| {
+            let mut byte = [0u8];
+            plane.scanline(0, 0, &mut byte);
+            byte[0]
+        };
+
+        let planes = to_bitplanes(&image, 2);
+        assert_eq!(first(&planes[0]) & 0b11, 0b01);
+        assert_eq!(first(&planes[1]) & 0b11, 0b10);
+
+        let plane = || {
+            Builder::owned(2, 1, Format::A1)
+                .with_bit_order(BitOrder::MsbFirst)
+                .finish()
+        };
+        let mut planes = [plane(), plane()];
+        assert!(to_bitplanes_into(&image, &mut planes));
+        assert_eq!(first(&planes[0]) & 0b1100_0000, 0b1000_0000);
+        assert_eq!(first(&planes[1]) & 0b1100_0000, 0b0100_0000);
+
+        let mut restored = Builder::from_buffer(2, 1, Format::A4, [0u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        assert!(from_bitplanes(&planes, &mut restored));
+        assert_eq!(restored.pixel(0, 0).raw_u32(), 1);
+        assert_eq!(restored.pixel(1, 0).raw_u32(), 2);
+    }
+}
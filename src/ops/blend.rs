@@ -0,0 +1,181 @@
+// BSL 1.0 License
+
+use super::ArithmeticPolicy;
+use crate::{
+    codec::{Codec, Components},
+    Endianness, Format,
+};
+use core::cmp;
+
+/// Draw a row of pixels over another, with the opacity of each pixel
+/// scaled by a row of coverage values.
+///
+/// `dst_row` and `src_row` hold pixels of `format` in the native
+/// endianness, and `alpha_row` holds one byte of coverage for each pixel,
+/// as produced by a rasterizer or an [`A8`] mask. The source is drawn with
+/// the source over operator, with its alpha multiplied by the coverage, so
+/// a coverage of zero leaves the destination untouched and `0xFF` draws
+/// the source as it is.
+///
+/// As many pixels are blended as all three rows hold. For formats smaller
+/// than a byte, the rows start at the first pixel of their first byte.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{blend_row, Format};
+///
+/// let mut dst = [0u8, 0, 0, 0xFF, 0xFF, 0xFF];
+/// let src = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+/// blend_row(&mut dst, &src, &[0xFF, 0x80], Format::RGB24);
+/// assert_eq!(dst, [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+/// ```
+///
+/// [`A8`]: crate::Format::A8
+pub fn blend_row(dst_row: &mut [u8], src_row: &[u8], alpha_row: &[u8], format: Format) {
+    blend_row_with_policy(
+        dst_row,
+        src_row,
+        alpha_row,
+        format,
+        ArithmeticPolicy::default(),
+    );
+}
+
+/// Like [`blend_row`], but with a choice of what is stored when a blended
+/// channel doesn't fit, such as a float channel brighter than `1.0`.
+///
+/// [`blend_row`]: crate::blend_row
+pub fn blend_row_with_policy(
+    dst_row: &mut [u8],
+    src_row: &[u8],
+    alpha_row: &[u8],
+    format: Format,
+    policy: ArithmeticPolicy,
+) {
+    let codec = Codec::new(format, Endianness::NATIVE);
+    let bpp = format.bpp() as usize;
+    let count = cmp::min(
+        cmp::min(dst_row.len(), src_row.len()) * 8 / bpp,
+        alpha_row.len(),
+    );
+
+    for (i, &coverage) in alpha_row.iter().enumerate().take(count) {
+        match coverage {
+            0 => continue,
+            0xFF if format.bpp() >= 8 && format.alpha_bits() == 0 => {
+                // opaque pixels at full coverage replace the destination
+                let bytes = format.bytes() as usize;
+                let range = i * bytes..(i + 1) * bytes;
+                dst_row[range.clone()].copy_from_slice(&src_row[range]);
+            }
+            coverage => {
+                let mut above = codec.read(src_row, i);
+                above[3] *= coverage as f32 / 255.0;
+                let below = codec.read(dst_row, i);
+                codec.write(dst_row, i, policy.apply(&codec, source_over(above, below)));
+            }
+        }
+    }
+}
+
+/// A Porter-Duff operator, which decides how much of a source color and
+/// of the destination color below it are kept where they overlap.
+///
+/// Each operator treats both colors as covering the same share of a pixel
+/// as their alpha. The result keeps the source where it is covered by
+/// the destination for [`In`], or not covered for [`Out`], and the
+/// destination where it isn't covered by the source for [`Over`],
+/// [`Atop`] and [`Xor`].
+///
+/// [`In`]: crate::BlendOp::In
+/// [`Out`]: crate::BlendOp::Out
+/// [`Over`]: crate::BlendOp::Over
+/// [`Atop`]: crate::BlendOp::Atop
+/// [`Xor`]: crate::BlendOp::Xor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlendOp {
+    /// The source drawn over the destination.
+    ///
+    /// This is the default.
+    Over,
+    /// The source where the destination is, and nothing elsewhere.
+    In,
+    /// The source where the destination isn't, and nothing elsewhere.
+    Out,
+    /// The source where the destination is, over the destination.
+    Atop,
+    /// The source where the destination isn't, and the destination where
+    /// the source isn't.
+    Xor,
+}
+
+impl Default for BlendOp {
+    fn default() -> Self {
+        BlendOp::Over
+    }
+}
+
+impl BlendOp {
+    /// The shares of the source and destination alphas that are kept.
+    fn factors(self, src_alpha: f32, dst_alpha: f32) -> (f32, f32) {
+        match self {
+            BlendOp::Over => (1.0, 1.0 - src_alpha),
+            BlendOp::In => (dst_alpha, 0.0),
+            BlendOp::Out => (1.0 - dst_alpha, 0.0),
+            BlendOp::Atop => (dst_alpha, 1.0 - src_alpha),
+            BlendOp::Xor => (1.0 - dst_alpha, 1.0 - src_alpha),
+        }
+    }
+}
+
+/// Draw one color over another.
+pub(crate) fn source_over(above: Components, below: Components) -> Components {
+    porter_duff(BlendOp::Over, above, below)
+}
+
+/// Combine two colors with straight alpha with a Porter-Duff operator.
+pub(crate) fn porter_duff(op: BlendOp, above: Components, below: Components) -> Components {
+    let (src, dst) = op.factors(above[3], below[3]);
+    let (top, bottom) = (above[3] * src, below[3] * dst);
+    let alpha = top + bottom;
+    if alpha <= 0.0 {
+        return [0.0; 4];
+    }
+
+    let mix = |a: f32, b: f32| (a * top + b * bottom) / alpha;
+    [
+        mix(above[0], below[0]),
+        mix(above[1], below[1]),
+        mix(above[2], below[2]),
+        alpha,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage() {
+        let pixels = |raw: [u32; 3]| {
+            let mut row = [0u8; 12];
+            for (bytes, raw) in row.chunks_exact_mut(4).zip(raw.iter()) {
+                bytes.copy_from_slice(&raw.to_ne_bytes());
+            }
+            row
+        };
+
+        // half transparent blue over opaque red
+        let mut dst = pixels([0x0000_FFFF; 3]);
+        let src = pixels([0xFF00_0080; 3]);
+        blend_row(&mut dst, &src, &[0, 0xFF, 0x80], Format::ARGB32);
+        assert_eq!(dst, pixels([0x0000_FFFF, 0x8000_7FFF, 0x4000_BFFF]));
+
+        // monochrome rows are blended a bit at a time
+        let mut bits = [0b0000_0000u8];
+        blend_row(&mut bits, &[0xFF], &[0xFF, 0, 0xFF], Format::A1);
+        assert_eq!(bits, [0b0000_0101]);
+    }
+}
@@ -0,0 +1,318 @@
+// BSL 1.0 License
+
+use super::{
+    chunking::{bands, ChunkingHints},
+    CHUNK_BYTES,
+};
+use crate::{codec::Codec, divide_rounding_up, BitOrder, Image, OpReport, Rect};
+use core::cmp;
+
+/// Copy a rectangle of pixels from one image to another.
+///
+/// The pixels in `src_rect` are copied so that the top left pixel lands
+/// on `dst_point` in `dst`. The copy is clipped to both images.
+///
+/// This works on images of any format, but it is mostly intended for
+/// images whose pixels are smaller than a byte. Copying a monochrome
+/// region whose x coordinates aren't a multiple of 8 means shifting the
+/// bits across byte boundaries, which this takes care of without
+/// touching the neighboring pixels. If the images have different
+/// formats or transfer functions, the pixels are converted, and indices
+/// into the palette of `src` are replaced with their colors.
+///
+/// Returns an [`OpReport`] that counts the rows of the rectangle that
+/// were cut short or left out by `dst`.
+///
+/// [`OpReport`]: crate::OpReport
+pub fn blit_bits<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    src_rect: Rect,
+    dst: &mut Dst,
+    dst_point: (usize, usize),
+) -> OpReport {
+    blit_bits_with_hints(src, src_rect, dst, dst_point, ChunkingHints::default())
+}
+
+/// Like [`blit_bits`], but splitting the rectangle into bands of rows as
+/// `hints` says.
+///
+/// [`blit_bits`]: crate::blit_bits
+pub fn blit_bits_with_hints<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    src_rect: Rect,
+    dst: &mut Dst,
+    dst_point: (usize, usize),
+    hints: ChunkingHints,
+) -> OpReport {
+    let _span = op_span!(
+        "blit",
+        width = src_rect.width,
+        height = src_rect.height,
+        src_format = %src.format(),
+        dst_format = %dst.format(),
+    );
+    // clip the rectangle to both images
+    let (src_width, src_height) = src.dimensions();
+    let src_rect = match src_rect.intersection(Rect::new(0, 0, src_width, src_height)) {
+        Some(rect) => rect,
+        None => return OpReport::default(),
+    };
+    let (dst_x, dst_y) = dst_point;
+    let (dst_width, dst_height) = dst.dimensions();
+    let width = cmp::min(src_rect.width, dst_width.saturating_sub(dst_x));
+    let height = cmp::min(src_rect.height, dst_height.saturating_sub(dst_y));
+
+    let mut report = OpReport {
+        rows_skipped: src_rect.height - height,
+        ..OpReport::default()
+    };
+    if width == 0 {
+        report.rows_skipped = src_rect.height;
+        return report;
+    }
+
+    let src_bpp = src.format().bpp() as usize;
+    let dst_bpp = dst.format().bpp() as usize;
+    let src_codec = Codec::new(src.format(), src.endianness()).with_bit_order(src.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+    let (from, to) = (src.transfer_function(), dst.transfer_function());
+    let palette = src.palette();
+    let same_format = src.format() == dst.format()
+        && src.endianness() == dst.endianness()
+        && (src_bpp >= 8 || src.bit_order() == dst.bit_order())
+        && from == to;
+
+    // leave a spare byte for pixels that don't start a byte
+    let per_chunk = (CHUNK_BYTES - 1) * 8 / cmp::max(src_bpp, dst_bpp);
+    let mut src_chunk = [0u8; CHUNK_BYTES];
+    let mut dst_chunk = [0u8; CHUNK_BYTES];
+
+    let rows_per_band =
+        hints.rows_for(cmp::max(src.bytes_per_scanline(), dst.bytes_per_scanline()));
+    for band in bands(0..height, rows_per_band) {
+        let mut done = 0;
+        while done < width {
+            let count = cmp::min(per_chunk, width - done);
+            let sx = src_rect.x + done;
+            let dx = dst_x + done;
+            let src_skip = sx * src_bpp % 8;
+            let dst_skip = dx * dst_bpp % 8;
+            let src_len = divide_rounding_up(src_skip + count * src_bpp, 8);
+            let dst_len = divide_rounding_up(dst_skip + count * dst_bpp, 8);
+
+            for row in band.clone() {
+                let dst_bytes = &mut dst_chunk[..dst_len];
+
+                src.scanline(sx, src_rect.y + row, &mut src_chunk[..src_len]);
+
+                if same_format && src_bpp >= 8 {
+                    dst_bytes.copy_from_slice(&src_chunk[..src_len]);
+                } else {
+                    // keep the bits of neighboring pixels that share a byte
+                    dst.scanline(dx, dst_y + row, dst_bytes);
+
+                    if same_format {
+                        let bits = count * src_bpp;
+                        copy_bits(
+                            &src_chunk,
+                            src_skip,
+                            dst_bytes,
+                            dst_skip,
+                            bits,
+                            dst.bit_order(),
+                        );
+                    } else {
+                        for i in 0..count {
+                            let components = src_codec.read_resolved(
+                                &src_chunk,
+                                src_skip / src_bpp + i,
+                                palette,
+                            );
+                            dst_codec.write(
+                                dst_bytes,
+                                dst_skip / dst_bpp + i,
+                                from.convert(to, components),
+                            );
+                        }
+                    }
+                }
+
+                let written = dst.set_scanline(dx, dst_y + row, dst_bytes);
+
+                // writes stop at the end of the row, so only the last
+                // batch can tell whether the row was cut short
+                if done + count == width {
+                    report.add_row(written == dst_len && width == src_rect.width);
+                }
+            }
+
+            done += count;
+        }
+    }
+
+    report
+}
+
+/// Copy `bits` bits from one bit stream to another.
+///
+/// Bits are numbered starting from the least significant bit of the
+/// first byte, or from the most significant bit for [`MsbFirst`]. Bits in
+/// `dst` outside of the copied range are preserved.
+///
+/// [`MsbFirst`]: crate::BitOrder::MsbFirst
+fn copy_bits(
+    src: &[u8],
+    src_bit: usize,
+    dst: &mut [u8],
+    dst_bit: usize,
+    bits: usize,
+    order: BitOrder,
+) {
+    // streams numbered from the top are numbered from the bottom once
+    // the bits of every byte are reversed
+    let flip = |byte: u8| match order {
+        BitOrder::LsbFirst => byte,
+        BitOrder::MsbFirst => byte.reverse_bits(),
+    };
+    let shift = src_bit as isize - dst_bit as isize;
+    let end = dst_bit + bits;
+
+    for (index, byte) in dst.iter_mut().enumerate() {
+        let first = index * 8;
+        if first >= end {
+            break;
+        }
+
+        // the bits of this byte that are being written
+        let low = dst_bit.saturating_sub(first);
+        let high = cmp::min(end - first, 8);
+        if low >= high {
+            continue;
+        }
+        let mask = ((0xFFu16 << low) & (0xFFu16 >> (8 - high))) as u8;
+
+        let value = byte_at(src, first as isize + shift, flip);
+        *byte = flip((flip(*byte) & !mask) | (value & mask));
+    }
+}
+
+/// The eight bits of a bit stream starting at `bit`, which may be
+/// negative, after passing every byte through `flip`.
+fn byte_at(src: &[u8], bit: isize, flip: impl Fn(u8) -> u8) -> u8 {
+    let get = |index: isize| -> u16 {
+        if index < 0 {
+            0
+        } else {
+            src.get(index as usize).copied().map_or(0, &flip) as u16
+        }
+    };
+
+    let index = bit.div_euclid(8);
+    let offset = bit.rem_euclid(8) as u32;
+    let word = get(index) | (get(index + 1) << 8);
+    (word >> offset) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, Format, GeneralImage};
+
+    #[test]
+    fn unaligned_monochrome_blit() {
+        let src_bits = [0b1111_0000u8, 0b0000_1111];
+        let src = GeneralImage::from_buffer(16, 1, Format::A1, src_bits);
+        let mut dst_bits = [0b0000_0000u8, 0b1000_0000];
+        let mut dst = GeneralImage::from_buffer(16, 1, Format::A1, &mut dst_bits[..]);
+
+        // copy pixels 4..12 to 3..11
+        blit_bits(&src, Rect::new(4, 0, 8, 1), &mut dst, (3, 0));
+        drop(dst);
+        assert_eq!(dst_bits, [0b1111_1000, 0b1000_0111]);
+    }
+
+    #[test]
+    fn copy_bits_with_offsets() {
+        let mut dst = [0xFFu8; 3];
+        copy_bits(
+            &[0b1010_1010, 0b0000_0000],
+            1,
+            &mut dst,
+            5,
+            12,
+            BitOrder::LsbFirst,
+        );
+        assert_eq!(dst, [0b1011_1111, 0b0000_1010, 0b1111_1110]);
+
+        let mut dst = [0xFFu8; 3];
+        copy_bits(
+            &[0b0101_0101, 0b0000_0000],
+            1,
+            &mut dst,
+            5,
+            12,
+            BitOrder::MsbFirst,
+        );
+        assert_eq!(dst, [0b1111_1101, 0b0101_0000, 0b0111_1111]);
+    }
+
+    #[test]
+    fn msb_first_blit() {
+        let src = Builder::from_buffer(16, 1, Format::A1, [0b0000_1111u8, 0b1111_0000])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let mut dst_bits = [0b0000_0000u8, 0b0000_0001];
+        let mut dst = Builder::from_buffer(16, 1, Format::A1, &mut dst_bits[..])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+
+        // copy pixels 4..12 to 3..11, within the same bit order
+        blit_bits(&src, Rect::new(4, 0, 8, 1), &mut dst, (3, 0));
+        drop(dst);
+        assert_eq!(dst_bits, [0b0001_1111, 0b1110_0001]);
+
+        // and into the other bit order
+        let mut lsb_bits = [0u8; 2];
+        let mut lsb = GeneralImage::from_buffer(16, 1, Format::A1, &mut lsb_bits[..]);
+        blit_bits(&src, Rect::new(4, 0, 8, 1), &mut lsb, (3, 0));
+        drop(lsb);
+        assert_eq!(lsb_bits, [0b1111_1000, 0b0000_0111]);
+    }
+
+    #[test]
+    fn indexed_source() {
+        use crate::{IndexedImage, Palette, Rgba};
+
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+        let indices = GeneralImage::from_buffer(2, 1, Format::INDEXED8, [1u8, 0]);
+        let src = IndexedImage::new(indices, palette);
+        let mut dst = GeneralImage::from_buffer(2, 1, Format::ARGB32, [0x55u8; 8]);
+
+        blit_bits(&src, Rect::new(0, 0, 2, 1), &mut dst, (0, 0));
+        assert_eq!(dst.pixel(0, 0).to_rgba(), red);
+        assert_eq!(dst.pixel(1, 0).to_rgba(), Rgba::default());
+    }
+
+    #[test]
+    fn converts_transfer_functions() {
+        use crate::TransferFunction;
+
+        let src = Builder::from_buffer(1, 1, Format::RGB24, [0xBCu8, 0xBC, 0xBC])
+            .with_transfer_function(TransferFunction::Srgb)
+            .finish();
+        let mut bytes = [0u8; 3];
+        let mut dst = Builder::from_buffer(1, 1, Format::RGB24, &mut bytes[..])
+            .with_transfer_function(TransferFunction::Linear)
+            .finish();
+
+        blit_bits(&src, Rect::new(0, 0, 1, 1), &mut dst, (0, 0));
+        drop(dst);
+        assert_eq!(bytes, [0x80, 0x80, 0x80]);
+    }
+}
@@ -0,0 +1,81 @@
+// BSL 1.0 License
+
+use core::{cmp, ops::Range};
+
+/// The number of bytes of each image that a band should take up when the
+/// number of rows is picked automatically, which fits in the first level
+/// of cache of most processors.
+const BAND_BYTES: usize = 32 * 1024;
+
+/// How the bulk operations split an image into bands of rows.
+///
+/// [`convert`], [`blit_bits`] and [`Scene::render`] work on a batch of
+/// pixels from each row of a band before moving on to the next batch, so
+/// that the rows of a band stay in cache while both images are read and
+/// written. Images whose rows are far apart in memory, or processors with
+/// unusual caches, can do better with a different number of rows than the
+/// default.
+///
+/// By default, the number of rows is picked so that a band of the wider
+/// image takes up about 32 KiB.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{convert_with_hints, ChunkingHints, Format, GeneralImage};
+///
+/// let src = GeneralImage::from_buffer(4, 4, Format::RGB24, vec![0x80u8; 48]);
+/// let mut dst = GeneralImage::from_buffer(4, 4, Format::ARGB32, vec![0u8; 64]);
+/// convert_with_hints(&src, &mut dst, ChunkingHints { rows_per_chunk: 2 });
+/// ```
+///
+/// [`convert`]: crate::convert
+/// [`blit_bits`]: crate::blit_bits
+/// [`Scene::render`]: crate::Scene::render
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ChunkingHints {
+    /// The number of rows in each band, or zero to pick it from the
+    /// length of the rows.
+    ///
+    /// This is `0` by default.
+    pub rows_per_chunk: usize,
+}
+
+impl ChunkingHints {
+    /// The number of rows in each band, for images with rows of
+    /// `bytes_per_scanline` bytes.
+    ///
+    /// This is never zero.
+    pub fn rows_for(&self, bytes_per_scanline: usize) -> usize {
+        match self.rows_per_chunk {
+            0 => cmp::max(BAND_BYTES / cmp::max(bytes_per_scanline, 1), 1),
+            rows => rows,
+        }
+    }
+}
+
+/// Split `rows` into consecutive bands of at most `rows_per_band` rows.
+pub(crate) fn bands(
+    rows: Range<usize>,
+    rows_per_band: usize,
+) -> impl Iterator<Item = Range<usize>> {
+    let end = rows.end;
+    rows.step_by(rows_per_band)
+        .map(move |start| start..cmp::min(start + rows_per_band, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn band_sizes() {
+        let hints = ChunkingHints::default();
+        assert_eq!(hints.rows_for(1920 * 4), 4);
+        assert_eq!(hints.rows_for(1 << 20), 1);
+        assert_eq!(hints.rows_for(0), BAND_BYTES);
+        assert_eq!(ChunkingHints { rows_per_chunk: 3 }.rows_for(1 << 20), 3);
+
+        assert!(bands(1..8, 3).eq([1..4, 4..7, 7..8].iter().cloned()));
+    }
+}
@@ -0,0 +1,169 @@
+// BSL 1.0 License
+
+use super::CHUNK_BYTES;
+use crate::{
+    codec::{low_bits, Codec},
+    divide_rounding_up, Channel, Format, Image,
+};
+use core::cmp;
+
+/// The differences that [`images_equal`] lets through.
+///
+/// The default options only accept images with the same pixels.
+///
+/// [`images_equal`]: crate::images_equal
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct ImagesEqualOptions {
+    /// The largest difference between two channels that still counts as
+    /// equal, as a fraction of the full range of the channel.
+    ///
+    /// Float conversions and dithering are often a step off, so a value
+    /// of `1.0 / 255.0` accepts off-by-one errors in 8-bit channels.
+    ///
+    /// This is `0.0` by default.
+    pub max_channel_delta: f32,
+    /// Whether alpha is left out of the comparison.
+    ///
+    /// This is `false` by default.
+    pub ignore_alpha: bool,
+    /// Whether bits of a pixel that no channel uses, such as the padding
+    /// byte of [`XRGB32`], are left out of the comparison.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [`XRGB32`]: crate::Format::XRGB32
+    pub ignore_padding: bool,
+}
+
+/// Whether two images have the same dimensions and the same pixels, within
+/// the tolerances of `options`.
+///
+/// The images can be in different formats, in which case their pixels are
+/// compared as colors. This is meant for tests, where conversions can
+/// legitimately round a channel differently.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{images_equal, Format, GeneralImage, ImagesEqualOptions};
+///
+/// let a = GeneralImage::from_buffer(2, 1, Format::GRAY8, [0x10u8, 0x80]);
+/// let b = GeneralImage::from_buffer(2, 1, Format::GRAY8, [0x11u8, 0x80]);
+/// assert!(!images_equal(&a, &b, &ImagesEqualOptions::default()));
+///
+/// let options = ImagesEqualOptions {
+///     max_channel_delta: 1.0 / 255.0,
+///     ..ImagesEqualOptions::default()
+/// };
+/// assert!(images_equal(&a, &b, &options));
+/// ```
+pub fn images_equal<A: Image + ?Sized, B: Image + ?Sized>(
+    a: &A,
+    b: &B,
+    options: &ImagesEqualOptions,
+) -> bool {
+    if a.dimensions() != b.dimensions() {
+        return false;
+    }
+
+    let (width, height) = a.dimensions();
+    let a_codec = Codec::new(a.format(), a.endianness()).with_bit_order(a.bit_order());
+    let b_codec = Codec::new(b.format(), b.endianness()).with_bit_order(b.bit_order());
+    let a_bpp = a.format().bpp() as usize;
+    let b_bpp = b.format().bpp() as usize;
+    let (a_padding, b_padding) = (padding_mask(a.format()), padding_mask(b.format()));
+    let compare_padding = !options.ignore_padding && (a_padding | b_padding) != 0;
+    let channels = if options.ignore_alpha { 3 } else { 4 };
+    // absorbs the rounding of the conversion to floats
+    let delta = options.max_channel_delta + 1.0e-6;
+
+    // keep batches byte-aligned
+    let pixels_per_chunk = CHUNK_BYTES * 8 / cmp::max(a_bpp, b_bpp) / 8 * 8;
+    let mut a_chunk = [0u8; CHUNK_BYTES];
+    let mut b_chunk = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let a_len = divide_rounding_up(count * a_bpp, 8);
+            let b_len = divide_rounding_up(count * b_bpp, 8);
+            a.scanline(x, y, &mut a_chunk[..a_len]);
+            b.scanline(x, y, &mut b_chunk[..b_len]);
+
+            for i in 0..count {
+                let (pa, pb) = (a_codec.read(&a_chunk, i), b_codec.read(&b_chunk, i));
+                let close = pa[..channels]
+                    .iter()
+                    .zip(&pb[..channels])
+                    .all(|(&ca, &cb)| ca - cb <= delta && cb - ca <= delta);
+                if !close {
+                    return false;
+                }
+
+                if compare_padding
+                    && a_codec.read_quantum(&a_chunk, i) & a_padding
+                        != b_codec.read_quantum(&b_chunk, i) & b_padding
+                {
+                    return false;
+                }
+            }
+
+            x += count;
+        }
+    }
+
+    true
+}
+
+/// The bits of a pixel quantum that no channel uses.
+fn padding_mask(format: Format) -> u64 {
+    let bits = cmp::min(format.bpp(), 64);
+    [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha]
+        .iter()
+        .fold(low_bits(bits), |mask, &channel| {
+            mask & !format.mask_for(channel)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeneralImage;
+
+    #[test]
+    fn tolerances() {
+        let options = |max_channel_delta, ignore_alpha, ignore_padding| ImagesEqualOptions {
+            max_channel_delta,
+            ignore_alpha,
+            ignore_padding,
+        };
+
+        // the same colors in different formats
+        let rgba = GeneralImage::from_buffer(1, 1, Format::RGBA32, [0x10u8, 0x20, 0x30, 0xFF]);
+        let bgr = GeneralImage::from_buffer(1, 1, Format::BGR24, [0x30u8, 0x20, 0x10]);
+        assert!(images_equal(&rgba, &bgr, &options(0.0, false, false)));
+
+        // padding bytes only count if they are compared
+        let xrgb = GeneralImage::from_buffer(1, 1, Format::XRGB32, [0x10u8, 0x20, 0x30, 0x00]);
+        let dirty = GeneralImage::from_buffer(1, 1, Format::XRGB32, [0x10u8, 0x20, 0x30, 0x55]);
+        assert!(!images_equal(&xrgb, &dirty, &options(0.0, false, false)));
+        assert!(images_equal(&xrgb, &dirty, &options(0.0, false, true)));
+
+        let faded = GeneralImage::from_buffer(1, 1, Format::RGBA32, [0x10u8, 0x20, 0x31, 0x80]);
+        assert!(!images_equal(
+            &rgba,
+            &faded,
+            &options(1.0 / 255.0, false, false)
+        ));
+        assert!(!images_equal(&rgba, &faded, &options(0.0, true, false)));
+        assert!(images_equal(
+            &rgba,
+            &faded,
+            &options(1.0 / 255.0, true, false)
+        ));
+
+        let wider = GeneralImage::from_buffer(2, 1, Format::BGR24, [0u8; 6]);
+        assert!(!images_equal(&bgr, &wider, &options(1.0, true, true)));
+    }
+}
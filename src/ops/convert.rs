@@ -0,0 +1,473 @@
+// BSL 1.0 License
+
+use super::{
+    chunking::{bands, ChunkingHints},
+    is_ycbcr, CHUNK_BYTES,
+};
+use crate::{codec::Codec, divide_rounding_up, Format, Image, OpReport, Palette};
+use core::{cmp, ops::Range};
+
+/// Copy the contents of one image into another, converting the pixels
+/// to the format and endianness of the destination.
+///
+/// Only the area where both images overlap is copied. If the images
+/// share a format and endianness, the bytes are copied directly. If their
+/// [`TransferFunction`]s differ, the colors are re-encoded.
+///
+/// Returns an [`OpReport`] that counts the rows of `src` that were cut
+/// short or left out.
+///
+/// [`TransferFunction`]: crate::TransferFunction
+/// [`OpReport`]: crate::OpReport
+pub fn convert<Src: Image + ?Sized, Dst: Image + ?Sized>(src: &Src, dst: &mut Dst) -> OpReport {
+    convert_with_hints(src, dst, ChunkingHints::default())
+}
+
+/// Like [`convert`], but splitting the images into bands of rows as
+/// `hints` says.
+///
+/// [`convert`]: crate::convert
+pub fn convert_with_hints<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    hints: ChunkingHints,
+) -> OpReport {
+    let _span = op_span!(
+        "convert",
+        width = src.width(),
+        height = src.height(),
+        src_format = %src.format(),
+        dst_format = %dst.format(),
+    );
+    let height = cmp::min(src.height(), dst.height());
+    let mut report = convert_rows(src, dst, 0..height, &mut [0u8; CHUNK_BYTES * 2], hints);
+    report.rows_skipped += src.height() - height;
+    report
+}
+
+/// Like [`convert`], but using `scratch` to hold the bytes in flight.
+///
+/// Half of `scratch` holds source bytes and the other half destination
+/// bytes, so a larger buffer converts more pixels at a time. Each half
+/// must fit at least one pixel of both images, or a byte's worth of
+/// pixels if either format is smaller than a byte.
+///
+/// # Panics
+///
+/// Panics if `scratch` is too small.
+///
+/// [`convert`]: crate::convert
+pub fn convert_into<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    scratch: &mut [u8],
+) -> OpReport {
+    let _span = op_span!(
+        "convert",
+        width = src.width(),
+        height = src.height(),
+        src_format = %src.format(),
+        dst_format = %dst.format(),
+    );
+    let height = cmp::min(src.height(), dst.height());
+    let mut report = convert_rows(src, dst, 0..height, scratch, ChunkingHints::default());
+    report.rows_skipped += src.height() - height;
+    report
+}
+
+/// Convert the given rows of `src` into `dst`, as in [`convert_into`].
+///
+/// `rows` must be within the height of both images.
+pub(crate) fn convert_rows<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    rows: Range<usize>,
+    scratch: &mut [u8],
+    hints: ChunkingHints,
+) -> OpReport {
+    let src_codec = Codec::new(src.format(), src.endianness())
+        .with_bit_order(src.bit_order())
+        .with_transfer_function(src.transfer_function());
+    let dst_codec = Codec::new(dst.format(), dst.endianness())
+        .with_bit_order(dst.bit_order())
+        .with_transfer_function(dst.transfer_function());
+    let src_bpp = src.format().bpp() as usize;
+    let dst_bpp = dst.format().bpp() as usize;
+
+    let width = cmp::min(src.width(), dst.width());
+
+    let half = scratch.len() / 2;
+    let (src_chunk, dst_chunk) = scratch.split_at_mut(half);
+    let mut pixels_per_chunk = half * 8 / cmp::max(src_bpp, dst_bpp);
+    if src_bpp < 8 || dst_bpp < 8 {
+        // keep batches byte-aligned
+        pixels_per_chunk = pixels_per_chunk / 8 * 8;
+    }
+    if is_ycbcr(src.format()) || is_ycbcr(dst.format()) {
+        // keep pairs of pixels that share their chroma together
+        pixels_per_chunk = pixels_per_chunk / 2 * 2;
+    }
+    assert!(pixels_per_chunk > 0, "The scratch buffer is too small");
+
+    let mut report = OpReport::default();
+    if width == 0 {
+        report.rows_skipped = rows.len();
+        return report;
+    }
+
+    let rows_per_band =
+        hints.rows_for(cmp::max(src.bytes_per_scanline(), dst.bytes_per_scanline()));
+    for band in bands(rows, rows_per_band) {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let src_len = divide_rounding_up(count * src_bpp, 8);
+            let dst_len = divide_rounding_up(count * dst_bpp, 8);
+
+            for y in band.clone() {
+                src.scanline(x, y, &mut src_chunk[..src_len]);
+                if dst_bpp < 8 {
+                    // preserve the bits of pixels we are not writing to
+                    dst.scanline(x, y, &mut dst_chunk[..dst_len]);
+                }
+
+                convert_row(
+                    &src_codec,
+                    &src_chunk[..src_len],
+                    src.palette(),
+                    &dst_codec,
+                    &mut dst_chunk[..dst_len],
+                    count,
+                );
+                let written = dst.set_scanline(x, y, &dst_chunk[..dst_len]);
+
+                // writes stop at the end of the row, so only the last
+                // batch can tell whether the row was cut short
+                if x + count == width {
+                    report.add_row(written == dst_len && width == src.width());
+                }
+            }
+
+            x += count;
+        }
+    }
+
+    report
+}
+
+/// Whether converting from format `a` to format `b` and back again always
+/// gives back the original pixels.
+///
+/// This is the case when every channel of `a` exists in `b` with at least
+/// as much precision. Integer channels of up to 16 bits survive a trip
+/// through floats, but floats never survive a trip through integers.
+/// Bits of `a` that aren't part of a channel, such as the padding in
+/// [`XRGB32`], are not preserved either way.
+///
+/// This is useful for deciding whether a conversion path is safe for
+/// archival data.
+///
+/// [`XRGB32`]: crate::Format::XRGB32
+pub fn roundtrip_lossless(a: Format, b: Format) -> bool {
+    Format::conversion_report(a, b).is_lossless()
+}
+
+/// Convert `count` pixels from one row of bytes to another.
+///
+/// Indices in `src` are looked up in `palette`, if there is one.
+pub(crate) fn convert_row(
+    src_codec: &Codec,
+    src: &[u8],
+    palette: Option<&Palette>,
+    dst_codec: &Codec,
+    dst: &mut [u8],
+    count: usize,
+) {
+    let (from, to) = (src_codec.transfer_function(), dst_codec.transfer_function());
+    if from != to {
+        for i in 0..count {
+            let components = src_codec.read_resolved(src, i, palette);
+            dst_codec.write(dst, i, from.convert(to, components));
+        }
+        return;
+    }
+
+    if src_codec.format() == dst_codec.format()
+        && src_codec.endianness() == dst_codec.endianness()
+        && src_codec.bit_order() == dst_codec.bit_order()
+    {
+        let len = divide_rounding_up(count * src_codec.format().bpp() as usize, 8);
+        dst[..len].copy_from_slice(&src[..len]);
+        return;
+    }
+
+    if src_codec.format() == dst_codec.format() {
+        // only the byte or bit order changes
+        for i in 0..count {
+            dst_codec.write_raw(dst, i, src_codec.read_raw(src, i));
+        }
+        return;
+    }
+
+    for i in 0..count {
+        dst_codec.write(dst, i, src_codec.read_resolved(src, i, palette));
+    }
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{Channel, Endianness, GeneralImage};
+    use alloc::vec;
+    use proptest::prelude::*;
+
+    const FORMATS: [Format; 36] = [
+        Format::ARGB32,
+        Format::XRGB32,
+        Format::ABGR32,
+        Format::XBGR32,
+        Format::RGBA32,
+        Format::RGBX32,
+        Format::BGRA32,
+        Format::BGRX32,
+        Format::RGB24,
+        Format::BGR24,
+        Format::RGB48,
+        Format::BGR48,
+        Format::RGBA64,
+        Format::ABGR64,
+        Format::RG8,
+        Format::RG16,
+        Format::LA8,
+        Format::GRAY8,
+        Format::GRAY16,
+        Format::RGB565,
+        Format::RGB555,
+        Format::ARGB1555,
+        Format::CMYK32,
+        Format::ARGB16,
+        Format::XRGB16,
+        Format::ABGR16,
+        Format::XBGR16,
+        Format::A8,
+        Format::A4,
+        Format::A1,
+        Format::ARGB_F32,
+        Format::RGB_F32,
+        Format::ARGB_F16,
+        Format::RGB_F16,
+        Format::custom(
+            32,
+            [Channel::Green, Channel::Blue, Channel::Red, Channel::Alpha],
+            [8, 8, 8, 8],
+        ),
+        Format::custom(
+            16,
+            [Channel::Red, Channel::Green, Channel::Alpha, Channel::Blue],
+            [5, 5, 1, 5],
+        ),
+    ];
+
+    fn image(format: Format, width: usize) -> GeneralImage<alloc::vec::Vec<u8>> {
+        let len = divide_rounding_up(width * format.bpp() as usize, 8);
+        GeneralImage::from_buffer(width, 1, format, vec![0u8; len])
+    }
+
+    proptest! {
+        #[test]
+        fn lossless_conversions_roundtrip(
+            a in 0..FORMATS.len(),
+            b in 0..FORMATS.len(),
+            big_endian in any::<bool>(),
+            pixels in proptest::collection::vec(any::<[u8; 4]>(), 1..20),
+        ) {
+            let (a, b) = (FORMATS[a], FORMATS[b]);
+            prop_assume!(roundtrip_lossless(a, b));
+
+            let endianness = if big_endian { Endianness::Big } else { Endianness::Little };
+            let codec = Codec::new(a, Endianness::Little);
+            let width = pixels.len();
+
+            // build a valid source image out of the random components
+            let mut original = image(a, width);
+            let mut row = vec![0u8; original.bytes_per_scanline()];
+            for (i, pixel) in pixels.iter().enumerate() {
+                let mut components = [0.0; 4];
+                for (component, &value) in components.iter_mut().zip(pixel.iter()) {
+                    *component = value as f32 / 255.0;
+                }
+                codec.write(&mut row, i, components);
+            }
+            original.set_scanline(0, 0, &row);
+
+            let mut middle = crate::Builder::like_image(&image(b, width))
+                .with_endianness(endianness)
+                .finish();
+            convert(&original, &mut middle);
+            let mut back = image(a, width);
+            convert(&middle, &mut back);
+
+            let mut back_row = vec![0u8; row.len()];
+            back.scanline(0, 0, &mut back_row);
+            for i in 0..width {
+                prop_assert_eq!(codec.read_raw(&row, i), codec.read_raw(&back_row, i));
+            }
+        }
+    }
+
+    #[test]
+    fn ycbcr() {
+        let mut rgb = image(Format::RGB24, 3);
+        rgb.set_scanline(0, 0, &[0xFF, 0, 0, 0xFF, 0, 0, 0x80, 0x80, 0x80]);
+        let mut yuyv = image(Format::YUYV, 3);
+        convert(&rgb, &mut yuyv);
+
+        let mut row = [0u8; 6];
+        yuyv.scanline(0, 0, &mut row);
+        assert_eq!(row, [81, 90, 81, 240, 126, 128]);
+
+        let mut back = image(Format::RGB24, 3);
+        convert(&yuyv, &mut back);
+        let mut row = [0u8; 9];
+        back.scanline(0, 0, &mut row);
+        for (&a, &b) in row
+            .iter()
+            .zip([0xFF, 0, 0, 0xFF, 0, 0, 0x80, 0x80, 0x80].iter())
+        {
+            assert!((a as i32 - b).abs() <= 1, "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn custom_order() {
+        use crate::{Channel::*, Pixel, Rgba};
+
+        let gbra = Format::custom(32, [Green, Blue, Red, Alpha], [8, 8, 8, 8]);
+        let rgab = Format::custom(32, [Red, Green, Alpha, Blue], [8, 8, 8, 8]);
+        let color = Rgba {
+            red: 0xFFFF,
+            green: 0x8080,
+            blue: 0,
+            alpha: 0x4040,
+        };
+
+        let pixel = Pixel::from_rgba(color, gbra, Endianness::Little);
+        assert_eq!(pixel.raw_u32(), 0x40FF_0080);
+        assert_eq!(pixel.to_rgba(), color);
+
+        let mut src = image(gbra, 1);
+        src.set_scanline(0, 0, &[0x80, 0, 0xFF, 0x40]);
+        let mut dst = image(rgab, 1);
+        convert(&src, &mut dst);
+        let mut row = [0u8; 4];
+        dst.scanline(0, 0, &mut row);
+        assert_eq!(row, [0xFF, 0x80, 0x40, 0]);
+        assert!(roundtrip_lossless(gbra, rgab));
+    }
+
+    #[test]
+    fn bands_of_rows() {
+        let bytes = (0..7 * 5 * 3)
+            .map(|i| i as u8)
+            .collect::<alloc::vec::Vec<_>>();
+        let src = GeneralImage::from_buffer(7, 5, Format::RGB24, bytes);
+        let mut expected = GeneralImage::from_buffer(7, 5, Format::RGB565, vec![0u8; 70]);
+        convert(&src, &mut expected);
+
+        for &rows_per_chunk in &[1, 2, 5, 8] {
+            let mut dst = GeneralImage::from_buffer(7, 5, Format::RGB565, vec![0u8; 70]);
+            convert_with_hints(&src, &mut dst, ChunkingHints { rows_per_chunk });
+            let options = crate::ImagesEqualOptions::default();
+            assert!(crate::images_equal(&dst, &expected, &options));
+        }
+    }
+
+    #[test]
+    fn reports() {
+        let src = GeneralImage::from_buffer(2, 3, Format::RGB24, vec![0u8; 18]);
+        let mut dst = GeneralImage::from_buffer(2, 3, Format::XRGB32, vec![0u8; 24]);
+        let report = convert(&src, &mut dst);
+        assert!(report.is_complete());
+        assert_eq!(report.rows_converted, 3);
+
+        // the buffer ends halfway through the last row
+        let mut short = GeneralImage::from_buffer(2, 4, Format::XRGB32, vec![0u8; 20]);
+        let report = convert(&src, &mut short);
+        assert_eq!((report.rows_converted, report.rows_clipped), (2, 1));
+
+        let mut narrow = GeneralImage::from_buffer(1, 2, Format::RGB24, vec![0u8; 6]);
+        let report = convert_into(&src, &mut narrow, &mut [0u8; 2 * 4]);
+        assert_eq!(
+            report,
+            OpReport {
+                rows_converted: 0,
+                rows_clipped: 2,
+                rows_skipped: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn lossiness() {
+        assert!(roundtrip_lossless(Format::RGB24, Format::ARGB32));
+        assert!(roundtrip_lossless(Format::XRGB32, Format::BGR24));
+        assert!(roundtrip_lossless(Format::ARGB16, Format::ARGB_F32));
+        assert!(!roundtrip_lossless(Format::ARGB32, Format::RGB24));
+        assert!(!roundtrip_lossless(Format::ARGB32, Format::ARGB16));
+        assert!(!roundtrip_lossless(Format::ARGB_F32, Format::ARGB32));
+    }
+
+    #[test]
+    fn caller_scratch() {
+        let bytes: alloc::vec::Vec<u8> = (0..11 * 3 * 4).map(|i| (i * 13) as u8).collect();
+        let src = GeneralImage::from_buffer(11, 3, Format::ARGB32, bytes);
+        let mut expected = GeneralImage::from_buffer(11, 3, Format::A4, vec![0u8; 18]);
+        convert(&src, &mut expected);
+
+        // the smallest scratch fits eight pixels, as the target is sub-byte,
+        // and odd lengths and rows split across batches give the same result
+        for &len in [2 * 4 * 8, 2 * 4 * 8 + 3, 2 * 4 * 16].iter() {
+            let mut dst = GeneralImage::from_buffer(11, 3, Format::A4, vec![0u8; 18]);
+            let report = convert_into(&src, &mut dst, &mut vec![0u8; len]);
+            assert!(report.is_complete());
+
+            let (mut row, mut expected_row) = ([0u8; 6], [0u8; 6]);
+            for y in 0..3 {
+                dst.scanline(0, y, &mut row);
+                expected.scanline(0, y, &mut expected_row);
+                assert_eq!(row, expected_row);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The scratch buffer is too small")]
+    fn caller_scratch_too_small() {
+        let src = image(Format::ARGB32, 8);
+        let mut dst = image(Format::A4, 8);
+        convert_into(&src, &mut dst, &mut [0u8; 2 * 4 * 7]);
+    }
+
+    #[test]
+    fn indexed_source() {
+        use crate::{IndexedImage, Palette, Rgba};
+
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+        // the last index is past the end of the palette
+        let indices = GeneralImage::from_buffer(3, 1, Format::INDEXED8, [1u8, 0, 2]);
+        let src = IndexedImage::new(indices, palette);
+
+        let mut dst = image(Format::ARGB32, 3);
+        dst.set_scanline(0, 0, &[0xFF; 12]);
+        convert(&src, &mut dst);
+        assert_eq!(dst.pixel(0, 0).to_rgba(), red);
+        assert_eq!(dst.pixel(1, 0).to_rgba(), Rgba::default());
+        assert_eq!(dst.pixel(2, 0).to_rgba(), Rgba::default());
+    }
+}
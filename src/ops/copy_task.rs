@@ -0,0 +1,199 @@
+// BSL 1.0 License
+
+use super::{chunking::ChunkingHints, convert::convert_rows, CHUNK_BYTES};
+use crate::Image;
+use core::cmp;
+
+/// A copy of one image into another that is done a few scanlines at a
+/// time.
+///
+/// Each call to [`step`] copies the next batch of rows, converting them
+/// like [`convert`] does. This lets a large conversion be spread over
+/// several frames of a UI thread, or interleaved with other work by a
+/// scheduler, instead of blocking until the whole image is done.
+///
+/// The task owns both images. Pass `&mut` references to copy between
+/// images that are owned elsewhere.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{CopyTask, Format, GeneralImage, Image};
+///
+/// let src = GeneralImage::from_buffer(4, 4, Format::RGB24, [0x7Fu8; 48]);
+/// let dst = GeneralImage::from_buffer(4, 4, Format::XRGB32, [0u8; 64]);
+///
+/// let mut task = CopyTask::new(src, dst).with_rows_per_step(3);
+/// assert!(!task.step());
+/// assert_eq!((task.rows_done(), task.total_rows()), (3, 4));
+/// assert!(task.step());
+///
+/// let (_, dst) = task.into_inner();
+/// assert_eq!(dst.pixel(3, 3).raw_u32() & 0xFF_FFFF, 0x7F7F7F);
+/// ```
+///
+/// [`step`]: crate::CopyTask::step
+/// [`convert`]: crate::convert
+#[derive(Debug, Clone)]
+pub struct CopyTask<Src, Dst> {
+    src: Src,
+    dst: Dst,
+    /// The next row to copy.
+    row: usize,
+    rows_per_step: usize,
+}
+
+impl<Src: Image, Dst: Image> CopyTask<Src, Dst> {
+    /// The number of rows copied by each step, unless it is changed with
+    /// [`with_rows_per_step`].
+    ///
+    /// [`with_rows_per_step`]: crate::CopyTask::with_rows_per_step
+    pub const DEFAULT_ROWS_PER_STEP: usize = 16;
+
+    /// Prepare to copy `src` into `dst`.
+    ///
+    /// Only the area where both images overlap is copied.
+    pub fn new(src: Src, dst: Dst) -> Self {
+        Self {
+            src,
+            dst,
+            row: 0,
+            rows_per_step: Self::DEFAULT_ROWS_PER_STEP,
+        }
+    }
+
+    /// Set the number of rows copied by each call to [`step`].
+    ///
+    /// Zero is treated as one.
+    ///
+    /// [`step`]: crate::CopyTask::step
+    pub fn with_rows_per_step(mut self, rows: usize) -> Self {
+        self.rows_per_step = cmp::max(rows, 1);
+        self
+    }
+
+    /// Copy the next batch of rows.
+    ///
+    /// Returns whether the copy is finished. Calling this after the copy
+    /// is finished does nothing.
+    pub fn step(&mut self) -> bool {
+        let total = self.total_rows();
+        let end = cmp::min(self.row.saturating_add(self.rows_per_step), total);
+        if self.row < end {
+            convert_rows(
+                &self.src,
+                &mut self.dst,
+                self.row..end,
+                &mut [0u8; CHUNK_BYTES * 2],
+                ChunkingHints::default(),
+            );
+            self.row = end;
+        }
+
+        self.is_finished()
+    }
+
+    /// Copy every row that is left.
+    pub fn finish(&mut self) {
+        let total = self.total_rows();
+        if self.row < total {
+            convert_rows(
+                &self.src,
+                &mut self.dst,
+                self.row..total,
+                &mut [0u8; CHUNK_BYTES * 2],
+                ChunkingHints::default(),
+            );
+            self.row = total;
+        }
+    }
+
+    /// The number of rows copied so far.
+    pub fn rows_done(&self) -> usize {
+        self.row
+    }
+
+    /// The number of rows copied once the task is finished.
+    pub fn total_rows(&self) -> usize {
+        cmp::min(self.src.height(), self.dst.height())
+    }
+
+    /// Whether every row has been copied.
+    pub fn is_finished(&self) -> bool {
+        self.row >= self.total_rows()
+    }
+
+    /// Get back the source and destination images.
+    pub fn into_inner(self) -> (Src, Dst) {
+        (self.src, self.dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, Endianness, Format, GeneralImage};
+
+    #[test]
+    fn steps() {
+        let src = GeneralImage::from_buffer(2, 5, Format::A8, [0xFFu8; 10]);
+        let mut bytes = [0u8; 12];
+        let dst = GeneralImage::from_buffer(3, 4, Format::A8, &mut bytes[..]);
+
+        // zero rows is treated as one, and only the overlap is copied
+        let mut task = CopyTask::new(src, dst).with_rows_per_step(0);
+        assert_eq!(task.total_rows(), 4);
+        assert!(!task.step());
+        assert_eq!(task.rows_done(), 1);
+
+        // the last step only copies the rows that are left
+        let mut task = task.with_rows_per_step(2);
+        assert!(!task.step());
+        assert_eq!(task.rows_done(), 3);
+        assert!(task.step());
+        assert_eq!(task.rows_done(), 4);
+
+        let (_, dst) = task.into_inner();
+        drop(dst);
+        assert_eq!(
+            bytes,
+            [0xFF, 0xFF, 0, 0xFF, 0xFF, 0, 0xFF, 0xFF, 0, 0xFF, 0xFF, 0]
+        );
+    }
+
+    #[test]
+    fn finish() {
+        let mut src = GeneralImage::from_buffer(1, 3, Format::A8, [1u8, 2, 3]);
+        let mut dst = Builder::from_buffer(1, 3, Format::ARGB32, [0u8; 12])
+            .with_endianness(Endianness::Little)
+            .finish();
+
+        let mut task = CopyTask::new(&mut src, &mut dst).with_rows_per_step(2);
+        assert!(!task.step());
+        task.finish();
+        assert!(task.is_finished());
+        assert_eq!(task.rows_done(), 3);
+
+        // stepping or finishing a finished task does nothing
+        assert!(task.step());
+        task.finish();
+        assert_eq!(task.rows_done(), 3);
+
+        // rows are converted on the way
+        let mut pixel = [0u8; 4];
+        for (y, &alpha) in [1u8, 2, 3].iter().enumerate() {
+            dst.scanline(0, y, &mut pixel);
+            assert_eq!(pixel, [alpha, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let src = GeneralImage::from_buffer(2, 0, Format::A8, [0u8; 0]);
+        let dst = GeneralImage::from_buffer(2, 2, Format::A8, [0u8; 4]);
+        let mut task = CopyTask::new(src, dst);
+        assert!(task.is_finished());
+        assert!(task.step());
+        assert_eq!((task.rows_done(), task.total_rows()), (0, 0));
+    }
+}
@@ -0,0 +1,238 @@
+// BSL 1.0 License
+
+use super::CHUNK_BYTES;
+use crate::{codec::Codec, divide_rounding_up, Channel, Image};
+#[cfg(feature = "alloc")]
+use crate::{Builder, Format, GeneralImage, Nothing};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use core::cmp;
+
+/// The arrangement of the color filter array over a camera sensor.
+///
+/// Raw sensor frames hold one sample per pixel, and each sample measures
+/// only the color of the filter over that pixel. The filters repeat in
+/// 2x2 cells with two greens, and the pattern names the colors of a cell
+/// from left to right and top to bottom, starting at the top left pixel
+/// of the frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CfaPattern {
+    /// Red and green on even rows, green and blue on odd rows.
+    Rggb,
+    /// Blue and green on even rows, green and red on odd rows.
+    Bggr,
+    /// Green and red on even rows, blue and green on odd rows.
+    Grbg,
+    /// Green and blue on even rows, red and green on odd rows.
+    Gbrg,
+}
+
+impl CfaPattern {
+    /// The colors of a cell, from left to right and top to bottom.
+    pub const fn colors(self) -> [Channel; 4] {
+        use Channel::{Blue as B, Green as G, Red as R};
+        match self {
+            CfaPattern::Rggb => [R, G, G, B],
+            CfaPattern::Bggr => [B, G, G, R],
+            CfaPattern::Grbg => [G, R, B, G],
+            CfaPattern::Gbrg => [G, B, R, G],
+        }
+    }
+
+    /// The color of the filter over the pixel at `(x, y)`.
+    pub const fn color_at(self, x: usize, y: usize) -> Channel {
+        self.colors()[(y % 2) * 2 + x % 2]
+    }
+}
+
+/// Turn a raw sensor frame into an RGB image, taking the colors that
+/// each pixel is missing from the other pixels of its 2x2 cell.
+///
+/// `src` holds one sample per pixel, usually in [`GRAY8`] or [`GRAY16`],
+/// and the samples are read from its gray or red channel. The result is
+/// [`RGB24`], or [`RGB48`] for samples deeper than 8 bits.
+///
+/// Every pixel keeps its own sample, takes the red or blue that it is
+/// missing from its cell, and takes the average of the two greens of its
+/// cell if it isn't green itself. This is the fastest demosaic there is,
+/// and good enough for previews, but it leaves colored fringes along
+/// sharp edges.
+///
+/// This requires the `alloc` feature; see [`demosaic_nearest_into`] for
+/// a version that doesn't allocate.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{demosaic_nearest, CfaPattern, Format, GeneralImage, Image};
+///
+/// let raw = GeneralImage::from_buffer(2, 2, Format::GRAY8, [0xFFu8, 0x40, 0x20, 0x80]);
+/// let rgb = demosaic_nearest(&raw, CfaPattern::Rggb);
+/// assert_eq!(rgb.format(), Format::RGB24);
+///
+/// let mut pixels = [0u8; 6];
+/// rgb.scanline(0, 0, &mut pixels);
+/// assert_eq!(pixels, [0xFF, 0x30, 0x80, 0xFF, 0x40, 0x80]);
+/// ```
+///
+/// [`GRAY8`]: crate::Format::GRAY8
+/// [`GRAY16`]: crate::Format::GRAY16
+/// [`RGB24`]: crate::Format::RGB24
+/// [`RGB48`]: crate::Format::RGB48
+/// [`demosaic_nearest_into`]: crate::demosaic_nearest_into
+#[cfg(feature = "alloc")]
+pub fn demosaic_nearest<Img: Image + ?Sized>(
+    src: &Img,
+    pattern: CfaPattern,
+) -> GeneralImage<Nothing> {
+    let (width, height) = src.dimensions();
+    let deep = src.format().channels().any(|info| info.bits > 8);
+    let format = if deep { Format::RGB48 } else { Format::RGB24 };
+
+    let mut output = Builder::owned(width, height, format).finish();
+    let mut scratch = vec![0.0; demosaic_scratch_len(width)];
+    demosaic_nearest_into(src, pattern, &mut output, &mut scratch);
+    output
+}
+
+/// Like [`demosaic_nearest`], but drawing the colors into `dst`, and
+/// keeping the samples of the current cells in `scratch`.
+///
+/// `dst` can be in any format. Only the area where both images overlap is
+/// drawn. `scratch` must be at least [`demosaic_scratch_len`] long, for
+/// the width of that area.
+///
+/// # Panics
+///
+/// Panics if `scratch` is too small.
+///
+/// [`demosaic_nearest`]: crate::demosaic_nearest
+/// [`demosaic_scratch_len`]: crate::demosaic_scratch_len
+pub fn demosaic_nearest_into<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    pattern: CfaPattern,
+    dst: &mut Dst,
+    scratch: &mut [f32],
+) {
+    let width = cmp::min(src.width(), dst.width());
+    let height = cmp::min(src.height(), dst.height());
+    assert!(
+        scratch.len() >= demosaic_scratch_len(width),
+        "The scratch buffer is too small"
+    );
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let src_codec = Codec::new(src.format(), src.endianness()).with_bit_order(src.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+    let src_bpp = src.format().bpp() as usize;
+    let dst_bpp = dst.format().bpp() as usize;
+
+    // keep batches byte-aligned
+    let pixels_per_chunk = CHUNK_BYTES * 8 / cmp::max(src_bpp, dst_bpp) / 8 * 8;
+    let mut chunk = [0u8; CHUNK_BYTES];
+
+    let (top, bottom) = scratch[..width * 2].split_at_mut(width);
+    let mut load = |y: usize, row: &mut [f32]| {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            src.scanline(x, y, &mut chunk[..divide_rounding_up(count * src_bpp, 8)]);
+            for (i, sample) in row[x..x + count].iter_mut().enumerate() {
+                *sample = src_codec.read(&chunk, i)[Channel::Red as usize];
+            }
+            x += count;
+        }
+    };
+
+    let colors = pattern.colors();
+    let mut dst_chunk = [0u8; CHUNK_BYTES];
+    for cell_y in (0..height).step_by(2) {
+        // a missing last row or column repeats the one before it
+        load(cell_y, top);
+        load(cmp::min(cell_y + 1, height - 1), bottom);
+
+        for y in cell_y..cmp::min(cell_y + 2, height) {
+            let mut x = 0;
+            while x < width {
+                let count = cmp::min(pixels_per_chunk, width - x);
+                let dst_len = divide_rounding_up(count * dst_bpp, 8);
+                if dst_bpp < 8 {
+                    // preserve the bits of pixels we are not writing to
+                    dst.scanline(x, y, &mut dst_chunk[..dst_len]);
+                }
+
+                for i in 0..count {
+                    let at = x + i;
+                    let left = at - at % 2;
+                    let right = cmp::min(left + 1, width - 1);
+                    let cell = [top[left], top[right], bottom[left], bottom[right]];
+                    let own = (y % 2) * 2 + at % 2;
+
+                    let mut components = [0.0, 0.0, 0.0, 1.0];
+                    let mut greens = 0.0;
+                    for (&color, &sample) in colors.iter().zip(cell.iter()) {
+                        if color == Channel::Green {
+                            greens += sample / 2.0;
+                        } else {
+                            components[color as usize] = sample;
+                        }
+                    }
+                    components[Channel::Green as usize] = greens;
+                    components[colors[own] as usize] = cell[own];
+
+                    dst_codec.write(&mut dst_chunk, i, components);
+                }
+                dst.set_scanline(x, y, &dst_chunk[..dst_len]);
+
+                x += count;
+            }
+        }
+    }
+}
+
+/// The length of the scratch buffer that [`demosaic_nearest_into`] needs
+/// for images that overlap over `width` pixels.
+///
+/// [`demosaic_nearest_into`]: crate::demosaic_nearest_into
+pub const fn demosaic_scratch_len(width: usize) -> usize {
+    width * 2
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::Endianness;
+
+    #[test]
+    fn patterns() {
+        assert_eq!(CfaPattern::Bggr.color_at(3, 3), Channel::Red);
+        assert_eq!(CfaPattern::Gbrg.color_at(1, 0), Channel::Blue);
+
+        // a 3x3 GRBG frame, with 16-bit samples
+        let samples: [u16; 9] = [100, 200, 110, 300, 400, 310, 120, 220, 130];
+        let mut bytes = [0u8; 18];
+        for (sample, pair) in samples.iter().zip(bytes.chunks_exact_mut(2)) {
+            pair.copy_from_slice(&(sample * 64).to_le_bytes());
+        }
+        let raw = crate::Builder::from_buffer(3, 3, Format::GRAY16, bytes)
+            .with_endianness(Endianness::Little)
+            .finish();
+        let rgb = demosaic_nearest(&raw, CfaPattern::Grbg);
+        assert_eq!(rgb.format(), Format::RGB48);
+
+        let pixel = |x, y| {
+            let rgba = rgb.pixel(x, y).to_rgba();
+            [rgba.red / 64, rgba.green / 64, rgba.blue / 64]
+        };
+        // green keeps its own sample, and red takes the average
+        assert_eq!(pixel(0, 0), [200, 100, 300]);
+        assert_eq!(pixel(1, 0), [200, 250, 300]);
+        assert_eq!(pixel(1, 1), [200, 400, 300]);
+        // the last column and row are cells of their own
+        assert_eq!(pixel(2, 1), [110, 210, 310]);
+        assert_eq!(pixel(2, 2), [130, 130, 130]);
+    }
+}
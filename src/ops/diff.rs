@@ -0,0 +1,227 @@
+// BSL 1.0 License
+
+use crate::{
+    codec::{self, Codec, Components},
+    divide_rounding_up, Format, Image,
+};
+#[cfg(feature = "alloc")]
+use crate::{Builder, GeneralImage, Nothing};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use core::cmp;
+
+/// Create a heatmap of the differences between two images.
+///
+/// The result is an opaque [`RGB24`] image as large as the larger of the
+/// two images. Pixels that are the same in both images are black, and
+/// pixels that differ go from red through yellow to white as the
+/// difference grows. The difference of a pixel is the largest difference
+/// between any of its channels, after converting both pixels to RGBA and
+/// looking up indexed pixels in their palette. Pixels that are only in
+/// one of the images are white.
+///
+/// This is intended for CI screenshot comparisons, and for tracking down
+/// bugs in format conversions.
+///
+/// [`RGB24`]: crate::Format::RGB24
+#[cfg(feature = "alloc")]
+pub fn diff_image<A: Image + ?Sized, B: Image + ?Sized>(a: &A, b: &B) -> GeneralImage<Nothing> {
+    let width = cmp::max(a.width(), b.width());
+    let height = cmp::max(a.height(), b.height());
+    let mut output = Builder::owned(width, height, Format::RGB24).finish();
+    let mut scratch = vec![0u8; diff_scratch_len(a, b, &output)];
+    diff_image_into(a, b, &mut output, &mut scratch);
+    output
+}
+
+/// Like [`diff_image`], but drawing the heatmap into `output`.
+///
+/// `output` must be as large as the larger of the two images, and can be
+/// in any format. `scratch` holds one row of each of the three images
+/// without padding, and must be at least [`diff_scratch_len`] bytes long.
+///
+/// # Panics
+///
+/// Panics if `output` has the wrong dimensions or `scratch` is too small.
+///
+/// [`diff_image`]: crate::diff_image
+/// [`diff_scratch_len`]: crate::diff_scratch_len
+pub fn diff_image_into<A, B, Out>(a: &A, b: &B, output: &mut Out, scratch: &mut [u8])
+where
+    A: Image + ?Sized,
+    B: Image + ?Sized,
+    Out: Image + ?Sized,
+{
+    let width = cmp::max(a.width(), b.width());
+    let height = cmp::max(a.height(), b.height());
+    assert_eq!(
+        output.dimensions(),
+        (width, height),
+        "The output must be as large as the larger image"
+    );
+    assert!(
+        scratch.len() >= diff_scratch_len(a, b, output),
+        "The scratch buffer is too small"
+    );
+
+    let a_codec = Codec::new(a.format(), a.endianness()).with_bit_order(a.bit_order());
+    let b_codec = Codec::new(b.format(), b.endianness()).with_bit_order(b.bit_order());
+    let output_codec =
+        Codec::new(output.format(), output.endianness()).with_bit_order(output.bit_order());
+    let (a_palette, b_palette) = (a.palette(), b.palette());
+
+    let (a_row, rest) = scratch.split_at_mut(row_len(a.width(), a.format()));
+    let (b_row, rest) = rest.split_at_mut(row_len(b.width(), b.format()));
+    let output_row = &mut rest[..row_len(width, output.format())];
+
+    for y in 0..height {
+        // rows that are past the bottom of an image have no pixels
+        let a_width = if y < a.height() { a.width() } else { 0 };
+        let b_width = if y < b.height() { b.width() } else { 0 };
+        a.scanline(0, y, &mut a_row[..row_len(a_width, a.format())]);
+        b.scanline(0, y, &mut b_row[..row_len(b_width, b.format())]);
+
+        for x in 0..width {
+            let difference = if x < a_width && x < b_width {
+                let pa = a_codec.read_resolved(a_row, x, a_palette);
+                let pb = b_codec.read_resolved(b_row, x, b_palette);
+                pa.iter()
+                    .zip(pb.iter())
+                    .map(|(&ca, &cb)| if ca > cb { ca - cb } else { cb - ca })
+                    .fold(0.0, f32::max)
+            } else {
+                1.0
+            };
+
+            output_codec.write(output_row, x, heat(difference));
+        }
+
+        output.set_scanline(0, y, output_row);
+    }
+}
+
+/// The number of scratch bytes that [`diff_image_into`] needs.
+///
+/// [`diff_image_into`]: crate::diff_image_into
+pub fn diff_scratch_len<A, B, Out>(a: &A, b: &B, output: &Out) -> usize
+where
+    A: Image + ?Sized,
+    B: Image + ?Sized,
+    Out: Image + ?Sized,
+{
+    row_len(a.width(), a.format())
+        + row_len(b.width(), b.format())
+        + row_len(output.width(), output.format())
+}
+
+/// The number of bytes in `width` pixels.
+fn row_len(width: usize, format: Format) -> usize {
+    divide_rounding_up(width * format.bpp() as usize, 8)
+}
+
+/// The color of the heatmap for a difference between `0.0` and `1.0`.
+fn heat(difference: f32) -> Components {
+    let difference = codec::clamp_unit(difference) * 3.0;
+    [
+        codec::clamp_unit(difference),
+        codec::clamp_unit(difference - 1.0),
+        codec::clamp_unit(difference - 2.0),
+        1.0,
+    ]
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{BitOrder, Endianness};
+
+    #[test]
+    fn heatmap() {
+        let a = GeneralImage::from_buffer(3, 1, Format::A8, [0u8, 0x80, 0xFF]);
+        let b = Builder::from_buffer(2, 2, Format::ARGB32, [0xFFu8; 16])
+            .with_endianness(Endianness::Little)
+            .finish();
+        let mut diff = diff_image(&a, &b);
+        assert_eq!(diff.dimensions(), (3, 2));
+
+        let mut row = [0u8; 9];
+        diff.scanline(0, 0, &mut row);
+        // the color channels of `a` are black, so every pixel differs
+        assert_eq!(row, [0xFF; 9]);
+
+        let c = GeneralImage::from_buffer(3, 1, Format::A8, [0u8, 0x40, 0xFF]);
+        diff = diff_image(&a, &c);
+        diff.scanline(0, 0, &mut row);
+        assert_eq!(&row[..3], &[0, 0, 0]);
+        assert_eq!(&row[3..6], &[0xC0, 0, 0]);
+
+        // only the first pixel of each mask is set
+        let msb = Builder::from_buffer(8, 1, Format::A1, [0b1000_0000u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let lsb = GeneralImage::from_buffer(8, 1, Format::A1, [0b0000_0001u8]);
+        let diff = diff_image(&msb, &lsb);
+        let mut row = [0u8; 24];
+        diff.scanline(0, 0, &mut row);
+        assert_eq!(row, [0; 24]);
+    }
+
+    #[test]
+    fn indexed_images() {
+        use crate::{IndexedImage, Palette, Rgba};
+
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+        let indices = GeneralImage::from_buffer(2, 1, Format::INDEXED8, [1u8, 0]);
+        let indexed = IndexedImage::new(indices, palette);
+        let argb = Builder::from_buffer(2, 1, Format::ARGB32, [0xFFu8, 0xFF, 0, 0, 0, 0, 0, 0])
+            .with_endianness(Endianness::Little)
+            .finish();
+        assert_eq!(argb.pixel(0, 0).to_rgba(), red);
+
+        // the colors in the palette are compared, not the indices
+        let diff = diff_image(&indexed, &argb);
+        let mut row = [0xFFu8; 6];
+        diff.scanline(0, 0, &mut row);
+        assert_eq!(row, [0; 6]);
+    }
+
+    #[test]
+    fn caller_buffers() {
+        let a = GeneralImage::from_buffer(3, 1, Format::A8, [0u8, 0x80, 0xFF]);
+        let b = GeneralImage::from_buffer(2, 2, Format::A4, [0x0Fu8, 0xF0]);
+        let expected = diff_image(&a, &b);
+
+        // one row of each image, with sub-byte rows rounded up
+        let output = || GeneralImage::from_buffer(3, 2, Format::RGB24, [0u8; 18]);
+        let len = diff_scratch_len(&a, &b, &output());
+        assert_eq!(len, 3 + 1 + 9);
+
+        // a longer scratch buffer than needed is fine
+        for &scratch_len in [len, 16].iter() {
+            let mut output = output();
+            let mut scratch = [0xFFu8; 16];
+            diff_image_into(&a, &b, &mut output, &mut scratch[..scratch_len]);
+
+            let (mut row, mut expected_row) = ([0u8; 9], [0u8; 9]);
+            for y in 0..2 {
+                output.scanline(0, y, &mut row);
+                expected.scanline(0, y, &mut expected_row);
+                assert_eq!(row, expected_row);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The scratch buffer is too small")]
+    fn caller_scratch_too_small() {
+        let a = GeneralImage::from_buffer(3, 1, Format::A8, [0u8; 3]);
+        let mut output = GeneralImage::from_buffer(3, 1, Format::A8, [0u8; 3]);
+        diff_image_into(&a, &a, &mut output, &mut [0u8; 8]);
+    }
+}
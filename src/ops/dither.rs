@@ -0,0 +1,199 @@
+// BSL 1.0 License
+
+use super::CHUNK_BYTES;
+use crate::{
+    codec::{self, Codec, Components},
+    divide_rounding_up, Channel, Image, MAX_BYTES_PER_PIXEL,
+};
+use core::cmp;
+
+/// Copy the contents of one image into another with ordered dithering.
+///
+/// This is like [`convert`], but instead of rounding each channel to the
+/// nearest value that the destination can hold, a threshold is added to
+/// it before rounding down. The thresholds are read from `thresholds`,
+/// which is tiled over the image, so any image can be used as a dither
+/// mask. [`BlueNoise`] is a good default.
+///
+/// Each channel of the destination uses the same channel of
+/// `thresholds`, or its first channel if it doesn't have that one. Integer
+/// thresholds of `n` bits are spread evenly over `0.0..1.0`, and float
+/// thresholds are used as they are. `phase` shifts the mask, so moving it
+/// from one frame to the next dithers over time as well as space.
+///
+/// Channels of float formats are never dithered.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{ordered_dither, BlueNoise, Format, GeneralImage, Image};
+///
+/// let alpha = GeneralImage::from_buffer(64, 64, Format::A8, vec![0x80u8; 64 * 64]);
+/// let mut bits = GeneralImage::from_buffer(64, 64, Format::A1, vec![0u8; 8 * 64]);
+/// ordered_dither(&alpha, &mut bits, &BlueNoise, (0, 0));
+/// ```
+///
+/// [`convert`]: crate::convert
+/// [`BlueNoise`]: crate::BlueNoise
+pub fn ordered_dither<Src: Image + ?Sized, Dst: Image + ?Sized, T: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    thresholds: &T,
+    phase: (usize, usize),
+) {
+    let src_codec = Codec::new(src.format(), src.endianness()).with_bit_order(src.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+    let src_bpp = src.format().bpp() as usize;
+    let dst_bpp = dst.format().bpp() as usize;
+
+    let width = cmp::min(src.width(), dst.width());
+    let height = cmp::min(src.height(), dst.height());
+    let mut mask = Mask::new(thresholds);
+
+    // keep batches byte-aligned
+    let pixels_per_chunk = CHUNK_BYTES * 8 / cmp::max(src_bpp, dst_bpp) / 8 * 8;
+    let mut src_chunk = [0u8; CHUNK_BYTES];
+    let mut dst_chunk = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        let mask_y = mask.load_row(y.wrapping_add(phase.1));
+
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let src_len = divide_rounding_up(count * src_bpp, 8);
+            let dst_len = divide_rounding_up(count * dst_bpp, 8);
+
+            src.scanline(x, y, &mut src_chunk[..src_len]);
+            if dst_bpp < 8 {
+                // preserve the bits of pixels we are not writing to
+                dst.scanline(x, y, &mut dst_chunk[..dst_len]);
+            }
+
+            for i in 0..count {
+                let threshold = mask.threshold((x + i).wrapping_add(phase.0), mask_y);
+                let components = src_codec.read(&src_chunk, i);
+                dst_codec.write_dithered(&mut dst_chunk, i, components, threshold);
+            }
+            dst.set_scanline(x, y, &dst_chunk[..dst_len]);
+
+            x += count;
+        }
+    }
+}
+
+/// Reads thresholds out of a tiled image.
+struct Mask<'a, T: ?Sized> {
+    image: &'a T,
+    codec: Codec,
+    /// The current row, if it fits.
+    row: [u8; CHUNK_BYTES],
+    row_fits: bool,
+}
+
+impl<'a, T: Image + ?Sized> Mask<'a, T> {
+    fn new(image: &'a T) -> Self {
+        let row_len = divide_rounding_up(image.width() * image.format().bpp() as usize, 8);
+        Self {
+            image,
+            codec: Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order()),
+            row: [0; CHUNK_BYTES],
+            row_fits: row_len <= CHUNK_BYTES,
+        }
+    }
+
+    /// Load the row of the mask for the row `y` of the image, and return
+    /// its coordinate in the mask.
+    fn load_row(&mut self, y: usize) -> usize {
+        let (width, height) = self.image.dimensions();
+        if height == 0 {
+            return 0;
+        }
+
+        let y = y % height;
+        if self.row_fits {
+            let row_len = divide_rounding_up(width * self.codec.format().bpp() as usize, 8);
+            self.image.scanline(0, y, &mut self.row[..row_len]);
+        }
+        y
+    }
+
+    /// The threshold of each channel at the given column of the mask's
+    /// current row.
+    fn threshold(&self, x: usize, y: usize) -> Components {
+        let width = self.image.width();
+        if width == 0 || self.image.height() == 0 {
+            return [0.5; 4];
+        }
+
+        let x = x % width;
+        let raw = if self.row_fits {
+            self.codec.read_raw(&self.row, x)
+        } else {
+            let bpp = self.codec.format().bpp() as usize;
+            let mut pixel = [0u8; MAX_BYTES_PER_PIXEL];
+            self.image
+                .scanline(x, y, &mut pixel[..self.codec.format().bytes() as usize]);
+            self.codec.read_raw(&pixel, x * bpp % 8 / bpp)
+        };
+
+        let first = self.codec.format().channels().next();
+        let mut thresholds = [0.5; 4];
+        for &channel in [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha].iter() {
+            let info = match self.codec.channel(channel).or(first) {
+                Some(info) => info,
+                None => continue,
+            };
+
+            let value = raw[info.channel as usize];
+            thresholds[channel as usize] = if self.codec.format().involves_float() {
+                codec::float_from_raw(value, info.bits)
+            } else {
+                (value as f32 + 0.5) / (1u64 << info.bits) as f32
+            };
+        }
+
+        thresholds
+    }
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{BitOrder, BlueNoise, Builder, Format, GeneralImage};
+    use alloc::vec;
+
+    #[test]
+    fn half_alpha_is_half_lit() {
+        let alpha = GeneralImage::from_buffer(64, 64, Format::A8, vec![0x80u8; 64 * 64]);
+        let mut bits = GeneralImage::from_buffer(64, 64, Format::A1, vec![0u8; 8 * 64]);
+        ordered_dither(&alpha, &mut bits, &BlueNoise, (3, 5));
+
+        // thresholds from 127 up round 0x80 up to a lit pixel
+        let mut lit = 0;
+        let mut row = [0u8; 8];
+        for y in 0..64 {
+            bits.scanline(0, y, &mut row);
+            lit += row.iter().map(|byte| byte.count_ones()).sum::<u32>();
+        }
+        assert_eq!(lit, 129 * 16);
+    }
+
+    #[test]
+    fn msb_first() {
+        // only the first pixel of the mask has a low threshold, which
+        // rounds a half-lit pixel down
+        let mask = Builder::from_buffer(8, 1, Format::A1, [0b0111_1111u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let alpha = GeneralImage::from_buffer(8, 1, Format::A8, [0x80u8; 8]);
+        let mut bits = Builder::from_buffer(8, 1, Format::A1, [0u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        ordered_dither(&alpha, &mut bits, &mask, (0, 0));
+
+        let mut row = [0u8];
+        bits.scanline(0, 0, &mut row);
+        assert_eq!(row, [0b0111_1111]);
+    }
+}
@@ -0,0 +1,218 @@
+// BSL 1.0 License
+
+use super::CHUNK_BYTES;
+use crate::{
+    codec::{self, Codec},
+    divide_rounding_up, ColorType, Image,
+};
+#[cfg(feature = "alloc")]
+use crate::{Builder, Format, GeneralImage, Nothing};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use core::cmp;
+
+/// Find the edges of an image with the Sobel operator.
+///
+/// The result is a [`GRAY8`] image as large as `src`, where each pixel is
+/// the magnitude of the gradient at that pixel. Flat areas are black, and
+/// the sharper an edge is, the brighter it gets, up to white for a step
+/// from black to white. Pixels past the edges of `src` repeat the pixels
+/// on the edge, so the border of the image isn't an edge.
+///
+/// Images with an [`Alpha`] color type are measured by their alpha, and
+/// every other image by the luma of its color, so this works on masks as
+/// well as on grayscale images and screenshots.
+///
+/// This requires the `alloc` feature; see [`edge_detect_into`] for a
+/// version that doesn't allocate.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{edge_detect, Format, GeneralImage, Image};
+///
+/// // a black square on the left and a white one on the right
+/// let image = GeneralImage::from_buffer(4, 1, Format::GRAY8, [0u8, 0, 0xFF, 0xFF]);
+/// let edges = edge_detect(&image);
+///
+/// let mut row = [0u8; 4];
+/// edges.scanline(0, 0, &mut row);
+/// assert_eq!(row, [0, 0xFF, 0xFF, 0]);
+/// ```
+///
+/// [`GRAY8`]: crate::Format::GRAY8
+/// [`Alpha`]: crate::ColorType::Alpha
+/// [`edge_detect_into`]: crate::edge_detect_into
+#[cfg(feature = "alloc")]
+pub fn edge_detect<Img: Image + ?Sized>(src: &Img) -> GeneralImage<Nothing> {
+    let (width, height) = src.dimensions();
+    let mut output = Builder::owned(width, height, Format::GRAY8).finish();
+    let mut scratch = vec![0.0; edge_scratch_len(width)];
+    edge_detect_into(src, &mut output, &mut scratch);
+    output
+}
+
+/// Like [`edge_detect`], but drawing the edges into `dst`, and keeping
+/// the rows around the current one in `scratch`.
+///
+/// The magnitudes are written as gray, so `dst` can be in any format.
+/// Only the area where both images overlap is drawn. `scratch` must be at
+/// least [`edge_scratch_len`] long, for the width of that area.
+///
+/// # Panics
+///
+/// Panics if `scratch` is too small.
+///
+/// [`edge_detect`]: crate::edge_detect
+/// [`edge_scratch_len`]: crate::edge_scratch_len
+pub fn edge_detect_into<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    scratch: &mut [f32],
+) {
+    let width = cmp::min(src.width(), dst.width());
+    let height = cmp::min(src.height(), dst.height());
+    assert!(
+        scratch.len() >= edge_scratch_len(width),
+        "The scratch buffer is too small"
+    );
+    if width == 0 || height == 0 {
+        return;
+    }
+
+    let src_codec = Codec::new(src.format(), src.endianness()).with_bit_order(src.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+    let src_bpp = src.format().bpp() as usize;
+    let dst_bpp = dst.format().bpp() as usize;
+    let alpha = src.format().color_type() == ColorType::Alpha;
+
+    // keep batches byte-aligned
+    let pixels_per_chunk = CHUNK_BYTES * 8 / cmp::max(src_bpp, dst_bpp) / 8 * 8;
+    let mut chunk = [0u8; CHUNK_BYTES];
+
+    // the gray levels of row `y` are kept in the `y % 3`th slot
+    let rows = &mut scratch[..width * 3];
+    let mut load = |y: usize, rows: &mut [f32]| {
+        let row = &mut rows[(y % 3) * width..][..width];
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            src.scanline(x, y, &mut chunk[..divide_rounding_up(count * src_bpp, 8)]);
+            for (i, level) in row[x..x + count].iter_mut().enumerate() {
+                let components = src_codec.read(&chunk, i);
+                *level = if alpha {
+                    components[3]
+                } else {
+                    codec::luma(components)
+                };
+            }
+            x += count;
+        }
+    };
+
+    load(0, rows);
+    if height > 1 {
+        load(1, rows);
+    }
+
+    let mut dst_chunk = [0u8; CHUNK_BYTES];
+    for y in 0..height {
+        if y > 0 && y + 1 < height {
+            load(y + 1, rows);
+        }
+
+        let slot = |y: usize| (y % 3) * width;
+        let (above, current, below) = (
+            slot(y.saturating_sub(1)),
+            slot(y),
+            slot(cmp::min(y + 1, height - 1)),
+        );
+
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let dst_len = divide_rounding_up(count * dst_bpp, 8);
+            if dst_bpp < 8 {
+                // preserve the bits of pixels we are not writing to
+                dst.scanline(x, y, &mut dst_chunk[..dst_len]);
+            }
+
+            for i in 0..count {
+                let at = x + i;
+                let (left, right) = (at.saturating_sub(1), cmp::min(at + 1, width - 1));
+                let level = |row: usize, x: usize| rows[row + x];
+
+                let gx = level(above, right) + 2.0 * level(current, right) + level(below, right)
+                    - level(above, left)
+                    - 2.0 * level(current, left)
+                    - level(below, left);
+                let gy = level(below, left) + 2.0 * level(below, at) + level(below, right)
+                    - level(above, left)
+                    - 2.0 * level(above, at)
+                    - level(above, right);
+
+                // a step from zero to one gives a gradient of four
+                let magnitude = codec::clamp_unit(hypot(gx, gy) / 4.0);
+                dst_codec.write(&mut dst_chunk, i, [magnitude, magnitude, magnitude, 1.0]);
+            }
+            dst.set_scanline(x, y, &dst_chunk[..dst_len]);
+
+            x += count;
+        }
+    }
+}
+
+/// The length of the scratch buffer that [`edge_detect_into`] needs for
+/// images that overlap over `width` pixels.
+///
+/// [`edge_detect_into`]: crate::edge_detect_into
+pub const fn edge_scratch_len(width: usize) -> usize {
+    width * 3
+}
+
+/// The length of the vector `(x, y)`.
+///
+/// `core` has no square root, so this refines the sum of the two sides
+/// with a few steps of Newton's method, which is plenty for 8-bit output.
+fn hypot(x: f32, y: f32) -> f32 {
+    let square = x * x + y * y;
+    let abs = |value: f32| if value < 0.0 { -value } else { value };
+    let mut root = abs(x) + abs(y);
+    if root == 0.0 {
+        return 0.0;
+    }
+
+    for _ in 0..4 {
+        root = (root + square / root) / 2.0;
+    }
+    root
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges() {
+        let length = hypot(3.0, -4.0);
+        assert!(length > 4.9999 && length < 5.0001);
+
+        // a mask with a filled square in the middle
+        let mut mask = [0u8; 5 * 5];
+        for y in 1..4 {
+            mask[y * 5 + 1..y * 5 + 4].copy_from_slice(&[0xFF; 3]);
+        }
+        let mask = GeneralImage::from_buffer(5, 5, Format::A8, mask);
+        let edges = edge_detect(&mask);
+
+        let mut rows = [[0u8; 5]; 5];
+        for (y, row) in rows.iter_mut().enumerate() {
+            edges.scanline(0, y, row);
+        }
+        // the center is flat, and the sides of the square are edges
+        assert_eq!(rows[2][2], 0);
+        assert_eq!(rows[2][0], 0xFF);
+        assert_eq!(rows[0][2], 0xFF);
+        assert!(rows[0][0] > 0 && rows[0][0] < 0xFF);
+    }
+}
@@ -0,0 +1,228 @@
+// BSL 1.0 License
+
+use super::CHUNK_BYTES;
+use crate::{
+    codec::{self, Codec},
+    divide_rounding_up, Image,
+};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use core::cmp;
+
+/// How [`eink_dither`] turns colors into gray levels.
+///
+/// [`eink_dither`]: crate::eink_dither
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EinkOptions {
+    /// How far the gray levels are stretched away from the middle, where
+    /// `1.0` leaves them alone. E-paper has a narrow range, so a bit of
+    /// extra contrast keeps text readable.
+    ///
+    /// This is `1.0` by default.
+    pub contrast: f32,
+    /// What is added to the gray levels after the contrast is applied.
+    ///
+    /// This is `0.0` by default.
+    pub brightness: f32,
+    /// Whether the lowest value of the output is white rather than black,
+    /// as on panels where a set bit turns a pixel black.
+    ///
+    /// This is `false` by default.
+    pub invert: bool,
+}
+
+impl Default for EinkOptions {
+    fn default() -> Self {
+        Self {
+            contrast: 1.0,
+            brightness: 0.0,
+            invert: false,
+        }
+    }
+}
+
+impl EinkOptions {
+    /// Apply the contrast curve to a gray level.
+    fn curve(&self, gray: f32) -> f32 {
+        codec::clamp_unit((gray - 0.5) * self.contrast + 0.5 + self.brightness)
+    }
+}
+
+/// Convert an image to the gray levels of an e-paper display, with
+/// Floyd-Steinberg error diffusion.
+///
+/// Each pixel is converted to gray and drawn over white, since that is
+/// the color of the paper, and then put through the contrast curve of
+/// `options`. The gray levels are written to the first channel of `dst`,
+/// which is usually [`A1`] for monochrome panels or [`A4`] for panels
+/// with sixteen levels. The bytes are packed with the bit order of `dst`,
+/// so a buffer built with [`with_bit_order`] can be sent straight to the
+/// panel.
+///
+/// Only the area where both images overlap is converted.
+///
+/// This requires the `alloc` feature; see [`eink_dither_into`] for a
+/// version that doesn't allocate.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{eink_dither, BitOrder, Builder, EinkOptions, Format, GeneralImage, Image};
+///
+/// let gray = GeneralImage::from_buffer(16, 16, Format::GRAY8, vec![0x80u8; 16 * 16]);
+/// let mut panel = Builder::from_buffer(16, 16, Format::A1, vec![0u8; 2 * 16])
+///     .with_bit_order(BitOrder::MsbFirst)
+///     .finish();
+/// eink_dither(&gray, &mut panel, &EinkOptions::default());
+/// ```
+///
+/// [`A1`]: crate::Format::A1
+/// [`A4`]: crate::Format::A4
+/// [`with_bit_order`]: crate::Builder::with_bit_order
+/// [`eink_dither_into`]: crate::eink_dither_into
+#[cfg(feature = "alloc")]
+pub fn eink_dither<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    options: &EinkOptions,
+) {
+    let width = cmp::min(src.width(), dst.width());
+    let mut scratch = vec![0.0; eink_scratch_len(width)];
+    eink_dither_into(src, dst, options, &mut scratch);
+}
+
+/// Like [`eink_dither`], but keeping the errors that are diffused to the
+/// next row in `scratch`.
+///
+/// `scratch` must be at least [`eink_scratch_len`] long, for the width
+/// of the area where the images overlap.
+///
+/// # Panics
+///
+/// Panics if `scratch` is too small.
+///
+/// [`eink_dither`]: crate::eink_dither
+/// [`eink_scratch_len`]: crate::eink_scratch_len
+pub fn eink_dither_into<Src: Image + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    options: &EinkOptions,
+    scratch: &mut [f32],
+) {
+    let width = cmp::min(src.width(), dst.width());
+    let height = cmp::min(src.height(), dst.height());
+    assert!(
+        scratch.len() >= eink_scratch_len(width),
+        "The scratch buffer is too small"
+    );
+
+    let src_codec = Codec::new(src.format(), src.endianness()).with_bit_order(src.bit_order());
+    let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+    let src_bpp = src.format().bpp() as usize;
+    let dst_bpp = dst.format().bpp() as usize;
+    let channel = match dst.format().channels().next() {
+        Some(channel) => channel,
+        None => return,
+    };
+    let max = codec::low_bits(channel.bits) as f32;
+
+    // the errors of this row and the next, with a column on either side
+    // so that the edges don't need special cases
+    let (current, rest) = scratch.split_at_mut(width + 2);
+    let next = &mut rest[..width + 2];
+    current.iter_mut().for_each(|error| *error = 0.0);
+
+    // keep batches byte-aligned
+    let pixels_per_chunk = CHUNK_BYTES * 8 / cmp::max(src_bpp, dst_bpp) / 8 * 8;
+    let mut src_chunk = [0u8; CHUNK_BYTES];
+    let mut dst_chunk = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        next.iter_mut().for_each(|error| *error = 0.0);
+
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let src_len = divide_rounding_up(count * src_bpp, 8);
+            let dst_len = divide_rounding_up(count * dst_bpp, 8);
+
+            src.scanline(x, y, &mut src_chunk[..src_len]);
+            if dst_bpp < 8 {
+                // preserve the bits of pixels we are not writing to
+                dst.scanline(x, y, &mut dst_chunk[..dst_len]);
+            }
+
+            for i in 0..count {
+                let components = src_codec.read(&src_chunk, i);
+                let alpha = components[3];
+                let gray = options.curve(codec::luma(components) * alpha + 1.0 - alpha);
+
+                // round the gray level with the error of its neighbors
+                let at = x + i + 1;
+                let wanted = gray + current[at];
+                let level = (codec::clamp_unit(wanted) * max + 0.5) as u64;
+                let error = wanted - level as f32 / max;
+                current[at + 1] += error * 7.0 / 16.0;
+                next[at - 1] += error * 3.0 / 16.0;
+                next[at] += error * 5.0 / 16.0;
+                next[at + 1] += error / 16.0;
+
+                let level = if options.invert {
+                    max as u64 - level
+                } else {
+                    level
+                };
+                let mut raw = dst_codec.read_raw(&dst_chunk, i);
+                raw[channel.channel as usize] = level;
+                dst_codec.write_raw(&mut dst_chunk, i, raw);
+            }
+            dst.set_scanline(x, y, &dst_chunk[..dst_len]);
+
+            x += count;
+        }
+
+        current.copy_from_slice(next);
+    }
+}
+
+/// The length of the scratch buffer that [`eink_dither_into`] needs for
+/// images that overlap over `width` pixels.
+///
+/// [`eink_dither_into`]: crate::eink_dither_into
+pub const fn eink_scratch_len(width: usize) -> usize {
+    (width + 2) * 2
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{BitOrder, Builder, Format, GeneralImage};
+    use alloc::vec;
+
+    #[test]
+    fn levels() {
+        // mid gray lights half of the pixels, in the packing of the panel
+        let gray = GeneralImage::from_buffer(16, 16, Format::GRAY8, vec![0x80u8; 16 * 16]);
+        let mut panel = Builder::from_buffer(16, 16, Format::A1, vec![0u8; 2 * 16])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        eink_dither(&gray, &mut panel, &EinkOptions::default());
+        let mut row = [0u8; 2];
+        panel.scanline(0, 0, &mut row);
+        assert_eq!(row, [0b1010_1010; 2]);
+
+        // extra contrast pushes light gray to white, and transparent pixels
+        // are paper
+        let light = GeneralImage::from_buffer(8, 1, Format::LA8, [0xC0, 0xFF, 0, 0].repeat(4));
+        let mut panel = GeneralImage::from_buffer(8, 1, Format::A4, vec![0u8; 4]);
+        let options = EinkOptions {
+            contrast: 2.0,
+            invert: true,
+            ..EinkOptions::default()
+        };
+        eink_dither(&light, &mut panel, &options);
+        let mut row = [0xFFu8; 4];
+        panel.scanline(0, 0, &mut row);
+        assert_eq!(row, [0; 4]);
+    }
+}
@@ -0,0 +1,119 @@
+// BSL 1.0 License
+
+use super::for_each_chunk;
+use crate::{codec::Codec, Image};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// The multiplier of the hash, from FxHash.
+const SEED: u64 = 0x517C_C1B7_2722_0A95;
+
+/// A hash of every row of the image.
+///
+/// Only the pixels of each row are hashed, so the padding at the end of
+/// a row, and the unused bits of its last byte for formats smaller than
+/// a byte, don't change its hash. Rows with the same pixels always have
+/// the same hash, and rows with different pixels almost never do, so
+/// comparing the hashes of two frames is a cheap way to find the rows
+/// that changed before diffing them byte by byte.
+///
+/// The hash is fast rather than cryptographic, and it is only meant to be
+/// compared between images with the same format, endianness and width.
+///
+/// This requires the `alloc` feature; see [`row_hashes_into`] for a
+/// version that doesn't allocate.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{row_hashes, Format, GeneralImage, Image};
+///
+/// let mut frame = GeneralImage::from_buffer(2, 3, Format::GRAY8, vec![0u8; 6]);
+/// let before = row_hashes(&frame);
+/// frame.set_scanline(0, 1, &[0xFF]);
+///
+/// let after = row_hashes(&frame);
+/// let changed = (0..3).filter(|&y| before[y] != after[y]).collect::<Vec<_>>();
+/// assert_eq!(changed, [1]);
+/// ```
+///
+/// [`row_hashes_into`]: crate::row_hashes_into
+#[cfg(feature = "alloc")]
+pub fn row_hashes<Img: Image + ?Sized>(image: &Img) -> Vec<u64> {
+    let mut hashes = vec![0; image.height()];
+    row_hashes_into(image, &mut hashes);
+    hashes
+}
+
+/// Like [`row_hashes`], but writing the hashes into `hashes`.
+///
+/// # Panics
+///
+/// Panics if `hashes` is not as long as the image is tall.
+///
+/// [`row_hashes`]: crate::row_hashes
+pub fn row_hashes_into<Img: Image + ?Sized>(image: &Img, hashes: &mut [u64]) {
+    assert_eq!(
+        hashes.len(),
+        image.height(),
+        "There must be one hash per row"
+    );
+    hashes.iter_mut().for_each(|hash| *hash = 0);
+
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    let subbyte = image.format().subbyte();
+
+    for_each_chunk(image, |_, y, bytes, count| {
+        let hash = &mut hashes[y];
+        if subbyte {
+            // leave out the bits past the last pixel
+            for i in 0..count {
+                *hash = mix(*hash, codec.read_quantum(bytes, i));
+            }
+        } else {
+            let mut words = bytes.chunks_exact(8);
+            for word in &mut words {
+                let mut value = [0u8; 8];
+                value.copy_from_slice(word);
+                *hash = mix(*hash, u64::from_le_bytes(value));
+            }
+            for &byte in words.remainder() {
+                *hash = mix(*hash, u64::from(byte));
+            }
+        }
+        true
+    });
+}
+
+/// Add a value to a hash.
+fn mix(hash: u64, value: u64) -> u64 {
+    (hash.rotate_left(5) ^ value).wrapping_mul(SEED)
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{Builder, Format, GeneralImage};
+
+    #[test]
+    fn logical_pixels() {
+        // rows padded to four bytes, with garbage in the padding
+        let bytes = [0xAAu8, 0x55, 0x11, 0x22, 0xAA, 0x55, 0x33, 0x44];
+        let padded = Builder::from_buffer(2, 2, Format::GRAY8, bytes)
+            .with_bytes_per_scanline(4)
+            .finish();
+        let hashes = row_hashes(&padded);
+        assert_eq!(hashes[0], hashes[1]);
+
+        // the unused bits of the last byte don't count
+        let mask = GeneralImage::from_buffer(3, 2, Format::A1, [0b0000_0101u8, 0b1111_0101]);
+        let hashes = row_hashes(&mask);
+        assert_eq!(hashes[0], hashes[1]);
+
+        let wide = GeneralImage::from_buffer(20, 2, Format::ARGB32, vec![0u8; 160]);
+        let mut changed = GeneralImage::from_buffer(20, 2, Format::ARGB32, vec![0u8; 160]);
+        changed.set_scanline(19, 1, &[0, 0, 0, 1]);
+        assert_eq!(row_hashes(&wide)[0], row_hashes(&changed)[0]);
+        assert_ne!(row_hashes(&wide)[1], row_hashes(&changed)[1]);
+    }
+}
@@ -0,0 +1,339 @@
+// BSL 1.0 License
+
+use super::{for_each_chunk_mut, ArithmeticPolicy};
+use crate::{codec::Codec, Channel, Image};
+
+/// A matrix that transforms the color of a pixel.
+///
+/// The matrix has four rows, one for each output channel in the order
+/// red, green, blue and alpha. Each row has five columns: one for each
+/// input channel in the same order, followed by a constant offset. This
+/// is the same layout as SVG's `feColorMatrix`.
+///
+/// Channels are treated as values between `0.0` and `1.0`.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default)]
+pub struct ColorMatrix {
+    rows: [[f32; 5]; 4],
+}
+
+impl ColorMatrix {
+    /// The matrix that leaves colors unchanged.
+    pub const IDENTITY: ColorMatrix = ColorMatrix::new([
+        [1.0, 0.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0, 0.0],
+    ]);
+
+    /// Create a new color matrix from its rows.
+    pub const fn new(rows: [[f32; 5]; 4]) -> Self {
+        Self { rows }
+    }
+
+    /// Create a new color matrix that only transforms the red, green
+    /// and blue channels, leaving alpha unchanged.
+    pub const fn from_rgb(rgb: [[f32; 3]; 3]) -> Self {
+        Self::new([
+            [rgb[0][0], rgb[0][1], rgb[0][2], 0.0, 0.0],
+            [rgb[1][0], rgb[1][1], rgb[1][2], 0.0, 0.0],
+            [rgb[2][0], rgb[2][1], rgb[2][2], 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0, 0.0],
+        ])
+    }
+
+    /// The rows of this matrix.
+    pub const fn rows(&self) -> [[f32; 5]; 4] {
+        self.rows
+    }
+
+    /// Create a matrix that applies this matrix, followed by `next`.
+    pub fn then(self, next: ColorMatrix) -> ColorMatrix {
+        let mut rows = [[0.0f32; 5]; 4];
+
+        for (row, next_row) in rows.iter_mut().zip(next.rows.iter()) {
+            for (col, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| next_row[k] * self.rows[k][col]).sum::<f32>();
+            }
+            row[4] += next_row[4];
+        }
+
+        ColorMatrix::new(rows)
+    }
+
+    /// Transform a set of components by this matrix.
+    fn transform(&self, components: [f32; 4]) -> [f32; 4] {
+        let mut output = [0.0f32; 4];
+
+        for (value, row) in output.iter_mut().zip(self.rows.iter()) {
+            *value = row[4]
+                + components
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(c, m)| c * m)
+                    .sum::<f32>();
+        }
+
+        output
+    }
+}
+
+/// Apply a color matrix to every pixel in the image.
+///
+/// Images where every channel is 8-bit are transformed using fixed-point
+/// integer arithmetic.
+pub fn apply_color_matrix<Img: Image + ?Sized>(image: &mut Img, matrix: &ColorMatrix) {
+    apply_color_matrix_with_policy(image, matrix, ArithmeticPolicy::default());
+}
+
+/// Like [`apply_color_matrix`], but with a choice of what is stored when
+/// a transformed channel doesn't fit.
+///
+/// [`apply_color_matrix`]: crate::apply_color_matrix
+pub fn apply_color_matrix_with_policy<Img: Image + ?Sized>(
+    image: &mut Img,
+    matrix: &ColorMatrix,
+    policy: ArithmeticPolicy,
+) {
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+
+    let offsets = [
+        codec.byte_offset(Channel::Red),
+        codec.byte_offset(Channel::Green),
+        codec.byte_offset(Channel::Blue),
+    ];
+
+    if let [Some(red), Some(green), Some(blue)] = offsets {
+        // a channel that the format doesn't have is treated as opaque
+        let alpha = codec.byte_offset(Channel::Alpha);
+        if alpha.is_none() && codec.channel(Channel::Alpha).is_some() {
+            return apply_generic(image, &codec, matrix, policy);
+        }
+
+        // convert the matrix to 16.16 fixed point, with the offset
+        // scaled to the range of a byte
+        let mut fixed = [[0i32; 5]; 4];
+        for (fixed_row, row) in fixed.iter_mut().zip(matrix.rows.iter()) {
+            for (fixed_value, value) in fixed_row.iter_mut().zip(row.iter()) {
+                *fixed_value = (value * 65536.0) as i32;
+            }
+            fixed_row[4] *= 255;
+        }
+
+        let stride = codec.format().bytes() as usize;
+        for_each_chunk_mut(image, |chunk, _| {
+            chunk.chunks_exact_mut(stride).for_each(|pixel| {
+                let input = [
+                    pixel[red] as i32,
+                    pixel[green] as i32,
+                    pixel[blue] as i32,
+                    alpha.map_or(255, |alpha| pixel[alpha] as i32),
+                ];
+
+                let mut output = [0u8; 4];
+                for (value, row) in output.iter_mut().zip(fixed.iter()) {
+                    let sum = row[4]
+                        + input
+                            .iter()
+                            .zip(row.iter())
+                            .map(|(c, m)| c * m)
+                            .sum::<i32>();
+                    let sum = (sum + 32768) >> 16;
+                    *value = match policy {
+                        ArithmeticPolicy::Wrapping => sum as u8,
+                        _ => sum.max(0).min(255) as u8,
+                    };
+                }
+
+                pixel[red] = output[0];
+                pixel[green] = output[1];
+                pixel[blue] = output[2];
+                if let Some(alpha) = alpha {
+                    pixel[alpha] = output[3];
+                }
+            });
+        });
+    } else {
+        apply_generic(image, &codec, matrix, policy);
+    }
+}
+
+fn apply_generic<Img: Image + ?Sized>(
+    image: &mut Img,
+    codec: &Codec,
+    matrix: &ColorMatrix,
+    policy: ArithmeticPolicy,
+) {
+    for_each_chunk_mut(image, |chunk, count| {
+        for i in 0..count {
+            let components = matrix.transform(codec.read(chunk, i));
+            codec.write(chunk, i, policy.apply(codec, components));
+        }
+    });
+}
+
+/// A type of color vision deficiency.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorBlindness {
+    /// Lack of red cones.
+    Protanopia,
+    /// Lack of green cones.
+    Deuteranopia,
+    /// Lack of blue cones.
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// The matrix that simulates how colors appear with this deficiency.
+    ///
+    /// These are the full-severity matrices from Machado, Oliveira and
+    /// Fernandes (2009).
+    pub const fn simulation_matrix(self) -> ColorMatrix {
+        ColorMatrix::from_rgb(match self {
+            ColorBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        })
+    }
+
+    /// The matrix that daltonizes colors for this deficiency.
+    ///
+    /// The information lost by the deficiency is shifted into channels
+    /// that can still be distinguished, making the colors easier to tell
+    /// apart.
+    pub fn correction_matrix(self) -> ColorMatrix {
+        // shift the error into the green and blue channels
+        const ERROR_SHIFT: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+        // correction = I + shift * (I - simulation)
+        let simulation = self.simulation_matrix().rows;
+        let mut error = [[0.0f32; 3]; 3];
+        for (i, row) in error.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                let identity = if i == j { 1.0 } else { 0.0 };
+                *value = identity - simulation[i][j];
+            }
+        }
+
+        let mut correction = [[0.0f32; 3]; 3];
+        for (i, row) in correction.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                let identity = if i == j { 1.0 } else { 0.0 };
+                *value = identity + (0..3).map(|k| ERROR_SHIFT[i][k] * error[k][j]).sum::<f32>();
+            }
+        }
+
+        ColorMatrix::from_rgb(correction)
+    }
+}
+
+/// Simulate how the image appears to someone with a color vision
+/// deficiency.
+pub fn simulate_color_blindness<Img: Image + ?Sized>(image: &mut Img, kind: ColorBlindness) {
+    apply_color_matrix(image, &kind.simulation_matrix());
+}
+
+/// Adjust the colors of the image so that they are easier to tell apart
+/// for someone with a color vision deficiency.
+pub fn daltonize<Img: Image + ?Sized>(image: &mut Img, kind: ColorBlindness) {
+    apply_color_matrix(image, &kind.correction_matrix());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, Endianness, Format, GeneralImage};
+
+    #[test]
+    fn fixed_point_matches_float() {
+        let matrix = ColorBlindness::Deuteranopia.simulation_matrix();
+        let original = [0x80u8, 0x20, 0xC0, 0x40, 0xFF, 0x00, 0x7F, 0x10];
+
+        let mut bytes = original;
+        let mut image = GeneralImage::from_buffer(2, 1, Format::ARGB32, &mut bytes[..]);
+        apply_color_matrix(&mut image, &matrix);
+        drop(image);
+
+        let codec = Codec::new(Format::ARGB32, crate::Endianness::NATIVE);
+        for i in 0..2 {
+            let expected = matrix.transform(codec.read(&original, i));
+            let actual = codec.read(&bytes, i);
+            for (e, a) in expected.iter().zip(actual.iter()) {
+                let e = crate::codec::clamp_unit(*e);
+                assert!((e - a).abs() <= 1.0 / 255.0, "{} vs {}", e, a);
+            }
+        }
+    }
+
+    #[test]
+    fn then_composes() {
+        let simulation = ColorBlindness::Protanopia.simulation_matrix();
+        let composed = ColorMatrix::IDENTITY.then(simulation);
+        assert_eq!(composed, simulation);
+    }
+
+    /// Apply `f` to an opaque ARGB32 pixel and return its color bytes.
+    fn filtered(color: [u8; 3], f: impl FnOnce(&mut GeneralImage<&mut [u8]>)) -> [u8; 3] {
+        let mut bytes = [0xFF, color[0], color[1], color[2]];
+        let mut image = Builder::from_buffer(1, 1, Format::ARGB32, &mut bytes[..])
+            .with_endianness(Endianness::Little)
+            .finish();
+        f(&mut image);
+        drop(image);
+        assert_eq!(bytes[0], 0xFF);
+        [bytes[1], bytes[2], bytes[3]]
+    }
+
+    #[test]
+    fn simulate_red() {
+        // red with the first row of each matrix scaled to a byte, and
+        // negative results clamped to zero
+        let red = |kind| filtered([0xFF, 0, 0], |image| simulate_color_blindness(image, kind));
+        assert_eq!(red(ColorBlindness::Protanopia), [39, 29, 0]);
+        assert_eq!(red(ColorBlindness::Deuteranopia), [94, 71, 0]);
+        assert_eq!(red(ColorBlindness::Tritanopia), [255, 0, 1]);
+    }
+
+    #[test]
+    fn deuteranopia_correction() {
+        let expected = [
+            [1.0, 0.0, 0.0],
+            [0.162_790, 0.725_047, 0.112_165],
+            [0.454_695, -0.645_392, 1.190_697],
+        ];
+        let rows = ColorBlindness::Deuteranopia.correction_matrix().rows();
+        for (row, expected) in rows.iter().zip(expected.iter()) {
+            for (value, expected) in row.iter().zip(expected.iter()) {
+                assert!((value - expected).abs() < 1e-5, "{:?}", rows);
+            }
+            assert_eq!(row[3..], [0.0, 0.0]);
+        }
+        assert_eq!(rows[3], [0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn daltonize_colors() {
+        let deuteranopia = |color| {
+            filtered(color, |image| {
+                daltonize(image, ColorBlindness::Deuteranopia)
+            })
+        };
+
+        // the lost red is shifted into green and blue
+        assert_eq!(deuteranopia([0xFF, 0, 0]), [0xFF, 42, 116]);
+
+        // grays are seen the same either way, so they are left alone
+        assert_eq!(deuteranopia([0x80, 0x80, 0x80]), [0x80, 0x80, 0x80]);
+    }
+}
@@ -0,0 +1,225 @@
+// BSL 1.0 License
+
+//! Bulk operations over entire images.
+//!
+//! These operations work on batches of scanline bytes rather than on
+//! individual `Pixel`s, and are much faster than calling `pixel()` and
+//! `set_pixel()` for every pixel of an image.
+//!
+//! Operations that need a buffer allocate it by default. Each of them
+//! also has an `_into` variant that takes the buffers from the caller
+//! instead, so that it can be used without the `alloc` feature or in
+//! loops that can't afford to allocate.
+
+use crate::{divide_rounding_up, Format, Image};
+use core::cmp;
+
+mod adjust;
+pub use adjust::{desaturate, desaturate_with_policy, fade, invert, tint, tint_with_policy};
+
+mod arithmetic;
+pub use arithmetic::ArithmeticPolicy;
+
+mod binary;
+pub use binary::{abs_diff, accumulate, accumulate_with_policy, per_pixel_max, per_pixel_min};
+
+mod bitplanes;
+#[cfg(feature = "alloc")]
+pub use bitplanes::to_bitplanes;
+pub use bitplanes::{from_bitplanes, to_bitplanes_into};
+
+mod blend;
+pub(crate) use blend::porter_duff;
+#[cfg(feature = "alloc")]
+pub(crate) use blend::source_over;
+pub use blend::{blend_row, blend_row_with_policy, BlendOp};
+
+mod blit;
+pub use blit::{blit_bits, blit_bits_with_hints};
+
+mod chunking;
+#[cfg(feature = "alloc")]
+pub(crate) use chunking::bands;
+pub use chunking::ChunkingHints;
+
+mod convert;
+pub(crate) use convert::convert_row;
+pub use convert::{convert, convert_into, convert_with_hints, roundtrip_lossless};
+
+mod compare;
+pub use compare::{images_equal, ImagesEqualOptions};
+
+mod copy_task;
+pub use copy_task::CopyTask;
+
+mod demosaic;
+#[cfg(feature = "alloc")]
+pub use demosaic::demosaic_nearest;
+pub use demosaic::{demosaic_nearest_into, demosaic_scratch_len, CfaPattern};
+
+mod diff;
+#[cfg(feature = "alloc")]
+pub use diff::diff_image;
+pub use diff::{diff_image_into, diff_scratch_len};
+
+mod dither;
+pub use dither::ordered_dither;
+
+mod edge;
+#[cfg(feature = "alloc")]
+pub use edge::edge_detect;
+pub use edge::{edge_detect_into, edge_scratch_len};
+
+mod eink;
+#[cfg(feature = "alloc")]
+pub use eink::eink_dither;
+pub use eink::{eink_dither_into, eink_scratch_len, EinkOptions};
+
+mod hash;
+#[cfg(feature = "alloc")]
+pub use hash::row_hashes;
+pub use hash::row_hashes_into;
+
+mod matrix;
+pub use matrix::{
+    apply_color_matrix, apply_color_matrix_with_policy, daltonize, simulate_color_blindness,
+    ColorBlindness, ColorMatrix,
+};
+
+mod planarize;
+#[cfg(feature = "alloc")]
+pub use planarize::{from_planar_bytes, to_planar_bytes};
+pub use planarize::{from_planar_bytes_into, planar_len, to_planar_bytes_into};
+
+mod projection;
+#[cfg(feature = "alloc")]
+pub use projection::{column_sums, row_sums};
+pub use projection::{column_sums_into, row_sums_into};
+
+mod swizzle;
+pub use swizzle::swizzle_row;
+
+mod search;
+pub use search::{count_pixels_matching, find_pixel, read_id_at};
+
+#[cfg(all(feature = "std", rustc_1_63))]
+mod tiled;
+#[cfg(all(feature = "std", rustc_1_63))]
+pub use tiled::convert_tiled;
+
+mod zip;
+pub use zip::zip_scanlines;
+
+/// The number of bytes processed at once by the bulk operations.
+///
+/// This is a multiple of every supported pixel size that is at least
+/// one byte, so that batches never split a pixel.
+pub(crate) const CHUNK_BYTES: usize = 960;
+
+/// Call `f` on every batch of pixels in the image, and then write the
+/// batch back into the image.
+///
+/// `f` receives the bytes of the batch as well as the number of pixels
+/// contained within them.
+pub(crate) fn for_each_chunk_mut<Img: Image + ?Sized>(
+    image: &mut Img,
+    mut f: impl FnMut(&mut [u8], usize),
+) {
+    let bpp = image.format().bpp() as usize;
+    let (width, height) = image.dimensions();
+    let pixels_per_chunk = CHUNK_BYTES * 8 / bpp;
+    let mut chunk = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let len = divide_rounding_up(count * bpp, 8);
+
+            let read = image.scanline(x, y, &mut chunk[..len]);
+            f(&mut chunk[..read], cmp::min(count, read * 8 / bpp));
+            image.set_scanline(x, y, &chunk[..read]);
+
+            x += count;
+        }
+    }
+}
+
+/// Call `f` on every batch of pixels in the image, along with the
+/// coordinates of the first pixel and the number of pixels in the
+/// batch.
+///
+/// Stops as soon as `f` returns `false`.
+pub(crate) fn for_each_chunk<Img: Image + ?Sized>(
+    image: &Img,
+    mut f: impl FnMut(usize, usize, &[u8], usize) -> bool,
+) {
+    let bpp = image.format().bpp() as usize;
+    let (width, height) = image.dimensions();
+    let pixels_per_chunk = CHUNK_BYTES * 8 / bpp;
+    let mut chunk = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let len = divide_rounding_up(count * bpp, 8);
+
+            let read = image.scanline(x, y, &mut chunk[..len]);
+            if !f(x, y, &chunk[..read], cmp::min(count, read * 8 / bpp)) {
+                return;
+            }
+
+            x += count;
+        }
+    }
+}
+
+/// How a scanline call maps onto pixels of the row.
+pub(crate) struct Span {
+    /// The index of pixel `x` within the first byte of the buffer.
+    pub(crate) skip: usize,
+    /// The number of pixels to transfer.
+    pub(crate) count: usize,
+}
+
+impl Span {
+    /// The pixels that a scanline buffer of `len` bytes holds from `x` on,
+    /// in a row of `width` pixels.
+    pub(crate) fn new(format: Format, x: usize, width: usize, len: usize) -> Self {
+        let bpp = format.bpp() as usize;
+        let skip = (x * bpp % 8) / bpp;
+        let available = (len * 8 / bpp).saturating_sub(skip);
+        Span {
+            skip,
+            count: cmp::min(available, width.saturating_sub(x)),
+        }
+    }
+
+    /// The number of bytes the span takes up in the given format.
+    pub(crate) fn bytes(&self, format: Format) -> usize {
+        divide_rounding_up((self.skip + self.count) * format.bpp() as usize, 8)
+    }
+}
+
+/// The number of pixels before `x` that have to be read and written
+/// along with it, and the number of bytes that hold them along with the
+/// `count` pixels from `x` on.
+///
+/// Sub-byte pixels start at the beginning of their byte, and YCbCr
+/// pixels at the beginning of the pair that shares their chroma.
+pub(crate) fn unit_span(format: Format, x: usize, count: usize) -> (usize, usize) {
+    let bpp = format.bpp() as usize;
+    if is_ycbcr(format) {
+        let skip = x % 2;
+        return (skip, (skip + count + 1) / 2 * 2 * bpp / 8);
+    }
+
+    let skip = x * bpp % 8 / bpp;
+    (skip, divide_rounding_up((skip + count) * bpp, 8))
+}
+
+/// Whether pairs of pixels of the format share their chroma.
+pub(crate) fn is_ycbcr(format: Format) -> bool {
+    format.color_type().ycbcr_matrix().is_some()
+}
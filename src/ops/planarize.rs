@@ -0,0 +1,177 @@
+// BSL 1.0 License
+
+use crate::{divide_rounding_up, Image};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Separate the bytes of an image into planes.
+///
+/// For an image with `n` bytes per pixel, the result contains `n`
+/// planes, one after the other. The `k`th plane holds the `k`th byte of
+/// every pixel, in row-major order. Row padding is not included. Images
+/// whose pixels are smaller than a byte are stored as a single plane
+/// containing the packed rows.
+///
+/// Neighboring pixels tend to have similar values in each byte, so
+/// general-purpose compressors such as LZ4 or zstd do a much better job
+/// on the planar bytes than on the interleaved ones. The original image
+/// can be restored with [`from_planar_bytes`].
+///
+/// [`from_planar_bytes`]: crate::from_planar_bytes
+#[cfg(feature = "alloc")]
+pub fn to_planar_bytes<Img: Image + ?Sized>(image: &Img) -> Vec<u8> {
+    let (row_len, _) = geometry(image);
+    let mut output = vec![0u8; planar_len(image)];
+    let mut row = vec![0u8; row_len];
+    to_planar_bytes_into(image, &mut output, &mut row);
+    output
+}
+
+/// Like [`to_planar_bytes`], but writing the planes into `output`.
+///
+/// `output` must be exactly [`planar_len`] bytes long, and `scratch_row`
+/// must be able to hold a row of the image without padding. Returns
+/// `false` without writing anything if either buffer is the wrong size.
+///
+/// [`to_planar_bytes`]: crate::to_planar_bytes
+/// [`planar_len`]: crate::planar_len
+pub fn to_planar_bytes_into<Img: Image + ?Sized>(
+    image: &Img,
+    output: &mut [u8],
+    scratch_row: &mut [u8],
+) -> bool {
+    let (width, height) = image.dimensions();
+    let (row_len, planes) = geometry(image);
+    let plane_len = row_len / planes * height;
+
+    if output.len() != plane_len * planes || scratch_row.len() < row_len {
+        return false;
+    }
+
+    let row = &mut scratch_row[..row_len];
+
+    for y in 0..height {
+        image.scanline(0, y, row);
+        let row_start = y * width;
+
+        if planes == 1 {
+            output[y * row_len..(y + 1) * row_len].copy_from_slice(row);
+            continue;
+        }
+
+        for (x, pixel) in row.chunks_exact(planes).enumerate() {
+            for (plane, &byte) in pixel.iter().enumerate() {
+                output[plane * plane_len + row_start + x] = byte;
+            }
+        }
+    }
+
+    true
+}
+
+/// Restore an image from the output of [`to_planar_bytes`].
+///
+/// `image` must have the same dimensions and format as the image that
+/// the planes were created from. Returns `false` without modifying the
+/// image if `planar` is not the right length.
+///
+/// [`to_planar_bytes`]: crate::to_planar_bytes
+#[cfg(feature = "alloc")]
+pub fn from_planar_bytes<Img: Image + ?Sized>(planar: &[u8], image: &mut Img) -> bool {
+    let (row_len, _) = geometry(image);
+    from_planar_bytes_into(planar, image, &mut vec![0u8; row_len])
+}
+
+/// Like [`from_planar_bytes`], but using `scratch_row` to rebuild each
+/// row of the image.
+///
+/// `scratch_row` must be able to hold a row of the image without padding.
+/// Returns `false` without modifying the image if either buffer is the
+/// wrong size.
+///
+/// [`from_planar_bytes`]: crate::from_planar_bytes
+pub fn from_planar_bytes_into<Img: Image + ?Sized>(
+    planar: &[u8],
+    image: &mut Img,
+    scratch_row: &mut [u8],
+) -> bool {
+    let (width, height) = image.dimensions();
+    let (row_len, planes) = geometry(image);
+    let plane_len = row_len / planes * height;
+
+    if planar.len() != plane_len * planes || scratch_row.len() < row_len {
+        return false;
+    }
+
+    let row = &mut scratch_row[..row_len];
+
+    for y in 0..height {
+        let row_start = y * width;
+
+        if planes == 1 {
+            row.copy_from_slice(&planar[y * row_len..(y + 1) * row_len]);
+        } else {
+            for (x, pixel) in row.chunks_exact_mut(planes).enumerate() {
+                for (plane, byte) in pixel.iter_mut().enumerate() {
+                    *byte = planar[plane * plane_len + row_start + x];
+                }
+            }
+        }
+
+        image.set_scanline(0, y, row);
+    }
+
+    true
+}
+
+/// The number of bytes needed to hold the planes of an image.
+pub fn planar_len<Img: Image + ?Sized>(image: &Img) -> usize {
+    let (row_len, _) = geometry(image);
+    row_len * image.height()
+}
+
+/// The number of bytes in a row without padding, and the number of
+/// planes to split it into.
+fn geometry<Img: Image + ?Sized>(image: &Img) -> (usize, usize) {
+    let format = image.format();
+    let row_len = divide_rounding_up(image.width() * format.bpp() as usize, 8);
+    let planes = if format.subbyte() {
+        1
+    } else {
+        format.bytes() as usize
+    };
+
+    (row_len, planes)
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{Format, GeneralImage};
+
+    #[test]
+    fn roundtrip() {
+        let bytes: Vec<u8> = (0..3 * 2 * 3).collect();
+        let image = GeneralImage::from_buffer(3, 2, Format::RGB24, bytes.clone());
+
+        let planar = to_planar_bytes(&image);
+        assert_eq!(
+            planar,
+            [0, 3, 6, 9, 12, 15, 1, 4, 7, 10, 13, 16, 2, 5, 8, 11, 14, 17]
+        );
+
+        let mut restored = GeneralImage::from_buffer(3, 2, Format::RGB24, vec![0u8; 18]);
+        assert!(from_planar_bytes(&planar, &mut restored));
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(restored.pixel(x, y), image.pixel(x, y));
+            }
+        }
+
+        // the row is 9 bytes long
+        let mut output = [0u8; 18];
+        assert!(!to_planar_bytes_into(&image, &mut output, &mut [0u8; 8]));
+        assert!(to_planar_bytes_into(&image, &mut output, &mut [0u8; 9]));
+        assert_eq!(output[..], planar[..]);
+    }
+}
@@ -0,0 +1,148 @@
+// BSL 1.0 License
+
+use super::for_each_chunk;
+use crate::{
+    codec::{self, Codec},
+    Channel, Image,
+};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// The total of one channel over every row of the image.
+///
+/// The sums are of the raw values of the channel, so for an 8-bit
+/// channel every pixel adds between 0 and 255. Float channels are
+/// counted as 16-bit values, and channels that the format does not
+/// have sum to zero.
+///
+/// A row that sums to zero is blank, which makes this a cheap way to
+/// trim empty rows off of a screenshot.
+#[cfg(feature = "alloc")]
+pub fn row_sums<Img: Image + ?Sized>(image: &Img, channel: Channel) -> Vec<u64> {
+    let mut sums = vec![0; image.height()];
+    row_sums_into(image, channel, &mut sums);
+    sums
+}
+
+/// Like [`row_sums`], but writing the sums into `sums`.
+///
+/// # Panics
+///
+/// Panics if `sums` is not as long as the image is tall.
+///
+/// [`row_sums`]: crate::row_sums
+pub fn row_sums_into<Img: Image + ?Sized>(image: &Img, channel: Channel, sums: &mut [u64]) {
+    assert_eq!(sums.len(), image.height(), "There must be one sum per row");
+    sums.iter_mut().for_each(|sum| *sum = 0);
+    project(image, channel, |_, y, value| sums[y] += value);
+}
+
+/// The total of one channel over every column of the image.
+///
+/// See [`row_sums`] for how the values are counted. Columns that sum to
+/// zero can be used to find gutters and scrollbars.
+///
+/// [`row_sums`]: crate::row_sums
+#[cfg(feature = "alloc")]
+pub fn column_sums<Img: Image + ?Sized>(image: &Img, channel: Channel) -> Vec<u64> {
+    let mut sums = vec![0; image.width()];
+    column_sums_into(image, channel, &mut sums);
+    sums
+}
+
+/// Like [`column_sums`], but writing the sums into `sums`.
+///
+/// # Panics
+///
+/// Panics if `sums` is not as long as the image is wide.
+///
+/// [`column_sums`]: crate::column_sums
+pub fn column_sums_into<Img: Image + ?Sized>(image: &Img, channel: Channel, sums: &mut [u64]) {
+    assert_eq!(
+        sums.len(),
+        image.width(),
+        "There must be one sum per column"
+    );
+    sums.iter_mut().for_each(|sum| *sum = 0);
+    project(image, channel, |x, _, value| sums[x] += value);
+}
+
+/// Call `f` with the coordinates and the raw value of the channel for
+/// every pixel in the image.
+fn project<Img: Image + ?Sized>(
+    image: &Img,
+    channel: Channel,
+    mut f: impl FnMut(usize, usize, u64),
+) {
+    let codec = Codec::new(image.format(), image.endianness()).with_bit_order(image.bit_order());
+    if codec.channel(channel).is_none() {
+        return;
+    }
+
+    let index = channel as usize;
+    let float = codec.format().involves_float();
+
+    for_each_chunk(image, |x, y, chunk, count| {
+        for i in 0..count {
+            let value = if float {
+                codec::quantize(codec.read(chunk, i)[index], 16)
+            } else {
+                codec.read_raw(chunk, i)[index]
+            };
+
+            f(x + i, y, value);
+        }
+
+        true
+    });
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{Format, GeneralImage};
+
+    #[test]
+    fn projections() {
+        let bits = [0x00u8, 0x00, 0x00, 0x03, 0x00, 0x01];
+        let image = GeneralImage::from_buffer(4, 3, Format::A4, bits);
+
+        assert_eq!(row_sums(&image, Channel::Alpha), [0, 3, 1]);
+        assert_eq!(column_sums(&image, Channel::Alpha), [0, 0, 4, 0]);
+        assert_eq!(row_sums(&image, Channel::Red), [0, 0, 0]);
+
+        let image = crate::Builder::from_buffer(8, 1, Format::A1, [0b1000_0000u8])
+            .with_bit_order(crate::BitOrder::MsbFirst)
+            .finish();
+        assert_eq!(
+            column_sums(&image, Channel::Alpha),
+            [1, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn caller_sums() {
+        let image = GeneralImage::from_buffer(2, 2, Format::A8, [1u8, 2, 3, 4]);
+
+        // previous contents are overwritten
+        let mut sums = [100u64; 2];
+        row_sums_into(&image, Channel::Alpha, &mut sums);
+        assert_eq!(sums, [3, 7]);
+        column_sums_into(&image, Channel::Alpha, &mut sums);
+        assert_eq!(sums, [4, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "There must be one sum per row")]
+    fn caller_sums_wrong_length() {
+        let image = GeneralImage::from_buffer(2, 2, Format::A8, [0u8; 4]);
+        row_sums_into(&image, Channel::Alpha, &mut [0u64; 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "There must be one sum per column")]
+    fn caller_column_sums_wrong_length() {
+        let image = GeneralImage::from_buffer(3, 2, Format::A8, [0u8; 6]);
+        column_sums_into(&image, Channel::Alpha, &mut [0u64; 2]);
+    }
+}
@@ -0,0 +1,208 @@
+// BSL 1.0 License
+
+use super::for_each_chunk;
+use crate::{codec, Image, Pixel, MAX_BYTES_PER_PIXEL};
+
+/// Find the first pixel in the image that is equal to `pixel`.
+///
+/// Pixels are searched row by row, from left to right. `pixel` is first
+/// converted to the image's format, and then rows are searched for its
+/// bytes directly without decoding them, which makes this cheap enough to
+/// hit-test color-keyed pick buffers.
+///
+/// Returns the coordinates of the pixel, or `None` if no pixel matches.
+pub fn find_pixel<Img: Image + ?Sized>(image: &Img, pixel: Pixel) -> Option<(usize, usize)> {
+    let (format, endianness) = (image.format(), image.endianness());
+    let mut needle = [0u8; MAX_BYTES_PER_PIXEL];
+    pixel
+        .into_new_format(endianness, format)
+        .insert(&mut needle, 0);
+
+    let bpp = format.bpp() as usize;
+    let bit_order = image.bit_order();
+    let mut found = None;
+
+    if format.subbyte() {
+        let mask = codec::low_bits(format.bpp()) as u8;
+        let needle = needle[0] & mask;
+        let per_byte = 8 / bpp;
+        // a byte where every pixel matches, for skipping whole bytes
+        let full = (0..per_byte).fold(0u8, |byte, i| byte | (needle << (i * bpp)));
+
+        for_each_chunk(image, |x, y, chunk, count| {
+            let position = chunk
+                .iter()
+                .enumerate()
+                .filter(|&(_, &byte)| byte == full || contains_bits(byte, needle, bpp))
+                .flat_map(|(index, &byte)| {
+                    (0..per_byte)
+                        .filter(move |&i| {
+                            (byte >> bit_order.index_in_byte(bpp as u8, i)) & mask == needle
+                        })
+                        .map(move |i| index * per_byte + i)
+                })
+                .find(|&i| i < count);
+
+            found = position.map(|i| (x + i, y));
+            found.is_none()
+        });
+    } else {
+        let needle = &needle[..format.bytes() as usize];
+
+        for_each_chunk(image, |x, y, chunk, _| {
+            found = chunk
+                .chunks_exact(needle.len())
+                .position(|candidate| candidate == needle)
+                .map(|i| (x + i, y));
+            found.is_none()
+        });
+    }
+
+    found
+}
+
+/// Count the pixels in the image that satisfy `predicate`.
+pub fn count_pixels_matching<Img: Image + ?Sized>(
+    image: &Img,
+    mut predicate: impl FnMut(Pixel) -> bool,
+) -> usize {
+    let (format, endianness) = (image.format(), image.endianness());
+    let bpp = format.bpp() as usize;
+    let bit_order = image.bit_order();
+    let mut total = 0;
+
+    for_each_chunk(image, |_, _, chunk, count| {
+        total += (0..count)
+            .filter(|&i| {
+                let index = bit_order.index_in_byte(bpp as u8, i);
+                let pixel = Pixel::with_index(&chunk[i * bpp / 8..], index, endianness, format);
+                predicate(pixel)
+            })
+            .count();
+        true
+    });
+
+    total
+}
+
+/// Read the object ID stored at the given location of a picking buffer.
+///
+/// This is shorthand for calling [`Pixel::to_id`] on the pixel. IDs are
+/// written into the buffer with [`Pixel::from_id`].
+///
+/// [`Pixel::to_id`]: crate::Pixel::to_id
+/// [`Pixel::from_id`]: crate::Pixel::from_id
+pub fn read_id_at<Img: Image + ?Sized>(image: &Img, x: usize, y: usize) -> u32 {
+    image.pixel(x, y).to_id()
+}
+
+/// Could any of the sub-byte pixels in `byte` be `needle`?
+///
+/// This is a cheap way to skip bytes that certainly don't contain the
+/// needle.
+fn contains_bits(byte: u8, needle: u8, bpp: usize) -> bool {
+    match bpp {
+        1 if needle == 0 => byte != 0xFF,
+        1 => byte != 0,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitOrder, Builder, Endianness, Format, GeneralImage, Rgba};
+
+    #[test]
+    fn finds_pick_ids() {
+        let mut buffer = [0u32; 12];
+        buffer[7] = 0x0012_3456;
+        let image = GeneralImage::from_buffer(
+            4,
+            3,
+            Format::XRGB32,
+            bytemuck::cast_slice_mut(&mut buffer[..]),
+        );
+
+        let pixel = image.pixel(3, 1);
+        assert_eq!(find_pixel(&image, pixel), Some((3, 1)));
+        assert_eq!(count_pixels_matching(&image, |p| p == pixel), 1);
+
+        let black = Rgba {
+            alpha: 0xFFFF,
+            ..Rgba::default()
+        };
+        let black = Pixel::from_rgba(black, Format::XRGB32, Endianness::NATIVE);
+        assert_eq!(find_pixel(&image, black), Some((0, 0)));
+    }
+
+    #[test]
+    fn ids_roundtrip() {
+        let ids = [0, 1, 0x00FF_FFFF, 0x8000_0001, 0xDEAD_BEEF, u32::MAX];
+        let mut buffer = [0u8; 6 * 16];
+
+        for &format in [Format::XRGB32, Format::ARGB32, Format::ARGB_F32].iter() {
+            for &endianness in [Endianness::Little, Endianness::Big].iter() {
+                let mut image = Builder::from_buffer(6, 1, format, &mut buffer[..])
+                    .with_endianness(endianness)
+                    .finish();
+
+                for (x, &id) in ids.iter().enumerate() {
+                    image.set_pixel(x, 0, Pixel::from_id(id, format));
+                }
+                for (x, &id) in ids.iter().enumerate() {
+                    assert_eq!(
+                        read_id_at(&image, x, 0),
+                        id,
+                        "{:?} {:?}",
+                        format,
+                        endianness
+                    );
+                }
+                assert_eq!(
+                    find_pixel(&image, Pixel::from_id(0xDEAD_BEEF, format)),
+                    Some((4, 0))
+                );
+            }
+        }
+
+        let image = GeneralImage::from_buffer(4, 1, Format::A4, [0x21u8, 0x43]);
+        assert_eq!(read_id_at(&image, 3, 0), 4);
+    }
+
+    #[test]
+    fn finds_subbyte_pixels() {
+        let bits = [0x00u8, 0x00, 0b0010_0000];
+        let image = GeneralImage::from_buffer(8, 3, Format::A1, bits);
+        let set = image.pixel(5, 2);
+
+        assert_eq!(find_pixel(&image, set), Some((5, 2)));
+        assert_eq!(count_pixels_matching(&image, |p| p == set), 1);
+        assert_eq!(count_pixels_matching(&image, |p| p != set), 23);
+    }
+
+    #[test]
+    fn finds_msb_first_pixels() {
+        let image = Builder::from_buffer(8, 2, Format::A1, [0x00u8, 0b0010_0000])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let set = image.pixel(2, 1);
+        assert_eq!(set.raw_u32(), 1);
+
+        assert_eq!(find_pixel(&image, set), Some((2, 1)));
+        assert_eq!(count_pixels_matching(&image, |p| p == set), 1);
+
+        // the first nibble is the high one, and the low nibble of the last
+        // byte is past the edge of the image
+        let image = Builder::from_buffer(3, 1, Format::A4, [0x12u8, 0x30])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let pixel = |raw: u8| Pixel::with_index(&[raw], 0, Endianness::NATIVE, Format::A4);
+        assert_eq!(find_pixel(&image, pixel(1)), Some((0, 0)));
+        assert_eq!(find_pixel(&image, pixel(2)), Some((1, 0)));
+        assert_eq!(find_pixel(&image, pixel(3)), Some((2, 0)));
+        assert_eq!(find_pixel(&image, pixel(0)), None);
+        assert_eq!(count_pixels_matching(&image, |p| p == pixel(3)), 1);
+        assert_eq!(count_pixels_matching(&image, |p| p == pixel(0)), 0);
+    }
+}
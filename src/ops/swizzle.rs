@@ -0,0 +1,124 @@
+// BSL 1.0 License
+
+use crate::{Channel, ColorType};
+
+/// Reorder the channels of a row of 24 or 32-bit pixels in place, by
+/// permuting their bytes.
+///
+/// This is the fast path for formats where every channel is a byte, such
+/// as turning [`RGBA32`] into [`BGRA32`] for an API that wants the other
+/// order. Unlike [`convert`], it never allocates and never decodes a
+/// pixel, but it doesn't look at bit depths either: every channel is
+/// assumed to be eight bits, including alpha at 32 bits per pixel. At 24
+/// bits per pixel, the alpha channel of both color types is left out.
+///
+/// Pixels are read in little-endian order, with the first channel of the
+/// color type in the first byte. Big-endian 32-bit pixels are the same as
+/// little-endian pixels of the reversed color type, such as [`Bgra`] for
+/// [`Argb`]. Bytes after the last whole pixel are left alone.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{swizzle_row, ColorType};
+///
+/// let mut row = [1, 2, 3, 4, 5, 6, 7, 8];
+/// swizzle_row(&mut row, ColorType::Rgba, ColorType::Bgra, 32);
+/// assert_eq!(row, [3, 2, 1, 4, 7, 6, 5, 8]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `bpp` isn't 24 or 32, or if either color type isn't made up
+/// of red, green, blue and alpha channels.
+///
+/// [`RGBA32`]: crate::Format::RGBA32
+/// [`BGRA32`]: crate::Format::BGRA32
+/// [`convert`]: crate::convert
+/// [`Bgra`]: crate::ColorType::Bgra
+/// [`Argb`]: crate::ColorType::Argb
+pub fn swizzle_row(bytes: &mut [u8], from: ColorType, to: ColorType, bpp: u8) {
+    let len = match bpp {
+        24 => 3,
+        32 => 4,
+        _ => panic!("Only 24 and 32-bit pixels can be swizzled"),
+    };
+    let from = byte_channels(from, len);
+    let to = byte_channels(to, len);
+
+    // the byte of the source pixel that each byte of the result comes from
+    let mut permutation = [0; 4];
+    for (source, channel) in permutation.iter_mut().zip(&to[..len]) {
+        *source = from.iter().position(|c| c == channel).unwrap();
+    }
+    if permutation[..len].iter().enumerate().all(|(i, &j)| i == j) {
+        return;
+    }
+
+    for pixel in bytes.chunks_exact_mut(len) {
+        let mut old = [0u8; 4];
+        old[..len].copy_from_slice(pixel);
+        for (byte, &source) in pixel.iter_mut().zip(&permutation) {
+            *byte = old[source];
+        }
+    }
+}
+
+/// The channel stored in each byte of a pixel of a color type, which is
+/// `len` bytes long.
+fn byte_channels(color_type: ColorType, len: usize) -> [Channel; 4] {
+    let valid = core::matches!(
+        color_type,
+        ColorType::Argb
+            | ColorType::Rgba
+            | ColorType::Abgr
+            | ColorType::Bgra
+            | ColorType::Custom(_)
+    );
+    let mut channels = [Channel::Alpha; 4];
+    let mut count = 0;
+    let mut seen = 0u8;
+    for channel in color_type
+        .channels()
+        .filter(|&channel| len == 4 || channel != Channel::Alpha)
+    {
+        if count < len {
+            channels[count] = channel;
+        }
+        count += 1;
+        seen |= 1 << channel as u8;
+    }
+
+    assert!(
+        valid && count == len && seen.count_ones() as usize == len,
+        "The color type does not have a byte for each channel"
+    );
+    channels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChannelOrder;
+
+    #[test]
+    fn permutations() {
+        let mut row = [1, 2, 3, 4, 5, 6, 7];
+        swizzle_row(&mut row, ColorType::Argb, ColorType::Bgra, 24);
+        assert_eq!(row, [3, 2, 1, 6, 5, 4, 7]);
+
+        let mut row = [1, 2, 3, 4];
+        swizzle_row(&mut row, ColorType::Argb, ColorType::Abgr, 32);
+        assert_eq!(row, [1, 4, 3, 2]);
+        let order =
+            ChannelOrder::new([Channel::Green, Channel::Alpha, Channel::Red, Channel::Blue]);
+        swizzle_row(&mut row, ColorType::Abgr, ColorType::Custom(order), 32);
+        assert_eq!(row, [3, 1, 2, 4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn gray() {
+        swizzle_row(&mut [0; 4], ColorType::Gray, ColorType::Rgba, 32);
+    }
+}
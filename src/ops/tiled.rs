@@ -0,0 +1,212 @@
+// BSL 1.0 License
+
+use super::convert::convert_row;
+use crate::{codec::Codec, divide_rounding_up, Image, OpReport};
+use alloc::vec;
+use core::{
+    cmp,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{sync::mpsc, thread};
+
+/// Convert one image into another using several threads.
+///
+/// This has the same effect as [`convert`], and returns the same
+/// [`OpReport`], but splits the image into
+/// horizontal bands of `tile_height` rows. The bands are converted on
+/// `threads` scoped worker threads, while the calling thread writes the
+/// converted bands into `dst`. This allows very large images to be
+/// converted on all cores without depending on a thread pool.
+///
+/// If `threads` is zero, the number of available cores is used. If
+/// `tile_height` is zero, it is treated as one.
+///
+/// This function requires the `std` feature, as well as Rust 1.63. On
+/// older compilers it is not available.
+///
+/// [`convert`]: crate::convert
+/// [`OpReport`]: crate::OpReport
+#[clippy::msrv = "1.63"]
+pub fn convert_tiled<Src: Image + Sync + ?Sized, Dst: Image + ?Sized>(
+    src: &Src,
+    dst: &mut Dst,
+    tile_height: usize,
+    threads: usize,
+) -> OpReport {
+    let _span = op_span!(
+        "convert_tiled",
+        width = src.width(),
+        height = src.height(),
+        src_format = %src.format(),
+        dst_format = %dst.format(),
+        tile_height,
+        threads,
+    );
+    let src_codec = Codec::new(src.format(), src.endianness())
+        .with_bit_order(src.bit_order())
+        .with_transfer_function(src.transfer_function());
+    let dst_codec = Codec::new(dst.format(), dst.endianness())
+        .with_bit_order(dst.bit_order())
+        .with_transfer_function(dst.transfer_function());
+
+    let width = cmp::min(src.width(), dst.width());
+    let height = cmp::min(src.height(), dst.height());
+    let mut report = OpReport {
+        rows_skipped: src.height() - height,
+        ..OpReport::default()
+    };
+    if width == 0 {
+        report.rows_skipped = src.height();
+        return report;
+    }
+
+    let tile_height = cmp::max(tile_height, 1);
+    let bands = divide_rounding_up(height, tile_height);
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+        threads => threads,
+    };
+    let threads = cmp::min(threads, bands);
+
+    let src_row_len = divide_rounding_up(width * src.format().bpp() as usize, 8);
+    let dst_bpp = dst.format().bpp() as usize;
+    let dst_row_len = divide_rounding_up(width * dst_bpp, 8);
+    // pixels in the last byte of each row that we are not converting
+    let kept = if dst_bpp < 8 {
+        (dst_row_len * 8 - width * dst_bpp) / dst_bpp
+    } else {
+        0
+    };
+    let mut existing = vec![0u8; if kept > 0 { dst_row_len } else { 0 }];
+
+    // workers take the next band from this counter
+    let next_band = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let (next_band, src_codec, dst_codec) = (&next_band, &src_codec, &dst_codec);
+            let palette = src.palette();
+
+            scope.spawn(move || {
+                let mut src_row = vec![0u8; src_row_len];
+
+                loop {
+                    let band = next_band.fetch_add(1, Ordering::Relaxed);
+                    if band >= bands {
+                        break;
+                    }
+
+                    let top = band * tile_height;
+                    let rows = cmp::min(tile_height, height - top);
+                    let mut output = vec![0u8; rows * dst_row_len];
+
+                    for (row, dst_row) in output.chunks_exact_mut(dst_row_len).enumerate() {
+                        src.scanline(0, top + row, &mut src_row);
+                        convert_row(src_codec, &src_row, palette, dst_codec, dst_row, width);
+                    }
+
+                    if sender.send((top, output)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // let the receiver finish once every worker is done
+        drop(sender);
+
+        for (top, mut output) in receiver {
+            for (row, dst_row) in output.chunks_exact_mut(dst_row_len).enumerate() {
+                if kept > 0 {
+                    // preserve the bits of pixels we are not writing to
+                    dst.scanline(0, top + row, &mut existing);
+                    for i in width..width + kept {
+                        dst_codec.copy_pixel(&existing, i, dst_row, i);
+                    }
+                }
+
+                let written = dst.set_scanline(0, top + row, dst_row);
+                report.add_row(written == dst_row_len && width == src.width());
+            }
+        }
+    });
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert_tiled;
+    use crate::{
+        convert, BitOrder, Builder, Format, GeneralImage, Image, IndexedImage, Palette, Rgba,
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn matches_convert() {
+        let src_bytes: Vec<u8> = (0..17 * 13 * 4).map(|i| (i * 7) as u8).collect();
+        let src = GeneralImage::from_buffer(17, 13, Format::ARGB32, src_bytes);
+
+        let mut expected =
+            GeneralImage::from_buffer(17, 13, Format::BGR24, alloc::vec![0u8; 17 * 13 * 3]);
+        convert(&src, &mut expected);
+        let mut actual =
+            GeneralImage::from_buffer(17, 13, Format::BGR24, alloc::vec![0u8; 17 * 13 * 3]);
+        convert_tiled(&src, &mut actual, 4, 3);
+
+        for y in 0..13 {
+            for x in 0..17 {
+                assert_eq!(
+                    crate::Image::pixel(&expected, x, y),
+                    crate::Image::pixel(&actual, x, y)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn msb_first() {
+        let src = Builder::from_buffer(8, 1, Format::A1, [0b1000_0000u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let mut dst = GeneralImage::from_buffer(8, 1, Format::A8, [0u8; 8]);
+        convert_tiled(&src, &mut dst, 1, 1);
+        assert_eq!(dst.pixel(0, 0).to_rgba().alpha, 0xFFFF);
+        assert_eq!(dst.pixel(7, 0).to_rgba().alpha, 0);
+    }
+
+    #[test]
+    fn indexed_source() {
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+        let indices = GeneralImage::from_buffer(2, 2, Format::INDEXED4, [0x10u8, 0x01]);
+        let src = IndexedImage::new(indices, palette);
+
+        let mut dst = GeneralImage::from_buffer(2, 2, Format::ARGB32, [0u8; 16]);
+        convert_tiled(&src, &mut dst, 1, 2);
+        assert_eq!(dst.pixel(0, 0).to_rgba(), Rgba::default());
+        assert_eq!(dst.pixel(1, 0).to_rgba(), red);
+        assert_eq!(dst.pixel(0, 1).to_rgba(), red);
+        assert_eq!(dst.pixel(1, 1).to_rgba(), Rgba::default());
+    }
+
+    #[test]
+    fn keeps_pixels_past_the_source() {
+        let src = GeneralImage::from_buffer(3, 2, Format::A8, [0u8; 6]);
+        let mut dst = GeneralImage::from_buffer(8, 2, Format::A1, [0xFFu8; 2]);
+        convert_tiled(&src, &mut dst, 1, 2);
+        for y in 0..2 {
+            for x in 0..8 {
+                let expected = if x < 3 { 0 } else { 0xFFFF };
+                assert_eq!(dst.pixel(x, y).to_rgba().alpha, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// BSL 1.0 License
+
+use super::CHUNK_BYTES;
+use crate::{divide_rounding_up, Image};
+use core::cmp;
+
+/// Walk over the scanlines of two images of the same size at once.
+///
+/// `f` is called with the coordinates of the first pixel of a batch, the
+/// bytes of the batch in `a`, the bytes of the same pixels in `b`, and
+/// the number of pixels in the batch. The bytes of each image are in its
+/// own format and endianness. Once `f` returns, the batch is written back
+/// into `b`.
+///
+/// Batches never start in the middle of a byte, so sub-byte formats can be
+/// indexed from the start of the batch. This is the building block for
+/// operations that combine two images.
+///
+/// # Panics
+///
+/// Panics if the images do not have the same dimensions.
+pub fn zip_scanlines<A: Image + ?Sized, B: Image + ?Sized>(
+    a: &A,
+    b: &mut B,
+    mut f: impl FnMut(usize, usize, &[u8], &mut [u8], usize),
+) {
+    assert_eq!(
+        a.dimensions(),
+        b.dimensions(),
+        "Both images must have the same dimensions"
+    );
+
+    let a_bpp = a.format().bpp() as usize;
+    let b_bpp = b.format().bpp() as usize;
+    let (width, height) = a.dimensions();
+
+    // keep batches to whole bytes in both images
+    let pixels_per_chunk = CHUNK_BYTES * 8 / cmp::max(a_bpp, b_bpp) / 8 * 8;
+    let mut a_chunk = [0u8; CHUNK_BYTES];
+    let mut b_chunk = [0u8; CHUNK_BYTES];
+
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            let count = cmp::min(pixels_per_chunk, width - x);
+            let a_len = divide_rounding_up(count * a_bpp, 8);
+            let b_len = divide_rounding_up(count * b_bpp, 8);
+
+            a.scanline(x, y, &mut a_chunk[..a_len]);
+            b.scanline(x, y, &mut b_chunk[..b_len]);
+            f(x, y, &a_chunk[..a_len], &mut b_chunk[..b_len], count);
+            b.set_scanline(x, y, &b_chunk[..b_len]);
+
+            x += count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, GeneralImage};
+
+    #[test]
+    #[should_panic]
+    fn mismatched_dimensions() {
+        let a = GeneralImage::from_buffer(2, 1, Format::A8, [0u8; 2]);
+        let mut b = GeneralImage::from_buffer(1, 2, Format::A8, [0u8; 2]);
+        zip_scanlines(&a, &mut b, |_, _, _, _, _| {});
+    }
+
+    #[test]
+    fn mixed_formats() {
+        let a = GeneralImage::from_buffer(300, 2, Format::ARGB_F32, [0u8; 300 * 2 * 16]);
+        let mut b = GeneralImage::from_buffer(300, 2, Format::A1, [0u8; 38 * 2]);
+        let mut seen = 0;
+
+        zip_scanlines(&a, &mut b, |x, _, a_bytes, b_bytes, count| {
+            assert_eq!(x % 8, 0);
+            assert_eq!(a_bytes.len(), count * 16);
+            b_bytes.iter_mut().for_each(|byte| *byte = 0xFF);
+            seen += count;
+        });
+
+        assert_eq!(seen, 600);
+        assert_eq!(b.pixel(299, 1), b.pixel(0, 0));
+    }
+}
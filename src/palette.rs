@@ -0,0 +1,115 @@
+// BSL 1.0 License
+
+use crate::{codec::Components, Error, Rgba};
+use core::cmp::Ordering;
+use tinyvec::ArrayVec;
+
+/// The colors that the pixels of an [`Indexed`] image refer to.
+///
+/// A palette holds up to 256 colors, which is enough for every index of
+/// [`INDEXED8`]. Index `i` maps to the `i`th color.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Endianness, Format, Palette, Pixel, Rgba};
+///
+/// let white = Rgba { red: 0xFFFF, green: 0xFFFF, blue: 0xFFFF, alpha: 0xFFFF };
+/// let palette = Palette::from_colors(&[Rgba::default(), white]).unwrap();
+///
+/// let pixel = Pixel::new(&[1], Endianness::NATIVE, Format::INDEXED8);
+/// assert_eq!(pixel.index(), Some(1));
+/// assert_eq!(pixel.resolve(&palette), white);
+/// ```
+///
+/// [`Indexed`]: crate::ColorType::Indexed
+/// [`INDEXED8`]: crate::Format::INDEXED8
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Palette {
+    colors: ArrayVec<[Rgba; 256]>,
+}
+
+impl Palette {
+    /// The largest number of colors that a palette can hold.
+    pub const MAX_COLORS: usize = 256;
+
+    /// Create an empty palette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a palette out of a list of colors, or return an error if
+    /// there are more than [`MAX_COLORS`] of them.
+    ///
+    /// [`MAX_COLORS`]: crate::Palette::MAX_COLORS
+    pub fn from_colors(colors: &[Rgba]) -> Result<Self, Error> {
+        if colors.len() > Self::MAX_COLORS {
+            return Err(Error::TooManyColors);
+        }
+
+        let mut palette = Self::new();
+        palette.colors.extend_from_slice(colors);
+        Ok(palette)
+    }
+
+    /// Add a color to the end of the palette, returning its index, or
+    /// `None` if the palette is full.
+    pub fn push(&mut self, color: Rgba) -> Option<usize> {
+        self.colors
+            .try_push(color)
+            .map_or(Some(self.colors.len() - 1), |_| None)
+    }
+
+    /// The color at the given index, if there is one.
+    pub fn get(&self, index: usize) -> Option<Rgba> {
+        self.colors.get(index).copied()
+    }
+
+    /// Replace the color at the given index.
+    ///
+    /// Returns `false` if the index is past the end of the palette.
+    pub fn set(&mut self, index: usize, color: Rgba) -> bool {
+        match self.colors.get_mut(index) {
+            Some(slot) => {
+                *slot = color;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The number of colors in the palette.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Whether the palette has no colors.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// The colors of the palette, in index order.
+    pub fn colors(&self) -> &[Rgba] {
+        &self.colors
+    }
+
+    /// The index of the color closest to the given components, or zero
+    /// if the palette is empty.
+    pub(crate) fn nearest(&self, components: Components) -> usize {
+        let distance = |color: &Rgba| {
+            color
+                .components()
+                .iter()
+                .zip(components.iter())
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f32>()
+        };
+
+        self.colors
+            .iter()
+            .map(distance)
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map_or(0, |(index, _)| index)
+    }
+}
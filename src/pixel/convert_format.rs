@@ -5,8 +5,13 @@ use crate::{Endianness, Format, Pixel};
 /// Convert pixels of one format to another.
 pub(crate) fn convert_to_format(pixel: Pixel, format: Format, endian: Endianness) -> Pixel {
     // if the formats are equal, no need to convert
-    if pixel.format() == format && pixel.endianness() == endian {
-        return pixel;
+    // the value is independent of the endianness, so only the bytes that
+    // it is written as change
+    if pixel.format() == format {
+        return Pixel {
+            endianness: endian,
+            ..pixel
+        };
     }
 
     Pixel::collect_channels(endian, format, pixel.channel_info())
@@ -1,9 +1,16 @@
 // BSL 1.0 License
 
 use crate::{
-    assert_exact_size::AssertExactSize, format::ChannelInfo, Channel, Endianness, Format, Rgba,
+    assert_exact_size::AssertExactSize,
+    codec::{self, Codec, Components},
+    format::ChannelInfo,
+    ops, AlphaMode, BlendOp, Channel, ColorType, Endianness, Error, Format, Palette, Rgba,
+    MAX_BYTES_PER_PIXEL,
+};
+use core::{
+    cmp, fmt,
+    iter::{self, FusedIterator},
 };
-use core::{cmp, fmt, iter::FusedIterator};
 use ordered_float::{NotNan, OrderedFloat};
 use tinyvec::ArrayVec;
 
@@ -32,7 +39,7 @@ pub struct Pixel {
 
 impl fmt::Debug for Pixel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        struct AsHex(u32);
+        struct AsHex(u64);
 
         impl fmt::Debug for AsHex {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -41,7 +48,7 @@ impl fmt::Debug for Pixel {
         }
 
         f.debug_tuple("Pixel")
-            .field(&AsHex(self.raw_u32()))
+            .field(&AsHex(self.raw_u64()))
             .finish()
     }
 }
@@ -59,12 +66,17 @@ impl cmp::PartialEq for Pixel {
             },
         ) = (self.value, other.value)
         {
-            return data1 == data2 && index1 == index2;
+            // identical bits in identical formats are always equal, but
+            // the same bits can mean different colors in different formats
+            if self.format == other.format && data1 == data2 && index1 == index2 {
+                return true;
+            }
         }
 
-        self.components_float()
-            .map(OrderedFloat)
-            .eq(other.components_float().map(OrderedFloat))
+        let (ours, theirs) = (self.components(), other.components());
+        ours.iter()
+            .map(|&x| OrderedFloat(x))
+            .eq(theirs.iter().map(|&x| OrderedFloat(x)))
     }
 }
 
@@ -78,15 +90,16 @@ impl cmp::PartialOrd for Pixel {
 
 impl cmp::Ord for Pixel {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.components_float()
-            .map(OrderedFloat)
-            .cmp(other.components_float().map(OrderedFloat))
+        let (ours, theirs) = (self.components(), other.components());
+        ours.iter()
+            .map(|&x| OrderedFloat(x))
+            .cmp(theirs.iter().map(|&x| OrderedFloat(x)))
     }
 }
 
 impl core::hash::Hash for Pixel {
     fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
-        for flt in self.components_float() {
+        for flt in self.components().iter() {
             state.write_u32(flt.to_bits());
         }
     }
@@ -99,8 +112,12 @@ enum Value {
         ///
         /// It is implied that the data is stored in the correct
         /// endianness.
-        data: u32,
+        data: u64,
         /// For sub-byte channels, the index into the first byte of the pixel.
+        ///
+        /// For YCbCr formats, this is the index of the pixel in its pair,
+        /// and the chroma of the other pixel is kept above the pixel's own
+        /// bits.
         index: u8,
     },
     Float {
@@ -119,20 +136,23 @@ impl Pixel {
     /// first byte the pixel is. It can normally be zero for other
     /// formats.
     pub(crate) fn from_bytes(
-        bytes: [u8; 4],
+        bytes: [u8; 8],
         index: u8,
         endian: Endianness,
         format: Format,
     ) -> Self {
         debug_assert!(!format.involves_float());
 
+        if format.color_type().ycbcr_matrix().is_some() {
+            return Self::from_pair(&bytes[..format.bytes() as usize * 2], index, endian, format);
+        }
+
         // depending on the quantum, make a new value
-        let data = match format.bytes() {
-            1 => bytes[0] as u32,
-            2 => endian.make_u16([bytes[0], bytes[1]]) as u32,
-            3..=4 => endian.make_u32(bytes),
-            bytes => panic!("has {} bytes, expected 1..=4", bytes),
+        let bytes = match format.bytes() {
+            count @ 1..=8 => &bytes[..count as usize],
+            count => panic!("has {} bytes, expected 1..=8", count),
         };
+        let data = endian.make_u64(bytes);
 
         Self {
             format,
@@ -141,12 +161,39 @@ impl Pixel {
         }
     }
 
+    /// Create a new pixel of a YCbCr format from the bytes of the pair of
+    /// pixels that it belongs to, or only its own bytes if it is the last
+    /// pixel of an odd-sized row.
+    fn from_pair(bytes: &[u8], index: u8, endian: Endianness, format: Format) -> Self {
+        let codec = Codec::new(format, endian);
+        let bits = format.green_bits();
+        let data = codec.read_quantum(bytes, index as usize);
+        let other = if bytes.len() >= format.bytes() as usize * 2 {
+            codec.read_raw(bytes, 1 - index as usize)[Channel::Green as usize]
+        } else {
+            codec::quantize(codec::NEUTRAL_CHROMA, bits)
+        };
+
+        Self {
+            format,
+            value: Value::NonFloat {
+                data: data | (other << format.bpp()),
+                index,
+            },
+            endianness: endian,
+        }
+    }
+
     /// Create a new pixel from the raw bytes for a float.
     pub(crate) fn from_float_bytes(bytes: [u8; 16], endian: Endianness, format: Format) -> Self {
         debug_assert!(format.involves_float());
 
-        let data: [[u8; 4]; 4] = bytemuck::cast(bytes);
-        let data = crate::array::map(data, |arr| f32::from_bits(endian.make_u32(arr)));
+        let mut data = [0f32; 4];
+        for (value, info) in data.iter_mut().zip(format.channels()) {
+            let start = (info.shift / 8) as usize;
+            let raw = endian.make_u64(&bytes[start..start + (info.bits / 8) as usize]);
+            *value = codec::float_from_raw(raw, info.bits);
+        }
 
         Self {
             format,
@@ -157,6 +204,14 @@ impl Pixel {
 
     /// Create a new pixel from the raw bytes, endianness, format and,
     /// if applicable, index into the bytes that the pixel exists at.
+    ///
+    /// For YCbCr formats, `bytes` starts at the pair of pixels that share
+    /// their chroma, and `index` is zero for the first pixel of the pair
+    /// and one for the second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than a pixel.
     pub fn with_index(bytes: &[u8], index: u8, endian: Endianness, format: Format) -> Self {
         if format.involves_float() {
             // create a float
@@ -165,9 +220,12 @@ impl Pixel {
             buffer[..cnt].copy_from_slice(&bytes[..cnt]);
 
             Self::from_float_bytes(buffer, endian, format)
+        } else if format.color_type().ycbcr_matrix().is_some() {
+            let cnt = cmp::min(bytes.len(), format.bytes() as usize * 2);
+            Self::from_pair(&bytes[..cnt], index, endian, format)
         } else {
             // create a raw
-            let mut buffer = [0u8; 4];
+            let mut buffer = [0u8; 8];
             let cnt = format.bytes() as usize;
             buffer[..cnt].copy_from_slice(&bytes[..cnt]);
 
@@ -180,14 +238,54 @@ impl Pixel {
     ///
     /// This allows a pixel to be created from a collection of color
     /// channels.
+    ///
+    /// Formats that store a gray level take the luma of the red, green
+    /// and blue channels, and CMYK formats convert them to CMYK. Formats
+    /// with premultiplied alpha multiply the color channels by alpha.
     pub fn collect_channels(
         endianness: Endianness,
         format: Format,
         channels: impl IntoIterator<Item = ChannelValue>,
+    ) -> Self {
+        if format.premultiplies() {
+            let straight = format.with_alpha_mode(AlphaMode::Straight);
+            let channels = premultiplied_channels(channels);
+            return Self {
+                format,
+                ..Self::collect_channels(endianness, straight, channels)
+            };
+        }
+
+        if format.color_type().is_gray() {
+            Self::collect_stored_channels(endianness, format, gray_channels(channels))
+        } else if format.color_type().is_cmyk() {
+            Self::collect_stored_channels(endianness, format, cmyk_channels(channels))
+        } else if let Some(matrix) = format.color_type().ycbcr_matrix() {
+            let [luma, blue, red] = codec::rgb_to_ycbcr(matrix, collect_components(channels));
+            let stored = [
+                ChannelValue::rounded(Channel::Red, luma),
+                ChannelValue::rounded(Channel::Green, blue),
+            ];
+            let mut pixel =
+                Self::collect_stored_channels(endianness, format, stored.iter().copied());
+            if let Value::NonFloat { ref mut data, .. } = pixel.value {
+                *data |= codec::quantize(red, format.green_bits()) << format.bpp();
+            }
+            pixel
+        } else {
+            Self::collect_stored_channels(endianness, format, channels)
+        }
+    }
+
+    /// Create a new pixel from the values of the channels that the format
+    /// stores.
+    fn collect_stored_channels(
+        endianness: Endianness,
+        format: Format,
+        channels: impl IntoIterator<Item = ChannelValue>,
     ) -> Self {
         // there will be at most 4 channels
         let our_channels: ArrayVec<[ChannelInfo; 4]> = format.channels().collect();
-        let non_native_endian = !endianness.is_native();
 
         if format.involves_float() {
             // we're dealing with floats here
@@ -197,11 +295,7 @@ impl Pixel {
                     .iter()
                     .position(|channel_info| channel_value.channel_type == channel_info.channel)
                 {
-                    let mut val = channel_value.float_value();
-                    if non_native_endian {
-                        val = f32::from_bits(val.to_bits().swap_bytes());
-                    }
-                    data[posn] = val;
+                    data[posn] = channel_value.float_value();
                 }
             });
 
@@ -212,22 +306,21 @@ impl Pixel {
             }
         } else {
             // we're dealing with raw values here
-            let mut data = 0u32;
+            let mut data = 0u64;
             channels.into_iter().for_each(|channel_value| {
                 if let Some(channel_info) = our_channels
                     .iter()
                     .find(|channel_info| channel_info.channel == channel_value.channel_type)
                 {
-                    let val = channel_value.value() as u32;
-                    data |= (val & LOW_BIT_MASKS[channel_info.bits as usize])
-                        << (channel_info.shift as u32);
+                    // scale the value to the depth of the channel
+                    let val = match channel_info.bits {
+                        8 => channel_value.value() as u64,
+                        bits => codec::quantize(channel_value.float_value(), bits),
+                    };
+                    data |= (val & codec::low_bits(channel_info.bits)) << channel_info.shift;
                 }
             });
 
-            if non_native_endian {
-                data = data.swap_bytes();
-            }
-
             Self {
                 format,
                 endianness,
@@ -242,10 +335,61 @@ impl Pixel {
     }
 
     /// Create a new pixel from raw bytes, endianness and format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than a pixel. See [`try_new`] for a
+    /// version that doesn't panic.
+    ///
+    /// [`try_new`]: crate::Pixel::try_new
     pub fn new(bytes: &[u8], endianness: Endianness, format: Format) -> Self {
         Self::with_index(bytes, 0, endianness, format)
     }
 
+    /// Create a new pixel from raw bytes, endianness and format, or
+    /// return an error if `bytes` is shorter than a pixel.
+    pub fn try_new(bytes: &[u8], endianness: Endianness, format: Format) -> Result<Self, Error> {
+        Self::try_with_index(bytes, 0, endianness, format)
+    }
+
+    /// Like [`with_index`], but returns an error instead of panicking if
+    /// `bytes` is shorter than a pixel or the pixel at `index` doesn't
+    /// fit in the first byte, or in the pair for YCbCr formats.
+    ///
+    /// [`with_index`]: crate::Pixel::with_index
+    pub fn try_with_index(
+        bytes: &[u8],
+        index: u8,
+        endian: Endianness,
+        format: Format,
+    ) -> Result<Self, Error> {
+        let ycbcr = format.color_type().ycbcr_matrix().is_some();
+        let expected = if ycbcr && index == 1 {
+            format.bytes() as usize * 2
+        } else {
+            format.bytes() as usize
+        };
+        if bytes.len() < expected {
+            return Err(Error::BufferTooSmall {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+
+        let fits = if format.subbyte() {
+            index as usize + format.bpp() as usize <= 8
+        } else if ycbcr {
+            index <= 1
+        } else {
+            index == 0
+        };
+        if !fits {
+            return Err(Error::InvalidIndex);
+        }
+
+        Ok(Self::with_index(bytes, index, endian, format))
+    }
+
     /// The format for this pixel.
     pub const fn format(self) -> Format {
         self.format
@@ -256,22 +400,89 @@ impl Pixel {
         self.endianness
     }
 
+    /// The number of bytes in the array returned by [`to_array`], which is
+    /// enough for a pixel of any format, or a pair of YCbCr pixels.
+    ///
+    /// [`to_array`]: crate::Pixel::to_array
+    pub const MAX_BYTES: usize = MAX_BYTES_PER_PIXEL;
+
+    /// Write this pixel into `bytes`, in its format and endianness, and
+    /// return the number of bytes written to.
+    ///
+    /// Pixels of sub-byte formats are written to the bits of the first
+    /// byte at the index that they were read from, and the other bits are
+    /// kept. Pixels of YCbCr formats are written at their index into the
+    /// pair of pixels that starts at `bytes`, replacing the chroma of both
+    /// pixels if the pair fits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than the pixel, or than both pixels of
+    /// the pair for the second pixel of a YCbCr pair.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Endianness, Format, Pixel};
+    ///
+    /// let pixel = Pixel::with_index(&[0xA0], 4, Endianness::Little, Format::A4);
+    /// let mut bytes = [0x05];
+    /// assert_eq!(pixel.write_to(&mut bytes), 1);
+    /// assert_eq!(bytes, [0xA5]);
+    /// ```
+    pub fn write_to(self, bytes: &mut [u8]) -> usize {
+        let index = match self.value {
+            Value::NonFloat { index, .. } => index,
+            Value::Float { .. } => 0,
+        };
+        let cnt = self.format.bytes() as usize;
+
+        if self.format.color_type().ycbcr_matrix().is_some() {
+            let len = cmp::min(bytes.len() / cnt, 2) * cnt;
+            assert!(
+                len > index as usize * cnt,
+                "The buffer is too small for the pixel"
+            );
+            self.insert_pair(&mut bytes[..len], index);
+            len
+        } else {
+            assert!(bytes.len() >= cnt, "The buffer is too small for the pixel");
+            self.insert(bytes, index);
+            cnt
+        }
+    }
+
+    /// The bytes of this pixel, in its format and endianness, and the
+    /// number of them that are used.
+    ///
+    /// This is the same as calling [`write_to`] on zeroed bytes, so pixels
+    /// of YCbCr formats give the bytes of their pair, with a black pixel
+    /// beside them.
+    ///
+    /// [`write_to`]: crate::Pixel::write_to
+    pub fn to_array(self) -> ([u8; Pixel::MAX_BYTES], usize) {
+        let mut bytes = [0u8; Pixel::MAX_BYTES];
+        let cnt = self.format.bytes() as usize;
+        let len = if self.format.color_type().ycbcr_matrix().is_some() {
+            cnt * 2
+        } else {
+            cnt
+        };
+        let len = self.write_to(&mut bytes[..len]);
+        (bytes, len)
+    }
+
     /// Get the components of this channel as floating point values.
     pub(crate) fn components_float(
         self,
     ) -> impl ExactSizeIterator<Item = f32> + FusedIterator + DoubleEndedIterator {
         // get an array of floats
         let floats = match self.value {
-            Value::Float { data } => {
-                ArrayVec::from_array_len(data, self.format.bpp() as usize / 32)
-            }
+            Value::Float { data } => ArrayVec::from_array_len(data, self.format.channels().len()),
             Value::NonFloat { data, index } => {
                 // manual channel conversion
                 iter_channels(data, index, self.format)
-                    .map(|x| {
-                        let x: f32 = x as f32;
-                        x / (core::u8::MAX as f32)
-                    })
+                    .map(|(info, raw)| raw as f32 / codec::low_bits(info.bits) as f32)
                     .collect()
             }
         };
@@ -279,19 +490,156 @@ impl Pixel {
         AssertExactSize(floats.into_iter())
     }
 
+    /// Get the normalized components of this pixel, indexed by channel.
+    ///
+    /// Pixels of different formats compare equal when these are equal, so
+    /// missing color channels are zero and a missing alpha channel is
+    /// fully opaque, like in the `Codec`.
+    pub(crate) fn components(self) -> Components {
+        let mut components = [0.0, 0.0, 0.0, 1.0];
+        for (info, value) in self.format.channels().zip(self.components_float()) {
+            components[info.channel as usize] = value;
+        }
+        if self.format.color_type().is_gray() {
+            components[Channel::Green as usize] = components[Channel::Red as usize];
+            components[Channel::Blue as usize] = components[Channel::Red as usize];
+        }
+        if self.format.color_type().is_cmyk() {
+            components = codec::cmyk_to_rgb(components);
+        }
+        if let Some(matrix) = self.format.color_type().ycbcr_matrix() {
+            let [luma, blue, red] = self.ycbcr_raw();
+            let luma = luma as f32 / codec::low_bits(self.format.red_bits()) as f32;
+            let max = codec::low_bits(self.format.green_bits()) as f32;
+            components = codec::ycbcr_to_rgb(matrix, [luma, blue as f32 / max, red as f32 / max]);
+        }
+        if self.format.premultiplies() {
+            components = codec::unpremultiply(components);
+        }
+        components
+    }
+
+    /// The raw luma, blue-difference and red-difference chroma of a pixel
+    /// of a YCbCr format.
+    fn ycbcr_raw(self) -> [u64; 3] {
+        let (data, index) = match self.value {
+            Value::NonFloat { data, index } => (data, index),
+            Value::Float { .. } => return [0; 3],
+        };
+
+        let mut own = [0u64; 4];
+        for (info, raw) in iter_channels(data, 0, self.format) {
+            own[info.channel as usize] = raw;
+        }
+        let other = (data >> self.format.bpp()) & codec::low_bits(self.format.green_bits());
+        let (luma, chroma) = (own[Channel::Red as usize], own[Channel::Green as usize]);
+
+        match index {
+            0 => [luma, chroma, other],
+            _ => [luma, other, chroma],
+        }
+    }
+
+    /// Convert this pixel to an RGBA color.
+    ///
+    /// Missing color channels are zero and a missing alpha channel is
+    /// fully opaque. Gray levels are given as red, green and blue.
+    pub fn to_rgba(self) -> Rgba {
+        let [red, green, blue, alpha] = self.components();
+        let scale = |value| codec::quantize(value, 16) as u16;
+        Rgba {
+            red: scale(red),
+            green: scale(green),
+            blue: scale(blue),
+            alpha: scale(alpha),
+        }
+    }
+
+    /// The value stored in a channel of this pixel, normalized to
+    /// `0.0..=1.0`.
+    ///
+    /// Integer channels are divided by their largest value, so a 5-bit
+    /// channel holding 31 and an 8-bit channel holding 255 both give `1.0`.
+    /// Float channels are given as they are. Nothing is converted: gray
+    /// levels are only in the red channel, and CMYK and YCbCr pixels give
+    /// the values they store. Color channels that the format doesn't have
+    /// are `0.0`, and a missing alpha channel is `1.0`.
+    pub fn channel_unorm(self, channel: Channel) -> f32 {
+        self.format
+            .channels()
+            .zip(self.components_float())
+            .find(|(info, _)| info.channel == channel)
+            .map_or(
+                if channel == Channel::Alpha { 1.0 } else { 0.0 },
+                |(_, value)| value,
+            )
+    }
+
+    /// The raw integer stored in a channel of this pixel.
+    ///
+    /// This is not scaled, so it ranges up to [`Format::max_value`] for the
+    /// channel. Float channels give the bits of the float. Channels that
+    /// the format doesn't have are zero.
+    ///
+    /// [`Format::max_value`]: crate::Format::max_value
+    pub fn channel_raw(self, channel: Channel) -> u32 {
+        match self.value {
+            Value::NonFloat { data, index } => iter_channels(data, index, self.format)
+                .find(|(info, _)| info.channel == channel)
+                .map_or(0, |(_, raw)| raw as u32),
+            Value::Float { data } => self
+                .format
+                .channels()
+                .zip(data.iter())
+                .find(|(info, _)| info.channel == channel)
+                .map_or(0, |(info, &value)| {
+                    codec::float_to_raw(value, info.bits) as u32
+                }),
+        }
+    }
+
+    /// The palette index stored in this pixel, if its format is
+    /// [`Indexed`].
+    ///
+    /// [`Indexed`]: crate::ColorType::Indexed
+    pub fn index(self) -> Option<usize> {
+        match self.format.color_type() {
+            ColorType::Indexed => Some(self.raw_u64() as usize),
+            _ => None,
+        }
+    }
+
+    /// Convert this pixel to an RGBA color, looking up indexed pixels in
+    /// `palette`.
+    ///
+    /// Indices that are past the end of the palette are transparent
+    /// black. Pixels of other formats are converted with [`to_rgba`].
+    ///
+    /// [`to_rgba`]: crate::Pixel::to_rgba
+    pub fn resolve(self, palette: &Palette) -> Rgba {
+        match self.index() {
+            Some(index) => palette.get(index).unwrap_or_default(),
+            None => self.to_rgba(),
+        }
+    }
+
     /// Create the raw `u32` that could be used to represent this pixel.
     ///
     /// Although this is a basic arithmetic operation for raw pixels, for
     /// pixels involving floats it will try to compute the pixel from scratch.
+    /// Sub-byte pixels are shifted down to the lowest bits. Pixels wider
+    /// than 32 bits are truncated; see [`raw_u64`] for those.
+    ///
+    /// [`raw_u64`]: crate::Pixel::raw_u64
     pub fn raw_u32(self) -> u32 {
         match self.value {
-            Value::NonFloat { data, index } => data << (index as u32),
+            Value::NonFloat { .. } => self.raw_u64() as u32,
             Value::Float { .. } => {
                 // manually construct it
                 let mut data = 0u32;
                 self.components_float().for_each(|x| {
                     data <<= 8;
-                    let component = (x * (core::u8::MAX as f32)) as u32;
+                    let component = (x * (u8::MAX as f32)) as u32;
                     data |= component;
                 });
                 data
@@ -299,29 +647,173 @@ impl Pixel {
         }
     }
 
+    /// Create the raw `u64` that could be used to represent this pixel.
+    ///
+    /// This is the same as [`raw_u32`], but holds every bit of pixels that
+    /// are wider than 32 bits.
+    ///
+    /// [`raw_u32`]: crate::Pixel::raw_u32
+    pub fn raw_u64(self) -> u64 {
+        match self.value {
+            Value::NonFloat { data, index } if self.format.subbyte() => {
+                (data >> index) & codec::low_bits(self.format.bpp())
+            }
+            Value::NonFloat { data, .. } if self.format.color_type().ycbcr_matrix().is_some() => {
+                data & codec::low_bits(self.format.bpp())
+            }
+            Value::NonFloat { data, .. } => data,
+            Value::Float { .. } => self.raw_u32() as u64,
+        }
+    }
+
+    /// Create a pixel that encodes an object ID, for use in picking buffers.
+    ///
+    /// The ID is stored in the raw bits of the pixel, so it comes back
+    /// unchanged from [`to_id`] as long as it fits into the pixel. For
+    /// 32-bit formats, every `u32` round trips. Float formats store eight
+    /// bits of the ID in each channel.
+    ///
+    /// [`to_id`]: crate::Pixel::to_id
+    pub fn from_id(id: u32, format: Format) -> Self {
+        let value = if format.involves_float() {
+            let mut data = [0f32; 4];
+            for (i, value) in data.iter_mut().take(format.channels().count()).enumerate() {
+                *value = ((id >> (i * 8)) & 0xFF) as f32 / (u8::MAX as f32);
+            }
+
+            Value::Float { data }
+        } else {
+            Value::NonFloat {
+                data: id as u64 & codec::low_bits(format.bpp()),
+                index: 0,
+            }
+        };
+
+        Self {
+            format,
+            value,
+            endianness: Endianness::NATIVE,
+        }
+    }
+
+    /// Get the object ID encoded in this pixel.
+    ///
+    /// This is the inverse of [`from_id`].
+    ///
+    /// [`from_id`]: crate::Pixel::from_id
+    pub fn to_id(self) -> u32 {
+        match self.value {
+            Value::NonFloat { .. } => self.raw_u32(),
+            Value::Float { data } => data
+                .iter()
+                .take(self.format.channels().count())
+                .enumerate()
+                .fold(0, |id, (i, value)| {
+                    let byte = (value * (u8::MAX as f32) + 0.5) as u32 & 0xFF;
+                    id | (byte << (i * 8))
+                }),
+        }
+    }
+
     /// Get channel information for this pixel.
+    ///
+    /// For formats that store a gray level, the gray level is given as
+    /// the red, green and blue channels. CMYK pixels are given as the
+    /// channels of the opaque color that they convert to, and so are
+    /// YCbCr pixels. Premultiplied colors are divided by alpha.
     pub fn channel_info(
         self,
     ) -> impl ExactSizeIterator<Item = ChannelValue> + DoubleEndedIterator + FusedIterator {
-        let values: ArrayVec<[ChannelValue; 4]> = match self.value {
+        let color_type = self.format.color_type();
+        if color_type.is_cmyk()
+            || color_type.ycbcr_matrix().is_some()
+            || self.format.premultiplies()
+        {
+            let values: ArrayVec<[ChannelValue; 4]> = CHANNELS
+                .iter()
+                .zip(self.components().iter())
+                .map(|(&channel, &value)| ChannelValue::rounded(channel, value))
+                .collect();
+            return AssertExactSize(values.into_iter());
+        }
+
+        let mut values: ArrayVec<[ChannelValue; 4]> = match self.value {
             Value::Float { .. } => {
                 // iterate over channels and calculate the values
                 self.components_float()
-                    .zip(self.format.color_type().channels())
-                    .map(|(x, channel)| ChannelValue::new_with_float(channel, x))
+                    .zip(self.format.channels())
+                    .map(|(x, info)| ChannelValue::new_with_float(info.channel, x))
                     .collect()
             }
             Value::NonFloat { data, index } => iter_channels(data, index, self.format)
-                .zip(self.format.color_type().channels())
-                .map(|(x, channel)| ChannelValue::new(channel, x))
+                .map(|(info, raw)| ChannelValue::from_raw(info.channel, raw, info.bits))
                 .collect(),
         };
 
+        if self.format.color_type().is_gray() {
+            if let Some(&gray) = values
+                .iter()
+                .find(|value| value.channel_type == Channel::Red)
+            {
+                values.push(ChannelValue {
+                    channel_type: Channel::Green,
+                    ..gray
+                });
+                values.push(ChannelValue {
+                    channel_type: Channel::Blue,
+                    ..gray
+                });
+            }
+        }
+
         // for some reason, ArrayVecIterator doesn't implement ExactSizeIterator
         // despite having a fixed consistent size
         AssertExactSize(values.into_iter())
     }
 
+    /// The value of one channel of this pixel, as [`channel_info`] gives
+    /// it, or `None` if there is no such channel.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Channel, Endianness, Format, Pixel};
+    ///
+    /// let pixel = Pixel::new(&[0x10, 0x20, 0x30], Endianness::Little, Format::RGB24);
+    /// assert_eq!(pixel.channel(Channel::Green).unwrap().value(), 0x20);
+    /// assert_eq!(pixel.channel(Channel::Alpha), None);
+    /// ```
+    ///
+    /// [`channel_info`]: crate::Pixel::channel_info
+    pub fn channel(self, channel: Channel) -> Option<ChannelValue> {
+        self.channel_info()
+            .find(|value| value.channel_type == channel)
+    }
+
+    /// This pixel, with one of its channels set to `value`.
+    ///
+    /// The channel type of `value` is ignored in favor of `channel`. The
+    /// other channels keep the values that [`channel_info`] gives, and
+    /// the pixel is put back together like [`collect_channels`] does, so
+    /// setting a color channel of a gray pixel changes its gray level by
+    /// the luma of that channel. Setting a channel that the format doesn't
+    /// store gives back the same pixel.
+    ///
+    /// [`channel_info`]: crate::Pixel::channel_info
+    /// [`collect_channels`]: crate::Pixel::collect_channels
+    pub fn with_channel(self, channel: Channel, value: ChannelValue) -> Pixel {
+        let value = ChannelValue {
+            channel_type: channel,
+            ..value
+        };
+        let channels: ArrayVec<[ChannelValue; 4]> = self
+            .channel_info()
+            .filter(|other| other.channel_type != channel)
+            .chain(iter::once(value))
+            .collect();
+        Self::collect_channels(self.endianness, self.format, channels)
+    }
+
     /// Convert this `Pixel` to the same value but in a new format.
     ///
     /// When converting from a higher-resolution format to a lower
@@ -330,45 +822,156 @@ impl Pixel {
         convert_format::convert_to_format(self, format, endian)
     }
 
+    /// Combine this pixel with the pixel below it, `dst`, using a
+    /// Porter-Duff operator, and return a pixel in the format and
+    /// endianness of `dst`.
+    ///
+    /// Colors are combined as they are stored, without converting them to
+    /// linear light, and a missing alpha channel is fully opaque. The
+    /// color of a result that is fully transparent is black.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{BlendOp, Endianness, Format, Pixel, Rgba};
+    ///
+    /// // half transparent white over opaque black
+    /// let white = Rgba { red: 0xFFFF, green: 0xFFFF, blue: 0xFFFF, alpha: 0x8000 };
+    /// let src = Pixel::from_rgba(white, Format::ARGB32, Endianness::Little);
+    /// let dst = Pixel::new(&[0, 0, 0], Endianness::Little, Format::RGB24);
+    /// let over = src.blend(dst, BlendOp::Over);
+    /// assert_eq!(over.format(), Format::RGB24);
+    /// assert_eq!(over.to_array().0[..3], [0x80, 0x80, 0x80]);
+    ///
+    /// // nothing is kept outside of an opaque destination
+    /// assert_eq!(src.blend(dst, BlendOp::Out).to_rgba().green, 0);
+    /// ```
+    pub fn blend(self, dst: Pixel, op: BlendOp) -> Pixel {
+        let components = ops::porter_duff(op, self.components(), dst.components());
+        Self::from_components(components, dst.format, dst.endianness)
+    }
+
+    /// Interpolate linearly between this pixel and `other`, where a `t` of
+    /// `0.0` gives this pixel and `1.0` gives `other`, and return a pixel
+    /// in the format and endianness of this pixel.
+    ///
+    /// Every channel is interpolated as a float, so the pixels can have
+    /// different formats; convert this pixel with [`into_new_format`]
+    /// first to get the result in another format. The colors are weighted
+    /// by their alpha, so the color of a transparent pixel doesn't bleed
+    /// into the result. Values of `t` outside of `0.0..=1.0` extrapolate,
+    /// and are clamped when they are stored in integer channels.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Endianness, Format, Pixel};
+    ///
+    /// let black = Pixel::new(&[0, 0, 0], Endianness::Little, Format::RGB24);
+    /// let white = Pixel::new(&[0xFF], Endianness::Little, Format::GRAY8);
+    /// let gray = black.lerp(white, 0.25);
+    /// assert_eq!(gray.format(), Format::RGB24);
+    /// assert_eq!(gray.to_array().0[..3], [0x40, 0x40, 0x40]);
+    /// ```
+    ///
+    /// [`into_new_format`]: crate::Pixel::into_new_format
+    pub fn lerp(self, other: Pixel, t: f32) -> Pixel {
+        let (from, to) = (self.components(), other.components());
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+
+        let alpha = mix(from[3], to[3]);
+        let mut components = [0.0, 0.0, 0.0, alpha];
+        if alpha > 0.0 {
+            for (i, component) in components[..3].iter_mut().enumerate() {
+                *component = mix(from[i] * from[3], to[i] * to[3]) / alpha;
+            }
+        }
+        Self::from_components(components, self.format, self.endianness)
+    }
+
+    /// Create a new pixel from normalized components, indexed by channel.
+    fn from_components(components: Components, format: Format, endianness: Endianness) -> Self {
+        let channels = CHANNELS
+            .iter()
+            .zip(components.iter())
+            .map(|(&channel, &value)| ChannelValue::rounded(channel, value));
+        Self::collect_channels(endianness, format, channels)
+    }
+
     /// Insert this `Pixel` into the corresponding bytes.
     ///
     /// Assumes that the bytes and this pixel are of the same format.
-    pub(crate) fn insert(self, bytes: &mut [u8]) {
-        if let Value::NonFloat { data, index } = self.value {
-            // if the format involves sub-bytes, we need to use bit
-            // masking to mutate the bytes
-            if self.format().subbyte() {
-                let mask = (LOW_BIT_MASKS[self.format().bpp() as usize] as u8) << index;
-                let data = data as u8 & mask;
-                bytes[0] = (bytes[0] & !mask) | data;
-                return;
+    /// For sub-byte formats, `index` is the bit index of the pixel in
+    /// the first byte.
+    pub(crate) fn insert(self, bytes: &mut [u8], index: u8) {
+        let cnt = self.format().bytes() as usize;
+
+        match self.value {
+            Value::NonFloat { .. } if self.format().subbyte() => {
+                // sub-byte pixels need bit masking to keep their neighbors
+                let mask = (codec::low_bits(self.format().bpp()) as u8) << index;
+                let data = (self.raw_u32() as u8) << index;
+                bytes[0] = (bytes[0] & !mask) | (data & mask);
+            }
+            Value::NonFloat { data, .. } => match self.endianness {
+                Endianness::Little => bytes[..cnt].copy_from_slice(&data.to_le_bytes()[..cnt]),
+                Endianness::Big => bytes[..cnt].copy_from_slice(&data.to_be_bytes()[8 - cnt..]),
+            },
+            Value::Float { data } => {
+                for (info, &value) in self.format.channels().zip(data.iter()) {
+                    let start = (info.shift / 8) as usize;
+                    let len = (info.bits / 8) as usize;
+                    let raw = codec::float_to_raw(value, info.bits);
+                    let bytes = &mut bytes[start..start + len];
+                    match self.endianness {
+                        Endianness::Little => bytes.copy_from_slice(&raw.to_le_bytes()[..len]),
+                        Endianness::Big => bytes.copy_from_slice(&raw.to_be_bytes()[8 - len..]),
+                    }
+                }
             }
         }
+    }
 
-        // otherwise, it's a pretty simple byte-wise copy
-        let data_bytes = match self.value {
-            Value::NonFloat { ref data, .. } => bytemuck::bytes_of(data),
-            Value::Float { ref data } => bytemuck::bytes_of(data),
-        };
+    /// Insert this pixel of a YCbCr format into the bytes of the pair of
+    /// pixels that it belongs to, at the given index in the pair.
+    ///
+    /// The chroma of both pixels is replaced, since they share it.
+    pub(crate) fn insert_pair(self, bytes: &mut [u8], index: u8) {
+        let codec = Codec::new(self.format, self.endianness);
+        let [luma, blue, red] = self.ycbcr_raw();
+        let count = cmp::min(bytes.len() / self.format.bytes() as usize, 2);
 
-        let cnt = self.format().bytes() as usize;
-        bytes[..cnt].copy_from_slice(&data_bytes[..cnt]);
+        for (i, &chroma) in [blue, red].iter().enumerate().take(count) {
+            let mut raw = codec.read_raw(bytes, i);
+            if i == index as usize {
+                raw[Channel::Red as usize] = luma;
+            }
+            raw[Channel::Green as usize] = chroma;
+            codec.write_raw(bytes, i, raw);
+        }
     }
 
     /// Fill a row of bytes with this pixel.
     ///
     /// Returns the number of bytes written.
     pub(crate) fn fill_row(self, bytes: &mut [u8]) -> usize {
+        if self.format.color_type().ycbcr_matrix().is_some() {
+            // pairs of pixels share their chroma
+            let bcount = self.format.bytes() as usize;
+            let len = bytes.len() / bcount * bcount;
+            for pair in bytes[..len].chunks_mut(bcount * 2) {
+                for index in 0..pair.len() / bcount {
+                    self.insert_pair(pair, index as u8);
+                }
+            }
+            return len;
+        }
+
         match self.format().bpp() {
             1 => {
                 // only one bit per pixel
-                let raw = self.raw_u32();
-                if raw == 0 {
-                    bytes.iter_mut().for_each(|x| *x = 0);
-                } else {
-                    debug_assert_eq!(raw, 1);
-                    bytes.iter_mut().for_each(|x| *x = 0xFF);
-                }
+                let byte = if self.raw_u32() == 0 { 0 } else { 0xFF };
+                bytes.iter_mut().for_each(|x| *x = byte);
 
                 bytes.len()
             }
@@ -380,44 +983,13 @@ impl Pixel {
 
                 bytes.len()
             }
-            8 => {
-                // it's a byte
-                let byte = self.raw_u32() as u8;
-                bytes.iter_mut().for_each(|x| *x = byte);
-
-                bytes.len()
-            }
-            16 => {
-                // it's a word
-                let word = self.raw_u32() as u16;
-                let word_bytes = word.to_ne_bytes();
-                bytes
-                    .chunks_exact_mut(2)
-                    .map(|chunk| {
-                        chunk.copy_from_slice(&word_bytes);
-                    })
-                    .count()
-                    * 2
-            }
-            32 => {
-                // it's a double word
-                let word = self.raw_u32() as u32;
-                let word_bytes = word.to_ne_bytes();
-                bytes
-                    .chunks_exact_mut(4)
-                    .map(|chunk| {
-                        chunk.copy_from_slice(&word_bytes);
-                    })
-                    .count()
-                    * 4
-            }
             bpp => {
                 // just call insert multiple times
                 let bcount: usize = (bpp / 8).into();
                 bytes
                     .chunks_exact_mut(bcount)
                     .map(|chunk| {
-                        self.insert(chunk);
+                        self.insert(chunk, 0);
                     })
                     .count()
                     * bcount
@@ -426,23 +998,88 @@ impl Pixel {
     }
 }
 
+/// Iterate over the channels of the format along with their raw values.
 fn iter_channels(
-    mut data: u32,
+    mut data: u64,
     index: u8,
     format: Format,
-) -> impl ExactSizeIterator<Item = u8> + FusedIterator + DoubleEndedIterator {
+) -> impl ExactSizeIterator<Item = (ChannelInfo, u64)> + FusedIterator + DoubleEndedIterator {
     // shift it over by index
-    data >>= index as u32;
+    if format.subbyte() {
+        data >>= index;
+    }
 
     // iterate over channels
     format.channels().map(move |channel_info| {
         // shift and mask data
-        let channel =
-            (data >> (channel_info.shift as u32)) & LOW_BIT_MASKS[channel_info.bits as usize];
-        channel as u8
+        let channel = (data >> channel_info.shift) & codec::low_bits(channel_info.bits);
+        (channel_info, channel)
     })
 }
 
+/// Replace the color channels of a set of values with their luma, stored
+/// in the red channel.
+fn gray_channels(channels: impl IntoIterator<Item = ChannelValue>) -> ArrayVec<[ChannelValue; 2]> {
+    let mut components = [0.0; 4];
+    let mut gray = ArrayVec::new();
+
+    for value in channels {
+        match value.channel_type {
+            Channel::Alpha => {
+                // only keep the last alpha, like the other formats
+                gray.retain(|value: &ChannelValue| value.channel_type != Channel::Alpha);
+                gray.push(value);
+            }
+            channel => components[channel as usize] = value.float_value(),
+        }
+    }
+
+    let luma = codec::clamp_unit(codec::luma(components));
+    gray.push(ChannelValue::rounded(Channel::Red, luma));
+    gray
+}
+
+/// Replace the channels of a set of values with the cyan, magenta,
+/// yellow and black that they convert to, stored in the red, green,
+/// blue and alpha channels.
+fn cmyk_channels(channels: impl IntoIterator<Item = ChannelValue>) -> ArrayVec<[ChannelValue; 4]> {
+    CHANNELS
+        .iter()
+        .zip(codec::rgb_to_cmyk(collect_components(channels)).iter())
+        .map(|(&channel, &value)| ChannelValue::rounded(channel, value))
+        .collect()
+}
+
+/// Multiply the color channels of a set of values by their alpha, where
+/// a missing alpha channel is fully opaque.
+fn premultiplied_channels(
+    channels: impl IntoIterator<Item = ChannelValue>,
+) -> ArrayVec<[ChannelValue; 4]> {
+    let mut components = [0.0, 0.0, 0.0, 1.0];
+    for value in channels {
+        components[value.channel_type as usize] = value.float_value();
+    }
+
+    CHANNELS
+        .iter()
+        .zip(codec::premultiply(components).iter())
+        .map(|(&channel, &value)| ChannelValue::rounded(channel, value))
+        .collect()
+}
+
+/// Gather a set of values into components, where missing channels are
+/// zero.
+fn collect_components(channels: impl IntoIterator<Item = ChannelValue>) -> Components {
+    let mut components = [0.0; 4];
+    for value in channels {
+        components[value.channel_type as usize] = value.float_value();
+    }
+    components
+}
+
+/// The channels, in the order that components are indexed by.
+const CHANNELS: [Channel; 4] = [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+
 /// The value of a channel combined with the type of the channel.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct ChannelValue {
@@ -471,7 +1108,45 @@ impl ChannelValue {
     pub fn new_with_float(channel_type: Channel, value: f32) -> Self {
         Self {
             channel_type,
-            value: (value * (core::u8::MAX as f32)) as u8,
+            value: (value * (u8::MAX as f32)) as u8,
+            float_value: NotNan::new(value).ok(),
+        }
+    }
+
+    /// Create a new `ChannelValue` from the raw value of a channel with
+    /// the given number of bits.
+    ///
+    /// Channels that aren't 8 bits wide are scaled to 8 bits, and keep
+    /// their full precision as a float.
+    pub(crate) fn from_raw(channel_type: Channel, raw: u64, bits: u8) -> Self {
+        if bits == 8 {
+            return Self::new(channel_type, raw as u8);
+        }
+
+        let value = raw as f32 / codec::low_bits(bits) as f32;
+        Self {
+            channel_type,
+            value: codec::quantize(value, 8) as u8,
+            float_value: NotNan::new(value).ok(),
+        }
+    }
+
+    /// Create a new `ChannelValue` from a 16-bit value, which is cut down
+    /// to 8 bits but keeps its full precision as a float.
+    pub(crate) fn from_u16(channel_type: Channel, value: u16) -> Self {
+        Self {
+            channel_type,
+            value: (value >> 8) as u8,
+            float_value: NotNan::new(value as f32 / u16::MAX as f32).ok(),
+        }
+    }
+
+    /// Create a new `ChannelValue` from a float value in `0.0..=1.0`,
+    /// rounding it to the nearest `u8` value.
+    fn rounded(channel_type: Channel, value: f32) -> Self {
+        Self {
+            channel_type,
+            value: codec::quantize(value, 8) as u8,
             float_value: NotNan::new(value).ok(),
         }
     }
@@ -488,28 +1163,11 @@ impl ChannelValue {
 
     /// The floating point value of the channel.
     pub fn float_value(self) -> f32 {
-        self.float_value.map_or_else(
-            || self.value as f32 / (core::u8::MAX as f32),
-            |x| x.into_inner(),
-        )
+        self.float_value
+            .map_or_else(|| self.value as f32 / (u8::MAX as f32), |x| x.into_inner())
     }
 }
 
-/// The mask for getting the `n` lowest bits of a `u32`.
-const LOW_BIT_MASKS: [u32; 33] = {
-    let mut low_bit_masks = [0u32; 33];
-    let mut i = 0;
-    let mut current = 0u32;
-
-    while i < 33 {
-        low_bit_masks[i as usize] = current;
-        current = (current << 1) | 1;
-        i += 1;
-    }
-
-    low_bit_masks
-};
-
 #[cfg(all(feature = "alloc", test))]
 mod tests {
     use alloc::vec::Vec;
@@ -520,14 +1178,29 @@ mod tests {
     /// Ready-bake pixels for use in testing.
     fn test_pixels() -> Vec<Pixel> {
         alloc::vec![
-            Pixel::from_bytes([255, 255, 255, 255], 0, Endianness::NATIVE, Format::ARGB32),
+            Pixel::from_bytes(
+                [255, 255, 255, 255, 0, 0, 0, 0],
+                0,
+                Endianness::NATIVE,
+                Format::ARGB32
+            ),
             Pixel::from_float_bytes(
                 bytemuck::cast([1.0f32, 1.0, 1.0, 1.0]),
                 Endianness::NATIVE,
                 Format::ARGB_F32,
             ),
-            Pixel::from_bytes([255, 255, 255, 0], 0, Endianness::NATIVE, Format::ARGB32),
-            Pixel::from_bytes([255, 255, 255, 0], 0, Endianness::NATIVE, Format::RGB24),
+            Pixel::from_bytes(
+                [255, 255, 255, 0, 0, 0, 0, 0],
+                0,
+                Endianness::NATIVE,
+                Format::ARGB32
+            ),
+            Pixel::from_bytes(
+                [255, 255, 255, 0, 0, 0, 0, 0],
+                0,
+                Endianness::NATIVE,
+                Format::RGB24
+            ),
         ]
     }
 
@@ -554,4 +1227,370 @@ mod tests {
             make_comparison(left, right);
         }
     }
+
+    #[test]
+    fn deep_channels() {
+        use crate::{Builder, GeneralImage, Image};
+
+        let bytes = [0x34u8, 0x12, 0xFF, 0xFF, 0x00, 0x80];
+        let image = Builder::from_buffer(1, 1, Format::RGB48, bytes)
+            .with_endianness(Endianness::Little)
+            .finish();
+        let pixel = image.pixel(0, 0);
+        assert_eq!(pixel.raw_u64(), 0x8000_FFFF_1234);
+
+        let rgb24 = pixel.into_new_format(Endianness::Little, Format::RGB24);
+        assert_eq!(rgb24.raw_u32(), 0x80_FF_12);
+
+        let mut out = [0u8; 6];
+        let mut image = GeneralImage::from_buffer(1, 1, Format::BGR48, &mut out[..]);
+        image.set_pixel(0, 0, pixel);
+        assert_eq!(image.pixel(0, 0), pixel);
+
+        // narrow channels are scaled rather than truncated
+        let nibble = Pixel::new(&[0x08], Endianness::Little, Format::A4);
+        assert_eq!(
+            nibble
+                .into_new_format(Endianness::Little, Format::A8)
+                .raw_u32(),
+            0x88
+        );
+    }
+
+    #[test]
+    fn two_channels() {
+        let gray = Pixel::new(&[0x40, 0xFF], Endianness::Little, Format::LA8);
+        let rgba = gray.into_new_format(Endianness::Little, Format::RGBA32);
+        assert_eq!(rgba.raw_u32(), 0xFF40_4040);
+        assert_eq!(rgba, gray);
+        assert_eq!(
+            rgba.into_new_format(Endianness::Little, Format::LA8)
+                .raw_u32(),
+            0xFF40
+        );
+
+        // colors become their luma
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let red = Pixel::from_rgba(red, Format::LA8, Endianness::Little);
+        assert_eq!(red.raw_u32(), 0xFF4C);
+
+        let gray = Pixel::new(&[0x80, 0x80], Endianness::Little, Format::GRAY16);
+        assert_eq!(
+            gray.to_rgba(),
+            Rgba {
+                red: 0x8080,
+                green: 0x8080,
+                blue: 0x8080,
+                alpha: 0xFFFF,
+            }
+        );
+        assert_eq!(
+            gray.into_new_format(Endianness::Little, Format::GRAY8)
+                .raw_u32(),
+            0x80
+        );
+
+        let rg = Pixel::new(&[0x12, 0x34], Endianness::Little, Format::RG8);
+        let rgb = rg.into_new_format(Endianness::Little, Format::RGB24);
+        assert_eq!(rgb.raw_u32(), 0x00_3412);
+        assert_eq!(
+            rg.into_new_format(Endianness::Little, Format::RG16)
+                .raw_u32(),
+            0x3434_1212
+        );
+    }
+
+    #[test]
+    fn five_and_six_bit_channels() {
+        let red = Pixel::new(&0xF800u16.to_le_bytes(), Endianness::Little, Format::RGB565);
+        assert_eq!(
+            red.into_new_format(Endianness::Little, Format::XRGB32)
+                .raw_u32()
+                & 0xFF_FFFF,
+            0x00_00FF
+        );
+
+        let color = Rgba {
+            red: 0xFFFF,
+            green: 0x8000,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let rgb565 = Pixel::from_rgba(color, Format::RGB565, Endianness::Big);
+        assert_eq!(rgb565.raw_u32(), 0xFC00);
+        let mut bytes = [0u8; 2];
+        rgb565.insert(&mut bytes, 0);
+        assert_eq!(bytes, [0xFC, 0x00]);
+
+        let argb1555 = Pixel::from_rgba(color, Format::ARGB1555, Endianness::Little);
+        assert_eq!(argb1555.raw_u32(), 0xFE00);
+        let rgb555 = argb1555.into_new_format(Endianness::Little, Format::RGB555);
+        assert_eq!(rgb555.raw_u32(), 0x7E00);
+        assert_eq!(
+            rgb565
+                .into_new_format(Endianness::Little, Format::RGB555)
+                .raw_u32(),
+            0x7E00
+        );
+    }
+
+    #[test]
+    fn ten_bit_channels() {
+        let quantum = 0x3FFu32 << 20 | 0x200 << 10 | 0x001;
+        let pixel = Pixel::new(
+            &quantum.to_le_bytes(),
+            Endianness::Little,
+            Format::XRGB2101010,
+        );
+        assert_eq!(pixel.channel_raw(Channel::Red), 0x3FF);
+        assert_eq!(
+            pixel.to_rgba(),
+            Rgba {
+                red: 0xFFFF,
+                green: 0x8020,
+                blue: 0x0040,
+                alpha: 0xFFFF,
+            }
+        );
+
+        // no bits are lost going through 16-bit channels and back
+        let deep = pixel.into_new_format(Endianness::Little, Format::RGB48);
+        let back = deep.into_new_format(Endianness::Little, Format::XRGB2101010);
+        assert_eq!(back.raw_u32(), quantum);
+        assert_eq!(
+            Pixel::from_rgba(back.to_rgba(), Format::XRGB2101010, Endianness::Little),
+            pixel
+        );
+    }
+
+    #[test]
+    fn single_channels() {
+        let pixel = Pixel::new(
+            &[0x40, 0x30, 0x20, 0x10],
+            Endianness::Little,
+            Format::ARGB32,
+        );
+        assert_eq!(pixel.channel(Channel::Alpha).unwrap().value(), 0x40);
+
+        let opaque = pixel.with_channel(Channel::Alpha, ChannelValue::new(Channel::Red, 0xFF));
+        let mut bytes = [0u8; 4];
+        opaque.insert(&mut bytes, 0);
+        assert_eq!(bytes, [0xFF, 0x30, 0x20, 0x10]);
+
+        // deeper channels keep their precision
+        let quantum = 0x155u32 << 20 | 0x2AA << 10 | 0x0FF;
+        let pixel = Pixel::new(
+            &quantum.to_le_bytes(),
+            Endianness::Little,
+            Format::XRGB2101010,
+        );
+        let blue = pixel.channel(Channel::Blue).unwrap();
+        let swapped = pixel.with_channel(Channel::Red, blue);
+        assert_eq!(swapped.raw_u32(), 0x0FF << 20 | 0x2AA << 10 | 0x0FF);
+        assert_eq!(pixel.with_channel(Channel::Alpha, blue), pixel);
+    }
+
+    #[test]
+    fn half_floats() {
+        let bytes = [0x00, 0x3C, 0x00, 0x38, 0x00, 0x34, 0x00, 0x00];
+        let pixel = Pixel::new(&bytes, Endianness::Little, Format::ARGB_F16);
+        let rgba = Rgba {
+            red: 0x8000,
+            green: 0x4000,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        assert_eq!(pixel.to_rgba(), rgba);
+
+        let mut big = [0u8; 8];
+        pixel
+            .into_new_format(Endianness::Big, Format::ARGB_F16)
+            .insert(&mut big, 0);
+        assert_eq!(big, [0x3C, 0x00, 0x38, 0x00, 0x34, 0x00, 0x00, 0x00]);
+        assert_eq!(Pixel::new(&big, Endianness::Big, Format::ARGB_F16), pixel);
+    }
+
+    #[test]
+    fn cmyk() {
+        let red = Pixel::new(&[0, 0xFF, 0xFF, 0], Endianness::Little, Format::CMYK32);
+        let argb = red.into_new_format(Endianness::Little, Format::ARGB32);
+        assert_eq!(argb.raw_u32(), 0x0000_FFFF);
+        assert_eq!(argb, red);
+        assert_eq!(
+            argb.into_new_format(Endianness::Little, Format::CMYK32)
+                .raw_u32(),
+            0x00FF_FF00
+        );
+
+        let gray = Pixel::new(&[0x80, 0x80, 0x80], Endianness::Little, Format::RGB24);
+        let cmyk = gray.into_new_format(Endianness::Little, Format::CMYK32);
+        assert_eq!(cmyk.raw_u32(), 0x7F00_0000);
+    }
+
+    #[test]
+    fn channel_accessors() {
+        let pixel = Pixel::new(&[0x1F, 0xF8], Endianness::Little, Format::RGB565);
+        assert_eq!(pixel.channel_raw(Channel::Red), 0x1F);
+        assert_eq!(pixel.channel_raw(Channel::Blue), 0x1F);
+        assert_eq!(pixel.channel_raw(Channel::Green), 0);
+        assert_eq!(pixel.channel_unorm(Channel::Red), 1.0);
+        assert_eq!(pixel.channel_unorm(Channel::Alpha), 1.0);
+        assert_eq!(pixel.channel_raw(Channel::Alpha), 0);
+
+        let gray = Pixel::new(&[0x80], Endianness::Little, Format::GRAY8);
+        assert_eq!(gray.channel_raw(Channel::Red), 0x80);
+        assert_eq!(gray.channel_unorm(Channel::Green), 0.0);
+
+        let float = Pixel::from_rgba(
+            Rgba {
+                red: 0xFFFF,
+                green: 0,
+                blue: 0,
+                alpha: 0xFFFF,
+            },
+            Format::ARGB_F32,
+            Endianness::Little,
+        );
+        assert_eq!(float.channel_unorm(Channel::Red), 1.0);
+        assert_eq!(float.channel_raw(Channel::Red), 1.0f32.to_bits());
+    }
+
+    #[test]
+    fn premultiplied() {
+        let red = Pixel::new(&[0x80, 0xFF, 0, 0], Endianness::Little, Format::ARGB32);
+        let premul = red.into_new_format(Endianness::Little, Format::ARGB32_PREMUL);
+        assert_eq!(premul.raw_u32(), 0x0000_8080);
+        assert_eq!(premul, red);
+        assert_eq!(
+            premul
+                .into_new_format(Endianness::Little, Format::ARGB32)
+                .raw_u32(),
+            0x0000_FF80
+        );
+
+        // formats without alpha are opaque, so nothing changes
+        let opaque = Pixel::new(
+            &[0xFF, 0x40, 0, 0],
+            Endianness::Little,
+            Format::ARGB32_PREMUL,
+        );
+        let rgb = opaque.into_new_format(Endianness::Little, Format::RGB24);
+        assert_eq!(rgb.raw_u32(), 0x40);
+        assert_eq!(Format::ARGB32_PREMUL.bpp(), 32);
+    }
+
+    #[test]
+    fn ycbcr() {
+        // red, followed by white with the same chroma
+        let pair = [81, 90, 235, 240];
+        let red = Pixel::with_index(&pair, 0, Endianness::Little, Format::YUYV);
+        let rgba = red.to_rgba();
+        assert!(rgba.red > 0xFF00 && rgba.green < 0x100 && rgba.blue < 0x100);
+        assert_eq!(rgba.alpha, 0xFFFF);
+
+        let again = Pixel::from_rgba(rgba, Format::YUYV, Endianness::Little);
+        assert_eq!(again.raw_u32(), 0x5A51);
+        assert_eq!(again, red);
+
+        // the second pixel of a pair stores the red-difference chroma
+        let mut bytes = [0u8; 4];
+        again.insert_pair(&mut bytes, 1);
+        assert_eq!(bytes, [0, 90, 81, 240]);
+        let second = Pixel::with_index(&bytes, 1, Endianness::Little, Format::YUYV);
+        assert_eq!(second.raw_u32(), 0xF051);
+        assert_eq!(second, red);
+
+        let uyvy = red.into_new_format(Endianness::Little, Format::UYVY);
+        assert_eq!(uyvy.raw_u32(), 0x515A);
+    }
+
+    #[test]
+    fn porter_duff() {
+        let color = |channel, alpha| {
+            let channels = [
+                ChannelValue::new_with_float(channel, 1.0),
+                ChannelValue::new_with_float(Channel::Alpha, alpha),
+            ];
+            Pixel::collect_channels(
+                Endianness::Little,
+                Format::ARGB_F32,
+                channels.iter().copied(),
+            )
+        };
+        let (red, blue) = (color(Channel::Red, 0.5), color(Channel::Blue, 0.25));
+
+        let cases = [
+            (BlendOp::Over, [0.8, 0.0, 0.2, 0.625]),
+            (BlendOp::In, [1.0, 0.0, 0.0, 0.125]),
+            (BlendOp::Out, [1.0, 0.0, 0.0, 0.375]),
+            (BlendOp::Atop, [0.5, 0.0, 0.5, 0.25]),
+            (BlendOp::Xor, [0.75, 0.0, 0.25, 0.5]),
+        ];
+        for &(op, expected) in cases.iter() {
+            let blended = red.blend(blue, op).components();
+            for (&value, &expected) in blended.iter().zip(expected.iter()) {
+                assert!((value - expected).abs() < 1e-6, "{:?} {:?}", op, blended);
+            }
+        }
+
+        // the result takes the format of the destination
+        let below = Pixel::new(&[0, 0, 0xFF, 0xFF], Endianness::Little, Format::RGBA32);
+        let blended = red.blend(below, BlendOp::Over);
+        assert_eq!(blended.format(), Format::RGBA32);
+        assert_eq!(blended.raw_u32(), 0xFF80_0080);
+    }
+
+    #[test]
+    fn interpolation() {
+        let red = Pixel::new(&[0xFF, 0, 0, 0xFF], Endianness::Little, Format::RGBA32);
+        let clear = Pixel::new(&[0, 0, 0xFF, 0], Endianness::Little, Format::RGBA32);
+
+        // the blue of a transparent pixel doesn't show
+        let faded = red.lerp(clear, 0.5);
+        assert_eq!(faded.to_array().0[..4], [0xFF, 0, 0, 0x80]);
+        assert_eq!(red.lerp(clear, 0.0), red);
+        assert_eq!(red.lerp(red, 0.7), red);
+
+        // floats extrapolate past the ends
+        let dark = Pixel::from_rgba(Rgba::default(), Format::RGB_F32, Endianness::Little);
+        let far = dark.lerp(red, 2.0);
+        assert!((far.channel_unorm(Channel::Red) - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn byte_arrays() {
+        let pixel = Pixel::new(&[0xF8, 0x1F], Endianness::Big, Format::RGB565);
+        let (bytes, len) = pixel.to_array();
+        assert_eq!(&bytes[..len], &[0xF8, 0x1F]);
+
+        let blue = Rgba {
+            red: 0,
+            green: 0,
+            blue: 0xFFFF,
+            alpha: 0xFFFF,
+        };
+        let pixel = Pixel::from_rgba(blue, Format::RGB_F32, Endianness::Big);
+        let (bytes, len) = pixel.to_array();
+        assert_eq!(len, 12);
+        assert_eq!(
+            Pixel::new(&bytes[..len], Endianness::Big, Format::RGB_F32),
+            pixel
+        );
+
+        // the second pixel of a pair writes the chroma of both
+        let pixel = Pixel::with_index(&[81, 90, 235, 240], 1, Endianness::Little, Format::YUYV);
+        let (bytes, len) = pixel.to_array();
+        assert_eq!(&bytes[..len], &[0, 90, 235, 240]);
+
+        // the last pixel of an odd-sized row only has its own bytes
+        let mut last = [0u8; 3];
+        let first = Pixel::with_index(&[81, 90], 0, Endianness::Little, Format::YUYV);
+        assert_eq!(first.write_to(&mut last[..2]), 2);
+        assert_eq!(last, [81, 90, 0]);
+    }
 }
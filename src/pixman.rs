@@ -0,0 +1,300 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the format codes of pixman, for
+//! handing images to compositors built on it.
+//!
+//! A pixman code packs the bits per pixel, a type and the number of bits
+//! of each channel into a `u32`, which [`pixman_format`] builds. The type
+//! says where the channels are: `PIXMAN_TYPE_ARGB` has blue in the lowest
+//! bits of the quantum and alpha above the colors, so `PIXMAN_a8r8g8b8`
+//! is [`Format::BGRA32`]. Pixman reads quanta in the byte order of the
+//! host, so images handed to it should have [`Endianness::NATIVE`].
+//!
+//! This requires the `pixman` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::{pixman, Format};
+//!
+//! assert_eq!(Format::BGRA32.to_pixman(), Some(pixman::PIXMAN_a8r8g8b8));
+//! assert_eq!(Format::from_pixman(pixman::PIXMAN_r5g6b5), Some(Format::RGB565));
+//! ```
+//!
+//! [`pixman_format`]: crate::pixman::pixman_format
+//! [`Format::BGRA32`]: crate::Format::BGRA32
+//! [`Endianness::NATIVE`]: crate::Endianness::NATIVE
+
+#![allow(non_upper_case_globals)]
+
+use crate::{codec::low_bits, AlphaMode, Channel, ColorType, Format};
+
+/// A format that isn't described by its channels.
+pub const PIXMAN_TYPE_OTHER: u32 = 0;
+/// Alpha only.
+pub const PIXMAN_TYPE_A: u32 = 1;
+/// Blue in the lowest bits, then green, red and alpha.
+pub const PIXMAN_TYPE_ARGB: u32 = 2;
+/// Red in the lowest bits, then green, blue and alpha.
+pub const PIXMAN_TYPE_ABGR: u32 = 3;
+/// Indices into a palette.
+pub const PIXMAN_TYPE_COLOR: u32 = 4;
+/// Gray levels, looked up in a palette.
+pub const PIXMAN_TYPE_GRAY: u32 = 5;
+/// Pairs of pixels that share their chroma, as in [`Format::YUYV`](crate::Format::YUYV).
+pub const PIXMAN_TYPE_YUY2: u32 = 6;
+/// Blue in the highest bits, then green, red and alpha.
+pub const PIXMAN_TYPE_BGRA: u32 = 8;
+/// Red in the highest bits, then green, blue and alpha.
+pub const PIXMAN_TYPE_RGBA: u32 = 9;
+
+/// Build a pixman format code, as the `PIXMAN_FORMAT` macro does.
+pub const fn pixman_format(bpp: u32, ty: u32, a: u32, r: u32, g: u32, b: u32) -> u32 {
+    bpp << 24 | ty << 16 | a << 12 | r << 8 | g << 4 | b
+}
+
+/// [`Format::BGRA32`](crate::Format::BGRA32).
+pub const PIXMAN_a8r8g8b8: u32 = pixman_format(32, PIXMAN_TYPE_ARGB, 8, 8, 8, 8);
+/// [`Format::XBGR32`](crate::Format::XBGR32).
+pub const PIXMAN_x8r8g8b8: u32 = pixman_format(32, PIXMAN_TYPE_ARGB, 0, 8, 8, 8);
+/// [`Format::RGBA32`](crate::Format::RGBA32).
+pub const PIXMAN_a8b8g8r8: u32 = pixman_format(32, PIXMAN_TYPE_ABGR, 8, 8, 8, 8);
+/// [`Format::XRGB32`](crate::Format::XRGB32).
+pub const PIXMAN_x8b8g8r8: u32 = pixman_format(32, PIXMAN_TYPE_ABGR, 0, 8, 8, 8);
+/// [`Format::ARGB32`](crate::Format::ARGB32).
+pub const PIXMAN_b8g8r8a8: u32 = pixman_format(32, PIXMAN_TYPE_BGRA, 8, 8, 8, 8);
+/// [`Format::ABGR32`](crate::Format::ABGR32).
+pub const PIXMAN_r8g8b8a8: u32 = pixman_format(32, PIXMAN_TYPE_RGBA, 8, 8, 8, 8);
+/// Ten bits of blue, green and red, then two bits of alpha.
+pub const PIXMAN_a2r10g10b10: u32 = pixman_format(32, PIXMAN_TYPE_ARGB, 2, 10, 10, 10);
+/// Ten bits of red, green and blue, then two bits of alpha.
+pub const PIXMAN_a2b10g10r10: u32 = pixman_format(32, PIXMAN_TYPE_ABGR, 2, 10, 10, 10);
+/// [`Format::BGR24`](crate::Format::BGR24).
+pub const PIXMAN_r8g8b8: u32 = pixman_format(24, PIXMAN_TYPE_ARGB, 0, 8, 8, 8);
+/// [`Format::RGB24`](crate::Format::RGB24).
+pub const PIXMAN_b8g8r8: u32 = pixman_format(24, PIXMAN_TYPE_ABGR, 0, 8, 8, 8);
+/// [`Format::RGB565`](crate::Format::RGB565).
+pub const PIXMAN_r5g6b5: u32 = pixman_format(16, PIXMAN_TYPE_ARGB, 0, 5, 6, 5);
+/// Red in the lowest five bits, then green and blue.
+pub const PIXMAN_b5g6r5: u32 = pixman_format(16, PIXMAN_TYPE_ABGR, 0, 5, 6, 5);
+/// [`Format::ARGB1555`](crate::Format::ARGB1555).
+pub const PIXMAN_a1r5g5b5: u32 = pixman_format(16, PIXMAN_TYPE_ARGB, 1, 5, 5, 5);
+/// [`Format::RGB555`](crate::Format::RGB555).
+pub const PIXMAN_x1r5g5b5: u32 = pixman_format(16, PIXMAN_TYPE_ARGB, 0, 5, 5, 5);
+/// Alpha in the top four bits, then red, green and blue.
+pub const PIXMAN_a4r4g4b4: u32 = pixman_format(16, PIXMAN_TYPE_ARGB, 4, 4, 4, 4);
+/// [`Format::XBGR16`](crate::Format::XBGR16).
+pub const PIXMAN_x4r4g4b4: u32 = pixman_format(16, PIXMAN_TYPE_ARGB, 0, 4, 4, 4);
+/// [`Format::A8`](crate::Format::A8).
+pub const PIXMAN_a8: u32 = pixman_format(8, PIXMAN_TYPE_A, 8, 0, 0, 0);
+/// [`Format::A4`](crate::Format::A4).
+pub const PIXMAN_a4: u32 = pixman_format(4, PIXMAN_TYPE_A, 4, 0, 0, 0);
+/// [`Format::A1`](crate::Format::A1).
+pub const PIXMAN_a1: u32 = pixman_format(1, PIXMAN_TYPE_A, 1, 0, 0, 0);
+/// [`Format::INDEXED8`](crate::Format::INDEXED8).
+pub const PIXMAN_c8: u32 = pixman_format(8, PIXMAN_TYPE_COLOR, 0, 0, 0, 0);
+/// [`Format::INDEXED4`](crate::Format::INDEXED4).
+pub const PIXMAN_c4: u32 = pixman_format(4, PIXMAN_TYPE_COLOR, 0, 0, 0, 0);
+/// [`Format::YUYV`](crate::Format::YUYV).
+pub const PIXMAN_yuy2: u32 = pixman_format(16, PIXMAN_TYPE_YUY2, 0, 0, 0, 0);
+
+/// Formats that aren't described by the channels of their code.
+const SPECIAL_FORMATS: &[(u32, Format)] = &[
+    (PIXMAN_a8, Format::A8),
+    (PIXMAN_a4, Format::A4),
+    (PIXMAN_a1, Format::A1),
+    (PIXMAN_c8, Format::INDEXED8),
+    (PIXMAN_c4, Format::INDEXED4),
+    (PIXMAN_yuy2, Format::YUYV),
+];
+
+/// The types whose channels are placed by their code.
+const RGB_TYPES: [u32; 4] = [
+    PIXMAN_TYPE_ARGB,
+    PIXMAN_TYPE_ABGR,
+    PIXMAN_TYPE_BGRA,
+    PIXMAN_TYPE_RGBA,
+];
+
+impl Format {
+    /// The pixman format code with the same layout as this format, if
+    /// there is one.
+    ///
+    /// Pixman always premultiplies alpha, so the alpha mode is ignored.
+    ///
+    /// This requires the `pixman` feature.
+    pub fn to_pixman(self) -> Option<u32> {
+        let format = self.with_alpha_mode(AlphaMode::Straight);
+        if let Some(&(code, _)) = SPECIAL_FORMATS.iter().find(|&&(_, known)| known == format) {
+            return Some(code);
+        }
+
+        let rgb = core::matches!(
+            format.color_type(),
+            ColorType::Argb
+                | ColorType::Rgba
+                | ColorType::Abgr
+                | ColorType::Bgra
+                | ColorType::Custom(_)
+        );
+        if !rgb {
+            return None;
+        }
+
+        let bits = |channel| format.channel_mask(channel).count_ones();
+        let (a, r, g, b) = (
+            bits(Channel::Alpha),
+            bits(Channel::Red),
+            bits(Channel::Green),
+            bits(Channel::Blue),
+        );
+        if [a, r, g, b].iter().any(|&bits| bits > 15) {
+            return None;
+        }
+
+        // find the type that puts every channel in the same place
+        RGB_TYPES
+            .iter()
+            .map(|&ty| pixman_format(u32::from(format.bpp()), ty, a, r, g, b))
+            .find(|&code| {
+                Format::from_pixman(code).map_or(false, |known| {
+                    known.bpp() == format.bpp()
+                        && [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha]
+                            .iter()
+                            .all(|&channel| {
+                                known.channel_mask(channel) == format.channel_mask(channel)
+                            })
+                })
+            })
+    }
+
+    /// The format with the same layout as a pixman format code, if there
+    /// is one.
+    ///
+    /// Formats with color and alpha are premultiplied, as pixman expects.
+    /// Gray formats, which pixman looks up in a palette, and formats whose
+    /// channels don't start at the lowest bit, such as `PIXMAN_b8g8r8x8`,
+    /// have no format.
+    ///
+    /// This requires the `pixman` feature.
+    pub fn from_pixman(code: u32) -> Option<Format> {
+        if let Some(&(_, format)) = SPECIAL_FORMATS.iter().find(|&&(known, _)| known == code) {
+            return Some(format);
+        }
+
+        let bpp = code >> 24;
+        let ty = code >> 16 & 0xFF;
+        let field = |shift: u32| code >> shift & 0xF;
+        let (a, r, g, b) = (field(12), field(8), field(4), field(0));
+        if bpp > 32 || a + r + g + b > bpp {
+            return None;
+        }
+
+        // the channels from the lowest bits up, along with their bits
+        let order = match ty {
+            PIXMAN_TYPE_ARGB => [
+                (Channel::Blue, b),
+                (Channel::Green, g),
+                (Channel::Red, r),
+                (Channel::Alpha, a),
+            ],
+            PIXMAN_TYPE_ABGR => [
+                (Channel::Red, r),
+                (Channel::Green, g),
+                (Channel::Blue, b),
+                (Channel::Alpha, a),
+            ],
+            PIXMAN_TYPE_BGRA => [
+                (Channel::Alpha, a),
+                (Channel::Red, r),
+                (Channel::Green, g),
+                (Channel::Blue, b),
+            ],
+            PIXMAN_TYPE_RGBA => [
+                (Channel::Alpha, a),
+                (Channel::Blue, b),
+                (Channel::Green, g),
+                (Channel::Red, r),
+            ],
+            _ => return None,
+        };
+
+        // BGRA and RGBA codes put their channels at the top of the pixel
+        let mut shift = match ty {
+            PIXMAN_TYPE_BGRA | PIXMAN_TYPE_RGBA => bpp - (a + r + g + b),
+            _ => 0,
+        };
+        let mut masks = [0u32; 4];
+        for &(channel, bits) in order.iter() {
+            masks[channel as usize] = (low_bits(bits as u8) as u32) << shift;
+            shift += bits;
+        }
+
+        let [red, green, blue, alpha] = masks;
+        let format = Format::from_masks(bpp as u8, red, green, blue, alpha)?;
+        Some(if alpha != 0 && red | green | blue != 0 {
+            format.with_alpha_mode(AlphaMode::Premultiplied)
+        } else {
+            format
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Endianness, Pixel, Rgba};
+
+    #[test]
+    fn codes() {
+        let known = [
+            (PIXMAN_a8r8g8b8, Format::BGRA32),
+            (PIXMAN_x8r8g8b8, Format::XBGR32),
+            (PIXMAN_a8b8g8r8, Format::RGBA32),
+            (PIXMAN_x8b8g8r8, Format::XRGB32),
+            (PIXMAN_b8g8r8a8, Format::ARGB32),
+            (PIXMAN_r8g8b8a8, Format::ABGR32),
+            (PIXMAN_r8g8b8, Format::BGR24),
+            (PIXMAN_b8g8r8, Format::RGB24),
+            (PIXMAN_r5g6b5, Format::RGB565),
+            (PIXMAN_a1r5g5b5, Format::ARGB1555),
+            (PIXMAN_x1r5g5b5, Format::RGB555),
+            (PIXMAN_x4r4g4b4, Format::XBGR16),
+            (PIXMAN_a8, Format::A8),
+            (PIXMAN_c8, Format::INDEXED8),
+            (PIXMAN_yuy2, Format::YUYV),
+        ];
+        for &(code, format) in known.iter() {
+            let straight =
+                Format::from_pixman(code).map(|f| f.with_alpha_mode(AlphaMode::Straight));
+            assert_eq!(straight, Some(format), "{:#x}", code);
+            assert_eq!(format.to_pixman(), Some(code), "{:?}", format);
+        }
+
+        assert_eq!(
+            Format::from_pixman(PIXMAN_a8r8g8b8),
+            Some(Format::BGRA32.with_alpha_mode(AlphaMode::Premultiplied))
+        );
+
+        // ten-bit channels, with alpha in the top bits
+        let format = Format::from_pixman(PIXMAN_a2r10g10b10)
+            .unwrap()
+            .with_alpha_mode(AlphaMode::Straight);
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        assert_eq!(
+            Pixel::from_rgba(red, format, Endianness::Little).raw_u32(),
+            0xFFF0_0000
+        );
+        assert_eq!(format.to_pixman(), Some(PIXMAN_a2r10g10b10));
+        assert_eq!(Format::BGRX32.to_pixman(), Some(PIXMAN_x8r8g8b8));
+
+        let b8g8r8x8 = pixman_format(32, PIXMAN_TYPE_BGRA, 0, 8, 8, 8);
+        assert_eq!(Format::from_pixman(b8g8r8x8), None);
+        assert_eq!(Format::CMYK32.to_pixman(), None);
+    }
+}
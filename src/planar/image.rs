@@ -0,0 +1,632 @@
+// BSL 1.0 License
+
+use super::ChromaSubsampling;
+use crate::{
+    codec::{self, low_bits, Codec},
+    divide_rounding_up,
+    ops::Span,
+    Endianness, Format, Image, TransferFunction, YcbcrMatrix,
+};
+use core::cmp;
+
+/// The number of pixels converted at once.
+const CHUNK_PIXELS: usize = 256;
+
+/// One plane of a [`PlanarImage`], with its own stride.
+///
+/// [`PlanarImage`]: crate::PlanarImage
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Plane<Storage> {
+    storage: Storage,
+    stride: usize,
+}
+
+impl<Storage> Plane<Storage> {
+    /// Create a plane out of a buffer where each row starts `stride` bytes
+    /// after the previous one.
+    pub const fn new(storage: Storage, stride: usize) -> Self {
+        Self { storage, stride }
+    }
+
+    /// The buffer holding the plane.
+    pub const fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    /// The number of bytes between the start of each row.
+    pub const fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Get back the buffer holding the plane.
+    pub fn into_storage(self) -> Storage {
+        self.storage
+    }
+}
+
+impl<Storage: AsRef<[u8]>> Plane<Storage> {
+    fn row(&self, y: usize) -> &[u8] {
+        &self.storage.as_ref()[y * self.stride..]
+    }
+
+    /// Panic unless the plane holds `height` rows of `len` bytes.
+    fn check(&self, len: usize, height: usize, name: &str) {
+        let needed = match height {
+            0 => 0,
+            height => (height - 1) * self.stride + len,
+        };
+        assert!(
+            self.stride >= len && self.storage.as_ref().len() >= needed,
+            "The {} plane is too small",
+            name
+        );
+    }
+}
+
+impl<Storage: AsMut<[u8]>> Plane<Storage> {
+    fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        &mut self.storage.as_mut()[y * self.stride..]
+    }
+}
+
+/// How the chroma of a `PlanarImage` is stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Chroma<Storage> {
+    /// Blue-difference and red-difference chroma in planes of their own.
+    Separate(Plane<Storage>, Plane<Storage>),
+    /// Blue-difference and red-difference chroma interleaved in one plane.
+    Interleaved(Plane<Storage>),
+}
+
+/// A YCbCr image stored across several planes, as most video frames are.
+///
+/// The luma is stored in a plane of its own, and the chroma is stored
+/// either in two more planes, as in I420, or interleaved in one plane, as
+/// in NV12. Each plane has its own stride, and the chroma planes are
+/// subsampled according to a [`ChromaSubsampling`]. Samples are 8 bits,
+/// or 10 bits for [`p010`] images, in the limited range used by video.
+///
+/// Scanlines are converted to and from packed pixels of [`RGB24`] by
+/// default, or of the format set by [`with_format`]. Chroma is
+/// interpolated between sites as it is read, and writes replace the
+/// chroma of every site they touch with the average of the pixels written
+/// to it, so writing one row of a vertically subsampled image also changes
+/// the color of its neighbor.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Format, Image, PlanarImage, Plane};
+///
+/// // a 2x2 red frame
+/// let image = PlanarImage::nv12(2, 2, Plane::new(vec![81u8; 4], 2), Plane::new(vec![90, 240], 2));
+/// assert_eq!(image.format(), Format::RGB24);
+///
+/// let red = image.pixel(1, 1).to_rgba();
+/// assert!(red.red > 0xF000 && red.green < 0x1000 && red.blue < 0x1000);
+/// ```
+///
+/// [`ChromaSubsampling`]: crate::ChromaSubsampling
+/// [`RGB24`]: crate::Format::RGB24
+/// [`with_format`]: crate::PlanarImage::with_format
+/// [`p010`]: crate::PlanarImage::p010
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct PlanarImage<Storage> {
+    width: usize,
+    height: usize,
+    subsampling: ChromaSubsampling,
+    matrix: YcbcrMatrix,
+    format: Format,
+    endianness: Endianness,
+    transfer_function: TransferFunction,
+    /// The number of bits in each sample.
+    depth: u8,
+    luma: Plane<Storage>,
+    chroma: Chroma<Storage>,
+}
+
+impl<Storage: AsRef<[u8]>> PlanarImage<Storage> {
+    /// Create an image out of a luma plane and separate blue-difference and
+    /// red-difference chroma planes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a plane is too small for the dimensions of the image.
+    pub fn new(
+        width: usize,
+        height: usize,
+        subsampling: ChromaSubsampling,
+        luma: Plane<Storage>,
+        blue: Plane<Storage>,
+        red: Plane<Storage>,
+    ) -> Self {
+        let (chroma_width, chroma_height) = subsampling.chroma_dimensions(width, height);
+        luma.check(width, height, "luma");
+        blue.check(chroma_width, chroma_height, "blue-difference");
+        red.check(chroma_width, chroma_height, "red-difference");
+
+        Self::from_parts(
+            width,
+            height,
+            subsampling,
+            luma,
+            Chroma::Separate(blue, red),
+        )
+    }
+
+    /// Create an image out of a luma plane and a plane where each chroma
+    /// site holds a blue-difference and then a red-difference sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a plane is too small for the dimensions of the image.
+    pub fn interleaved(
+        width: usize,
+        height: usize,
+        subsampling: ChromaSubsampling,
+        luma: Plane<Storage>,
+        chroma: Plane<Storage>,
+    ) -> Self {
+        let (chroma_width, chroma_height) = subsampling.chroma_dimensions(width, height);
+        luma.check(width, height, "luma");
+        chroma.check(chroma_width * 2, chroma_height, "chroma");
+
+        Self::from_parts(
+            width,
+            height,
+            subsampling,
+            luma,
+            Chroma::Interleaved(chroma),
+        )
+    }
+
+    /// Create an I420 image, where both chroma planes are subsampled
+    /// by two in each direction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a plane is too small for the dimensions of the image.
+    pub fn i420(
+        width: usize,
+        height: usize,
+        luma: Plane<Storage>,
+        blue: Plane<Storage>,
+        red: Plane<Storage>,
+    ) -> Self {
+        Self::new(width, height, ChromaSubsampling::Yuv420, luma, blue, red)
+    }
+
+    /// Create an NV12 image, where the interleaved chroma plane is
+    /// subsampled by two in each direction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a plane is too small for the dimensions of the image.
+    pub fn nv12(width: usize, height: usize, luma: Plane<Storage>, chroma: Plane<Storage>) -> Self {
+        Self::interleaved(width, height, ChromaSubsampling::Yuv420, luma, chroma)
+    }
+
+    /// Create a P010 image, which is laid out like NV12 but with 10-bit
+    /// samples in the high bits of 16-bit little-endian words, as HDR video
+    /// decoders produce.
+    ///
+    /// Scanlines are converted to and from [`RGB48`] by default, so that
+    /// the extra bits aren't lost.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a plane is too small for the dimensions of the image.
+    ///
+    /// [`RGB48`]: crate::Format::RGB48
+    pub fn p010(width: usize, height: usize, luma: Plane<Storage>, chroma: Plane<Storage>) -> Self {
+        let subsampling = ChromaSubsampling::Yuv420;
+        let (chroma_width, chroma_height) = subsampling.chroma_dimensions(width, height);
+        luma.check(width * 2, height, "luma");
+        chroma.check(chroma_width * 4, chroma_height, "chroma");
+
+        Self {
+            depth: 10,
+            format: Format::RGB48,
+            ..Self::from_parts(
+                width,
+                height,
+                subsampling,
+                luma,
+                Chroma::Interleaved(chroma),
+            )
+        }
+    }
+
+    fn from_parts(
+        width: usize,
+        height: usize,
+        subsampling: ChromaSubsampling,
+        luma: Plane<Storage>,
+        chroma: Chroma<Storage>,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            subsampling,
+            matrix: YcbcrMatrix::Bt601,
+            format: Format::RGB24,
+            endianness: Endianness::NATIVE,
+            transfer_function: TransferFunction::Srgb,
+            depth: 8,
+            luma,
+            chroma,
+        }
+    }
+}
+
+impl<Storage> PlanarImage<Storage> {
+    /// Use a different matrix to convert between YCbCr and RGB.
+    ///
+    /// The default is BT.601.
+    pub fn with_matrix(self, matrix: YcbcrMatrix) -> Self {
+        Self { matrix, ..self }
+    }
+
+    /// Present the image as packed pixels of a different format and
+    /// endianness.
+    pub fn with_format(self, format: Format, endianness: Endianness) -> Self {
+        Self {
+            format,
+            endianness,
+            ..self
+        }
+    }
+
+    /// Set the transfer function that the decoded RGB values are encoded
+    /// with, such as [`Pq`] for HDR10 frames.
+    ///
+    /// The default is sRGB.
+    ///
+    /// [`Pq`]: crate::TransferFunction::Pq
+    pub fn with_transfer_function(self, transfer_function: TransferFunction) -> Self {
+        Self {
+            transfer_function,
+            ..self
+        }
+    }
+
+    /// How much the chroma is subsampled.
+    pub const fn subsampling(&self) -> ChromaSubsampling {
+        self.subsampling
+    }
+
+    /// The matrix used to convert between YCbCr and RGB.
+    pub const fn matrix(&self) -> YcbcrMatrix {
+        self.matrix
+    }
+
+    /// The number of bits in each sample: 10 for P010, and 8 otherwise.
+    pub const fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// The number of planes: three for separate chroma planes, or two for
+    /// interleaved chroma.
+    pub fn plane_count(&self) -> usize {
+        match self.chroma {
+            Chroma::Separate(..) => 3,
+            Chroma::Interleaved(_) => 2,
+        }
+    }
+
+    /// The plane at the given index, starting with the luma plane.
+    pub fn plane(&self, index: usize) -> Option<&Plane<Storage>> {
+        match (index, &self.chroma) {
+            (0, _) => Some(&self.luma),
+            (1, Chroma::Separate(blue, _)) | (1, Chroma::Interleaved(blue)) => Some(blue),
+            (2, Chroma::Separate(_, red)) => Some(red),
+            _ => None,
+        }
+    }
+}
+
+impl<Storage: AsRef<[u8]>> PlanarImage<Storage> {
+    /// Read the upsampled chroma of `count` pixels starting at `x` into
+    /// `blue` and `red`.
+    fn read_chroma(&self, x: usize, y: usize, blue: &mut [u16], red: &mut [u16]) {
+        let (horizontal, _) = self.subsampling.factors();
+        let (chroma_width, _) = self.subsampling.chroma_dimensions(self.width, self.height);
+        let row = self.subsampling.chroma_row(y);
+        let depth = self.depth;
+
+        // upsample from the site holding the first pixel
+        let site = x / horizontal;
+        let offset = x % horizontal;
+        let count = blue.len();
+
+        match self.chroma {
+            Chroma::Separate(ref blue_plane, ref red_plane) => {
+                for (plane, output) in [(blue_plane, &mut *blue), (red_plane, &mut *red)].iter_mut()
+                {
+                    let chroma = plane.row(row);
+                    self.subsampling.upsample_with(
+                        chroma_width - site,
+                        offset + count,
+                        1,
+                        |i| load(chroma, site + i, depth),
+                        |i, value| {
+                            if i >= offset {
+                                output[i - offset] = value as u16;
+                            }
+                        },
+                    );
+                }
+            }
+            Chroma::Interleaved(ref plane) => {
+                let chroma = plane.row(row);
+                self.subsampling.upsample_with(
+                    chroma_width - site,
+                    offset + count,
+                    2,
+                    |i| load(chroma, site * 2 + i, depth),
+                    |i, value| {
+                        if i >= offset * 2 {
+                            let i = i - offset * 2;
+                            let output = if i % 2 == 0 { &mut *blue } else { &mut *red };
+                            output[i / 2] = value as u16;
+                        }
+                    },
+                );
+            }
+        }
+    }
+}
+
+impl<Storage: AsMut<[u8]>> PlanarImage<Storage> {
+    /// Write the chroma of the site at the given coordinates.
+    fn write_chroma(&mut self, site: usize, row: usize, blue: u32, red: u32) {
+        let depth = self.depth;
+        match self.chroma {
+            Chroma::Separate(ref mut blue_plane, ref mut red_plane) => {
+                store(blue_plane.row_mut(row), site, depth, blue);
+                store(red_plane.row_mut(row), site, depth, red);
+            }
+            Chroma::Interleaved(ref mut plane) => {
+                let row = plane.row_mut(row);
+                store(row, site * 2, depth, blue);
+                store(row, site * 2 + 1, depth, red);
+            }
+        }
+    }
+}
+
+impl<Storage: AsRef<[u8]> + AsMut<[u8]>> Image for PlanarImage<Storage> {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.transfer_function
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        divide_rounding_up(self.width * self.format.bpp() as usize, 8)
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        if y >= self.height {
+            return 0;
+        }
+
+        let codec = Codec::new(self.format, self.endianness);
+        let span = Span::new(self.format, x, self.width, scanline.len());
+        let luma = self.luma.row(y);
+        let (mut blue, mut red) = ([0u16; CHUNK_PIXELS], [0u16; CHUNK_PIXELS]);
+
+        let mut done = 0;
+        while done < span.count {
+            let count = cmp::min(CHUNK_PIXELS, span.count - done);
+            let start = x + done;
+            self.read_chroma(start, y, &mut blue[..count], &mut red[..count]);
+
+            for i in 0..count {
+                let ycbcr = [
+                    load(luma, start + i, self.depth),
+                    u32::from(blue[i]),
+                    u32::from(red[i]),
+                ];
+                let components = codec::ycbcr_to_rgb(self.matrix, normalize(ycbcr, self.depth));
+                codec.write(scanline, span.skip + done + i, components);
+            }
+
+            done += count;
+        }
+
+        span.bytes(self.format)
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        if y >= self.height {
+            return 0;
+        }
+
+        let codec = Codec::new(self.format, self.endianness);
+        let span = Span::new(self.format, x, self.width, scanline.len());
+        let (horizontal, _) = self.subsampling.factors();
+        let row = self.subsampling.chroma_row(y);
+
+        // the chroma of the site being written, summed over its pixels
+        let mut site = x / horizontal;
+        let mut sums = [0.0f32; 2];
+        let mut pixels = 0;
+
+        for i in 0..span.count {
+            let px = x + i;
+            if px / horizontal != site {
+                self.write_chroma(
+                    site,
+                    row,
+                    average(sums[0], pixels, self.depth),
+                    average(sums[1], pixels, self.depth),
+                );
+                site = px / horizontal;
+                sums = [0.0; 2];
+                pixels = 0;
+            }
+
+            let components = codec.read(scanline, span.skip + i);
+            let [luma, blue, red] = codec::rgb_to_ycbcr(self.matrix, components);
+            let luma = codec::quantize(luma, self.depth) as u32;
+            store(self.luma.row_mut(y), px, self.depth, luma);
+            sums[0] += blue;
+            sums[1] += red;
+            pixels += 1;
+        }
+
+        if pixels > 0 {
+            self.write_chroma(
+                site,
+                row,
+                average(sums[0], pixels, self.depth),
+                average(sums[1], pixels, self.depth),
+            );
+        }
+
+        span.bytes(self.format)
+    }
+}
+
+/// Read the `i`th sample of a row with samples of `depth` bits.
+fn load(row: &[u8], i: usize, depth: u8) -> u32 {
+    match depth {
+        8 => u32::from(row[i]),
+        // deeper samples are in the high bits of little-endian words
+        _ => u32::from(u16::from_le_bytes([row[i * 2], row[i * 2 + 1]])) >> (16 - depth),
+    }
+}
+
+/// Write the `i`th sample of a row with samples of `depth` bits.
+fn store(row: &mut [u8], i: usize, depth: u8, value: u32) {
+    match depth {
+        8 => row[i] = value as u8,
+        _ => {
+            let word = ((value << (16 - depth)) as u16).to_le_bytes();
+            row[i * 2..i * 2 + 2].copy_from_slice(&word);
+        }
+    }
+}
+
+fn normalize(ycbcr: [u32; 3], depth: u8) -> [f32; 3] {
+    let [luma, blue, red] = ycbcr;
+    let scale = |value| value as f32 / low_bits(depth) as f32;
+    [scale(luma), scale(blue), scale(red)]
+}
+
+fn average(sum: f32, count: usize, depth: u8) -> u32 {
+    codec::quantize(sum / count as f32, depth) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i420_roundtrip() {
+        // 3x2 with a two-site chroma row: red on the left, blue on the right
+        let mut luma = [81u8, 81, 41, 0, 81, 81, 41, 0];
+        let mut blue = [90u8, 240];
+        let mut red = [240u8, 110];
+        let mut image = PlanarImage::i420(
+            3,
+            2,
+            Plane::new(&mut luma[..], 4),
+            Plane::new(&mut blue[..], 2),
+            Plane::new(&mut red[..], 2),
+        )
+        .with_format(Format::RGB24, Endianness::Little);
+        assert_eq!(image.plane_count(), 3);
+
+        let mut row = [0u8; 9];
+        assert_eq!(image.scanline(0, 1, &mut row), 9);
+        let codec = Codec::new(Format::RGB24, Endianness::Little);
+        let first = codec.read(&row, 0);
+        assert!(first[0] > 0.99 && first[2] < 0.01, "{:?}", first);
+
+        // the middle pixel is interpolated and the last one is blue
+        let last = codec.read(&row, 2);
+        assert!(last[2] > 0.99 && last[0] < 0.01, "{:?}", last);
+
+        // writing back the same pixels gives the same planes
+        image.set_scanline(0, 0, &row);
+        assert_eq!(luma[..3], [81, 81, 41]);
+        assert_eq!((blue[1], red[1]), (240, 110));
+    }
+
+    #[test]
+    fn p010_keeps_ten_bits() {
+        let word = |sample: u16| (sample << 6).to_le_bytes();
+        let mut luma = [0u8; 8];
+        let mut chroma = [0u8; 4];
+        for (bytes, &sample) in luma.chunks_exact_mut(2).zip([300u16, 301, 302, 303].iter()) {
+            bytes.copy_from_slice(&word(sample));
+        }
+        chroma[..2].copy_from_slice(&word(512));
+        chroma[2..].copy_from_slice(&word(512));
+
+        let mut image = PlanarImage::p010(
+            2,
+            2,
+            Plane::new(&mut luma[..], 4),
+            Plane::new(&mut chroma[..], 4),
+        )
+        .with_format(Format::RGB48, Endianness::Little);
+        assert_eq!((image.depth(), image.plane_count()), (10, 2));
+
+        // neighboring luma levels stay apart through 16-bit pixels
+        let mut row = [0u8; 12];
+        assert_eq!(image.scanline(0, 0, &mut row), 12);
+        assert_ne!(row[..2], row[6..8]);
+
+        image.set_scanline(0, 0, &row);
+        assert_eq!(luma[..4], [word(300), word(301)].concat()[..]);
+        assert_eq!(chroma, [word(512), word(512)].concat()[..]);
+    }
+
+    #[test]
+    fn transfer_function() {
+        use crate::{convert, Builder};
+
+        let (mut luma, mut chroma) = ([126u8; 4], [128u8; 2]);
+        let frame = PlanarImage::nv12(
+            2,
+            2,
+            Plane::new(&mut luma[..], 2),
+            Plane::new(&mut chroma[..], 2),
+        );
+        assert_eq!(frame.transfer_function(), TransferFunction::Srgb);
+        let mut gray = [0u8; 6];
+        frame.scanline(0, 0, &mut gray);
+        let gray = [gray, gray].concat();
+
+        // the frame is only converted when the transfer functions differ
+        let frame = frame.with_transfer_function(TransferFunction::Pq);
+        assert_eq!(frame.transfer_function(), TransferFunction::Pq);
+        let mut bytes = [0u8; 12];
+        let mut pq = Builder::from_buffer(2, 2, Format::RGB24, &mut bytes[..])
+            .with_transfer_function(TransferFunction::Pq)
+            .finish();
+        convert(&frame, &mut pq);
+        drop(pq);
+        assert_eq!(bytes[..], gray[..]);
+
+        let mut linear = Builder::from_buffer(2, 2, Format::RGB24, &mut bytes[..])
+            .with_transfer_function(TransferFunction::Linear)
+            .finish();
+        convert(&frame, &mut linear);
+        drop(linear);
+        assert_ne!(bytes[..], gray[..]);
+    }
+}
@@ -0,0 +1,9 @@
+// BSL 1.0 License
+
+//! Support for images stored across several planes.
+
+mod image;
+pub use image::{PlanarImage, Plane};
+
+mod subsampling;
+pub use subsampling::ChromaSubsampling;
@@ -0,0 +1,173 @@
+// BSL 1.0 License
+
+use crate::divide_rounding_up;
+
+/// How much the chroma planes of an image are subsampled relative to
+/// the luma plane.
+///
+/// Video frames often store the color (chroma) information at a lower
+/// resolution than the brightness (luma) information. This describes
+/// the ratio between the two, and provides the resampling needed to
+/// bring them back to the same size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChromaSubsampling {
+    /// Chroma is stored at full resolution.
+    Yuv444,
+    /// Chroma is stored at half the horizontal resolution.
+    Yuv422,
+    /// Chroma is stored at half the horizontal and half the vertical
+    /// resolution, as in NV12 and I420.
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    /// The horizontal and vertical factors that the chroma resolution
+    /// is divided by.
+    pub const fn factors(self) -> (usize, usize) {
+        match self {
+            ChromaSubsampling::Yuv444 => (1, 1),
+            ChromaSubsampling::Yuv422 => (2, 1),
+            ChromaSubsampling::Yuv420 => (2, 2),
+        }
+    }
+
+    /// The dimensions of the chroma planes for an image of the given
+    /// dimensions.
+    ///
+    /// Odd dimensions are rounded up, so the last chroma sample covers
+    /// the last luma sample.
+    pub const fn chroma_dimensions(self, width: usize, height: usize) -> (usize, usize) {
+        let (horizontal, vertical) = self.factors();
+        (
+            divide_rounding_up(width, horizontal),
+            divide_rounding_up(height, vertical),
+        )
+    }
+
+    /// The chroma row that holds the chroma for the given luma row.
+    pub const fn chroma_row(self, y: usize) -> usize {
+        let (_, vertical) = self.factors();
+        y / vertical
+    }
+
+    /// Upsample a row of chroma samples to the full resolution.
+    ///
+    /// `chroma` contains the samples of a chroma row, and `output`
+    /// receives the upsampled samples. `interleave` is the number of
+    /// samples per chroma site: 1 for separate planes such as in I420,
+    /// or 2 for the interleaved CbCr plane of NV12.
+    ///
+    /// Chroma samples are assumed to be sited with the left luma sample
+    /// of each pair, so the in-between samples are interpolated
+    /// linearly. `output.len() / interleave` full-resolution sites are
+    /// written.
+    pub fn upsample_row(self, chroma: &[u8], output: &mut [u8], interleave: usize) {
+        self.upsample_with(
+            chroma.len() / interleave,
+            output.len() / interleave,
+            interleave,
+            |i| chroma[i] as u32,
+            |i, value| output[i] = value as u8,
+        );
+    }
+
+    /// Like [`upsample_row`], but for samples of any depth: sample `i` of
+    /// the `sites` chroma sites is read with `sample(i)`, and sample `i`
+    /// of the `count` full-resolution sites is written with `write`.
+    ///
+    /// [`upsample_row`]: ChromaSubsampling::upsample_row
+    pub(crate) fn upsample_with(
+        self,
+        sites: usize,
+        count: usize,
+        interleave: usize,
+        sample: impl Fn(usize) -> u32,
+        mut write: impl FnMut(usize, u32),
+    ) {
+        let (horizontal, _) = self.factors();
+        if sites == 0 {
+            return;
+        }
+
+        for x in 0..count {
+            let left = x / horizontal;
+            let right = if left + 1 < sites { left + 1 } else { left };
+            let left = if left < sites { left } else { sites - 1 };
+            let between = x % horizontal != 0;
+
+            for i in 0..interleave {
+                let a = sample(left * interleave + i);
+                let value = if between {
+                    let b = sample(right * interleave + i);
+                    (a + b + 1) / 2
+                } else {
+                    a
+                };
+                write(x * interleave + i, value);
+            }
+        }
+    }
+
+    /// Downsample full-resolution chroma into a row of subsampled chroma.
+    ///
+    /// `top` and `bottom` are consecutive full-resolution rows; for
+    /// formats that are not vertically subsampled, `bottom` is ignored.
+    /// Samples that are merged together are averaged. `interleave` has
+    /// the same meaning as in [`upsample_row`].
+    ///
+    /// [`upsample_row`]: ChromaSubsampling::upsample_row
+    pub fn downsample_rows(self, top: &[u8], bottom: &[u8], output: &mut [u8], interleave: usize) {
+        let (horizontal, vertical) = self.factors();
+        let sites = top.len() / interleave;
+
+        for (x, site) in output.chunks_exact_mut(interleave).enumerate() {
+            for (i, sample) in site.iter_mut().enumerate() {
+                let mut sum = 0u32;
+                let mut count = 0u32;
+
+                for dx in 0..horizontal {
+                    let column = x * horizontal + dx;
+                    if column >= sites {
+                        break;
+                    }
+
+                    let index = column * interleave + i;
+                    sum += top[index] as u32;
+                    count += 1;
+                    if vertical > 1 && index < bottom.len() {
+                        sum += bottom[index] as u32;
+                        count += 1;
+                    }
+                }
+
+                if let Some(average) = (sum + count / 2).checked_div(count) {
+                    *sample = average as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChromaSubsampling;
+
+    #[test]
+    fn resample_nv12_row() {
+        // two CbCr pairs, interleaved
+        let chroma = [10u8, 200, 30, 100];
+        let mut upsampled = [0u8; 8];
+        ChromaSubsampling::Yuv420.upsample_row(&chroma, &mut upsampled, 2);
+        assert_eq!(upsampled, [10, 200, 20, 150, 30, 100, 30, 100]);
+
+        let mut downsampled = [0u8; 4];
+        ChromaSubsampling::Yuv420.downsample_rows(&upsampled, &upsampled, &mut downsampled, 2);
+        assert_eq!(downsampled, [15, 175, 30, 100]);
+    }
+
+    #[test]
+    fn chroma_dimensions_round_up() {
+        assert_eq!(ChromaSubsampling::Yuv420.chroma_dimensions(5, 3), (3, 2));
+        assert_eq!(ChromaSubsampling::Yuv422.chroma_row(3), 3);
+    }
+}
@@ -0,0 +1,19 @@
+// BSL 1.0 License
+
+//! Commonly used items, for glob importing.
+//!
+//! ```
+//! use genimage::prelude::*;
+//!
+//! let mut bytes = [0u8; 4 * 4 * 4];
+//! let image = GeneralImage::from_buffer(4, 4, Format::ARGB32, &mut bytes[..]);
+//! assert_eq!(image.dimensions(), (4, 4));
+//! ```
+
+pub use crate::{
+    BitOrder, Border, BorderedImage, Builder, ColorType, ConvertedImage, Endianness, Format,
+    GeneralImage, Image, IndexedImage, MultiViewImage, Pixel, Rgba, TransferFunction,
+};
+
+#[cfg(feature = "alloc")]
+pub use crate::{DynImage, ImageAny, ImageDecoder, ImageEncoder};
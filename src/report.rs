@@ -0,0 +1,239 @@
+// BSL 1.0 License
+
+//! Reports on what is lost when converting between formats, and on what
+//! bulk operations left out.
+
+use crate::{Channel, Format};
+use core::{iter::FusedIterator, ops::AddAssign};
+
+/// A set of channels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ChannelSet(u8);
+
+impl ChannelSet {
+    /// The set with no channels in it.
+    pub const EMPTY: ChannelSet = ChannelSet(0);
+
+    /// Add a channel to the set.
+    pub const fn with(self, channel: Channel) -> Self {
+        ChannelSet(self.0 | (1 << channel as u8))
+    }
+
+    /// Whether the set contains a channel.
+    pub const fn contains(self, channel: Channel) -> bool {
+        self.0 & (1 << channel as u8) != 0
+    }
+
+    /// Whether the set has no channels in it.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The number of channels in the set.
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Iterate over the channels in the set.
+    pub fn iter(self) -> impl FusedIterator<Item = Channel> {
+        [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha]
+            .iter()
+            .copied()
+            .filter(move |&channel| self.contains(channel))
+    }
+}
+
+/// What is lost when converting pixels from one format to another.
+///
+/// This is returned by [`Format::conversion_report`].
+///
+/// [`Format::conversion_report`]: crate::Format::conversion_report
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ConversionReport {
+    /// Channels that are kept, but that can't represent every value of
+    /// the original.
+    pub lossy_channels: ChannelSet,
+    /// Channels that the target format doesn't have at all.
+    pub dropped_channels: ChannelSet,
+    /// Whether any of the kept channels has fewer bits in the target
+    /// format.
+    pub depth_reduced: bool,
+}
+
+impl ConversionReport {
+    /// Whether every pixel survives the conversion.
+    pub const fn is_lossless(&self) -> bool {
+        self.lossy_channels.is_empty() && self.dropped_channels.is_empty()
+    }
+}
+
+/// What a bulk operation did with each row it was asked to write.
+///
+/// Operations like [`convert`] and [`blit_bits`] only write the area
+/// where both images overlap, and images can write fewer bytes than they
+/// are given. Rather than leaving the caller to compare sizes, these
+/// operations count the rows that didn't make it in full, so pipelines
+/// can check that nothing was clipped.
+///
+/// Reports of several operations can be added together with `+=`.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{convert, Format, GeneralImage};
+///
+/// let src = GeneralImage::from_buffer(3, 3, Format::GRAY8, [0u8; 9]);
+/// let mut dst = GeneralImage::from_buffer(2, 4, Format::GRAY8, [0u8; 8]);
+///
+/// let report = convert(&src, &mut dst);
+/// assert_eq!(report.rows_clipped, 3);
+/// assert!(!report.is_complete());
+/// ```
+///
+/// [`convert`]: crate::convert
+/// [`blit_bits`]: crate::blit_bits
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct OpReport {
+    /// Rows that were written in full.
+    pub rows_converted: usize,
+    /// Rows that were written, but not in full, because the destination is
+    /// too narrow or wrote fewer bytes than it was given.
+    pub rows_clipped: usize,
+    /// Rows that weren't written at all, because they fall outside of the
+    /// destination.
+    pub rows_skipped: usize,
+}
+
+impl OpReport {
+    /// Whether every row was written in full.
+    pub const fn is_complete(&self) -> bool {
+        self.rows_clipped == 0 && self.rows_skipped == 0
+    }
+
+    /// Count a row that was written, in full or not.
+    pub(crate) fn add_row(&mut self, complete: bool) {
+        if complete {
+            self.rows_converted += 1;
+        } else {
+            self.rows_clipped += 1;
+        }
+    }
+}
+
+impl AddAssign for OpReport {
+    fn add_assign(&mut self, other: OpReport) {
+        self.rows_converted += other.rows_converted;
+        self.rows_clipped += other.rows_clipped;
+        self.rows_skipped += other.rows_skipped;
+    }
+}
+
+impl Format {
+    /// Describe what is lost when converting pixels from `from` to `to`.
+    ///
+    /// This lets applications warn users before degrading their content,
+    /// for instance that saving to a given format drops the alpha channel.
+    ///
+    /// Integer channels of up to 16 bits are represented exactly by
+    /// floats, but floats always lose precision when they are stored as
+    /// integers. Bits that aren't part of a channel, such as the padding in
+    /// [`XRGB32`], are not considered.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use genimage::{Channel, Format};
+    ///
+    /// let report = Format::conversion_report(Format::ARGB32, Format::RGB24);
+    /// assert!(report.dropped_channels.contains(Channel::Alpha));
+    /// assert!(!report.depth_reduced);
+    /// ```
+    ///
+    /// [`XRGB32`]: crate::Format::XRGB32
+    pub fn conversion_report(from: Format, to: Format) -> ConversionReport {
+        let mut report = ConversionReport::default();
+
+        for info in from.channels() {
+            let kept = match to.channels().find(|other| other.channel == info.channel) {
+                Some(kept) => kept,
+                None => {
+                    report.dropped_channels = report.dropped_channels.with(info.channel);
+                    continue;
+                }
+            };
+
+            let lossy = match (from.involves_float(), to.involves_float()) {
+                // half floats have 11 bits of precision, which is enough
+                // to give back integers of up to 10 bits
+                (false, true) if kept.bits == 16 => info.bits > 10,
+                (false, true) => info.bits > 16,
+                (true, false) => true,
+                _ => kept.bits < info.bits,
+            };
+            // the red channel of a gray format holds the level of all colors
+            let lossy = lossy || (info.channel == Channel::Red && gray_changes(from, to));
+            // CMYK is stored in the slots of other channels
+            let lossy = lossy || from.color_type().is_cmyk() != to.color_type().is_cmyk();
+            // YCbCr goes through RGB unless the format stays the same
+            let lossy = lossy || (from != to && (is_ycbcr(from) || is_ycbcr(to)));
+            // multiplying by alpha and dividing again rounds the colors
+            let lossy = lossy
+                || (info.channel != Channel::Alpha && from.premultiplies() != to.premultiplies());
+
+            if lossy {
+                report.lossy_channels = report.lossy_channels.with(info.channel);
+            }
+            if kept.bits < info.bits {
+                report.depth_reduced = true;
+            }
+        }
+
+        report
+    }
+}
+
+fn is_ycbcr(format: Format) -> bool {
+    format.color_type().ycbcr_matrix().is_some()
+}
+
+/// Whether converting from `from` to `to` either mixes several colors
+/// into one gray level, or keeps a gray level in a format that can't
+/// give it back as every color.
+fn gray_changes(from: Format, to: Format) -> bool {
+    match (from.color_type().is_gray(), to.color_type().is_gray()) {
+        (false, true) => from.green_bits() > 0 || from.blue_bits() > 0,
+        (true, false) => to.green_bits() == 0 || to.blue_bits() == 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports() {
+        let report = Format::conversion_report(Format::ARGB_F32, Format::XRGB16);
+        assert_eq!(report.lossy_channels.len(), 3);
+        assert!(report.dropped_channels.contains(Channel::Alpha));
+        assert!(report.depth_reduced);
+
+        let report = Format::conversion_report(Format::RGB24, Format::ARGB_F32);
+        assert!(report.is_lossless());
+        assert!(!report.depth_reduced);
+
+        let report = Format::conversion_report(Format::RGB24, Format::A8);
+        assert!(report.lossy_channels.is_empty());
+        assert!(itertools::equal(
+            report.dropped_channels.iter(),
+            [Channel::Red, Channel::Green, Channel::Blue]
+                .iter()
+                .copied()
+        ));
+
+        assert!(Format::conversion_report(Format::LA8, Format::RGBA32).is_lossless());
+        let report = Format::conversion_report(Format::RGBA32, Format::LA8);
+        assert!(report.lossy_channels.contains(Channel::Red));
+        assert_eq!(report.dropped_channels.len(), 2);
+    }
+}
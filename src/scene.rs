@@ -0,0 +1,451 @@
+// BSL 1.0 License
+
+use crate::{
+    codec::{Codec, Components},
+    ops::{bands, is_ycbcr, source_over, unit_span},
+    ChunkingHints, Image, Rect, TransferFunction, MAX_BYTES_PER_PIXEL,
+};
+use alloc::vec::Vec;
+use core::cmp;
+
+/// The number of pixels composited at once.
+const CHUNK_PIXELS: usize = 256;
+
+/// Identifies a layer of a [`Scene`].
+///
+/// [`Scene`]: crate::Scene
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LayerId(usize);
+
+#[derive(Debug)]
+struct Layer<I> {
+    id: LayerId,
+    image: I,
+    x: usize,
+    y: usize,
+    visible: bool,
+}
+
+impl<I: Image> Layer<I> {
+    fn bounds(&self) -> Rect {
+        let (width, height) = self.image.dimensions();
+        Rect::new(self.x, self.y, width, height)
+    }
+}
+
+/// A stack of positioned images that are composited together.
+///
+/// Layers are kept from bottom to top, and each of them can be moved or
+/// hidden. Every change to the scene adds the area that it affects to the
+/// damage, which is the bounding rectangle of everything that needs to be
+/// drawn again. [`render`] composites the layers over transparent black
+/// within a rectangle of the destination, so redrawing only the damage
+/// leaves the rest of the destination untouched. Layers are re-encoded
+/// with the [`TransferFunction`] of the destination before they are
+/// composited.
+///
+/// Changes made to the images of the layers through [`image_mut`] damage
+/// the whole layer; use [`damage_layer`] after editing an image in place
+/// to damage less.
+///
+/// This requires the `alloc` feature.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Format, GeneralImage, Image, Rect, Rgba, Scene};
+///
+/// let red = Rgba { red: 0xFFFF, green: 0, blue: 0, alpha: 0xFFFF };
+/// let mut scene = Scene::new();
+/// let layer = scene.push(GeneralImage::solid_color(2, 2, Format::ARGB32, red), 1, 1);
+///
+/// let mut screen = GeneralImage::from_buffer(4, 4, Format::ARGB32, vec![0u8; 64]);
+/// let damage = scene.take_damage();
+/// scene.render(&mut screen, damage);
+/// assert_eq!(screen.pixel(2, 2).to_rgba(), red);
+///
+/// scene.set_visible(layer, false);
+/// assert_eq!(scene.damage(), Rect::new(1, 1, 2, 2));
+/// ```
+///
+/// [`render`]: crate::Scene::render
+/// [`TransferFunction`]: crate::TransferFunction
+/// [`image_mut`]: crate::Scene::image_mut
+/// [`damage_layer`]: crate::Scene::damage_layer
+#[derive(Debug)]
+pub struct Scene<I> {
+    /// The layers, from bottom to top.
+    layers: Vec<Layer<I>>,
+    next_id: usize,
+    damage: Rect,
+}
+
+impl<I> Default for Scene<I> {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            next_id: 0,
+            damage: Rect::default(),
+        }
+    }
+}
+
+impl<I: Image> Scene<I> {
+    /// Create an empty scene.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a visible image on top of every other layer, with its top left
+    /// pixel at the given position.
+    pub fn push(&mut self, image: I, x: usize, y: usize) -> LayerId {
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+
+        let layer = Layer {
+            id,
+            image,
+            x,
+            y,
+            visible: true,
+        };
+        self.add_damage(layer.bounds());
+        self.layers.push(layer);
+        id
+    }
+
+    /// Remove a layer, giving back its image.
+    pub fn remove(&mut self, id: LayerId) -> Option<I> {
+        let index = self.index(id)?;
+        let layer = self.layers.remove(index);
+        if layer.visible {
+            self.add_damage(layer.bounds());
+        }
+        Some(layer.image)
+    }
+
+    /// Move a layer so that its top left pixel is at the given position.
+    pub fn set_position(&mut self, id: LayerId, x: usize, y: usize) {
+        if let Some(index) = self.index(id) {
+            self.damage_index(index);
+            let layer = &mut self.layers[index];
+            layer.x = x;
+            layer.y = y;
+            self.damage_index(index);
+        }
+    }
+
+    /// Show or hide a layer.
+    pub fn set_visible(&mut self, id: LayerId, visible: bool) {
+        if let Some(index) = self.index(id) {
+            if self.layers[index].visible != visible {
+                self.layers[index].visible = visible;
+                self.add_damage(self.layers[index].bounds());
+            }
+        }
+    }
+
+    /// Move a layer to the given position in the stack, where zero is the
+    /// bottom. Positions past the top move the layer to the top.
+    pub fn set_z_index(&mut self, id: LayerId, z_index: usize) {
+        if let Some(index) = self.index(id) {
+            let layer = self.layers.remove(index);
+            let z_index = cmp::min(z_index, self.layers.len());
+            self.layers.insert(z_index, layer);
+            self.damage_index(z_index);
+        }
+    }
+
+    /// The image of a layer.
+    pub fn image(&self, id: LayerId) -> Option<&I> {
+        self.index(id).map(|index| &self.layers[index].image)
+    }
+
+    /// The image of a layer, for editing.
+    ///
+    /// The whole layer is damaged. If the image grows, damage the new
+    /// area with [`damage_layer`] afterwards.
+    ///
+    /// [`damage_layer`]: crate::Scene::damage_layer
+    pub fn image_mut(&mut self, id: LayerId) -> Option<&mut I> {
+        let index = self.index(id)?;
+        self.damage_index(index);
+        Some(&mut self.layers[index].image)
+    }
+
+    /// The position of a layer, and whether it is visible.
+    pub fn layer(&self, id: LayerId) -> Option<((usize, usize), bool)> {
+        self.index(id).map(|index| {
+            let layer = &self.layers[index];
+            ((layer.x, layer.y), layer.visible)
+        })
+    }
+
+    /// The layers from bottom to top.
+    pub fn layers(&self) -> impl Iterator<Item = LayerId> + '_ {
+        self.layers.iter().map(|layer| layer.id)
+    }
+
+    /// Damage a rectangle within a layer, in the coordinates of its image.
+    pub fn damage_layer(&mut self, id: LayerId, rect: Rect) {
+        if let Some(index) = self.index(id) {
+            let layer = &self.layers[index];
+            if let Some(rect) =
+                rect.intersection(Rect::new(0, 0, layer.image.width(), layer.image.height()))
+            {
+                let rect = Rect::new(layer.x + rect.x, layer.y + rect.y, rect.width, rect.height);
+                self.add_damage(rect);
+            }
+        }
+    }
+
+    /// Damage a rectangle of the scene.
+    pub fn add_damage(&mut self, rect: Rect) {
+        self.damage = self.damage.union(rect);
+    }
+
+    /// The bounding rectangle of everything that needs to be drawn again.
+    ///
+    /// This is empty if nothing changed.
+    pub fn damage(&self) -> Rect {
+        self.damage
+    }
+
+    /// Get the damage and reset it, usually right before rendering it.
+    pub fn take_damage(&mut self) -> Rect {
+        core::mem::take(&mut self.damage)
+    }
+
+    /// Composite the visible layers within `damage` into `dst`.
+    ///
+    /// The layers are drawn over transparent black with the source over
+    /// operator, and the result replaces the pixels of `dst`. Pixels
+    /// outside of `damage` are left untouched.
+    pub fn render<Dst: Image + ?Sized>(&self, dst: &mut Dst, damage: Rect) {
+        self.render_with_hints(dst, damage, ChunkingHints::default());
+    }
+
+    /// Like [`render`], but splitting `damage` into bands of rows as
+    /// `hints` says.
+    ///
+    /// [`render`]: crate::Scene::render
+    pub fn render_with_hints<Dst: Image + ?Sized>(
+        &self,
+        dst: &mut Dst,
+        damage: Rect,
+        hints: ChunkingHints,
+    ) {
+        let _span = op_span!(
+            "composite",
+            width = damage.width,
+            height = damage.height,
+            layers = self.layers.len(),
+            dst_format = %dst.format(),
+        );
+        let damage = match damage.intersection(Rect::new(0, 0, dst.width(), dst.height())) {
+            Some(damage) => damage,
+            None => return,
+        };
+
+        let dst_codec = Codec::new(dst.format(), dst.endianness()).with_bit_order(dst.bit_order());
+        let mut components = [[0.0f32; 4]; CHUNK_PIXELS];
+        let mut bytes = [0u8; CHUNK_PIXELS * MAX_BYTES_PER_PIXEL + 1];
+
+        let rows_per_band = hints.rows_for(dst.bytes_per_scanline());
+        for band in bands(damage.y..damage.bottom(), rows_per_band) {
+            let mut x = damage.x;
+            while x < damage.right() {
+                let count = cmp::min(CHUNK_PIXELS, damage.right() - x);
+                for y in band.clone() {
+                    let span = Rect::new(x, y, count, 1);
+                    let components = &mut components[..count];
+                    components.iter_mut().for_each(|pixel| *pixel = [0.0; 4]);
+
+                    for layer in self.layers.iter().filter(|layer| layer.visible) {
+                        if let Some(overlap) = span.intersection(layer.bounds()) {
+                            composite_layer(
+                                layer,
+                                overlap,
+                                &mut components[overlap.x - x..],
+                                &mut bytes,
+                                dst.transfer_function(),
+                            );
+                        }
+                    }
+
+                    // write the chunk, keeping the neighbors of sub-byte
+                    // pixels and the other half of shared chroma
+                    let (skip, len) = unit_span(dst.format(), x, count);
+                    let bytes = &mut bytes[..len];
+                    if dst.format().subbyte() || is_ycbcr(dst.format()) {
+                        dst.scanline(x - skip, y, bytes);
+                    }
+                    for (i, &pixel) in components.iter().enumerate() {
+                        dst_codec.write(bytes, skip + i, pixel);
+                    }
+                    dst.set_scanline(x - skip, y, bytes);
+                }
+
+                x += count;
+            }
+        }
+    }
+
+    fn index(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.id == id)
+    }
+
+    fn damage_index(&mut self, index: usize) {
+        if self.layers[index].visible {
+            self.add_damage(self.layers[index].bounds());
+        }
+    }
+}
+
+/// Composite the pixels of a layer within `overlap`, a part of a single
+/// row of the scene, over the start of `components`.
+fn composite_layer<I: Image>(
+    layer: &Layer<I>,
+    overlap: Rect,
+    components: &mut [Components],
+    bytes: &mut [u8],
+    target: TransferFunction,
+) {
+    let codec = Codec::new(layer.image.format(), layer.image.endianness())
+        .with_bit_order(layer.image.bit_order());
+    let palette = layer.image.palette();
+    let transfer_function = layer.image.transfer_function();
+    let x = overlap.x - layer.x;
+    let (skip, len) = unit_span(layer.image.format(), x, overlap.width);
+
+    layer
+        .image
+        .scanline(x - skip, overlap.y - layer.y, &mut bytes[..len]);
+    for (i, below) in components.iter_mut().take(overlap.width).enumerate() {
+        let above = codec.read_resolved(bytes, skip + i, palette);
+        let above = transfer_function.convert(target, above);
+        *below = source_over(above, *below);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitOrder, Builder, Format, GeneralImage, Rgba};
+    use alloc::vec;
+
+    #[test]
+    fn damage_and_order() {
+        let opaque = |red, blue| Rgba {
+            red,
+            green: 0,
+            blue,
+            alpha: 0xFFFF,
+        };
+        let mut scene = Scene::new();
+        let bottom = scene.push(
+            GeneralImage::solid_color(3, 1, Format::RGB24, opaque(0xFFFF, 0)),
+            0,
+            0,
+        );
+        let top = scene.push(
+            GeneralImage::solid_color(1, 1, Format::RGB24, opaque(0, 0xFFFF)),
+            1,
+            0,
+        );
+        assert_eq!(scene.take_damage(), Rect::new(0, 0, 3, 1));
+
+        let mut screen = GeneralImage::from_buffer(4, 1, Format::XRGB32, vec![0u8; 16]);
+        scene.render(&mut screen, Rect::new(0, 0, 4, 1));
+        assert_eq!(screen.pixel(1, 0).to_rgba(), opaque(0, 0xFFFF));
+        assert_eq!(screen.pixel(3, 0).to_rgba(), opaque(0, 0));
+
+        // only the damaged area is drawn again
+        scene.set_z_index(top, 0);
+        assert_eq!(scene.layers().collect::<Vec<_>>(), [top, bottom]);
+        scene.set_position(top, 3, 0);
+        assert_eq!(scene.damage(), Rect::new(1, 0, 3, 1));
+        scene.render(&mut screen, Rect::new(3, 0, 1, 1));
+        assert_eq!(screen.pixel(1, 0).to_rgba(), opaque(0, 0xFFFF));
+        assert_eq!(screen.pixel(3, 0).to_rgba(), opaque(0, 0xFFFF));
+    }
+
+    #[test]
+    fn msb_first() {
+        let layer = Builder::from_buffer(8, 1, Format::A1, [0b1000_0000u8])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let mut scene = Scene::new();
+        scene.push(layer, 0, 0);
+
+        let mut screen = GeneralImage::from_buffer(8, 1, Format::A8, [0u8; 8]);
+        scene.render(&mut screen, Rect::new(0, 0, 8, 1));
+        let mut row = [0u8; 8];
+        screen.scanline(0, 0, &mut row);
+        assert_eq!(row, [0xFF, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut bits = [0u8];
+        let mut mono = Builder::from_buffer(8, 1, Format::A1, &mut bits[..])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        scene.render(&mut mono, Rect::new(0, 0, 8, 1));
+        drop(mono);
+        assert_eq!(bits, [0b1000_0000]);
+    }
+
+    #[test]
+    fn ycbcr_at_odd_positions() {
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let is_red =
+            |color: Rgba| color.red > 0xF000 && color.green < 0x1000 && color.blue < 0x1000;
+
+        let mut layer = GeneralImage::from_buffer(2, 1, Format::YUYV, vec![0u8; 4]);
+        crate::convert(
+            &GeneralImage::solid_color(2, 1, Format::RGB24, red),
+            &mut layer,
+        );
+        let mut scene = Scene::new();
+        scene.push(layer, 1, 0);
+
+        let mut screen = GeneralImage::from_buffer(4, 1, Format::ARGB32, vec![0u8; 16]);
+        scene.render(&mut screen, Rect::new(0, 0, 4, 1));
+        assert!(is_red(screen.pixel(1, 0).to_rgba()));
+        assert!(is_red(screen.pixel(2, 0).to_rgba()));
+
+        // the second pixel of a pair only writes its half of the chroma
+        let mut scene = Scene::new();
+        scene.push(GeneralImage::solid_color(2, 1, Format::RGB24, red), 0, 0);
+        let mut screen = GeneralImage::from_buffer(2, 1, Format::YUYV, vec![0u8; 4]);
+        scene.render(&mut screen, Rect::new(1, 0, 1, 1));
+        scene.render(&mut screen, Rect::new(0, 0, 1, 1));
+        assert!(is_red(screen.pixel(0, 0).to_rgba()));
+        assert!(is_red(screen.pixel(1, 0).to_rgba()));
+    }
+
+    #[test]
+    fn indexed_layer() {
+        use crate::{IndexedImage, Palette};
+
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+        let indices = GeneralImage::from_buffer(2, 1, Format::INDEXED8, [1u8, 0]);
+        let mut scene = Scene::new();
+        scene.push(IndexedImage::new(indices, palette), 0, 0);
+
+        // the first index is transparent, so the background shows through
+        let mut screen = GeneralImage::from_buffer(2, 1, Format::ARGB32, vec![0u8; 8]);
+        scene.render(&mut screen, Rect::new(0, 0, 2, 1));
+        assert_eq!(screen.pixel(0, 0).to_rgba(), red);
+        assert_eq!(screen.pixel(1, 0).to_rgba(), Rgba::default());
+    }
+}
@@ -0,0 +1,142 @@
+// BSL 1.0 License
+
+//! Serialization for the types that describe formats.
+//!
+//! The enums are derived, and are represented by the names of their
+//! variants. `Format` and `ChannelOrder` pack their fields, so they go
+//! through the representations below instead, which are checked when
+//! they are deserialized.
+
+use crate::{AlphaMode, Channel, ChannelOrder, ColorType, Format};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Format")]
+struct FormatRepr {
+    bpp: u8,
+    color_type: ColorType,
+    alpha_bits: u8,
+    red_bits: u8,
+    green_bits: u8,
+    blue_bits: u8,
+    #[serde(default)]
+    alpha_mode: AlphaMode,
+}
+
+impl Serialize for Format {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FormatRepr {
+            bpp: self.bpp(),
+            color_type: self.color_type(),
+            alpha_bits: self.alpha_bits(),
+            red_bits: self.red_bits(),
+            green_bits: self.green_bits(),
+            blue_bits: self.blue_bits(),
+            alpha_mode: self.alpha_mode(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Format {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = FormatRepr::deserialize(deserializer)?;
+        let format = Format::try_new(
+            repr.bpp,
+            repr.color_type,
+            repr.alpha_bits,
+            repr.red_bits,
+            repr.green_bits,
+            repr.blue_bits,
+        )
+        .map_err(D::Error::custom)?;
+        Ok(format.with_alpha_mode(repr.alpha_mode))
+    }
+}
+
+/// Channel orders are the array of their channels, from the lowest bits
+/// up.
+impl Serialize for ChannelOrder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.channels().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChannelOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let order = <[Channel; 4]>::deserialize(deserializer)?;
+        for (i, channel) in order.iter().enumerate() {
+            if order[i + 1..].contains(channel) {
+                return Err(D::Error::custom("a channel appears more than once"));
+            }
+        }
+
+        Ok(ChannelOrder::new(order))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+    #[test]
+    fn format_tokens() {
+        assert_tokens(
+            &Format::RGB565,
+            &[
+                Token::Struct {
+                    name: "Format",
+                    len: 7,
+                },
+                Token::Str("bpp"),
+                Token::U8(16),
+                Token::Str("color_type"),
+                Token::UnitVariant {
+                    name: "ColorType",
+                    variant: "Abgr",
+                },
+                Token::Str("alpha_bits"),
+                Token::U8(0),
+                Token::Str("red_bits"),
+                Token::U8(5),
+                Token::Str("green_bits"),
+                Token::U8(6),
+                Token::Str("blue_bits"),
+                Token::U8(5),
+                Token::Str("alpha_mode"),
+                Token::UnitVariant {
+                    name: "AlphaMode",
+                    variant: "Straight",
+                },
+                Token::StructEnd,
+            ],
+        );
+
+        assert_de_tokens_error::<Format>(
+            &[
+                Token::Struct {
+                    name: "Format",
+                    len: 6,
+                },
+                Token::Str("bpp"),
+                Token::U8(16),
+                Token::Str("color_type"),
+                Token::UnitVariant {
+                    name: "ColorType",
+                    variant: "Argb",
+                },
+                Token::Str("alpha_bits"),
+                Token::U8(0),
+                Token::Str("red_bits"),
+                Token::U8(9),
+                Token::Str("green_bits"),
+                Token::U8(0),
+                Token::Str("blue_bits"),
+                Token::U8(0),
+                Token::StructEnd,
+            ],
+            "9 bits is not supported for the Red channel",
+        );
+    }
+}
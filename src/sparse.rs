@@ -0,0 +1,498 @@
+// BSL 1.0 License
+
+use crate::{
+    codec::Codec, divide_rounding_up, Endianness, Format, Image, Rect, Rgba, MAX_BYTES_PER_PIXEL,
+};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::{cmp, fmt, mem, ops::Range};
+
+/// The row and column of a tile.
+type TileKey = (usize, usize);
+
+/// The width and height of the tiles of a [`SparseImage`], in pixels,
+/// unless another size is chosen.
+///
+/// [`SparseImage`]: crate::SparseImage
+const DEFAULT_TILE_SIZE: usize = 256;
+
+/// An image that only stores the tiles that have been written to.
+///
+/// The image is split into square tiles, and every pixel of a tile that
+/// was never written to reads as the background color. Writing to a tile
+/// allocates it, unless the write only contains the background color. This
+/// allows for very large virtual canvases, such as the pages of a
+/// whiteboard, where a buffer holding every pixel would not fit in memory.
+///
+/// Tiles are shared between an image and its [`snapshot`]s, and a shared
+/// tile is only copied once either of them writes to it. Taking a snapshot
+/// only visits the tiles written to since the last one, which makes undo
+/// history cheap to keep, and [`changed_tiles`] finds what changed between
+/// two snapshots without comparing any pixels.
+///
+/// This requires the `alloc` feature.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Format, Image, Pixel, Rgba, SparseImage};
+///
+/// let white = Rgba { red: 0xFFFF, green: 0xFFFF, blue: 0xFFFF, alpha: 0xFFFF };
+/// let mut canvas = SparseImage::new(100_000, 100_000, Format::XRGB32, white);
+///
+/// let black = Rgba { alpha: 0xFFFF, ..Rgba::default() };
+/// let black = Pixel::from_rgba(black, Format::XRGB32, canvas.endianness());
+/// canvas.set_pixel(54_321, 12_345, black);
+/// assert_eq!(canvas.tile_count(), 1);
+/// assert_eq!(canvas.pixel(54_321, 12_345), black);
+/// assert_eq!(canvas.pixel(0, 0).to_rgba(), white);
+///
+/// // only the tile that is written to after the snapshot is copied
+/// let before = canvas.snapshot();
+/// canvas.set_pixel(0, 0, black);
+/// assert_eq!(before.pixel(0, 0).to_rgba(), white);
+/// assert_eq!(canvas.changed_tiles(&before).count(), 1);
+/// ```
+///
+/// [`snapshot`]: crate::SparseImage::snapshot
+/// [`changed_tiles`]: crate::SparseImage::changed_tiles
+#[derive(Clone)]
+pub struct SparseImage {
+    width: usize,
+    height: usize,
+    format: Format,
+    endianness: Endianness,
+    background: Rgba,
+    /// The background color, repeated to fill a row.
+    pattern: ([u8; MAX_BYTES_PER_PIXEL], usize),
+    tile_size: usize,
+    /// The number of bytes in each row of a tile.
+    tile_row_len: usize,
+    /// The tiles that have been written to since the last snapshot, by
+    /// their row and column.
+    tiles: BTreeMap<TileKey, Arc<Vec<u8>>>,
+    /// The tiles that were written to before the last snapshot, which are
+    /// shared with snapshots until they are written to.
+    frozen: Option<Arc<Layer>>,
+    /// The number of tiles in `tiles` and `frozen` together.
+    tile_count: usize,
+}
+
+/// Tiles written to between two snapshots.
+#[derive(Debug)]
+struct Layer {
+    tiles: BTreeMap<TileKey, Arc<Vec<u8>>>,
+    /// The tiles written to before these, which these override.
+    below: Option<Arc<Layer>>,
+}
+
+impl SparseImage {
+    /// Create an image where every pixel is the background color, without
+    /// allocating any tiles.
+    pub fn new(width: usize, height: usize, format: Format, background: Rgba) -> Self {
+        let mut image = Self {
+            width,
+            height,
+            format,
+            endianness: Endianness::NATIVE,
+            background,
+            pattern: ([0; MAX_BYTES_PER_PIXEL], 1),
+            tile_size: 0,
+            tile_row_len: 0,
+            tiles: BTreeMap::new(),
+            frozen: None,
+            tile_count: 0,
+        };
+        image.update_pattern();
+        image.with_tile_size(DEFAULT_TILE_SIZE)
+    }
+
+    /// Use tiles of a different size.
+    ///
+    /// The size is rounded up to a multiple of eight pixels, so that tiles
+    /// never split bytes or pairs of pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tiles have been allocated.
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        assert!(self.tile_count == 0, "Tiles have already been allocated");
+        self.tile_size = cmp::max(divide_rounding_up(tile_size, 8) * 8, 8);
+        self.tile_row_len = self.tile_size * self.format.bpp() as usize / 8;
+        self
+    }
+
+    /// Use a different endianness.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tiles have been allocated.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        assert!(self.tile_count == 0, "Tiles have already been allocated");
+        self.endianness = endianness;
+        self.update_pattern();
+        self
+    }
+
+    /// The width and height of each tile, in pixels.
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    /// The number of tiles that have been allocated.
+    pub fn tile_count(&self) -> usize {
+        self.tile_count
+    }
+
+    /// The number of bytes that the allocated tiles take up, including
+    /// the tiles that are shared with snapshots.
+    pub fn allocated_bytes(&self) -> usize {
+        self.tile_count * self.tile_row_len * self.tile_size
+    }
+
+    /// The color of the pixels that have not been written to.
+    pub fn background(&self) -> Rgba {
+        self.background
+    }
+
+    /// Free every tile, so that the whole image is the background color.
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.frozen = None;
+        self.tile_count = 0;
+    }
+
+    /// Take a copy of this image that shares all of its tiles.
+    ///
+    /// No pixels are copied, and only the tiles written to since the last
+    /// snapshot are visited. Writing to a shared tile afterwards, through
+    /// either image, copies that tile first.
+    pub fn snapshot(&mut self) -> Self {
+        self.freeze();
+        self.clone()
+    }
+
+    /// The areas of the tiles that differ between this image and `other`,
+    /// which should be a snapshot of it or an image it was snapshotted
+    /// from.
+    ///
+    /// Tiles count as changed if they were written to after the snapshot
+    /// was taken, even if the same pixels were written back. The areas are
+    /// clipped to the bounds of this image.
+    pub fn changed_tiles<'a>(&'a self, other: &'a SparseImage) -> impl Iterator<Item = Rect> + 'a {
+        let mut keys = self.keys();
+        keys.extend(other.keys());
+
+        keys.into_iter()
+            .filter(move |key| match (self.tile(key), other.tile(key)) {
+                (Some(tile), Some(other)) => !Arc::ptr_eq(tile, other),
+                (tile, other) => tile.is_some() || other.is_some(),
+            })
+            .filter_map(move |(row, column)| {
+                let bounds = Rect::new(0, 0, self.width, self.height);
+                let size = self.tile_size;
+                Rect::new(column * size, row * size, size, size).intersection(bounds)
+            })
+    }
+
+    /// Move the tiles written to since the last snapshot into a layer
+    /// that can be shared.
+    fn freeze(&mut self) {
+        if self.tiles.is_empty() {
+            return;
+        }
+
+        let mut layer = Layer {
+            tiles: mem::take(&mut self.tiles),
+            below: self.frozen.take(),
+        };
+
+        // merge layers of similar sizes, so that there are only a few of
+        // them to look through, and each tile is rarely merged again
+        while let Some(below) = layer.below.take() {
+            if below.tiles.len() > layer.tiles.len() * 2 {
+                layer.below = Some(below);
+                break;
+            }
+
+            let below = Arc::try_unwrap(below).unwrap_or_else(|shared| Layer {
+                tiles: shared.tiles.clone(),
+                below: shared.below.clone(),
+            });
+            let mut tiles = below.tiles;
+            tiles.extend(layer.tiles);
+            layer = Layer {
+                tiles,
+                below: below.below,
+            };
+        }
+
+        self.frozen = Some(Arc::new(layer));
+    }
+
+    /// The tile with the given key, if it has been written to.
+    fn tile(&self, key: &TileKey) -> Option<&Arc<Vec<u8>>> {
+        if let Some(tile) = self.tiles.get(key) {
+            return Some(tile);
+        }
+
+        let mut layer = self.frozen.as_deref();
+        while let Some(frozen) = layer {
+            if let Some(tile) = frozen.tiles.get(key) {
+                return Some(tile);
+            }
+            layer = frozen.below.as_deref();
+        }
+        None
+    }
+
+    /// The keys of every tile that has been written to.
+    fn keys(&self) -> BTreeSet<TileKey> {
+        let mut keys: BTreeSet<_> = self.tiles.keys().copied().collect();
+        let mut layer = self.frozen.as_deref();
+        while let Some(frozen) = layer {
+            keys.extend(frozen.tiles.keys().copied());
+            layer = frozen.below.as_deref();
+        }
+        keys
+    }
+
+    fn update_pattern(&mut self) {
+        let codec = Codec::new(self.format, self.endianness);
+        self.pattern = codec.pattern(self.background.components());
+    }
+
+    /// Fill `bytes` with the background color, where `bytes` starts at
+    /// the given byte of a row.
+    fn fill_background(&self, bytes: &mut [u8], start: usize) {
+        let (pattern, len) = &self.pattern;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = pattern[(start + i) % len];
+        }
+    }
+
+    /// Whether `bytes`, starting at the given byte of a row, are all the
+    /// background color.
+    fn is_background(&self, bytes: &[u8], start: usize) -> bool {
+        let (pattern, len) = &self.pattern;
+        bytes
+            .iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == pattern[(start + i) % len])
+    }
+
+    /// The parts of each tile that the bytes `start..end` of the row `y`
+    /// overlap, as the key of the tile, the range within the row of the
+    /// tile, and the offset of the part into the bytes.
+    fn parts(
+        &self,
+        y: usize,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = ((usize, usize), Range<usize>, usize)> {
+        let (tile_size, tile_row_len) = (self.tile_size, self.tile_row_len);
+        let mut at = start;
+        core::iter::from_fn(move || {
+            if at >= end {
+                return None;
+            }
+
+            let tile_start = at % tile_row_len;
+            let len = cmp::min(tile_row_len - tile_start, end - at);
+            let part = (
+                (y / tile_size, at / tile_row_len),
+                tile_start..tile_start + len,
+                at - start,
+            );
+            at += len;
+            Some(part)
+        })
+    }
+
+    /// The range of bytes of a row that a scanline at `x` covers.
+    fn row_range(&self, x: usize, len: usize) -> (usize, usize) {
+        let row_len = self.bytes_per_scanline();
+        let start = cmp::min(x.saturating_mul(self.format.bpp() as usize) / 8, row_len);
+        (start, start + cmp::min(len, row_len - start))
+    }
+}
+
+impl fmt::Debug for SparseImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SparseImage")
+            .field("format", &self.format)
+            .field("endianness", &self.endianness)
+            .field("dimensions", &(self.width, self.height))
+            .field("tile_size", &self.tile_size)
+            .field("tile_count", &self.tile_count)
+            .finish()
+    }
+}
+
+impl Image for SparseImage {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        divide_rounding_up(self.width.saturating_mul(self.format.bpp() as usize), 8)
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        if y >= self.height {
+            return 0;
+        }
+
+        let (start, end) = self.row_range(x, scanline.len());
+        let row = y % self.tile_size * self.tile_row_len;
+        for (key, range, offset) in self.parts(y, start, end) {
+            let part = &mut scanline[offset..offset + range.len()];
+            match self.tile(&key) {
+                Some(tile) => part.copy_from_slice(&tile[row + range.start..row + range.end]),
+                None => self.fill_background(part, range.start),
+            }
+        }
+
+        end - start
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        if y >= self.height {
+            return 0;
+        }
+
+        let (start, end) = self.row_range(x, scanline.len());
+        let row = y % self.tile_size * self.tile_row_len;
+        for (key, range, offset) in self.parts(y, start, end) {
+            let part = &scanline[offset..offset + range.len()];
+            if !self.tiles.contains_key(&key) {
+                let tile = match self.tile(&key) {
+                    // shared with a snapshot until it is copied below
+                    Some(tile) => tile.clone(),
+                    // writing the background doesn't need a tile
+                    None if self.is_background(part, range.start) => continue,
+                    None => {
+                        let mut tile = vec![0u8; self.tile_row_len * self.tile_size];
+                        for tile_row in tile.chunks_exact_mut(self.tile_row_len) {
+                            self.fill_background(tile_row, 0);
+                        }
+                        self.tile_count += 1;
+                        Arc::new(tile)
+                    }
+                };
+                self.tiles.insert(key, tile);
+            }
+
+            let tile = self
+                .tiles
+                .get_mut(&key)
+                .expect("the tile was just inserted");
+            // copy the tile first if a snapshot shares it
+            Arc::make_mut(tile)[row + range.start..row + range.end].copy_from_slice(part);
+        }
+
+        end - start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocates_written_tiles() {
+        let mut image =
+            SparseImage::new(1000, 1000, Format::RGB24, Rgba::default()).with_tile_size(100);
+        assert_eq!(image.tile_size(), 104);
+
+        // the background doesn't allocate anything
+        image.set_scanline(0, 5, &[0; 3000]);
+        assert_eq!(image.tile_count(), 0);
+
+        // a write across a tile boundary allocates both tiles
+        image.set_scanline(103, 500, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(image.tile_count(), 2);
+        assert_eq!(image.allocated_bytes(), 2 * 104 * 104 * 3);
+
+        let mut row = [0xFFu8; 12];
+        assert_eq!(image.scanline(102, 500, &mut row), 12);
+        assert_eq!(row, [0, 0, 0, 1, 2, 3, 4, 5, 6, 0, 0, 0]);
+        assert_eq!(image.scanline(102, 501, &mut row), 12);
+        assert_eq!(row, [0; 12]);
+
+        // writes are clipped to the image
+        assert_eq!(image.set_scanline(999, 0, &[7; 6]), 3);
+        assert_eq!(image.set_scanline(0, 1000, &[7; 3]), 0);
+        image.clear();
+        assert_eq!(image.tile_count(), 0);
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut image = SparseImage::new(20, 20, Format::A8, Rgba::default()).with_tile_size(8);
+        image.set_scanline(0, 0, &[1; 20]);
+        let snapshot = image.snapshot();
+        assert_eq!(image.tile_count(), 3);
+        assert_eq!(image.changed_tiles(&snapshot).count(), 0);
+
+        // writes copy the tiles that they touch, and leave the snapshot alone
+        image.set_scanline(9, 1, &[2]);
+        image.set_scanline(0, 19, &[3]);
+        let mut row = [0u8; 1];
+        snapshot.scanline(9, 1, &mut row);
+        assert_eq!(row, [0]);
+        assert_eq!(
+            image.changed_tiles(&snapshot).collect::<Vec<_>>(),
+            [Rect::new(8, 0, 8, 8), Rect::new(0, 16, 8, 4)]
+        );
+        assert_eq!(snapshot.changed_tiles(&image).count(), 2);
+        assert_eq!(image.tile_count(), 4);
+        assert_eq!(snapshot.tile_count(), 3);
+    }
+
+    #[test]
+    fn many_snapshots() {
+        let mut image = SparseImage::new(64, 64, Format::A8, Rgba::default()).with_tile_size(8);
+        let mut history = Vec::new();
+        for i in 0..64 {
+            image.set_scanline(i, i, &[i as u8 + 1]);
+            history.push(image.snapshot());
+        }
+        assert_eq!(image.tile_count(), 8);
+
+        // the layers were merged, and every snapshot still sees its pixels
+        let mut depth = 0;
+        let mut layer = image.frozen.as_deref();
+        while let Some(frozen) = layer {
+            depth += 1;
+            layer = frozen.below.as_deref();
+        }
+        assert!(depth <= 7, "{} layers", depth);
+
+        let mut pixel = [0u8];
+        for (i, snapshot) in history.iter().enumerate() {
+            snapshot.scanline(i, i, &mut pixel);
+            assert_eq!(pixel, [i as u8 + 1]);
+            if i < 63 {
+                snapshot.scanline(i + 1, i + 1, &mut pixel);
+                assert_eq!(pixel, [0]);
+            }
+        }
+        assert_eq!(
+            history[7].changed_tiles(&history[8]).collect::<Vec<_>>(),
+            [Rect::new(8, 8, 8, 8)]
+        );
+    }
+}
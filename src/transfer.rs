@@ -0,0 +1,246 @@
+// BSL 1.0 License
+
+use crate::codec::Components;
+use core::f64::consts::LN_2;
+
+/// How the values stored in the color channels of an image map to light.
+///
+/// Most images are encoded with the sRGB curve, which is what every image
+/// is assumed to use unless it says otherwise. Video decoders hand out
+/// HDR10 frames encoded with [`Pq`] and broadcast HDR encoded with
+/// [`Hlg`], and converting those as if they were sRGB gives washed out
+/// colors. [`convert`] and [`Scene`] re-encode the colors of images whose
+/// transfer functions differ, by way of linear light where `1.0` is the
+/// reference white of SDR content. Float formats keep linear values above
+/// `1.0`, so highlights survive; integer formats clamp them.
+///
+/// Alpha is always linear.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{convert, Builder, Format, Image, TransferFunction};
+///
+/// let decoded = Builder::from_buffer(1, 1, Format::RGB24, [0xBCu8, 0xBC, 0xBC])
+///     .with_transfer_function(TransferFunction::Srgb)
+///     .finish();
+/// let mut linear = Builder::from_buffer(1, 1, Format::RGB24, [0u8; 3])
+///     .with_transfer_function(TransferFunction::Linear)
+///     .finish();
+/// convert(&decoded, &mut linear);
+///
+/// // sRGB 0xBC is about half as bright as white
+/// let mut bytes = [0u8; 3];
+/// linear.scanline(0, 0, &mut bytes);
+/// assert_eq!(bytes, [0x80, 0x80, 0x80]);
+/// ```
+///
+/// [`Pq`]: crate::TransferFunction::Pq
+/// [`Hlg`]: crate::TransferFunction::Hlg
+/// [`convert`]: crate::convert
+/// [`Scene`]: crate::Scene
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferFunction {
+    /// The sRGB curve, from IEC 61966-2-1.
+    ///
+    /// This is the default.
+    Srgb,
+    /// Values are proportional to light.
+    Linear,
+    /// The perceptual quantizer of SMPTE ST 2084, used by HDR10, where
+    /// `1.0` is 10,000 nits and reference white is 203 nits.
+    Pq,
+    /// The hybrid log-gamma curve of ARIB STD-B67, where reference white is
+    /// stored as `0.75`.
+    ///
+    /// Only the curve is applied, without the system gamma of a display.
+    Hlg,
+    /// A pure power curve, where light is the stored value raised to this
+    /// power, such as `2.2` for many displays.
+    ///
+    /// The power is expected to be positive.
+    Gamma(f32),
+}
+
+impl Default for TransferFunction {
+    fn default() -> Self {
+        TransferFunction::Srgb
+    }
+}
+
+/// The brightness of reference white for PQ, as a fraction of its peak.
+const PQ_REFERENCE_WHITE: f64 = 203.0 / 10_000.0;
+
+// the constants of SMPTE ST 2084
+const PQ_M1: f64 = 2610.0 / 16384.0;
+const PQ_M2: f64 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f64 = 3424.0 / 4096.0;
+const PQ_C2: f64 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f64 = 2392.0 / 4096.0 * 32.0;
+
+// the constants of ARIB STD-B67
+const HLG_A: f64 = 0.178_832_77;
+const HLG_B: f64 = 1.0 - 4.0 * HLG_A;
+const HLG_C: f64 = 0.559_910_73;
+
+impl TransferFunction {
+    /// Turn a stored value into linear light, where `1.0` is reference
+    /// white.
+    pub fn to_linear(self, value: f32) -> f32 {
+        let value = f64::from(value);
+        let linear = match self {
+            TransferFunction::Srgb if value <= 0.04045 => value / 12.92,
+            TransferFunction::Srgb => pow((value + 0.055) / 1.055, 2.4),
+            TransferFunction::Linear => value,
+            TransferFunction::Pq => {
+                let p = pow(value, 1.0 / PQ_M2);
+                let numerator = if p > PQ_C1 { p - PQ_C1 } else { 0.0 };
+                pow(numerator / (PQ_C2 - PQ_C3 * p), 1.0 / PQ_M1) / PQ_REFERENCE_WHITE
+            }
+            TransferFunction::Hlg => hlg_to_scene(value) / hlg_to_scene(0.75),
+            TransferFunction::Gamma(gamma) => pow(value, f64::from(gamma)),
+        };
+        linear as f32
+    }
+
+    /// Turn linear light, where `1.0` is reference white, into a stored
+    /// value.
+    ///
+    /// This is the inverse of [`to_linear`].
+    ///
+    /// [`to_linear`]: crate::TransferFunction::to_linear
+    pub fn from_linear(self, value: f32) -> f32 {
+        let value = f64::from(value);
+        let stored = match self {
+            TransferFunction::Srgb if value <= 0.003_130_8 => value * 12.92,
+            TransferFunction::Srgb => 1.055 * pow(value, 1.0 / 2.4) - 0.055,
+            TransferFunction::Linear => value,
+            TransferFunction::Pq => {
+                let y = pow(value * PQ_REFERENCE_WHITE, PQ_M1);
+                pow((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y), PQ_M2)
+            }
+            TransferFunction::Hlg => {
+                let scene = value * hlg_to_scene(0.75);
+                if scene <= 1.0 / 12.0 {
+                    pow(3.0 * scene, 0.5)
+                } else {
+                    HLG_A * ln(12.0 * scene - HLG_B) + HLG_C
+                }
+            }
+            TransferFunction::Gamma(gamma) => pow(value, 1.0 / f64::from(gamma)),
+        };
+        stored as f32
+    }
+
+    /// Re-encode the color of components stored with this transfer
+    /// function for storing with `target`.
+    pub(crate) fn convert(
+        self,
+        target: TransferFunction,
+        mut components: Components,
+    ) -> Components {
+        if self != target {
+            for component in &mut components[..3] {
+                *component = target.from_linear(self.to_linear(*component));
+            }
+        }
+        components
+    }
+}
+
+/// The scene light of a value stored with the HLG curve, from `0.0` to
+/// `1.0`.
+fn hlg_to_scene(value: f64) -> f64 {
+    if value <= 0.5 {
+        value * value / 3.0
+    } else {
+        (exp((value - HLG_C) / HLG_A) + HLG_B) / 12.0
+    }
+}
+
+/// Raise `base` to a power, treating bases that aren't positive as zero.
+fn pow(base: f64, exponent: f64) -> f64 {
+    if base > 0.0 {
+        exp(exponent * ln(base))
+    } else {
+        0.0
+    }
+}
+
+/// The natural logarithm of a positive, finite value.
+fn ln(value: f64) -> f64 {
+    // split the value into a mantissa in 1..2 and an exponent
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7FF) as i64 - 1023;
+    let mantissa = f64::from_bits((bits & 0x000F_FFFF_FFFF_FFFF) | 0x3FF0_0000_0000_0000);
+
+    // ln(m) = 2 atanh(s), where s = (m - 1) / (m + 1) is at most 1/3
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let s2 = s * s;
+    let (mut power, mut series) = (s, 0.0);
+    for n in 0..12 {
+        series += power / (2 * n + 1) as f64;
+        power *= s2;
+    }
+
+    2.0 * series + exponent as f64 * LN_2
+}
+
+/// The exponential function.
+fn exp(value: f64) -> f64 {
+    if value < -700.0 {
+        return 0.0;
+    }
+    if value > 700.0 {
+        return f64::INFINITY;
+    }
+
+    // split the value into k ln(2) + r, where r is at most ln(2) / 2
+    let k = value / LN_2;
+    let k = if k < 0.0 { k - 0.5 } else { k + 0.5 } as i64;
+    let r = value - k as f64 * LN_2;
+
+    let (mut term, mut series) = (1.0, 1.0);
+    for n in 1..14 {
+        term *= r / n as f64;
+        series += term;
+    }
+
+    series * f64::from_bits(((k + 1023) as u64) << 52)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_roundtrip() {
+        let close = |a: f32, b: f32| (a - b) < 1e-4 && (b - a) < 1e-4;
+        assert!(close(TransferFunction::Srgb.to_linear(0.5), 0.214_041));
+        assert!(close(TransferFunction::Pq.from_linear(1.0), 0.580_689));
+        assert!(close(TransferFunction::Hlg.to_linear(0.75), 1.0));
+        assert!(close(
+            TransferFunction::Gamma(2.2).to_linear(0.5),
+            0.217_638
+        ));
+
+        // HDR curves go past reference white
+        assert!(TransferFunction::Pq.to_linear(1.0) > 49.0);
+        assert!(TransferFunction::Hlg.to_linear(1.0) > 3.0);
+
+        let functions = [
+            TransferFunction::Srgb,
+            TransferFunction::Linear,
+            TransferFunction::Pq,
+            TransferFunction::Hlg,
+            TransferFunction::Gamma(2.4),
+        ];
+        for &function in functions.iter() {
+            for &value in [0.0f32, 0.01, 0.25, 0.5, 0.75, 1.0].iter() {
+                let back = function.from_linear(function.to_linear(value));
+                assert!(close(back, value), "{:?} {} {}", function, value, back);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// BSL 1.0 License
+
+//! Counts of bytes and pixels that can't be mixed up.
+
+use crate::{divide_rounding_up, Format};
+use core::fmt;
+
+/// A number of bytes, such as the stride of an image.
+///
+/// Strides, widths and offsets are all `usize`s, so it is easy to pass a
+/// width where a number of bytes is expected. The APIs that take a
+/// `ByteCount` or a [`PixelCount`] catch that at compile time, and
+/// converting between the two needs a [`Format`].
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{ByteCount, Format, PixelCount};
+///
+/// let width = PixelCount::new(10);
+/// assert_eq!(width.to_bytes(Format::RGB24), ByteCount::new(30));
+/// assert_eq!(width.to_bytes(Format::A1), ByteCount::new(2));
+/// assert_eq!(ByteCount::new(30).to_pixels(Format::ARGB32), PixelCount::new(7));
+/// ```
+///
+/// [`PixelCount`]: crate::PixelCount
+/// [`Format`]: crate::Format
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteCount(usize);
+
+/// A number of pixels, such as the width of an image.
+///
+/// See [`ByteCount`] for why this exists.
+///
+/// [`ByteCount`]: crate::ByteCount
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PixelCount(usize);
+
+impl ByteCount {
+    /// Wrap a number of bytes.
+    pub const fn new(bytes: usize) -> Self {
+        ByteCount(bytes)
+    }
+
+    /// The number of bytes.
+    pub const fn get(self) -> usize {
+        self.0
+    }
+
+    /// The number of whole pixels of the given format that fit in this
+    /// many bytes.
+    pub const fn to_pixels(self, format: Format) -> PixelCount {
+        PixelCount(self.0.saturating_mul(8) / format.bpp() as usize)
+    }
+}
+
+impl PixelCount {
+    /// Wrap a number of pixels.
+    pub const fn new(pixels: usize) -> Self {
+        PixelCount(pixels)
+    }
+
+    /// The number of pixels.
+    pub const fn get(self) -> usize {
+        self.0
+    }
+
+    /// The number of bytes needed to hold this many pixels of the given
+    /// format, rounded up to a whole byte.
+    pub const fn to_bytes(self, format: Format) -> ByteCount {
+        ByteCount(divide_rounding_up(
+            self.0.saturating_mul(format.bpp() as usize),
+            8,
+        ))
+    }
+}
+
+impl From<ByteCount> for usize {
+    fn from(count: ByteCount) -> usize {
+        count.0
+    }
+}
+
+impl From<PixelCount> for usize {
+    fn from(count: PixelCount) -> usize {
+        count.0
+    }
+}
+
+impl fmt::Display for ByteCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", self.0)
+    }
+}
+
+impl fmt::Display for PixelCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} pixels", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, Error, Image};
+
+    #[test]
+    fn conversions() {
+        // partial bytes round up, and partial pixels round down
+        assert_eq!(PixelCount::new(3).to_bytes(Format::A4), ByteCount::new(2));
+        assert_eq!(ByteCount::new(2).to_pixels(Format::A4), PixelCount::new(4));
+        assert_eq!(
+            ByteCount::new(5).to_pixels(Format::RGB24),
+            PixelCount::new(1)
+        );
+        assert_eq!(
+            PixelCount::new(0).to_bytes(Format::ARGB32),
+            ByteCount::default()
+        );
+
+        // huge counts saturate instead of overflowing
+        assert_eq!(
+            PixelCount::new(usize::MAX).to_bytes(Format::ARGB32),
+            ByteCount::new(usize::MAX / 8 + 1)
+        );
+        assert_eq!(
+            ByteCount::new(usize::MAX).to_pixels(Format::A1),
+            PixelCount::new(usize::MAX)
+        );
+
+        assert_eq!(usize::from(ByteCount::new(7)), 7);
+        assert_eq!(usize::from(PixelCount::new(7)), 7);
+    }
+
+    #[test]
+    fn strides() {
+        let image = Builder::from_buffer(3, 2, Format::RGB24, [0u8; 24])
+            .with_stride(ByteCount::new(12))
+            .finish();
+        assert_eq!(image.stride(), ByteCount::new(12));
+        assert_eq!(image.pixel_width(), PixelCount::new(3));
+
+        // a stride that can't hold the width of the image
+        let width = PixelCount::new(3).to_bytes(Format::RGB24);
+        assert_eq!(width, ByteCount::new(9));
+        let short = Builder::from_buffer(3, 2, Format::RGB24, [0u8; 24])
+            .try_with_stride(ByteCount::new(width.get() - 1));
+        assert_eq!(short.err(), Some(Error::InvalidStride));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn display() {
+        use alloc::string::ToString;
+
+        assert_eq!(ByteCount::new(30).to_string(), "30 bytes");
+        assert_eq!(PixelCount::new(2).to_string(), "2 pixels");
+    }
+}
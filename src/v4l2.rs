@@ -0,0 +1,205 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the pixel format codes of Video4Linux,
+//! for wrapping the frames of webcams and capture cards.
+//!
+//! V4L2 codes describe the order of bytes in memory rather than the bits
+//! of a quantum, so `V4L2_PIX_FMT_RGB24` stores red, green and blue in
+//! that order and is [`Format::RGB24`]. A few codes describe pixels with
+//! big-endian quanta, and come back with [`Endianness::Big`].
+//!
+//! Only packed formats with a single plane are covered. Planar, Bayer and
+//! compressed codes such as `NV12` or `MJPG` have no format, and neither
+//! do codes whose padding byte comes first, such as `BX24` and the
+//! deprecated `RGB4`.
+//!
+//! This requires the `v4l2` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::{v4l2, Builder, Format, Image};
+//!
+//! let frame = vec![0u8; 640 * 480 * 2];
+//! let (format, endianness) = Format::from_v4l2_fourcc(v4l2::V4L2_PIX_FMT_YUYV).unwrap();
+//! let image = Builder::from_buffer(640, 480, format, frame)
+//!     .with_endianness(endianness)
+//!     .finish();
+//! assert_eq!(image.format(), Format::YUYV);
+//!
+//! assert_eq!(Format::from_v4l2_fourcc(v4l2::fourcc(b"MJPG")), None);
+//! ```
+//!
+//! [`Format::RGB24`]: crate::Format::RGB24
+//! [`Endianness::Big`]: crate::Endianness::Big
+
+use crate::{AlphaMode, Endianness, Format};
+
+/// Build a fourcc code out of its four characters.
+pub const fn fourcc(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+/// The flag that is set in the codes of formats with big-endian quanta.
+const BIG_ENDIAN: u32 = 1 << 31;
+
+macro_rules! v4l2_codes {
+    ($(
+        $(#[$meta: meta])*
+        $code_name: ident = $code: expr => $format: expr, $endianness: ident,
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub const $code_name: u32 = $code;
+        )*
+
+        /// Every format with a code, in the order that codes are looked
+        /// up in.
+        const FORMATS: &[(u32, Format, Endianness)] =
+            &[$(($code, $format, Endianness::$endianness),)*];
+    };
+}
+
+v4l2_codes! {
+    /// [`Format::GRAY8`](crate::Format::GRAY8).
+    V4L2_PIX_FMT_GREY = fourcc(b"GREY") => Format::GRAY8, Little,
+    /// [`Format::GRAY16`](crate::Format::GRAY16).
+    V4L2_PIX_FMT_Y16 = fourcc(b"Y16 ") => Format::GRAY16, Little,
+    /// [`Format::GRAY16`](crate::Format::GRAY16), big endian.
+    V4L2_PIX_FMT_Y16_BE = fourcc(b"Y16 ") | BIG_ENDIAN => Format::GRAY16, Big,
+    /// [`Format::INDEXED8`](crate::Format::INDEXED8).
+    V4L2_PIX_FMT_PAL8 = fourcc(b"PAL8") => Format::INDEXED8, Little,
+
+    /// [`Format::RGB565`](crate::Format::RGB565).
+    V4L2_PIX_FMT_RGB565 = fourcc(b"RGBP") => Format::RGB565, Little,
+    /// [`Format::RGB565`](crate::Format::RGB565), big endian.
+    V4L2_PIX_FMT_RGB565X = fourcc(b"RGBR") => Format::RGB565, Big,
+    /// [`Format::RGB555`](crate::Format::RGB555).
+    V4L2_PIX_FMT_XRGB555 = fourcc(b"XR15") => Format::RGB555, Little,
+    /// [`Format::RGB555`](crate::Format::RGB555), big endian.
+    V4L2_PIX_FMT_XRGB555X = fourcc(b"XR15") | BIG_ENDIAN => Format::RGB555, Big,
+    /// [`Format::ARGB1555`](crate::Format::ARGB1555).
+    V4L2_PIX_FMT_ARGB555 = fourcc(b"AR15") => Format::ARGB1555, Little,
+    /// [`Format::ARGB1555`](crate::Format::ARGB1555), big endian.
+    V4L2_PIX_FMT_ARGB555X = fourcc(b"AR15") | BIG_ENDIAN => Format::ARGB1555, Big,
+
+    /// [`Format::RGB24`](crate::Format::RGB24).
+    V4L2_PIX_FMT_RGB24 = fourcc(b"RGB3") => Format::RGB24, Little,
+    /// [`Format::BGR24`](crate::Format::BGR24).
+    V4L2_PIX_FMT_BGR24 = fourcc(b"BGR3") => Format::BGR24, Little,
+
+    /// [`Format::XBGR32`](crate::Format::XBGR32).
+    V4L2_PIX_FMT_XBGR32 = fourcc(b"XR24") => Format::XBGR32, Little,
+    /// [`Format::BGRA32`](crate::Format::BGRA32).
+    V4L2_PIX_FMT_ABGR32 = fourcc(b"AR24") => Format::BGRA32, Little,
+    /// [`Format::XRGB32`](crate::Format::XRGB32).
+    V4L2_PIX_FMT_RGBX32 = fourcc(b"XB24") => Format::XRGB32, Little,
+    /// [`Format::RGBA32`](crate::Format::RGBA32).
+    V4L2_PIX_FMT_RGBA32 = fourcc(b"AB24") => Format::RGBA32, Little,
+    /// [`Format::ARGB32`](crate::Format::ARGB32).
+    V4L2_PIX_FMT_ARGB32 = fourcc(b"BA24") => Format::ARGB32, Little,
+    /// [`Format::ABGR32`](crate::Format::ABGR32).
+    V4L2_PIX_FMT_BGRA32 = fourcc(b"RA24") => Format::ABGR32, Little,
+
+    /// [`Format::YUYV`](crate::Format::YUYV).
+    V4L2_PIX_FMT_YUYV = fourcc(b"YUYV") => Format::YUYV, Little,
+    /// [`Format::UYVY`](crate::Format::UYVY).
+    V4L2_PIX_FMT_UYVY = fourcc(b"UYVY") => Format::UYVY, Little,
+}
+
+/// The deprecated code of [`V4L2_PIX_FMT_XBGR32`], which maps to the same
+/// format but isn't mapped back to.
+pub const V4L2_PIX_FMT_BGR32: u32 = fourcc(b"BGR4");
+
+/// Deprecated codes, which map to the same formats as the codes that
+/// replace them.
+const DEPRECATED: &[(u32, Format, Endianness)] =
+    &[(V4L2_PIX_FMT_BGR32, Format::XBGR32, Endianness::Little)];
+
+/// Formats that have the same layout as a format in [`FORMATS`], and so
+/// map to its code, but that the code doesn't map back to.
+const ALIASES: &[(Format, Format)] = &[
+    (Format::RGBX32, Format::XRGB32),
+    (Format::BGRX32, Format::XBGR32),
+];
+
+impl Format {
+    /// The V4L2 pixel format code for this format with the given
+    /// endianness, if there is one.
+    ///
+    /// The endianness of formats with pixels of a single byte doesn't
+    /// matter. V4L2 doesn't say whether alpha is premultiplied, so it is
+    /// ignored.
+    ///
+    /// This requires the `v4l2` feature.
+    pub fn to_v4l2_fourcc(self, endianness: Endianness) -> Option<u32> {
+        let format = self.with_alpha_mode(AlphaMode::Straight);
+        let format = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == format)
+            .map_or(format, |&(_, format)| format);
+        let endianness = if format.bpp() <= 8 {
+            Endianness::Little
+        } else {
+            endianness
+        };
+
+        FORMATS
+            .iter()
+            .find(|&&(_, known, known_endianness)| {
+                known == format && known_endianness == endianness
+            })
+            .map(|&(code, _, _)| code)
+    }
+
+    /// The format and endianness for a V4L2 pixel format code, or `None`
+    /// if frames with this code can't be wrapped as they are.
+    ///
+    /// This requires the `v4l2` feature.
+    pub fn from_v4l2_fourcc(code: u32) -> Option<(Format, Endianness)> {
+        FORMATS
+            .iter()
+            .chain(DEPRECATED)
+            .find(|&&(known, _, _)| known == code)
+            .map(|&(_, format, endianness)| (format, endianness))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeneralImage, Image, Rgba};
+
+    #[test]
+    fn layouts() {
+        // V4L2 codes describe bytes in memory
+        let (format, endianness) = Format::from_v4l2_fourcc(V4L2_PIX_FMT_ABGR32).unwrap();
+        let image = crate::Builder::from_buffer(1, 1, format, [0x00u8, 0x00, 0xFF, 0xFF])
+            .with_endianness(endianness)
+            .finish();
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        assert_eq!(image.pixel(0, 0).to_rgba(), red);
+        let image = GeneralImage::from_buffer(1, 1, Format::RGB24, [0xFFu8, 0x00, 0x00]);
+        assert_eq!(image.pixel(0, 0).to_rgba(), red);
+
+        for &(code, format, endianness) in FORMATS {
+            assert_eq!(format.to_v4l2_fourcc(endianness), Some(code));
+            assert_eq!(Format::from_v4l2_fourcc(code), Some((format, endianness)));
+        }
+        assert_eq!(
+            Format::from_v4l2_fourcc(V4L2_PIX_FMT_BGR32),
+            Some((Format::XBGR32, Endianness::Little))
+        );
+        assert_eq!(
+            Format::GRAY8.to_v4l2_fourcc(Endianness::Big),
+            Some(V4L2_PIX_FMT_GREY)
+        );
+        assert_eq!(Format::RGB24.to_v4l2_fourcc(Endianness::Big), None);
+        assert_eq!(Format::from_v4l2_fourcc(fourcc(b"NV12")), None);
+    }
+}
@@ -0,0 +1,538 @@
+// BSL 1.0 License
+
+use super::{scanline_from_pairs, set_scanline_in_pairs, splits_pairs};
+use crate::{
+    codec::{Codec, Components},
+    divide_rounding_up,
+    ops::{is_ycbcr, unit_span, CHUNK_BYTES},
+    BitOrder, Endianness, Format, Image, Rgba, TransferFunction, MAX_BYTES_PER_PIXEL,
+};
+use core::cmp;
+
+/// What the pixels around an image are filled with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Border {
+    /// The pixels are a solid color.
+    Color(Rgba),
+    /// The pixels repeat the nearest pixel on the edge of the image.
+    Clamp,
+    /// The pixels are the [`border_pixel`] of the image, or transparent
+    /// black if it doesn't have one.
+    ///
+    /// [`border_pixel`]: crate::Image::border_pixel
+    Inherit,
+}
+
+/// An image that extends another image with a border.
+///
+/// The border is purely logical: no pixels are allocated for it. This is
+/// useful ahead of convolution filters, which need to read pixels past
+/// the edges of the image.
+///
+/// Writes with [`set_scanline`] are passed through to the inner image,
+/// and any part of them that lands on the border is discarded.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Border, BorderedImage, Format, GeneralImage, Image};
+///
+/// let image = GeneralImage::from_buffer(2, 1, Format::A8, [0x10u8, 0x20]);
+/// let bordered = BorderedImage::new(image, 1, Border::Clamp);
+/// assert_eq!(bordered.dimensions(), (4, 3));
+///
+/// let mut row = [0u8; 4];
+/// bordered.scanline(0, 0, &mut row);
+/// assert_eq!(row, [0x10, 0x10, 0x20, 0x20]);
+/// ```
+///
+/// [`set_scanline`]: crate::Image::set_scanline
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BorderedImage<I> {
+    inner: I,
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+    border: Border,
+}
+
+impl<I> BorderedImage<I> {
+    /// Extend `inner` by `size` pixels on each side.
+    pub const fn new(inner: I, size: usize, border: Border) -> Self {
+        Self::with_sizes(inner, size, size, size, size, border)
+    }
+
+    /// Extend `inner` by a different number of pixels on each side.
+    pub const fn with_sizes(
+        inner: I,
+        left: usize,
+        top: usize,
+        right: usize,
+        bottom: usize,
+        border: Border,
+    ) -> Self {
+        Self {
+            inner,
+            left,
+            top,
+            right,
+            bottom,
+            border,
+        }
+    }
+
+    /// The kind of border around the image.
+    pub const fn border(&self) -> Border {
+        self.border
+    }
+
+    /// The image inside of the border.
+    pub const fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// The image inside of the border.
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// Get back the image inside of the border.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Image> BorderedImage<I> {
+    /// The row of the inner image to read for the given row, if any.
+    fn inner_row(&self, y: usize) -> Option<usize> {
+        let height = self.inner.height();
+        if height == 0 {
+            return None;
+        }
+
+        match self.border {
+            Border::Clamp => Some(cmp::min(y.saturating_sub(self.top), height - 1)),
+            _ if y < self.top || y - self.top >= height => None,
+            _ => Some(y - self.top),
+        }
+    }
+
+    /// Copy `count` inner pixels starting at `inner_x` into `scanline`,
+    /// starting at pixel index `start`.
+    fn copy_inner(
+        &self,
+        codec: &Codec,
+        inner_x: usize,
+        y: usize,
+        count: usize,
+        scanline: &mut [u8],
+        start: usize,
+    ) {
+        let format = codec.format();
+        let per_chunk = pixels_per_chunk(format);
+        let reencode = self.moves_chroma();
+        let mut chunk = [0u8; CHUNK_BYTES];
+
+        let mut done = 0;
+        while done < count {
+            let n = cmp::min(per_chunk, count - done);
+            let (skip, len) = unit_span(format, inner_x + done, n);
+
+            let read = self
+                .inner
+                .scanline(inner_x + done - skip, y, &mut chunk[..len]);
+            let chunk = &chunk[..read];
+            for i in 0..n {
+                let index = start + done + i;
+                if reencode {
+                    codec.write(scanline, index, codec.read(chunk, skip + i));
+                } else {
+                    codec.write_raw(scanline, index, codec.read_raw(chunk, skip + i));
+                }
+            }
+
+            done += n;
+        }
+    }
+
+    /// Whether YCbCr pixels land on the other half of a pair than they
+    /// have in the inner image, and so have to hold the other half of
+    /// their chroma.
+    fn moves_chroma(&self) -> bool {
+        is_ycbcr(self.inner.format()) && self.left % 2 == 1
+    }
+}
+
+/// The number of pixels to copy at once, leaving room for the pixels that
+/// share a byte or chroma with the first and last ones.
+fn pixels_per_chunk(format: Format) -> usize {
+    ((CHUNK_BYTES - 1) * 8 / format.bpp() as usize - 1) / 2 * 2
+}
+
+/// The raw values of a color for a pixel at the start and at the end of a
+/// pair, which only differ for YCbCr pixels.
+fn raw_pair(codec: &Codec, components: Components) -> [[u64; 4]; 2] {
+    let mut pixels = [0u8; 2 * MAX_BYTES_PER_PIXEL];
+    codec.write(&mut pixels, 0, components);
+    codec.write(&mut pixels, 1, components);
+    [codec.read_raw(&pixels, 0), codec.read_raw(&pixels, 1)]
+}
+
+impl<I: Image> Image for BorderedImage<I> {
+    fn format(&self) -> Format {
+        self.inner.format()
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.inner.endianness()
+    }
+
+    fn bit_order(&self) -> BitOrder {
+        self.inner.bit_order()
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.inner.transfer_function()
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        self.inner.border_pixel()
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        let (width, height) = self.inner.dimensions();
+        (
+            self.left + width + self.right,
+            self.top + height + self.bottom,
+        )
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        divide_rounding_up(self.width() * self.format().bpp() as usize, 8)
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        let (width, height) = self.dimensions();
+        if y >= height || x >= width {
+            return 0;
+        }
+        if is_ycbcr(self.format()) && x % 2 == 1 {
+            return scanline_from_pairs(self, x, y, scanline);
+        }
+
+        let codec = Codec::new(self.format(), self.endianness()).with_bit_order(self.bit_order());
+        let bpp = self.format().bpp() as usize;
+        let skip = (x * bpp % 8) / bpp;
+        let count = cmp::min((scanline.len() * 8 / bpp).saturating_sub(skip), width - x);
+        let inner_width = self.inner.width();
+
+        // the pixels of the row that come from the inner image
+        let inner_row = self.inner_row(y);
+        let (inner_start, inner_end) = match inner_row {
+            Some(_) if inner_width > 0 => (self.left, self.left + inner_width),
+            _ => (width, width),
+        };
+
+        let start = cmp::max(x, inner_start);
+        let end = cmp::min(x + count, inner_end);
+        if let (Some(inner_y), true) = (inner_row, start < end) {
+            self.copy_inner(
+                &codec,
+                start - self.left,
+                inner_y,
+                end - start,
+                scanline,
+                skip + start - x,
+            );
+        }
+
+        // fill in the border
+        let color = match self.border {
+            Border::Color(color) => Some(color),
+            Border::Inherit => self.inner.border_pixel(),
+            Border::Clamp => None,
+        };
+        let color = color.map_or([[0; 4]; 2], |color| raw_pair(&codec, color.components()));
+        let mut edges = [None, None];
+        for i in 0..count {
+            let outer_x = x + i;
+            if outer_x >= start && outer_x < end {
+                continue;
+            }
+
+            let raw = match (self.border, inner_row) {
+                (Border::Clamp, Some(inner_y)) if inner_width > 0 => {
+                    let right = outer_x >= inner_start;
+                    *edges[right as usize].get_or_insert_with(|| {
+                        let mut pixels = [0u8; 2 * MAX_BYTES_PER_PIXEL];
+                        let inner_x = if right { inner_width - 1 } else { 0 };
+                        let (pixel_skip, len) = unit_span(self.format(), inner_x, 1);
+                        let read =
+                            self.inner
+                                .scanline(inner_x - pixel_skip, inner_y, &mut pixels[..len]);
+                        let pixels = &pixels[..read];
+                        if is_ycbcr(self.format()) {
+                            raw_pair(&codec, codec.read(pixels, pixel_skip))
+                        } else {
+                            [codec.read_raw(pixels, pixel_skip); 2]
+                        }
+                    })
+                }
+                _ => color,
+            };
+
+            codec.write_raw(scanline, skip + i, raw[outer_x % 2]);
+        }
+
+        divide_rounding_up((skip + count) * bpp, 8)
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        let (width, height) = self.dimensions();
+        if y >= height || x >= width {
+            return 0;
+        }
+
+        if is_ycbcr(self.format()) && splits_pairs(self, x, scanline.len()) {
+            return set_scanline_in_pairs(self, x, y, scanline);
+        }
+
+        let bpp = self.format().bpp() as usize;
+        let skip = (x * bpp % 8) / bpp;
+        let count = cmp::min((scanline.len() * 8 / bpp).saturating_sub(skip), width - x);
+        let consumed = divide_rounding_up((skip + count) * bpp, 8);
+
+        let inner_height = self.inner.height();
+        if y < self.top || y - self.top >= inner_height {
+            return consumed;
+        }
+
+        let inner_y = y - self.top;
+        let start = cmp::max(x, self.left);
+        let end = cmp::min(x + count, self.left + self.inner.width());
+        if start >= end {
+            return consumed;
+        }
+
+        let format = self.format();
+        let codec = Codec::new(format, self.endianness()).with_bit_order(self.bit_order());
+        let per_chunk = pixels_per_chunk(format);
+        let reencode = self.moves_chroma();
+        let mut chunk = [0u8; CHUNK_BYTES];
+
+        let mut done = 0;
+        while start + done < end {
+            let n = cmp::min(per_chunk, end - start - done);
+            let inner_x = start - self.left + done;
+            let (inner_skip, len) = unit_span(format, inner_x, n);
+            let chunk = &mut chunk[..len];
+
+            // keep the bits of neighboring pixels that share a byte, and
+            // the other half of shared chroma
+            if format.subbyte() || is_ycbcr(format) {
+                self.inner.scanline(inner_x - inner_skip, inner_y, chunk);
+            }
+
+            for i in 0..n {
+                let index = skip + start - x + done + i;
+                if reencode {
+                    codec.write(chunk, inner_skip + i, codec.read(scanline, index));
+                } else {
+                    codec.write_raw(chunk, inner_skip + i, codec.read_raw(scanline, index));
+                }
+            }
+
+            self.inner
+                .set_scanline(inner_x - inner_skip, inner_y, chunk);
+            done += n;
+        }
+
+        consumed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Builder, GeneralImage};
+
+    #[test]
+    fn msb_first() {
+        let mut bits = [0b1000_0000u8];
+        let mask = Builder::from_buffer(8, 1, Format::A1, &mut bits[..])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let mut bordered = BorderedImage::with_sizes(mask, 1, 0, 7, 0, Border::Clamp);
+        assert_eq!(bordered.bit_order(), BitOrder::MsbFirst);
+
+        // the clamped left edge and the first pixel are set
+        let mut row = [0u8; 2];
+        bordered.scanline(0, 0, &mut row);
+        assert_eq!(row, [0b1100_0000, 0]);
+
+        bordered.set_scanline(8, 0, &[0b1000_0000]);
+        drop(bordered);
+        assert_eq!(bits, [0b1000_0001]);
+    }
+
+    #[test]
+    fn color() {
+        let white = Rgba {
+            red: 0xFFFF,
+            green: 0xFFFF,
+            blue: 0xFFFF,
+            alpha: 0xFFFF,
+        };
+        let image =
+            GeneralImage::from_buffer(2, 1, Format::RGB24, [0x10u8, 0x10, 0x10, 0x20, 0x20, 0x20]);
+        let bordered = BorderedImage::new(image, 1, Border::Color(white));
+        assert_eq!(bordered.dimensions(), (4, 3));
+
+        let mut row = [0u8; 12];
+        assert_eq!(bordered.scanline(0, 0, &mut row), 12);
+        assert_eq!(row, [0xFF; 12]);
+        bordered.scanline(0, 1, &mut row);
+        assert_eq!(
+            row,
+            [0xFF, 0xFF, 0xFF, 0x10, 0x10, 0x10, 0x20, 0x20, 0x20, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn clamp_corners() {
+        let image = GeneralImage::from_buffer(2, 2, Format::A8, [1u8, 2, 3, 4]);
+        let bordered = BorderedImage::with_sizes(image, 2, 1, 1, 2, Border::Clamp);
+        assert_eq!(bordered.dimensions(), (5, 5));
+
+        let rows = [
+            [1, 1, 1, 2, 2],
+            [1, 1, 1, 2, 2],
+            [3, 3, 3, 4, 4],
+            [3, 3, 3, 4, 4],
+            [3, 3, 3, 4, 4],
+        ];
+        for (y, expected) in rows.iter().enumerate() {
+            let mut row = [0u8; 5];
+            assert_eq!(bordered.scanline(0, y, &mut row), 5);
+            assert_eq!(&row, expected, "row {}", y);
+        }
+
+        // starting partway into the row
+        let mut row = [0u8; 2];
+        assert_eq!(bordered.scanline(3, 4, &mut row), 2);
+        assert_eq!(row, [4, 4]);
+    }
+
+    #[test]
+    fn inherit() {
+        let image = Builder::from_buffer(1, 1, Format::A8, [0x10u8])
+            .with_border_pixel(Rgba {
+                alpha: 0x8080,
+                ..Rgba::default()
+            })
+            .finish();
+        let bordered = BorderedImage::new(image, 1, Border::Inherit);
+        let mut row = [0u8; 3];
+        bordered.scanline(0, 1, &mut row);
+        assert_eq!(row, [0x80, 0x10, 0x80]);
+        bordered.scanline(0, 2, &mut row);
+        assert_eq!(row, [0x80; 3]);
+
+        // transparent black without a border pixel
+        let image = GeneralImage::from_buffer(1, 1, Format::A8, [0x10u8]);
+        let bordered = BorderedImage::new(image, 1, Border::Inherit);
+        bordered.scanline(0, 0, &mut row);
+        assert_eq!(row, [0; 3]);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut bytes = [0x10u8, 0x20];
+        let image = GeneralImage::from_buffer(2, 1, Format::A8, &mut bytes[..]);
+        let mut bordered = BorderedImage::with_sizes(image, 1, 1, 0, 0, Border::Clamp);
+
+        let mut row = [0xFFu8; 4];
+        assert_eq!(bordered.scanline(3, 0, &mut row), 0);
+        assert_eq!(bordered.scanline(0, 2, &mut row), 0);
+        assert_eq!(row, [0xFF; 4]);
+        // rows are cut short at the right edge
+        assert_eq!(bordered.scanline(1, 1, &mut row), 2);
+        assert_eq!(row, [0x10, 0x20, 0xFF, 0xFF]);
+
+        assert_eq!(bordered.set_scanline(3, 1, &[0]), 0);
+        assert_eq!(bordered.set_scanline(0, 2, &[0]), 0);
+        // writes to the border are discarded, but still consumed
+        assert_eq!(bordered.set_scanline(0, 0, &[0; 3]), 3);
+        assert_eq!(bordered.set_scanline(0, 1, &[1, 2, 3, 4]), 3);
+        drop(bordered);
+        assert_eq!(bytes, [2, 3]);
+    }
+
+    #[test]
+    fn subbyte_pixels() {
+        // the border shifts the inner pixels to a different position in
+        // their bytes
+        let mut bytes = [0x12u8, 0x34];
+        let image = GeneralImage::from_buffer(4, 1, Format::A4, &mut bytes[..]);
+        let mut bordered = BorderedImage::with_sizes(image, 1, 0, 1, 0, Border::Clamp);
+
+        let codec = Codec::new(Format::A4, bordered.endianness());
+        let mut row = [0u8; 3];
+        assert_eq!(bordered.scanline(0, 0, &mut row), 3);
+        let alpha = |i| codec.read_raw(&row, i)[3];
+        assert_eq!(
+            [alpha(0), alpha(1), alpha(2), alpha(3), alpha(4), alpha(5)],
+            [2, 2, 1, 4, 3, 3]
+        );
+
+        // writing the second pixel keeps the others in its byte
+        let mut pixel = [0u8; 1];
+        codec.write_raw(&mut pixel, 1, [0, 0, 0, 0xF]);
+        assert_eq!(bordered.set_scanline(1, 0, &pixel), 1);
+        drop(bordered);
+        assert_eq!(bytes, [0x1F, 0x34]);
+    }
+
+    #[test]
+    fn ycbcr_pairs() {
+        let opaque = |red, green, blue| Rgba {
+            red,
+            green,
+            blue,
+            alpha: 0xFFFF,
+        };
+        let (red, white) = (opaque(0xFFFF, 0, 0), opaque(0xFFFF, 0xFFFF, 0xFFFF));
+        let pair = |color| {
+            let mut image = GeneralImage::from_buffer(2, 1, Format::YUYV, [0u8; 4]);
+            image.fill(color);
+            let mut pair = [0u8; 4];
+            image.scanline(0, 0, &mut pair);
+            pair
+        };
+        let (r, w) = (pair(red), pair(white));
+
+        // an odd border moves every pixel to the other half of a pair, which
+        // holds the other half of the chroma
+        let image = GeneralImage::from_buffer(2, 1, Format::YUYV, w);
+        let bordered = BorderedImage::with_sizes(image, 1, 0, 1, 0, Border::Color(red));
+        let mut row = [0u8; 8];
+        assert_eq!(bordered.scanline(0, 0, &mut row), 8);
+        assert_eq!(row, [r[0], r[1], w[2], w[3], w[0], w[1], r[2], r[3]]);
+        let mut tail = [0u8; 6];
+        assert_eq!(bordered.scanline(1, 0, &mut tail), 6);
+        assert_eq!(tail, row[2..]);
+
+        let image = GeneralImage::from_buffer(2, 1, Format::YUYV, r);
+        let bordered = BorderedImage::with_sizes(image, 1, 0, 1, 0, Border::Clamp);
+        bordered.scanline(0, 0, &mut row);
+        assert_eq!(row, [r[0], r[1], r[2], r[3], r[0], r[1], r[2], r[3]]);
+
+        let mut bytes = w;
+        let image = GeneralImage::from_buffer(2, 1, Format::YUYV, &mut bytes[..]);
+        let mut bordered = BorderedImage::with_sizes(image, 1, 0, 1, 0, Border::Clamp);
+        assert_eq!(bordered.set_scanline(0, 0, &[r, r].concat()), 8);
+        drop(bordered);
+        assert_eq!(bytes, r);
+    }
+}
@@ -0,0 +1,436 @@
+// BSL 1.0 License
+
+use super::{scanline_from_pairs, set_scanline_in_pairs, splits_pairs};
+use crate::{
+    codec::Codec,
+    divide_rounding_up,
+    ops::{is_ycbcr, unit_span, Span, CHUNK_BYTES},
+    BitOrder, Endianness, Format, Image, Rgba, TransferFunction,
+};
+use core::cmp;
+
+/// An image that presents another image in a different format.
+///
+/// Rows are converted as they are read with [`scanline`], so a consumer
+/// that expects a specific format can read any image without having to
+/// convert all of it up front. Writes with [`set_scanline`] are converted
+/// back into the format of the inner image. Pixels smaller than a byte
+/// keep the bit order of the inner image.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{ConvertedImage, Endianness, Format, GeneralImage, Image};
+///
+/// let bytes = [0xFFu8, 0x10, 0x20, 0x30];
+/// let argb = GeneralImage::from_buffer(1, 1, Format::ARGB32, bytes);
+/// let rgb = ConvertedImage::new(argb, Format::RGB24, Endianness::Little);
+///
+/// let mut row = [0u8; 3];
+/// rgb.scanline(0, 0, &mut row);
+/// assert_eq!(row, [0x10, 0x20, 0x30]);
+/// ```
+///
+/// [`scanline`]: crate::Image::scanline
+/// [`set_scanline`]: crate::Image::set_scanline
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConvertedImage<I> {
+    inner: I,
+    format: Format,
+    endianness: Endianness,
+}
+
+impl<I> ConvertedImage<I> {
+    /// Present `inner` in the given format and endianness.
+    pub const fn new(inner: I, format: Format, endianness: Endianness) -> Self {
+        Self {
+            inner,
+            format,
+            endianness,
+        }
+    }
+
+    /// The image being converted.
+    pub const fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// The image being converted.
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// Get back the image being converted.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+/// Split a span into chunks of pixels that fit into the chunk buffer.
+///
+/// `f` is called with the `x` coordinate to read the chunk from in the
+/// inner image, the number of bytes of the chunk, the index of the first
+/// pixel within the chunk, the index of the first pixel within the outer
+/// scanline, and the number of pixels in the chunk. It returns how many of
+/// those pixels the inner image took, and splitting stops at the first
+/// chunk that it didn't take in full.
+///
+/// Returns the number of pixels transferred.
+fn for_each_chunk(
+    span: &Span,
+    x: usize,
+    inner_format: Format,
+    mut f: impl FnMut(usize, usize, usize, usize, usize) -> usize,
+) -> usize {
+    // leave room for the pixels that share a byte or chroma with the
+    // first and last pixels, and keep pairs of YCbCr pixels together
+    let per_chunk = ((CHUNK_BYTES - 1) * 8 / inner_format.bpp() as usize - 1) / 2 * 2;
+
+    let mut done = 0;
+    while done < span.count {
+        let count = cmp::min(per_chunk, span.count - done);
+        let inner_x = x + done;
+        let (inner_skip, inner_len) = unit_span(inner_format, inner_x, count);
+
+        let taken = f(
+            inner_x - inner_skip,
+            inner_len,
+            inner_skip,
+            span.skip + done,
+            count,
+        );
+        done += taken;
+        if taken < count {
+            break;
+        }
+    }
+
+    done
+}
+
+/// The number of the `count` pixels from `skip` on that `len` bytes of a
+/// scanline hold.
+fn pixels_in(format: Format, len: usize, skip: usize, count: usize) -> usize {
+    cmp::min(
+        (len * 8 / format.bpp() as usize).saturating_sub(skip),
+        count,
+    )
+}
+
+/// The number of bytes that the first `count` pixels of `span` take up.
+fn bytes_of(span: &Span, count: usize, format: Format) -> usize {
+    match count {
+        0 => 0,
+        count => Span {
+            skip: span.skip,
+            count,
+        }
+        .bytes(format),
+    }
+}
+
+impl<I: Image> Image for ConvertedImage<I> {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.endianness
+    }
+
+    fn bit_order(&self) -> BitOrder {
+        self.inner.bit_order()
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.inner.transfer_function()
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        self.inner.border_pixel()
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        self.inner.dimensions()
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        divide_rounding_up(self.inner.width() * self.format.bpp() as usize, 8)
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        if self.format == self.inner.format() && self.endianness == self.inner.endianness() {
+            return self.inner.scanline(x, y, scanline);
+        }
+        if y >= self.inner.height() {
+            return 0;
+        }
+        if is_ycbcr(self.format) && x % 2 == 1 {
+            return scanline_from_pairs(self, x, y, scanline);
+        }
+
+        let bit_order = self.inner.bit_order();
+        let inner_codec =
+            Codec::new(self.inner.format(), self.inner.endianness()).with_bit_order(bit_order);
+        let codec = Codec::new(self.format, self.endianness).with_bit_order(bit_order);
+        let palette = self.inner.palette();
+        let span = Span::new(self.format, x, self.inner.width(), scanline.len());
+        let mut chunk = [0u8; CHUNK_BYTES];
+
+        let inner_format = self.inner.format();
+        let done = for_each_chunk(
+            &span,
+            x,
+            inner_format,
+            |inner_x, inner_len, inner_skip, skip, count| {
+                let chunk = &mut chunk[..inner_len];
+                let read = self.inner.scanline(inner_x, y, chunk);
+                let count = pixels_in(inner_format, read, inner_skip, count);
+                for i in 0..count {
+                    let components = inner_codec.read_resolved(chunk, inner_skip + i, palette);
+                    codec.write(scanline, skip + i, components);
+                }
+                count
+            },
+        );
+
+        bytes_of(&span, done, self.format)
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        if self.format == self.inner.format() && self.endianness == self.inner.endianness() {
+            return self.inner.set_scanline(x, y, scanline);
+        }
+        if y >= self.inner.height() {
+            return 0;
+        }
+        if is_ycbcr(self.format) && splits_pairs(self, x, scanline.len()) {
+            return set_scanline_in_pairs(self, x, y, scanline);
+        }
+
+        let bit_order = self.inner.bit_order();
+        let inner_codec =
+            Codec::new(self.inner.format(), self.inner.endianness()).with_bit_order(bit_order);
+        let codec = Codec::new(self.format, self.endianness).with_bit_order(bit_order);
+        let span = Span::new(self.format, x, self.inner.width(), scanline.len());
+        let inner_format = self.inner.format();
+        let inner = &mut self.inner;
+        let mut chunk = [0u8; CHUNK_BYTES];
+
+        let done = for_each_chunk(
+            &span,
+            x,
+            inner_format,
+            |inner_x, inner_len, inner_skip, skip, count| {
+                let chunk = &mut chunk[..inner_len];
+
+                // keep the bits of neighboring pixels that share a byte, and
+                // the other half of shared chroma
+                if inner_format.subbyte() || is_ycbcr(inner_format) {
+                    inner.scanline(inner_x, y, chunk);
+                }
+
+                for i in 0..count {
+                    inner_codec.write(chunk, inner_skip + i, codec.read(scanline, skip + i));
+                }
+
+                let written = inner.set_scanline(inner_x, y, chunk);
+                pixels_in(inner_format, written, inner_skip, count)
+            },
+        );
+
+        bytes_of(&span, done, self.format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeneralImage;
+
+    #[test]
+    fn subbyte_source() {
+        let mut bits = [0b1100_1010u8, 0b0000_0001];
+        let mask = GeneralImage::from_buffer(10, 1, Format::A1, &mut bits[..]);
+        let mut view = ConvertedImage::new(mask, Format::A8, Endianness::NATIVE);
+
+        let mut row = [0u8; 8];
+        assert_eq!(view.scanline(3, 0, &mut row), 7);
+        assert_eq!(row, [0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0]);
+
+        assert_eq!(view.set_scanline(1, 0, &[0x00, 0xFF]), 2);
+        drop(view);
+        assert_eq!(bits, [0b1100_1100, 0b0000_0001]);
+    }
+
+    #[test]
+    fn msb_first_source() {
+        let mut bits = [0b1000_0000u8];
+        let mask = crate::Builder::from_buffer(8, 1, Format::A1, &mut bits[..])
+            .with_bit_order(BitOrder::MsbFirst)
+            .finish();
+        let mut view = ConvertedImage::new(mask, Format::A8, Endianness::NATIVE);
+
+        let mut row = [0u8; 8];
+        view.scanline(0, 0, &mut row);
+        assert_eq!(row, [0xFF, 0, 0, 0, 0, 0, 0, 0]);
+
+        view.set_scanline(7, 0, &[0xFF]);
+        drop(view);
+        assert_eq!(bits, [0b1000_0001]);
+    }
+
+    #[test]
+    fn indexed_source() {
+        use crate::{IndexedImage, Palette, Rgba};
+
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+        let indices = GeneralImage::from_buffer(2, 1, Format::INDEXED8, [1u8, 0]);
+        let view = ConvertedImage::new(
+            IndexedImage::new(indices, palette),
+            Format::ARGB32,
+            Endianness::NATIVE,
+        );
+        assert_eq!(view.pixel(0, 0).to_rgba(), red);
+        assert_eq!(view.pixel(1, 0).to_rgba(), Rgba::default());
+    }
+
+    /// A pair of YUYV pixels of the given color.
+    fn yuyv_pair(red: u16, green: u16, blue: u16) -> [u8; 4] {
+        let mut image = GeneralImage::from_buffer(2, 1, Format::YUYV, [0u8; 4]);
+        image.fill(crate::Rgba {
+            red,
+            green,
+            blue,
+            alpha: 0xFFFF,
+        });
+        let mut pair = [0u8; 4];
+        image.scanline(0, 0, &mut pair);
+        pair
+    }
+
+    #[test]
+    fn ycbcr_source() {
+        let (red, white) = (yuyv_pair(0xFFFF, 0, 0), yuyv_pair(0xFFFF, 0xFFFF, 0xFFFF));
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&red);
+        bytes[4..].copy_from_slice(&white);
+        let image = GeneralImage::from_buffer(4, 1, Format::YUYV, &mut bytes[..]);
+        let mut view = ConvertedImage::new(image, Format::RGB24, Endianness::NATIVE);
+
+        // pixels read from the middle of a pair keep both halves of their
+        // chroma
+        let mut row = [0u8; 12];
+        assert_eq!(view.scanline(0, 0, &mut row), 12);
+        assert!(row[0] > 0xF0 && row[1] < 0x10 && row[2] < 0x10);
+        let mut tail = [0u8; 9];
+        assert_eq!(view.scanline(1, 0, &mut tail), 9);
+        assert_eq!(tail, row[3..]);
+        view.scanline(3, 0, &mut tail[..3]);
+        assert_eq!(tail[..3], row[9..]);
+
+        // writing the second pixel of a pair only writes its half of the
+        // chroma
+        view.set_scanline(1, 0, &row[9..]);
+        drop(view);
+        assert_eq!(bytes[..2], red[..2]);
+        assert_eq!(bytes[2..4], white[2..]);
+        assert_eq!(bytes[4..], white);
+    }
+
+    #[test]
+    fn ycbcr_view() {
+        let (red, white) = (yuyv_pair(0xFFFF, 0, 0), yuyv_pair(0xFFFF, 0xFFFF, 0xFFFF));
+        let mut rgb = [0u8; 12];
+        for pixel in rgb.chunks_exact_mut(3) {
+            pixel[0] = 0xFF;
+        }
+        let image = GeneralImage::from_buffer(4, 1, Format::RGB24, &mut rgb[..]);
+        let mut view = ConvertedImage::new(image, Format::YUYV, Endianness::NATIVE);
+
+        // the second pixel of a pair holds the red-difference chroma, even
+        // if it is the first one read
+        let mut row = [0u8; 8];
+        assert_eq!(view.scanline(0, 0, &mut row), 8);
+        assert_eq!(row[..4], red);
+        assert_eq!(view.scanline(1, 0, &mut row[..6]), 6);
+        assert_eq!(row[..6], [red[2], red[3], red[0], red[1], red[2], red[3]]);
+
+        // a write that starts on the second pixel of a pair is decoded with
+        // the chroma of the first one, which is already in the image
+        let written = [red[2], red[3], white[0], white[1], white[2], white[3]];
+        assert_eq!(view.set_scanline(1, 0, &written), 6);
+        drop(view);
+
+        let mut expected = [0u8; 8];
+        expected[..4].copy_from_slice(&red);
+        expected[4..].copy_from_slice(&white);
+        let expected = GeneralImage::from_buffer(4, 1, Format::YUYV, expected);
+        let expected = ConvertedImage::new(expected, Format::RGB24, Endianness::NATIVE);
+        let mut expected_rgb = [0u8; 12];
+        expected.scanline(0, 0, &mut expected_rgb);
+        assert_eq!(rgb, expected_rgb);
+    }
+
+    /// An image that never transfers more than `limit` bytes at once.
+    struct Short<I> {
+        inner: I,
+        limit: usize,
+    }
+
+    impl<I: Image> Image for Short<I> {
+        fn format(&self) -> Format {
+            self.inner.format()
+        }
+
+        fn endianness(&self) -> Endianness {
+            self.inner.endianness()
+        }
+
+        fn dimensions(&self) -> (usize, usize) {
+            self.inner.dimensions()
+        }
+
+        fn bytes_per_scanline(&self) -> usize {
+            self.inner.bytes_per_scanline()
+        }
+
+        fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+            let len = cmp::min(scanline.len(), self.limit);
+            self.inner.scanline(x, y, &mut scanline[..len])
+        }
+
+        fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+            let len = cmp::min(scanline.len(), self.limit);
+            self.inner.set_scanline(x, y, &scanline[..len])
+        }
+    }
+
+    #[test]
+    fn reports_what_the_inner_image_took() {
+        let image = GeneralImage::from_buffer(4, 2, Format::A8, [0x80u8; 8]);
+        let mut view = ConvertedImage::new(image, Format::RGBA32, Endianness::NATIVE);
+        let mut row = [0u8; 16];
+        assert_eq!(view.scanline(0, 2, &mut row), 0);
+        assert_eq!(view.set_scanline(0, 2, &row), 0);
+
+        let image = GeneralImage::from_buffer(4, 1, Format::A8, [0x80u8; 4]);
+        let mut view = ConvertedImage::new(
+            Short {
+                inner: image,
+                limit: 3,
+            },
+            Format::RGBA32,
+            Endianness::NATIVE,
+        );
+        assert_eq!(view.scanline(0, 0, &mut row), 12);
+        assert_eq!(row[12..], [0; 4]);
+        assert_eq!(view.set_scanline(0, 0, &row), 12);
+    }
+}
@@ -0,0 +1,126 @@
+// BSL 1.0 License
+
+use crate::{BitOrder, Endianness, Format, Image, Palette, Pixel, Rgba, TransferFunction};
+
+/// An image of palette indices along with the palette they refer to.
+///
+/// The pixels of the inner image are read and written as indices, and
+/// [`color`] resolves them through the palette. The palette is also
+/// available to generic code through [`Image::palette`].
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Format, GeneralImage, Image, IndexedImage, Palette, Rgba};
+///
+/// let red = Rgba { red: 0xFFFF, green: 0, blue: 0, alpha: 0xFFFF };
+/// let palette = Palette::from_colors(&[Rgba::default(), red]).unwrap();
+///
+/// // two 4-bit indices in one byte
+/// let indices = GeneralImage::from_buffer(2, 1, Format::INDEXED4, [0x10u8]);
+/// let image = IndexedImage::new(indices, palette);
+///
+/// assert_eq!(image.pixel(1, 0).index(), Some(1));
+/// assert_eq!(image.color(1, 0), red);
+/// assert_eq!(image.color(0, 0), Rgba::default());
+/// ```
+///
+/// [`color`]: crate::IndexedImage::color
+/// [`Image::palette`]: crate::Image::palette
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexedImage<I> {
+    inner: I,
+    palette: Palette,
+}
+
+impl<I> IndexedImage<I> {
+    /// Attach a palette to an image of indices.
+    pub const fn new(inner: I, palette: Palette) -> Self {
+        Self { inner, palette }
+    }
+
+    /// The image of indices.
+    pub const fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// The image of indices.
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// The palette that the indices refer to.
+    pub fn palette_mut(&mut self) -> &mut Palette {
+        &mut self.palette
+    }
+
+    /// Get back the image of indices and the palette.
+    pub fn into_inner(self) -> (I, Palette) {
+        (self.inner, self.palette)
+    }
+}
+
+impl<I: Image> IndexedImage<I> {
+    /// The color of the pixel at the given location.
+    ///
+    /// See [`Pixel::resolve`] for how indices are resolved.
+    ///
+    /// [`Pixel::resolve`]: crate::Pixel::resolve
+    pub fn color(&self, x: usize, y: usize) -> Rgba {
+        self.inner.pixel(x, y).resolve(&self.palette)
+    }
+}
+
+impl<I: Image> Image for IndexedImage<I> {
+    fn format(&self) -> Format {
+        self.inner.format()
+    }
+
+    fn endianness(&self) -> Endianness {
+        self.inner.endianness()
+    }
+
+    fn bit_order(&self) -> BitOrder {
+        self.inner.bit_order()
+    }
+
+    fn transfer_function(&self) -> TransferFunction {
+        self.inner.transfer_function()
+    }
+
+    fn border_pixel(&self) -> Option<Rgba> {
+        self.inner.border_pixel()
+    }
+
+    fn dimensions(&self) -> (usize, usize) {
+        self.inner.dimensions()
+    }
+
+    fn bytes_per_scanline(&self) -> usize {
+        self.inner.bytes_per_scanline()
+    }
+
+    fn scanline(&self, x: usize, y: usize, scanline: &mut [u8]) -> usize {
+        self.inner.scanline(x, y, scanline)
+    }
+
+    fn set_scanline(&mut self, x: usize, y: usize, scanline: &[u8]) -> usize {
+        self.inner.set_scanline(x, y, scanline)
+    }
+
+    fn column(&self, x: usize, y: usize, column: &mut [u8]) -> usize {
+        self.inner.column(x, y, column)
+    }
+
+    fn palette(&self) -> Option<&Palette> {
+        Some(&self.palette)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Pixel {
+        self.inner.pixel(x, y)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.inner.set_pixel(x, y, pixel)
+    }
+}
@@ -0,0 +1,98 @@
+// BSL 1.0 License
+
+//! Images that present another image in a different way.
+
+use crate::{ops::CHUNK_BYTES, Image};
+use core::cmp;
+
+mod bordered;
+pub use bordered::{Border, BorderedImage};
+
+mod converted;
+pub use converted::ConvertedImage;
+
+mod indexed;
+pub use indexed::IndexedImage;
+
+mod multi_view;
+pub use multi_view::MultiViewImage;
+
+/// Read a scanline of a YCbCr image that starts at the second pixel of a
+/// pair, by reading whole pairs and copying them out.
+///
+/// Views convert YCbCr pixels with a codec, which can only tell which
+/// half of the chroma a pixel holds from its place in the buffer. Their
+/// scanline buffers have to start at the beginning of a pair.
+pub(crate) fn scanline_from_pairs<Img: Image + ?Sized>(
+    image: &Img,
+    x: usize,
+    y: usize,
+    scanline: &mut [u8],
+) -> usize {
+    let bytes = image.format().bytes() as usize;
+    // whole pairs after the first pixel, so that every batch starts a pair
+    let per_batch = (CHUNK_BYTES - bytes) / (2 * bytes) * (2 * bytes);
+    let mut pairs = [0u8; CHUNK_BYTES];
+
+    let mut done = 0;
+    while done < scanline.len() {
+        let len = cmp::min(per_batch, scanline.len() - done);
+        let read = image.scanline(x - 1 + done / bytes, y, &mut pairs[..bytes + len]);
+        let read = read.saturating_sub(bytes);
+        scanline[done..done + read].copy_from_slice(&pairs[bytes..bytes + read]);
+
+        done += read;
+        if read < len {
+            break;
+        }
+    }
+    done
+}
+
+/// Whether a scanline written at `x` starts or ends partway into a pair of
+/// YCbCr pixels that the image holds.
+pub(crate) fn splits_pairs<Img: Image + ?Sized>(image: &Img, x: usize, len: usize) -> bool {
+    let width = image.width();
+    let end = cmp::min(x + len / image.format().bytes() as usize, width);
+    x % 2 == 1 || (end % 2 == 1 && end < width)
+}
+
+/// Write a scanline of a YCbCr image that starts or ends partway into a
+/// pair, by completing the pairs with the pixels already in the image.
+pub(crate) fn set_scanline_in_pairs<Img: Image + ?Sized>(
+    image: &mut Img,
+    x: usize,
+    y: usize,
+    scanline: &[u8],
+) -> usize {
+    let bytes = image.format().bytes() as usize;
+    let width = image.width();
+    let count = cmp::min(scanline.len() / bytes, width.saturating_sub(x));
+    let (start, end) = (x - x % 2, cmp::min(x + count + (x + count) % 2, width));
+    let per_batch = CHUNK_BYTES / (2 * bytes) * 2;
+    let mut pairs = [0u8; CHUNK_BYTES];
+
+    let mut batch_x = start;
+    while batch_x < end {
+        let n = cmp::min(per_batch, end - batch_x);
+        let pairs = &mut pairs[..n * bytes];
+
+        // the pixels paired with the first and the last of the scanline
+        if batch_x < x {
+            image.scanline(batch_x, y, &mut pairs[..bytes]);
+        }
+        if batch_x + n > x + count {
+            let i = x + count - batch_x;
+            image.scanline(x + count, y, &mut pairs[i * bytes..(i + 1) * bytes]);
+        }
+
+        let (from, to) = (cmp::max(batch_x, x), cmp::min(batch_x + n, x + count));
+        pairs[(from - batch_x) * bytes..(to - batch_x) * bytes]
+            .copy_from_slice(&scanline[(from - x) * bytes..(to - x) * bytes]);
+        image.set_scanline(batch_x, y, pairs);
+
+        batch_x += n;
+    }
+
+    count * bytes
+}
@@ -0,0 +1,277 @@
+// BSL 1.0 License
+
+use crate::{divide_rounding_up, Builder, Endianness, Error, Format, GeneralImage};
+use core::{cmp, mem};
+use tinyvec::ArrayVec;
+
+/// The largest number of views in a `MultiViewImage`.
+const MAX_VIEWS: usize = 8;
+
+/// Several images of the same size and format stored in one buffer.
+///
+/// Stereoscopic and XR presentation paths keep the view for each eye in
+/// one contiguous buffer, so that it can be handed to the compositor at
+/// once. Each view starts at its own offset into the buffer, and can be
+/// accessed as an [`Image`] of its own.
+///
+/// ## Example
+///
+/// ```
+/// use genimage::{Format, Image, MultiViewImage};
+///
+/// // a left and a right eye, one after the other
+/// let mut stereo = MultiViewImage::new(2, 2, Format::A8, 2, [0u8; 8]);
+/// for (index, mut view) in stereo.views_mut() {
+///     view.set_scanline(0, 0, &[index as u8 + 1; 2]);
+/// }
+/// assert_eq!(stereo.into_inner(), [1, 1, 0, 0, 2, 2, 0, 0]);
+/// ```
+///
+/// [`Image`]: crate::Image
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MultiViewImage<Storage> {
+    shape: Shape,
+    offsets: ArrayVec<[usize; MAX_VIEWS]>,
+    storage: Storage,
+}
+
+/// What every view of a `MultiViewImage` looks like.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Shape {
+    width: usize,
+    height: usize,
+    format: Format,
+    endianness: Endianness,
+    bytes_per_scanline: usize,
+}
+
+impl Shape {
+    /// The number of bytes in a row, without padding.
+    fn row_len(&self) -> usize {
+        divide_rounding_up(self.width.saturating_mul(self.format.bpp() as usize), 8)
+    }
+
+    /// The number of bytes that a view covers, from the start of its first
+    /// scanline to the end of the pixels of its last one.
+    fn view_len(&self) -> Option<usize> {
+        match self.height {
+            0 => Some(0),
+            height => (height - 1)
+                .checked_mul(self.bytes_per_scanline)?
+                .checked_add(self.row_len()),
+        }
+    }
+
+    /// Wrap the bytes of a view in an image.
+    fn view(self, bytes: &mut [u8]) -> GeneralImage<&mut [u8]> {
+        Builder::from_buffer(self.width, self.height, self.format, bytes)
+            .with_bytes_per_scanline(self.bytes_per_scanline)
+            .with_endianness(self.endianness)
+            .finish()
+    }
+}
+
+impl<Storage> MultiViewImage<Storage> {
+    /// The largest number of views that an image can have.
+    pub const MAX_VIEWS: usize = MAX_VIEWS;
+
+    /// Create an image of `views` tightly packed views, one after the
+    /// other.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than [`MAX_VIEWS`] views.
+    ///
+    /// [`MAX_VIEWS`]: crate::MultiViewImage::MAX_VIEWS
+    pub fn new(
+        width: usize,
+        height: usize,
+        format: Format,
+        views: usize,
+        storage: Storage,
+    ) -> Self {
+        assert!(views <= MAX_VIEWS, "There can be at most eight views");
+
+        let mut shape = Shape {
+            width,
+            height,
+            format,
+            endianness: Endianness::NATIVE,
+            bytes_per_scanline: 0,
+        };
+        shape.bytes_per_scanline = shape.row_len();
+        let view_len = height.saturating_mul(shape.bytes_per_scanline);
+
+        Self {
+            shape,
+            offsets: (0..views).map(|i| i.saturating_mul(view_len)).collect(),
+            storage,
+        }
+    }
+
+    /// Use a different endianness for the views.
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.shape.endianness = endianness;
+        self
+    }
+
+    /// The number of views.
+    pub fn view_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// The offset into the buffer of each view.
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Get back the buffer holding the views.
+    pub fn into_inner(self) -> Storage {
+        self.storage
+    }
+}
+
+impl<Storage: AsRef<[u8]>> MultiViewImage<Storage> {
+    /// Create an image whose views start at the given offsets into
+    /// `storage`, with the given number of bytes per scanline.
+    ///
+    /// Returns an error if a view doesn't fit in `storage`, if the views
+    /// overlap, or if there are more than [`MAX_VIEWS`] of them.
+    ///
+    /// [`MAX_VIEWS`]: crate::MultiViewImage::MAX_VIEWS
+    pub fn try_with_offsets(
+        width: usize,
+        height: usize,
+        format: Format,
+        bytes_per_scanline: usize,
+        offsets: &[usize],
+        storage: Storage,
+    ) -> Result<Self, Error> {
+        if offsets.len() > MAX_VIEWS {
+            return Err(Error::InvalidViews);
+        }
+
+        let shape = Shape {
+            width,
+            height,
+            format,
+            endianness: Endianness::NATIVE,
+            bytes_per_scanline,
+        };
+        if width.checked_mul(format.bpp() as usize).is_none() {
+            return Err(Error::TooLarge);
+        }
+        if bytes_per_scanline < shape.row_len() || bytes_per_scanline % format.bytes() as usize != 0
+        {
+            return Err(Error::InvalidStride);
+        }
+        let view_len = shape.view_len().ok_or(Error::TooLarge)?;
+
+        // the views must not overlap, so that they can be written at once
+        let actual = storage.as_ref().len();
+        let mut sorted: ArrayVec<[usize; MAX_VIEWS]> = offsets.iter().copied().collect();
+        sorted.sort_unstable();
+        for (i, &offset) in sorted.iter().enumerate() {
+            let expected = offset.checked_add(view_len).ok_or(Error::TooLarge)?;
+            if expected > actual {
+                return Err(Error::BufferTooSmall { expected, actual });
+            }
+            if sorted.get(i + 1).map_or(false, |&next| next < expected) {
+                return Err(Error::InvalidViews);
+            }
+        }
+
+        Ok(Self {
+            shape,
+            offsets: offsets.iter().copied().collect(),
+            storage,
+        })
+    }
+}
+
+impl<Storage: AsMut<[u8]>> MultiViewImage<Storage> {
+    /// Get the view with the given index as an image, or `None` if there
+    /// is no such view.
+    pub fn view_mut(&mut self, index: usize) -> Option<GeneralImage<&mut [u8]>> {
+        let offset = *self.offsets.get(index)?;
+        let view_len = self.shape.view_len().unwrap_or(usize::MAX);
+        let bytes = self.storage.as_mut();
+
+        // views of images that were built with too little storage are short
+        let start = cmp::min(offset, bytes.len());
+        let end = cmp::min(offset.saturating_add(view_len), bytes.len());
+        Some(self.shape.view(&mut bytes[start..end]))
+    }
+
+    /// Iterate over every view at once, along with its index.
+    ///
+    /// The views are given in the order that they are stored in, which
+    /// may not be the order of their indices.
+    pub fn views_mut(&mut self) -> impl Iterator<Item = (usize, GeneralImage<&mut [u8]>)> + '_ {
+        let shape = self.shape;
+        let view_len = shape.view_len().unwrap_or(usize::MAX);
+        let offsets = self.offsets;
+        let mut order: ArrayVec<[usize; MAX_VIEWS]> = (0..offsets.len()).collect();
+        order.sort_unstable_by_key(|&index| offsets[index]);
+
+        let mut rest = self.storage.as_mut();
+        let mut position = 0;
+        order.into_iter().map(move |index| {
+            // split the view off of the front of the rest of the buffer
+            let bytes = mem::take(&mut rest);
+            let skip = cmp::min(offsets[index].saturating_sub(position), bytes.len());
+            let (_, bytes) = bytes.split_at_mut(skip);
+            let len = cmp::min(view_len, bytes.len());
+            let (view, bytes) = bytes.split_at_mut(len);
+
+            rest = bytes;
+            position += skip + len;
+            (index, shape.view(view))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Image;
+
+    #[test]
+    fn offsets() {
+        let buffer = [0u8; 16];
+        let err = |offsets: &[usize]| {
+            MultiViewImage::try_with_offsets(2, 2, Format::A8, 3, offsets, buffer).err()
+        };
+        assert_eq!(err(&[0, 4]), Some(Error::InvalidViews));
+        assert_eq!(
+            err(&[12, 0]),
+            Some(Error::BufferTooSmall {
+                expected: 17,
+                actual: 16,
+            })
+        );
+
+        // the right eye comes first in the buffer
+        let mut image =
+            MultiViewImage::try_with_offsets(2, 2, Format::A8, 3, &[8, 1], buffer).unwrap();
+        let indices: ArrayVec<[usize; MAX_VIEWS]> = image
+            .views_mut()
+            .map(|(index, mut view)| {
+                view.set_scanline(0, 1, &[index as u8 + 1; 2]);
+                index
+            })
+            .collect();
+        assert_eq!(indices.as_slice(), [1, 0]);
+
+        image.view_mut(0).unwrap().set_pixel(
+            0,
+            0,
+            crate::Pixel::new(&[9], Endianness::NATIVE, Format::A8),
+        );
+        assert!(image.view_mut(2).is_none());
+        assert_eq!(
+            image.into_inner(),
+            [0, 0, 0, 0, 2, 2, 0, 0, 9, 0, 0, 1, 1, 0, 0, 0]
+        );
+    }
+}
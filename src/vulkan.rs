@@ -0,0 +1,176 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the `VkFormat` values of Vulkan, for
+//! picking the format of a texture to upload an image to.
+//!
+//! The values are plain `u32`s, so they can be passed to `ash` with
+//! `vk::Format::from_raw`. Formats are matched by their layout in
+//! memory, with multi-byte pixels in little-endian order, which is how
+//! Vulkan implementations store them. Packed Vulkan formats list their
+//! channels from the most significant bits down, so
+//! `VK_FORMAT_R5G6B5_UNORM_PACK16` has blue in its lowest bits and is
+//! [`Format::RGB565`].
+//!
+//! Vulkan formats store color channels before alpha, while the float
+//! formats of `genimage` store alpha first, so only the float formats
+//! without alpha have a match.
+//!
+//! This requires the `vulkan` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::{vulkan, Format};
+//!
+//! assert_eq!(Format::BGRA32.to_vk_format(), Some(vulkan::VK_FORMAT_B8G8R8A8_UNORM));
+//! assert_eq!(
+//!     Format::from_vk_format(vulkan::VK_FORMAT_R8G8B8A8_SRGB),
+//!     Some(Format::RGBA32)
+//! );
+//! ```
+//!
+//! [`Format::RGB565`]: crate::Format::RGB565
+
+use crate::{AlphaMode, ColorType, Format};
+
+macro_rules! vk_formats {
+    ($($(#[$meta: meta])* $name: ident = $value: literal => $format: expr,)*) => {
+        $(
+            $(#[$meta])*
+            pub const $name: u32 = $value;
+        )*
+
+        /// Every format with a `VkFormat`, in the order that values are
+        /// looked up in.
+        const FORMATS: &[(u32, Format)] = &[$(($value, $format),)*];
+    };
+}
+
+vk_formats! {
+    /// [`Format::ABGR16`](crate::Format::ABGR16).
+    VK_FORMAT_R4G4B4A4_UNORM_PACK16 = 2 => Format::ABGR16,
+    /// [`Format::ARGB16`](crate::Format::ARGB16).
+    VK_FORMAT_B4G4R4A4_UNORM_PACK16 = 3 => Format::ARGB16,
+    /// [`Format::RGB565`](crate::Format::RGB565).
+    VK_FORMAT_R5G6B5_UNORM_PACK16 = 4 => Format::RGB565,
+    /// Red in the lowest five bits, then green and blue.
+    VK_FORMAT_B5G6R5_UNORM_PACK16 = 5 => Format::new(16, ColorType::Argb, 0, 5, 6, 5),
+    /// [`Format::ARGB1555`](crate::Format::ARGB1555).
+    VK_FORMAT_A1R5G5B5_UNORM_PACK16 = 8 => Format::ARGB1555,
+    /// Gray stored in a red channel, which is [`Format::GRAY8`](crate::Format::GRAY8).
+    VK_FORMAT_R8_UNORM = 9 => Format::GRAY8,
+    /// [`Format::RG8`](crate::Format::RG8).
+    VK_FORMAT_R8G8_UNORM = 16 => Format::RG8,
+    /// [`Format::RGB24`](crate::Format::RGB24).
+    VK_FORMAT_R8G8B8_UNORM = 23 => Format::RGB24,
+    /// [`Format::BGR24`](crate::Format::BGR24).
+    VK_FORMAT_B8G8R8_UNORM = 30 => Format::BGR24,
+    /// [`Format::RGBA32`](crate::Format::RGBA32).
+    VK_FORMAT_R8G8B8A8_UNORM = 37 => Format::RGBA32,
+    /// [`Format::BGRA32`](crate::Format::BGRA32).
+    VK_FORMAT_B8G8R8A8_UNORM = 44 => Format::BGRA32,
+    /// [`Format::ARGB2101010`](crate::Format::ARGB2101010).
+    VK_FORMAT_A2R10G10B10_UNORM_PACK32 = 58 => Format::ARGB2101010,
+    /// Ten bits of red, green and blue, then two bits of alpha.
+    VK_FORMAT_A2B10G10R10_UNORM_PACK32 = 64 => Format::new(32, ColorType::Rgba, 2, 10, 10, 10),
+    /// Gray stored in a red channel, which is [`Format::GRAY16`](crate::Format::GRAY16).
+    VK_FORMAT_R16_UNORM = 70 => Format::GRAY16,
+    /// [`Format::RG16`](crate::Format::RG16).
+    VK_FORMAT_R16G16_UNORM = 77 => Format::RG16,
+    /// [`Format::RGB48`](crate::Format::RGB48).
+    VK_FORMAT_R16G16B16_UNORM = 84 => Format::RGB48,
+    /// [`Format::RGB_F16`](crate::Format::RGB_F16).
+    VK_FORMAT_R16G16B16_SFLOAT = 90 => Format::RGB_F16,
+    /// [`Format::RGBA64`](crate::Format::RGBA64).
+    VK_FORMAT_R16G16B16A16_UNORM = 91 => Format::RGBA64,
+    /// [`Format::RGB_F32`](crate::Format::RGB_F32).
+    VK_FORMAT_R32G32B32_SFLOAT = 106 => Format::RGB_F32,
+    /// [`Format::A8`](crate::Format::A8), from `VK_KHR_maintenance5`.
+    VK_FORMAT_A8_UNORM_KHR = 1_000_470_001 => Format::A8,
+}
+
+/// The sRGB variants of formats in [`FORMATS`], which map to the same
+/// format but aren't mapped back to.
+const SRGB_FORMATS: &[(u32, Format)] = &[
+    (VK_FORMAT_R8_SRGB, Format::GRAY8),
+    (VK_FORMAT_R8G8B8_SRGB, Format::RGB24),
+    (VK_FORMAT_B8G8R8_SRGB, Format::BGR24),
+    (VK_FORMAT_R8G8B8A8_SRGB, Format::RGBA32),
+    (VK_FORMAT_B8G8R8A8_SRGB, Format::BGRA32),
+];
+
+/// The sRGB variant of [`VK_FORMAT_R8_UNORM`].
+pub const VK_FORMAT_R8_SRGB: u32 = 15;
+/// The sRGB variant of [`VK_FORMAT_R8G8B8_UNORM`].
+pub const VK_FORMAT_R8G8B8_SRGB: u32 = 29;
+/// The sRGB variant of [`VK_FORMAT_B8G8R8_UNORM`].
+pub const VK_FORMAT_B8G8R8_SRGB: u32 = 36;
+/// The sRGB variant of [`VK_FORMAT_R8G8B8A8_UNORM`].
+pub const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+/// The sRGB variant of [`VK_FORMAT_B8G8R8A8_UNORM`].
+pub const VK_FORMAT_B8G8R8A8_SRGB: u32 = 50;
+
+impl Format {
+    /// The `VkFormat` with the same layout as this format, if there is
+    /// one.
+    ///
+    /// Formats are matched with the `UNORM` variant of 8-bit formats,
+    /// since whether a texture is sRGB depends on how it is sampled.
+    /// Vulkan doesn't say whether alpha is premultiplied, so it is
+    /// ignored.
+    ///
+    /// This requires the `vulkan` feature.
+    pub fn to_vk_format(self) -> Option<u32> {
+        let format = self.with_alpha_mode(AlphaMode::Straight);
+        FORMATS
+            .iter()
+            .find(|&&(_, known)| known == format)
+            .map(|&(value, _)| value)
+    }
+
+    /// The format with the same layout as a `VkFormat`, if there is one.
+    ///
+    /// sRGB formats give the same format as their `UNORM` variants.
+    ///
+    /// This requires the `vulkan` feature.
+    pub fn from_vk_format(value: u32) -> Option<Format> {
+        FORMATS
+            .iter()
+            .chain(SRGB_FORMATS)
+            .find(|&&(known, _)| known == value)
+            .map(|&(_, format)| format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Channel, Endianness, Pixel, Rgba};
+
+    #[test]
+    fn layouts() {
+        // packed formats list their channels from the top bits down
+        let format = Format::from_vk_format(VK_FORMAT_A2R10G10B10_UNORM_PACK32).unwrap();
+        assert_eq!(format.shift_for(Channel::Blue), Some(0));
+        assert_eq!(format.shift_for(Channel::Alpha), Some(30));
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        assert_eq!(
+            Pixel::from_rgba(red, format, Endianness::Little).raw_u32(),
+            0xFFF0_0000
+        );
+
+        for &(value, format) in FORMATS {
+            assert_eq!(format.to_vk_format(), Some(value));
+        }
+        for &(value, format) in SRGB_FORMATS {
+            assert_eq!(Format::from_vk_format(value), Some(format));
+        }
+        assert_eq!(Format::ARGB_F32.to_vk_format(), None);
+        assert_eq!(Format::from_vk_format(0), None);
+    }
+}
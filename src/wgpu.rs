@@ -0,0 +1,177 @@
+// BSL 1.0 License
+
+//! Conversions between formats and the texture formats of wgpu, and the
+//! layout of an image for `Queue::write_texture`.
+//!
+//! So that `genimage` doesn't depend on a particular version of wgpu,
+//! [`TextureFormat`] and [`ImageDataLayout`] mirror the wgpu types of the
+//! same names, covering the uncompressed color formats that an image can
+//! be uploaded to. Their variants and fields are named like wgpu's, so
+//! converting them is a matter of a `match`.
+//!
+//! Texture formats describe bytes in memory, so only images that store
+//! their multi-byte pixels in little-endian order can be uploaded as
+//! they are. wgpu has no formats with 24 bits per pixel.
+//!
+//! This requires the `wgpu` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::wgpu::{texture_layout, TextureFormat};
+//! use genimage::{Builder, Endianness, Format};
+//!
+//! let image = Builder::owned(64, 64, Format::BGRA32)
+//!     .with_endianness(Endianness::Little)
+//!     .finish();
+//! let (format, layout) = texture_layout(&image).unwrap();
+//! assert_eq!(format, TextureFormat::Bgra8Unorm);
+//! assert_eq!(layout.bytes_per_row, Some(256));
+//! assert_eq!(layout.rows_per_image, Some(64));
+//! ```
+//!
+//! [`TextureFormat`]: crate::wgpu::TextureFormat
+//! [`ImageDataLayout`]: crate::wgpu::ImageDataLayout
+
+use crate::{AlphaMode, ColorType, Endianness, Error, Format, Image};
+use core::convert::TryFrom;
+
+/// The texture formats of wgpu that have a format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum TextureFormat {
+    /// Gray stored in a red channel, which is [`Format::GRAY8`](crate::Format::GRAY8).
+    R8Unorm,
+    /// [`Format::RG8`](crate::Format::RG8).
+    Rg8Unorm,
+    /// Gray stored in a red channel, which is [`Format::GRAY16`](crate::Format::GRAY16).
+    R16Unorm,
+    /// [`Format::RG16`](crate::Format::RG16).
+    Rg16Unorm,
+    /// [`Format::RGBA32`](crate::Format::RGBA32).
+    Rgba8Unorm,
+    /// [`Format::RGBA32`](crate::Format::RGBA32), sampled as sRGB.
+    Rgba8UnormSrgb,
+    /// [`Format::BGRA32`](crate::Format::BGRA32).
+    Bgra8Unorm,
+    /// [`Format::BGRA32`](crate::Format::BGRA32), sampled as sRGB.
+    Bgra8UnormSrgb,
+    /// Ten bits of red, green and blue, then two bits of alpha.
+    Rgb10a2Unorm,
+    /// [`Format::RGBA64`](crate::Format::RGBA64).
+    Rgba16Unorm,
+}
+
+/// Every texture format, along with its format.
+const FORMATS: &[(TextureFormat, Format)] = &[
+    (TextureFormat::R8Unorm, Format::GRAY8),
+    (TextureFormat::Rg8Unorm, Format::RG8),
+    (TextureFormat::R16Unorm, Format::GRAY16),
+    (TextureFormat::Rg16Unorm, Format::RG16),
+    (TextureFormat::Rgba8Unorm, Format::RGBA32),
+    (TextureFormat::Rgba8UnormSrgb, Format::RGBA32),
+    (TextureFormat::Bgra8Unorm, Format::BGRA32),
+    (TextureFormat::Bgra8UnormSrgb, Format::BGRA32),
+    (
+        TextureFormat::Rgb10a2Unorm,
+        Format::new(32, ColorType::Rgba, 2, 10, 10, 10),
+    ),
+    (TextureFormat::Rgba16Unorm, Format::RGBA64),
+];
+
+/// The layout of the bytes of an image in a buffer, as `write_texture`
+/// takes it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ImageDataLayout {
+    /// The offset of the first pixel in the buffer.
+    pub offset: u64,
+    /// The number of bytes from the start of one row to the next.
+    pub bytes_per_row: Option<u32>,
+    /// The number of rows from the start of one layer to the next.
+    pub rows_per_image: Option<u32>,
+}
+
+impl Format {
+    /// The wgpu texture format with the same layout as this format, if
+    /// there is one.
+    ///
+    /// Formats are matched with the linear variant of 8-bit formats, since
+    /// whether a texture is sRGB depends on how it is sampled. wgpu doesn't
+    /// say whether alpha is premultiplied, so it is ignored.
+    ///
+    /// This requires the `wgpu` feature.
+    pub fn to_wgpu_format(self) -> Option<TextureFormat> {
+        let format = self.with_alpha_mode(AlphaMode::Straight);
+        FORMATS
+            .iter()
+            .find(|&&(_, known)| known == format)
+            .map(|&(texture_format, _)| texture_format)
+    }
+
+    /// The format with the same layout as a wgpu texture format.
+    ///
+    /// This requires the `wgpu` feature.
+    pub fn from_wgpu_format(texture_format: TextureFormat) -> Format {
+        FORMATS
+            .iter()
+            .find(|&&(known, _)| known == texture_format)
+            .map(|&(_, format)| format)
+            .expect("Every texture format has a format")
+    }
+}
+
+/// The texture format and data layout for uploading an image with
+/// `Queue::write_texture`, straight from the bytes of its buffer.
+///
+/// # Errors
+///
+/// Returns [`Error::Unsupported`] if there is no texture format for the
+/// format of the image, or its pixels are big endian, and
+/// [`Error::TooLarge`] if the image is too large to describe.
+///
+/// [`Error::Unsupported`]: crate::Error::Unsupported
+/// [`Error::TooLarge`]: crate::Error::TooLarge
+pub fn texture_layout<Img: Image + ?Sized>(
+    image: &Img,
+) -> Result<(TextureFormat, ImageDataLayout), Error> {
+    let format = image.format();
+    if format.bytes() > 1 && image.endianness() != Endianness::Little {
+        return Err(Error::Unsupported);
+    }
+    let texture_format = format.to_wgpu_format().ok_or(Error::Unsupported)?;
+
+    let too_large = |_| Error::TooLarge;
+    let layout = ImageDataLayout {
+        offset: 0,
+        bytes_per_row: Some(u32::try_from(image.bytes_per_scanline()).map_err(too_large)?),
+        rows_per_image: Some(u32::try_from(image.height()).map_err(too_large)?),
+    };
+    Ok((texture_format, layout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GeneralImage;
+
+    #[test]
+    fn formats() {
+        for &(texture_format, format) in FORMATS {
+            assert_eq!(Format::from_wgpu_format(texture_format), format);
+            let linear = format.to_wgpu_format().unwrap();
+            assert_eq!(Format::from_wgpu_format(linear), format);
+        }
+        assert_eq!(
+            Format::RGBA32
+                .with_alpha_mode(AlphaMode::Premultiplied)
+                .to_wgpu_format(),
+            Some(TextureFormat::Rgba8Unorm)
+        );
+        assert_eq!(Format::RGB24.to_wgpu_format(), None);
+
+        let gray = GeneralImage::from_buffer(3, 2, Format::GRAY8, [0u8; 8]);
+        let (texture_format, layout) = texture_layout(&gray).unwrap();
+        assert_eq!(texture_format, TextureFormat::R8Unorm);
+        assert_eq!(layout.bytes_per_row, Some(3));
+    }
+}
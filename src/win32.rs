@@ -0,0 +1,294 @@
+// BSL 1.0 License
+
+//! Headers of Windows device-independent bitmaps, for handing images to
+//! GDI functions such as `StretchDIBits` and `SetDIBitsToDevice`.
+//!
+//! [`BitmapInfo`] has the same layout as a `BITMAPINFO` with room for
+//! three color masks, so a pointer to it can be passed wherever GDI
+//! wants one. It is built from a [`FramebufferDesc`], which says where
+//! the pixels are, and turns back into one to map the pixels of a DIB
+//! section with [`map_framebuffer`].
+//!
+//! DIBs are little endian, and their rows are padded to four bytes.
+//! Bottom-up bitmaps have a positive height and top-down bitmaps have a
+//! negative one, which follows the [`Origin`] of the description.
+//!
+//! This requires the `win32` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::win32::{BitmapInfo, BI_RGB};
+//! use genimage::{map_framebuffer, Format, FramebufferDesc, GeneralImage};
+//!
+//! let image = GeneralImage::from_buffer(640, 480, Format::XBGR32, vec![0u8; 640 * 480 * 4]);
+//! let info = BitmapInfo::from_desc(&FramebufferDesc::from_image(&image)).unwrap();
+//! assert_eq!(info.header.height, -480);
+//! assert_eq!(info.header.compression, BI_RGB);
+//!
+//! // StretchDIBits(hdc, .., bits.as_ptr(), &info as *const _ as *const BITMAPINFO, ..)
+//!
+//! let desc = info.to_desc().unwrap();
+//! let mut bits = vec![0u8; desc.len()];
+//! let dib_section = map_framebuffer(&desc, &mut bits[..]).unwrap();
+//! ```
+//!
+//! [`BitmapInfo`]: crate::win32::BitmapInfo
+//! [`FramebufferDesc`]: crate::FramebufferDesc
+//! [`map_framebuffer`]: crate::map_framebuffer
+//! [`Origin`]: crate::Origin
+
+use crate::{
+    ByteCount, Channel, ColorType, Endianness, Error, Format, FramebufferDesc, Origin, PixelCount,
+};
+use core::convert::TryFrom;
+
+/// The compression of a bitmap whose pixels are stored as they are.
+pub const BI_RGB: u32 = 0;
+
+/// The compression of a bitmap whose channels are described by color
+/// masks.
+pub const BI_BITFIELDS: u32 = 3;
+
+/// The fields of a `BITMAPINFOHEADER`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct BitmapInfoHeader {
+    /// The size of this structure in bytes, which is always 40.
+    pub size: u32,
+    /// The width of the bitmap in pixels.
+    pub width: i32,
+    /// The height of the bitmap in pixels, which is negative for bitmaps
+    /// whose first row is the top of the picture.
+    pub height: i32,
+    /// The number of planes, which is always 1.
+    pub planes: u16,
+    /// The number of bits per pixel.
+    pub bit_count: u16,
+    /// How the pixels are stored, such as [`BI_RGB`] or [`BI_BITFIELDS`].
+    ///
+    /// [`BI_RGB`]: crate::win32::BI_RGB
+    /// [`BI_BITFIELDS`]: crate::win32::BI_BITFIELDS
+    pub compression: u32,
+    /// The number of bytes that the pixels take up.
+    pub size_image: u32,
+    /// The horizontal resolution in pixels per meter, or zero.
+    pub x_pels_per_meter: i32,
+    /// The vertical resolution in pixels per meter, or zero.
+    pub y_pels_per_meter: i32,
+    /// The number of colors in the color table, or zero for all of them.
+    pub clr_used: u32,
+    /// The number of colors that are needed to show the bitmap, or zero
+    /// for all of them.
+    pub clr_important: u32,
+}
+
+/// A `BITMAPINFOHEADER` followed by the masks of the red, green and blue
+/// channels, as a `BITMAPINFO` holds them for [`BI_BITFIELDS`] bitmaps.
+///
+/// The masks are zero for [`BI_RGB`] bitmaps. Bitmaps with eight bits per
+/// pixel are indexed, and their color table has to be sent separately.
+///
+/// [`BI_BITFIELDS`]: crate::win32::BI_BITFIELDS
+/// [`BI_RGB`]: crate::win32::BI_RGB
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct BitmapInfo {
+    /// The header of the bitmap.
+    pub header: BitmapInfoHeader,
+    /// The red, green and blue masks of the pixels.
+    pub masks: [u32; 3],
+}
+
+/// The masks of the pixels of [`BI_RGB`] bitmaps with 16, 24 and 32 bits
+/// per pixel.
+const RGB_MASKS: [(u16, [u32; 3]); 3] = [
+    (16, [0x7C00, 0x03E0, 0x001F]),
+    (24, [0xFF_0000, 0xFF00, 0xFF]),
+    (32, [0xFF_0000, 0xFF00, 0xFF]),
+];
+
+impl BitmapInfo {
+    /// The header of a bitmap with the layout of a framebuffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unsupported`] if there is no DIB with this format
+    /// or endianness, [`Error::InvalidStride`] if the rows aren't padded to
+    /// four bytes, and [`Error::TooLarge`] if the size doesn't fit in the
+    /// header.
+    ///
+    /// [`Error::Unsupported`]: crate::Error::Unsupported
+    /// [`Error::InvalidStride`]: crate::Error::InvalidStride
+    /// [`Error::TooLarge`]: crate::Error::TooLarge
+    pub fn from_desc(desc: &FramebufferDesc) -> Result<Self, Error> {
+        let format = desc.format;
+        let bit_count = u16::from(format.bpp());
+        let (compression, masks) = if format == Format::INDEXED8 {
+            (BI_RGB, [0; 3])
+        } else {
+            if desc.endianness != Endianness::Little {
+                return Err(Error::Unsupported);
+            }
+
+            let masks = [
+                format.channel_mask(Channel::Red),
+                format.channel_mask(Channel::Green),
+                format.channel_mask(Channel::Blue),
+            ];
+            let rgb = core::matches!(
+                format.color_type(),
+                ColorType::Argb
+                    | ColorType::Rgba
+                    | ColorType::Abgr
+                    | ColorType::Bgra
+                    | ColorType::Custom(_)
+            );
+            if !rgb || !core::matches!(bit_count, 16 | 24 | 32) || masks.contains(&0) {
+                return Err(Error::Unsupported);
+            }
+
+            if RGB_MASKS.contains(&(bit_count, masks)) {
+                (BI_RGB, [0; 3])
+            } else if bit_count == 24 {
+                return Err(Error::Unsupported);
+            } else {
+                (BI_BITFIELDS, masks)
+            }
+        };
+
+        if desc.stride != dib_stride(desc.width, bit_count) {
+            return Err(Error::InvalidStride);
+        }
+
+        let too_large = |_| Error::TooLarge;
+        let width = i32::try_from(desc.width.get()).map_err(too_large)?;
+        let height = i32::try_from(desc.height).map_err(too_large)?;
+        let size_image = desc
+            .stride
+            .get()
+            .checked_mul(desc.height)
+            .and_then(|size| u32::try_from(size).ok())
+            .ok_or(Error::TooLarge)?;
+
+        Ok(Self {
+            header: BitmapInfoHeader {
+                size: core::mem::size_of::<BitmapInfoHeader>() as u32,
+                width,
+                height: match desc.origin {
+                    Origin::TopLeft => -height,
+                    Origin::BottomLeft => height,
+                },
+                planes: 1,
+                bit_count,
+                compression,
+                size_image,
+                x_pels_per_meter: 0,
+                y_pels_per_meter: 0,
+                clr_used: 0,
+                clr_important: 0,
+            },
+            masks,
+        })
+    }
+
+    /// The format of the pixels of the bitmap.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unsupported`] if the bitmap is compressed or has a
+    /// number of bits per pixel that there is no format for.
+    ///
+    /// [`Error::Unsupported`]: crate::Error::Unsupported
+    pub fn format(&self) -> Result<Format, Error> {
+        let header = &self.header;
+        match (header.compression, header.bit_count) {
+            (BI_RGB, 8) => Ok(Format::INDEXED8),
+            (BI_RGB, 16) => Ok(Format::RGB555),
+            (BI_RGB, 24) => Ok(Format::BGR24),
+            (BI_RGB, 32) => Ok(Format::XBGR32),
+            (BI_BITFIELDS, 16) | (BI_BITFIELDS, 32) => {
+                let [red, green, blue] = self.masks;
+                Format::from_masks(header.bit_count as u8, red, green, blue, 0)
+                    .ok_or(Error::Unsupported)
+            }
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// The layout of the pixels of the bitmap, for [`map_framebuffer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`format`] does, or [`Error::TooLarge`] if the
+    /// width is negative.
+    ///
+    /// [`map_framebuffer`]: crate::map_framebuffer
+    /// [`format`]: crate::win32::BitmapInfo::format
+    /// [`Error::TooLarge`]: crate::Error::TooLarge
+    pub fn to_desc(&self) -> Result<FramebufferDesc, Error> {
+        let format = self.format()?;
+        let width = usize::try_from(self.header.width).map_err(|_| Error::TooLarge)?;
+        let width = PixelCount::new(width);
+        let origin = if self.header.height < 0 {
+            Origin::TopLeft
+        } else {
+            Origin::BottomLeft
+        };
+
+        Ok(FramebufferDesc {
+            width,
+            height: i64::from(self.header.height).abs() as usize,
+            stride: dib_stride(width, self.header.bit_count),
+            format,
+            endianness: Endianness::Little,
+            origin,
+        })
+    }
+}
+
+/// The number of bytes in each row of a bitmap, which are padded to four
+/// bytes.
+fn dib_stride(width: PixelCount, bit_count: u16) -> ByteCount {
+    let bits = width.get() * usize::from(bit_count);
+    ByteCount::new((bits + 31) / 32 * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers() {
+        let round_trip = |format, origin| {
+            let desc = FramebufferDesc::new(3, 2, format)
+                .with_endianness(Endianness::Little)
+                .with_origin(origin);
+            let desc = desc.with_stride(dib_stride(desc.width, u16::from(format.bpp())));
+            let info = BitmapInfo::from_desc(&desc).unwrap();
+            assert_eq!(info.to_desc(), Ok(desc));
+            info
+        };
+
+        let info = round_trip(Format::BGR24, Origin::BottomLeft);
+        assert_eq!(info.header.height, 2);
+        assert_eq!(info.header.size_image, 24);
+        assert_eq!(info.header.compression, BI_RGB);
+        let info = round_trip(Format::RGB565, Origin::TopLeft);
+        assert_eq!(info.header.height, -2);
+        assert_eq!(info.header.compression, BI_BITFIELDS);
+        assert_eq!(info.masks, [0xF800, 0x07E0, 0x1F]);
+        round_trip(Format::INDEXED8, Origin::TopLeft);
+
+        // alpha is left out of the header
+        let desc = FramebufferDesc::new(2, 2, Format::BGRA32).with_endianness(Endianness::Little);
+        let info = BitmapInfo::from_desc(&desc).unwrap();
+        assert_eq!(info.format(), Ok(Format::XBGR32));
+
+        let unpadded =
+            FramebufferDesc::new(3, 2, Format::BGR24).with_endianness(Endianness::Little);
+        assert_eq!(BitmapInfo::from_desc(&unpadded), Err(Error::InvalidStride));
+        let gray = FramebufferDesc::new(4, 2, Format::GRAY16).with_endianness(Endianness::Little);
+        assert_eq!(BitmapInfo::from_desc(&gray), Err(Error::Unsupported));
+    }
+}
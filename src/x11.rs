@@ -0,0 +1,327 @@
+// BSL 1.0 License
+
+//! Layouts of X11 images, for use with `XPutImage` and `XGetImage` or the
+//! `PutImage` and `GetImage` requests of breadx and x11rb.
+//!
+//! An X server lists the pixmap formats that it supports in its setup
+//! reply, along with the byte order of images and the bit order of
+//! bitmaps. Together with the masks of a visual, they say everything that
+//! is needed to lay out a `ZPixmap` image, which is what [`ZPixmap`]
+//! holds.
+//!
+//! This requires the `x11` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use genimage::x11::{PixmapFormat, VisualMasks, ZPixmap};
+//! use genimage::{Endianness, Format, GeneralImage};
+//!
+//! // a TrueColor visual on a little-endian server
+//! let pixmap_format = PixmapFormat {
+//!     depth: 24,
+//!     bits_per_pixel: 32,
+//!     scanline_pad: 32,
+//! };
+//! let zpixmap = ZPixmap::new(pixmap_format, Endianness::Little)
+//!     .with_visual(VisualMasks::TRUE_COLOR_24);
+//! assert_eq!(zpixmap.format(), Ok(Format::XBGR32));
+//!
+//! let image = GeneralImage::solid_color(16, 16, Format::RGB24, Default::default());
+//! let mut bytes = vec![0u8; zpixmap.len(16, 16)];
+//! zpixmap.put_image(&image, &mut bytes).unwrap();
+//! ```
+//!
+//! [`ZPixmap`]: crate::x11::ZPixmap
+
+use crate::{
+    codec::low_bits, convert, divide_rounding_up, BitOrder, Builder, Endianness, Error, Format,
+    Image,
+};
+
+/// One of the pixmap formats in the setup reply of an X server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PixmapFormat {
+    /// The number of bits of each pixel that hold its value.
+    pub depth: u8,
+    /// The number of bits that each pixel takes up.
+    pub bits_per_pixel: u8,
+    /// The number of bits that each scanline is padded to a multiple of.
+    pub scanline_pad: u8,
+}
+
+impl PixmapFormat {
+    /// The number of bytes in each scanline of an image `width` pixels
+    /// wide.
+    ///
+    /// A scanline pad that isn't a whole number of bytes is treated as a
+    /// pad of one byte.
+    pub fn bytes_per_line(&self, width: usize) -> usize {
+        let pad = core::cmp::max(usize::from(self.scanline_pad / 8), 1);
+        let bytes = divide_rounding_up(width * usize::from(self.bits_per_pixel), 8);
+        divide_rounding_up(bytes, pad) * pad
+    }
+}
+
+/// The masks of the red, green and blue bits of the pixels of a visual.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VisualMasks {
+    /// The bits that hold the red channel.
+    pub red_mask: u32,
+    /// The bits that hold the green channel.
+    pub green_mask: u32,
+    /// The bits that hold the blue channel.
+    pub blue_mask: u32,
+}
+
+impl VisualMasks {
+    /// The masks of the usual 24-bit TrueColor visual.
+    pub const TRUE_COLOR_24: VisualMasks = VisualMasks {
+        red_mask: 0xFF_0000,
+        green_mask: 0xFF00,
+        blue_mask: 0xFF,
+    };
+
+    /// The masks of the usual 16-bit TrueColor visual.
+    pub const TRUE_COLOR_16: VisualMasks = VisualMasks {
+        red_mask: 0xF800,
+        green_mask: 0x07E0,
+        blue_mask: 0x1F,
+    };
+}
+
+/// The layout of a `ZPixmap` image on an X server.
+///
+/// Without a visual, pixels are indices into a colormap, as on
+/// PseudoColor and StaticGray visuals and for bitmaps. With one, the
+/// masks of the visual say where the channels are, and any bits of the
+/// depth that are left over are alpha, as on the 32-bit visuals of
+/// compositing servers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ZPixmap {
+    /// The pixmap format for the depth of the image.
+    pub pixmap_format: PixmapFormat,
+    /// The masks of the visual, if it has them.
+    pub visual: Option<VisualMasks>,
+    /// The byte order of images on the server.
+    pub byte_order: Endianness,
+    /// The bit order of bitmaps on the server.
+    ///
+    /// This is only used for images with one bit per pixel. Bitmap units
+    /// are assumed to be a byte long.
+    pub bitmap_bit_order: BitOrder,
+}
+
+impl ZPixmap {
+    /// Describe images without a visual with the given byte order, and
+    /// bitmaps with their least significant bit first.
+    pub const fn new(pixmap_format: PixmapFormat, byte_order: Endianness) -> Self {
+        Self {
+            pixmap_format,
+            visual: None,
+            byte_order,
+            bitmap_bit_order: BitOrder::LsbFirst,
+        }
+    }
+
+    /// Use the masks of a visual.
+    pub const fn with_visual(self, visual: VisualMasks) -> Self {
+        Self {
+            visual: Some(visual),
+            ..self
+        }
+    }
+
+    /// Use a different bit order for bitmaps.
+    pub const fn with_bitmap_bit_order(self, bitmap_bit_order: BitOrder) -> Self {
+        Self {
+            bitmap_bit_order,
+            ..self
+        }
+    }
+
+    /// The format of the pixels.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unsupported`] if [`Format::from_masks`] has no
+    /// format for the masks of the visual, or if they don't fit in the
+    /// depth.
+    ///
+    /// [`Error::Unsupported`]: crate::Error::Unsupported
+    /// [`Format::from_masks`]: crate::Format::from_masks
+    pub fn format(&self) -> Result<Format, Error> {
+        let PixmapFormat {
+            depth,
+            bits_per_pixel,
+            ..
+        } = self.pixmap_format;
+
+        let masks = match self.visual {
+            Some(masks) => masks,
+            None => {
+                return match (depth, bits_per_pixel) {
+                    (1, 1) => Ok(Format::INDEXED1),
+                    (4, 4) => Ok(Format::INDEXED4),
+                    (8, 8) => Ok(Format::INDEXED8),
+                    _ => Err(Error::Unsupported),
+                }
+            }
+        };
+
+        // the bits of the depth that the visual doesn't use are alpha,
+        // which has to come after the colors
+        let color_mask = masks.red_mask | masks.green_mask | masks.blue_mask;
+        let depth_mask = low_bits(depth) as u32;
+        let alpha_mask = depth_mask & !color_mask;
+        let color_top = 32 - color_mask.leading_zeros() as u8;
+        if depth > bits_per_pixel
+            || color_mask & !depth_mask != 0
+            || alpha_mask & low_bits(color_top) as u32 != 0
+        {
+            return Err(Error::Unsupported);
+        }
+
+        Format::from_masks(
+            bits_per_pixel,
+            masks.red_mask,
+            masks.green_mask,
+            masks.blue_mask,
+            alpha_mask,
+        )
+        .ok_or(Error::Unsupported)
+    }
+
+    /// The order of the pixels within each byte, for images with fewer
+    /// than eight bits per pixel.
+    ///
+    /// Bitmaps use the bitmap bit order, while nibbles follow the byte
+    /// order.
+    pub fn bit_order(&self) -> BitOrder {
+        match (self.pixmap_format.bits_per_pixel, self.byte_order) {
+            (1, _) => self.bitmap_bit_order,
+            (_, Endianness::Big) => BitOrder::MsbFirst,
+            (_, Endianness::Little) => BitOrder::LsbFirst,
+        }
+    }
+
+    /// The number of bytes in an image with these dimensions, including
+    /// the padding at the end of the last scanline.
+    pub fn len(&self, width: usize, height: usize) -> usize {
+        self.pixmap_format.bytes_per_line(width) * height
+    }
+
+    /// Start building an image over the bytes of a `ZPixmap` image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no format for this layout, or if the
+    /// padded scanlines aren't a whole number of pixels long, which can
+    /// happen with 24 bits per pixel.
+    pub fn builder<Storage>(
+        &self,
+        width: usize,
+        height: usize,
+        bytes: Storage,
+    ) -> Result<Builder<Storage>, Error> {
+        Builder::from_buffer(width, height, self.format()?, bytes)
+            .with_endianness(self.byte_order)
+            .with_bit_order(self.bit_order())
+            .try_with_bytes_per_scanline(self.pixmap_format.bytes_per_line(width))
+    }
+
+    /// Convert an image into the bytes of a `ZPixmap` image of the same
+    /// size, ready to be sent with a `PutImage` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`builder`] does, or if `bytes` is shorter than
+    /// [`len`].
+    ///
+    /// [`builder`]: crate::x11::ZPixmap::builder
+    /// [`len`]: crate::x11::ZPixmap::len
+    pub fn put_image<Img: Image + ?Sized>(&self, src: &Img, bytes: &mut [u8]) -> Result<(), Error> {
+        let (width, height) = src.dimensions();
+        let mut image = self.builder(width, height, bytes)?.try_finish()?;
+        convert(src, &mut image);
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "alloc", test))]
+mod tests {
+    use super::*;
+    use crate::{GeneralImage, Rgba};
+    use alloc::vec;
+
+    #[test]
+    fn formats() {
+        let zpixmap = |depth, bits_per_pixel, visual| ZPixmap {
+            visual,
+            ..ZPixmap::new(
+                PixmapFormat {
+                    depth,
+                    bits_per_pixel,
+                    scanline_pad: 32,
+                },
+                Endianness::Little,
+            )
+        };
+        let true_color = Some(VisualMasks::TRUE_COLOR_24);
+        assert_eq!(zpixmap(24, 32, true_color).format(), Ok(Format::XBGR32));
+        assert_eq!(zpixmap(32, 32, true_color).format(), Ok(Format::BGRA32));
+        assert_eq!(zpixmap(24, 24, true_color).format(), Ok(Format::BGR24));
+        assert_eq!(
+            zpixmap(16, 16, Some(VisualMasks::TRUE_COLOR_16)).format(),
+            Ok(Format::RGB565)
+        );
+        assert_eq!(zpixmap(8, 8, None).format(), Ok(Format::INDEXED8));
+
+        let gappy = VisualMasks {
+            red_mask: 0xFF_0000,
+            green_mask: 0xF000,
+            blue_mask: 0xFF,
+        };
+        assert_eq!(
+            zpixmap(24, 32, Some(gappy)).format(),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(
+            zpixmap(16, 32, true_color).format(),
+            Err(Error::Unsupported)
+        );
+    }
+
+    #[test]
+    fn padding() {
+        // 1x2 red on a big-endian server with rows padded to 32 bits
+        let zpixmap = ZPixmap::new(
+            PixmapFormat {
+                depth: 16,
+                bits_per_pixel: 16,
+                scanline_pad: 32,
+            },
+            Endianness::Big,
+        )
+        .with_visual(VisualMasks::TRUE_COLOR_16);
+        let red = Rgba {
+            red: 0xFFFF,
+            green: 0,
+            blue: 0,
+            alpha: 0xFFFF,
+        };
+        let src = GeneralImage::solid_color(1, 2, Format::ARGB32, red);
+        let mut bytes = vec![0u8; zpixmap.len(1, 2)];
+        zpixmap.put_image(&src, &mut bytes).unwrap();
+        assert_eq!(bytes, [0xF8, 0, 0, 0, 0xF8, 0, 0, 0]);
+
+        let mut short = [0u8; 6];
+        assert_eq!(
+            zpixmap.put_image(&src, &mut short),
+            Err(Error::BufferTooSmall {
+                expected: 8,
+                actual: 6
+            })
+        );
+    }
+}